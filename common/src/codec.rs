@@ -1,10 +1,15 @@
 pub use asynchronous_codec::{Framed, FramedRead, FramedWrite};
 
+pub use self::length_delimited::LengthDelimited;
+
 #[cfg(feature = "prost_codec")]
-pub use self::prost_protobuf::{Codec as ProstCodec, Error as ProstCodecError};
+pub use self::prost_protobuf::{
+    Codec as ProstCodec, Error as ProstCodecError, Inspector as ProstCodecInspector,
+};
 #[cfg(feature = "quick_protobuf_codec")]
 pub use self::quick_protobuf::{Codec as QuickProtobufCodec, Error as QuickProtobufCodecError};
 
+mod length_delimited;
 #[cfg(feature = "prost_codec")]
 mod prost_protobuf;
 #[cfg(feature = "quick_protobuf_codec")]
@@ -0,0 +1,141 @@
+use bytes::{Buf, BytesMut};
+use unsigned_varint::decode;
+
+/// A decoder of [`unsigned_varint`] length-prefixed frames.
+///
+/// The declared length of a frame is validated against the maximum length as soon as the length
+/// prefix is read, before reserving any buffer space for the frame. Frames declaring a length
+/// larger than the maximum are rejected without being read.
+#[derive(Debug)]
+pub struct LengthDelimited {
+    /// The maximum length of a frame, not including the length prefix.
+    max_len: usize,
+
+    /// The declared length of the frame being read, if its length prefix was already consumed.
+    pending_len: Option<usize>,
+}
+
+impl LengthDelimited {
+    /// Create a new decoder accepting frames of up to `max_len` bytes.
+    pub fn new(max_len: usize) -> Self {
+        Self {
+            max_len,
+            pending_len: None,
+        }
+    }
+
+    /// Decode a frame from the buffer.
+    ///
+    /// Returns `Ok(None)` if the buffer does not contain a whole frame yet.
+    pub fn decode(&mut self, src: &mut BytesMut) -> Result<Option<BytesMut>, std::io::Error> {
+        let len = match self.pending_len.take() {
+            Some(len) => len,
+            None => {
+                let (len, remaining) = match decode::usize(src) {
+                    Ok(decoded) => decoded,
+                    Err(decode::Error::Insufficient) => return Ok(None),
+                    Err(err) => {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            err.to_string(),
+                        ));
+                    }
+                };
+
+                if len > self.max_len {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("frame length {len} exceeds the maximum {}", self.max_len),
+                    ));
+                }
+
+                let prefix_len = src.len() - remaining.len();
+                src.advance(prefix_len);
+                len
+            }
+        };
+
+        if src.len() < len {
+            src.reserve(len - src.len());
+            self.pending_len = Some(len);
+            return Ok(None);
+        }
+
+        Ok(Some(src.split_to(len)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode the length as an unsigned varint.
+    fn length_prefix(len: u64) -> Vec<u8> {
+        let mut buffer = unsigned_varint::encode::u64_buffer();
+        unsigned_varint::encode::u64(len, &mut buffer).to_vec()
+    }
+
+    #[test]
+    fn decode_frame() {
+        //// Given
+        let mut decoder = LengthDelimited::new(1024);
+
+        let mut src = BytesMut::from(length_prefix(4).as_slice());
+        src.extend_from_slice(b"test");
+
+        //// When
+        let frame = decoder.decode(&mut src);
+
+        //// Then
+        assert_eq!(frame.unwrap().as_deref(), Some(&b"test"[..]));
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn decode_frame_received_in_chunks() {
+        //// Given
+        let mut decoder = LengthDelimited::new(1024);
+
+        let mut src = BytesMut::from(length_prefix(4).as_slice());
+        src.extend_from_slice(b"te");
+        let first = decoder.decode(&mut src).unwrap();
+
+        //// When
+        src.extend_from_slice(b"st");
+        let second = decoder.decode(&mut src).unwrap();
+
+        //// Then
+        assert!(first.is_none());
+        assert_eq!(second.as_deref(), Some(&b"test"[..]));
+    }
+
+    #[test]
+    fn reject_oversized_declared_length_without_allocating() {
+        //// Given
+        let mut decoder = LengthDelimited::new(1024);
+
+        // A frame declaring a length of 4 GiB.
+        let mut src = BytesMut::from(length_prefix(4 * 1024 * 1024 * 1024).as_slice());
+
+        //// When
+        let result = decoder.decode(&mut src);
+
+        //// Then
+        assert!(result.is_err());
+        assert!(src.capacity() < 1024);
+    }
+
+    #[test]
+    fn reject_overlong_length_prefix() {
+        //// Given
+        let mut decoder = LengthDelimited::new(1024);
+
+        let mut src = BytesMut::from(&[0xff; 11][..]);
+
+        //// When
+        let result = decoder.decode(&mut src);
+
+        //// Then
+        assert!(result.is_err());
+    }
+}
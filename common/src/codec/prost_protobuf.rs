@@ -1,12 +1,15 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
 use std::marker::PhantomData;
+use std::sync::Arc;
 
 use asynchronous_codec::{Decoder, Encoder};
 use bytes::BytesMut;
 use prost::{DecodeError, Message};
 use unsigned_varint::codec::UviBytes;
 
+use super::length_delimited::LengthDelimited;
+
 #[derive(Debug, thiserror::Error)]
 #[error("Failed to encode/decode message")]
 pub struct Error(#[from] std::io::Error);
@@ -23,11 +26,19 @@ impl From<DecodeError> for Error {
     }
 }
 
+/// A callback inspecting the raw bytes of the received messages, before decoding them.
+pub type Inspector = dyn Fn(&[u8]) + Send + Sync + 'static;
+
 /// [`Codec`] implements [`Encoder`] and [`Decoder`], uses [`unsigned_varint`]
 /// to prefix messages with their length and uses [`prost`] and a provided
 /// `struct` implementing [`Message`] to do the encoding.
+///
+/// The declared length of the received messages is validated before reading them, so messages
+/// exceeding the maximum length are rejected without allocating buffer space for them.
 pub struct Codec<In, Out = In> {
     uvi: UviBytes,
+    decoder: LengthDelimited,
+    inspector: Option<Arc<Inspector>>,
     phantom: PhantomData<(In, Out)>,
 }
 
@@ -42,9 +53,19 @@ impl<In, Out> Codec<In, Out> {
         uvi.set_max_len(max_message_len_bytes);
         Self {
             uvi,
+            decoder: LengthDelimited::new(max_message_len_bytes),
+            inspector: None,
             phantom: PhantomData::default(),
         }
     }
+
+    /// Set a callback invoked with the raw bytes of every received message, not including the
+    /// length prefix, before decoding it.
+    #[must_use]
+    pub fn with_inspector(mut self, inspector: Arc<Inspector>) -> Self {
+        self.inspector = Some(inspector);
+        self
+    }
 }
 
 impl<In: Message, Out> Encoder for Codec<In, Out> {
@@ -67,9 +88,90 @@ impl<In, Out: Message + Default> Decoder for Codec<In, Out> {
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
         Ok(self
-            .uvi
+            .decoder
             .decode(src)?
-            .map(|msg| Message::decode(msg))
+            .map(|msg| {
+                if let Some(inspector) = &self.inspector {
+                    inspector(&msg);
+                }
+
+                Message::decode(msg)
+            })
             .transpose()?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct TestMessage {
+        #[prost(bytes = "vec", tag = "1")]
+        data: Vec<u8>,
+    }
+
+    #[test]
+    fn encode_and_decode_message() {
+        //// Given
+        let mut codec = Codec::<TestMessage>::new(1024);
+        let message = TestMessage {
+            data: b"test-payload".to_vec(),
+        };
+
+        let mut buffer = BytesMut::new();
+        codec
+            .encode(message.clone(), &mut buffer)
+            .expect("encode the message");
+
+        //// When
+        let decoded = codec.decode(&mut buffer);
+
+        //// Then
+        assert_eq!(decoded.unwrap(), Some(message));
+    }
+
+    #[test]
+    fn inspector_receives_the_raw_message_bytes() {
+        //// Given
+        let inspected = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut codec = Codec::<TestMessage>::new(1024).with_inspector({
+            let inspected = inspected.clone();
+            Arc::new(move |bytes: &[u8]| inspected.lock().unwrap().push(bytes.to_vec()))
+        });
+        let message = TestMessage {
+            data: b"test-payload".to_vec(),
+        };
+
+        let mut buffer = BytesMut::new();
+        codec
+            .encode(message.clone(), &mut buffer)
+            .expect("encode the message");
+
+        //// When
+        let decoded = codec.decode(&mut buffer);
+
+        //// Then
+        assert_eq!(decoded.unwrap(), Some(message.clone()));
+        assert_eq!(*inspected.lock().unwrap(), vec![message.encode_to_vec()]);
+    }
+
+    #[test]
+    fn reject_message_with_oversized_length_prefix() {
+        //// Given
+        let mut codec = Codec::<TestMessage>::new(1024);
+
+        // A length prefix declaring a 1 GiB message, followed by a few bytes of payload.
+        let mut buffer = BytesMut::new();
+        let mut prefix = unsigned_varint::encode::usize_buffer();
+        buffer.extend_from_slice(unsigned_varint::encode::usize(1 << 30, &mut prefix));
+        buffer.extend_from_slice(&[0x0a, 0x01, 0xff]);
+
+        //// When
+        let decoded = codec.decode(&mut buffer);
+
+        //// Then
+        assert!(decoded.is_err());
+        assert!(buffer.capacity() < 1024);
+    }
+}
@@ -9,6 +9,8 @@ use bytes::{Bytes, BytesMut};
 use quick_protobuf::{BytesReader, MessageRead, MessageWrite, Writer};
 use unsigned_varint::codec::UviBytes;
 
+use super::length_delimited::LengthDelimited;
+
 #[derive(thiserror::Error, Debug)]
 #[error("Failed to encode/decode message")]
 pub struct Error(#[from] std::io::Error);
@@ -22,8 +24,12 @@ impl From<Error> for std::io::Error {
 /// [`Codec`] implements [`Encoder`] and [`Decoder`], uses [`unsigned_varint`]
 /// to prefix messages with their length and uses [`quick_protobuf`] and a provided
 /// `struct` implementing [`MessageRead`] and [`MessageWrite`] to do the encoding.
+///
+/// The declared length of the received messages is validated before reading them, so messages
+/// exceeding the maximum length are rejected without allocating buffer space for them.
 pub struct Codec<In, Out = In> {
     uvi: UviBytes,
+    decoder: LengthDelimited,
     phantom: PhantomData<(In, Out)>,
 }
 
@@ -38,6 +44,7 @@ impl<In, Out> Codec<In, Out> {
         uvi.set_max_len(max_message_len_bytes);
         Self {
             uvi,
+            decoder: LengthDelimited::new(max_message_len_bytes),
             phantom: PhantomData::default(),
         }
     }
@@ -66,7 +73,7 @@ where
     type Error = Error;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        let msg = match self.uvi.decode(src)? {
+        let msg = match self.decoder.decode(src)? {
             None => return Ok(None),
             Some(msg) => msg,
         };
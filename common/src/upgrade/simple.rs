@@ -1,5 +1,5 @@
 use std::convert::Infallible;
-use std::iter;
+use std::vec;
 
 use futures::{future, AsyncRead, AsyncWrite};
 use libp2p::core::{InboundUpgrade, OutboundUpgrade, UpgradeInfo};
@@ -11,9 +11,12 @@ pub struct SimpleUpgradeOutput<TInfo, TSocket> {
 }
 
 /// A protocol upgrade implementation that just returns the socket and the upgrade protocol info.
+///
+/// The upgrade may support several protocols, offered in order of preference: the dialer proposes
+/// them in this order, and the first one supported by the listener is negotiated.
 #[derive(Debug, Clone)]
 pub struct SimpleUpgrade<TInfo> {
-    protocol_info: TInfo,
+    protocols: Vec<TInfo>,
 }
 
 impl<TInfo> SimpleUpgrade<TInfo>
@@ -22,9 +25,16 @@ where
 {
     pub fn new(info: TInfo) -> Self {
         Self {
-            protocol_info: info,
+            protocols: vec![info],
         }
     }
+
+    /// Offer the given protocol in preference to the already supported ones.
+    #[must_use]
+    pub fn with_preferred_protocol(mut self, info: TInfo) -> Self {
+        self.protocols.insert(0, info);
+        self
+    }
 }
 
 impl<TInfo> UpgradeInfo for SimpleUpgrade<TInfo>
@@ -32,10 +42,10 @@ where
     TInfo: AsRef<str> + Clone,
 {
     type Info = TInfo;
-    type InfoIter = iter::Once<Self::Info>;
+    type InfoIter = vec::IntoIter<Self::Info>;
 
     fn protocol_info(&self) -> Self::InfoIter {
-        iter::once(self.protocol_info.clone())
+        self.protocols.clone().into_iter()
     }
 }
 
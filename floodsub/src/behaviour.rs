@@ -1,55 +1,300 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::task::{Context, Poll};
+use std::time::Duration;
 
-use libp2p::core::Endpoint;
+use bytes::Bytes;
+use futures::channel::mpsc;
+use hashlink::LinkedHashSet;
+use libp2p::core::{ConnectedPoint, Endpoint};
 use libp2p::identity::PeerId;
 use libp2p::swarm::behaviour::ConnectionEstablished;
 use libp2p::swarm::{
-    AddressChange, ConnectionClosed, ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour,
-    NotifyHandler, PollParameters, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+    AddressChange, CloseConnection, ConnectionClosed, ConnectionDenied, ConnectionHandler,
+    ConnectionId, FromSwarm, NetworkBehaviour, NotifyHandler, PollParameters, THandler,
+    THandlerInEvent, THandlerOutEvent, ToSwarm,
 };
-use libp2p::Multiaddr;
+use libp2p::{Multiaddr, StreamProtocol};
+use prost::Message as _;
 
-use crate::config::Config;
+use crate::config::{AnonymousSeqno, Config, DedupScope, TopicParams};
 use crate::connections::ConnectionManager;
+use crate::event_stream::EventStream;
 use crate::frame::{Frame, Message, SubscriptionAction};
-use crate::handler::{Command as HandlerCommand, Event as HandlerEvent, Handler};
+use crate::handler::{Command as HandlerCommand, Event as HandlerEvent, Handler, PeerKind};
+use crate::message_id::{default_message_id_fn, MessageId, MessageIdFn};
+use crate::pending_publish::{PendingPublish, PublishFuture};
 use crate::proto::{
     fragment_rpc_message, validate_message_proto, validate_rpc_proto, validate_subopts_proto,
-    FragmentationError, RpcProto,
+    FragmentationError, MessageProto, MessageValidationError, RpcProto,
 };
-use crate::router::Router;
-use crate::seqno::{LinearSequenceNumber, MessageSeqNumberGenerator};
-use crate::topic::{Hasher, Topic, TopicHash};
+use crate::rate_limiter::PeerRateLimiter;
+use crate::router::{flood_fanout, Router, PREFIX_WILDCARD};
+use crate::seen_cache::SeenCache;
+use crate::seqno::{
+    LinearSequenceNumber, MessageSeqNumberGenerator, NoSequenceNumber, RandomSequenceNumber,
+};
+use crate::signing::{self, Signer};
+use crate::topic::{Hasher, IdentityHash, Topic, TopicHash};
+use crate::ttl::{self, TtlSequenceNumber};
 
+/// The floodsub protocol ID, with the default [protocol ID
+/// prefix](crate::ConfigBuilder::protocol_id_prefix).
 pub const FLOODSUB_PROTOCOL_ID: &str = "/floodsub/1.0.0";
 
+/// The variant of the floodsub protocol exchanging zstd compressed RPC frames, with the default
+/// protocol ID prefix.
+#[cfg(feature = "zstd")]
+pub const FLOODSUB_ZSTD_PROTOCOL_ID: &str = "/floodsub/1.0.0/zstd";
+
+/// The version of the floodsub protocol, following the protocol ID prefix.
+const FLOODSUB_PROTOCOL_VERSION: &str = "1.0.0";
+
 /// Events that can be produced by the behaviour.
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum Event {
     /// Message received.
+    ///
+    /// The original author of the message, if any, is available through [`Message::source`].
+    ///
+    /// The messages are emitted in the order they are accepted (i.e., after the deduplication),
+    /// regardless of the peer that propagated them. Within a topic, the messages are therefore
+    /// delivered in acceptance order, so a topic can be consumed as an ordered log.
     Message {
-        /// Peer that propagated the message.
-        source: PeerId,
-
-        /// Message topic.
+        /// The peer that propagated the message to the local node. For locally published
+        /// messages, this is the local peer ID.
+        propagation_source: PeerId,
+
+        /// The role of the local node on the connection with the propagation source:
+        /// [`Endpoint::Dialer`] if the local node initiated the connection, [`Endpoint::Listener`]
+        /// if the peer did. This is `None` for locally published messages.
+        ///
+        /// See [`Behaviour::peer_endpoint`].
+        propagation_endpoint: Option<Endpoint>,
+
+        /// The ID of the message, computed from the message as received (i.e., before applying
+        /// the inbound data transform).
+        message_id: MessageId,
+
+        /// Message topic, or its parent topic for the aggregated events (see
+        /// [`Behaviour::subscribe_with_parent`]).
         topic: TopicHash,
 
         /// Message.
         message: Message,
     },
+
+    /// An inbound message was dropped before being delivered to the application.
+    ///
+    /// Only emitted if [`Config::emit_dropped_events`] is enabled.
+    MessageDropped {
+        /// The ID of the dropped message.
+        message_id: MessageId,
+
+        /// The reason why the message was dropped.
+        reason: DropReason,
+    },
+
+    /// A message published with [`Behaviour::publish_to_peers`] was handed to a connection
+    /// handler of the target peer, or could not be.
+    ///
+    /// Emitted once per target peer. A successful attempt means the message was queued for
+    /// sending on a connection with the peer, not that the peer received or processed it.
+    DeliveryAttempted {
+        /// The ID of the published message.
+        message_id: MessageId,
+
+        /// The target peer.
+        peer_id: PeerId,
+
+        /// Whether the message was queued for sending to the peer (`false` if the peer is not
+        /// connected or is blacklisted).
+        succeeded: bool,
+    },
+
+    /// The number of connected peers crossed one of the configured thresholds.
+    PeerCountThreshold {
+        /// The current number of connected peers.
+        count: usize,
+
+        /// Whether the threshold was crossed upwards (`true`) or downwards (`false`).
+        crossed_up: bool,
+    },
+
+    /// A topic the local node is subscribed to has fewer subscribed peers than the configured
+    /// minimum, so the messages propagation may be unreliable.
+    ///
+    /// Only emitted if a [`Config::min_peers_per_topic`] is configured. The event is not emitted
+    /// again for the topic until it reaches the minimum and drops below it again.
+    InsufficientPeers {
+        /// The topic with insufficient peers.
+        topic: TopicHash,
+    },
+
+    /// A misbehaving peer reached the configured violations threshold and its connections are
+    /// being closed.
+    ///
+    /// Only emitted if a [`Config::disconnect_threshold`] is configured.
+    PeerDisconnected {
+        /// The misbehaving peer.
+        peer_id: PeerId,
+
+        /// The reason why the peer was disconnected.
+        reason: DisconnectReason,
+    },
+
+    /// A connected peer was detected to support the gossipsub protocol, speaking floodsub for
+    /// interoperability.
+    ///
+    /// Emitted once per peer. The peer protocols are only known if a protocol advertising them
+    /// (e.g., identify) is running on the connection.
+    GossipsubPeerDetected {
+        /// The gossipsub peer.
+        peer_id: PeerId,
+    },
+
+    /// The behaviour event queue grew beyond the configured threshold: the swarm is not polled
+    /// fast enough, and the application should slow down publishing.
+    ///
+    /// Only emitted if a [`Config::event_queue_overload_threshold`] is configured. The event is
+    /// not emitted again until the queue drains below the threshold and crosses it again.
+    QueueOverloaded {
+        /// The number of events pending in the queue.
+        pending: usize,
+    },
+}
+
+/// The reason why an inbound message was dropped.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, strum_macros::Display)]
+pub enum DropReason {
+    /// The message was already seen.
+    Duplicate,
+
+    /// The message exceeds the maximum allowed size.
+    TooLarge,
+
+    /// The message signature is invalid, or its `key` is not the public key of its author.
+    InvalidSignature,
+
+    /// The propagation peer exceeded its message rate limit.
+    RateLimited,
+
+    /// The local node is not subscribed to the message topic, and the forwarding of these
+    /// messages is disabled (see [`Config::forward_unsubscribed`]).
+    NotSubscribed,
+
+    /// The propagation peer is blacklisted.
+    Blacklisted,
+
+    /// The message was published by the local node and a peer sent it back.
+    SelfOrigin,
+
+    /// The inbound data transform failed.
+    TransformFailed,
+
+    /// The message topic exceeds the maximum topic length, or contains control characters.
+    InvalidTopic,
+
+    /// The message TTL has expired.
+    Expired,
+
+    /// The message has no topic. These messages are also counted as invalid messages.
+    NoTopic,
+
+    /// The message author is not in the topic allowed authors set (see
+    /// [`Behaviour::set_topic_allowed_authors`]).
+    AuthorNotAllowed,
+
+    /// The message ID exceeds the maximum message ID length (see
+    /// [`Config::max_message_id_len`]).
+    MessageIdTooLarge,
+
+    /// The propagation peer did not advertise a subscription to the message topic (see
+    /// [`Config::only_accept_from_subscribed_peers`]).
+    PeerNotSubscribed,
+}
+
+/// The reason why a misbehaving peer was disconnected, i.e., the kind of violation that made the
+/// peer reach the disconnect threshold.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, strum_macros::Display)]
+pub enum DisconnectReason {
+    /// The peer sent invalid messages (e.g., malformed messages or invalid signatures).
+    InvalidMessages,
+
+    /// The peer repeatedly exceeded its message rate limit.
+    RateLimited,
+}
+
+/// The statistics of the messages received from a peer.
+///
+/// See [`Behaviour::peer_stats`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct PeerStats {
+    /// The number of messages received from the peer and delivered to the application.
+    pub messages_delivered: u64,
+
+    /// The number of already seen messages received from the peer.
+    pub duplicates: u64,
+
+    /// The number of invalid messages received from the peer (e.g., malformed messages, invalid
+    /// signatures or invalid topics).
+    pub invalid_messages: u64,
+
+    /// The number of messages dropped because the peer exceeded its message rate limit.
+    pub rate_limit_hits: u64,
+
+    /// The number of RPC frames received from the peer rejected as a whole, because they carried
+    /// more subscriptions than the [`Config::max_subscriptions_per_incoming_rpc`].
+    pub rejected_rpcs: u64,
+}
+
+/// The aggregate RPC frames traffic on the pubsub substreams, across all peers.
+///
+/// The frames are counted with their length prefix, as encoded before any compression. They
+/// include the messages, the subscriptions and the control messages.
+///
+/// See [`Behaviour::bandwidth_stats`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct BandwidthStats {
+    /// The number of bytes of the RPC frames received from the peers.
+    pub bytes_in: u64,
+
+    /// The number of bytes of the RPC frames sent to the peers, i.e., handed to the connection
+    /// handlers to be sent.
+    pub bytes_out: u64,
+}
+
+/// A point-in-time snapshot of the behaviour state and counters.
+///
+/// See [`Behaviour::stats`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Stats {
+    /// The number of peers with at least one established connection.
+    pub connected_peers: usize,
+
+    /// The number of topics the local node is subscribed to, including the prefix subscriptions.
+    pub subscribed_topics: usize,
+
+    /// The number of message IDs in the seen messages cache.
+    pub seen_messages: usize,
+
+    /// The number of messages published by the local node.
+    pub messages_published: u64,
+
+    /// The number of received messages forwarded to at least one peer.
+    pub messages_forwarded: u64,
+
+    /// The number of received messages dropped before being delivered or forwarded (see
+    /// [`DropReason`]).
+    pub messages_dropped: u64,
+
+    /// The RPC frames traffic, across all peers.
+    pub bandwidth: BandwidthStats,
 }
 
 /// Errors that can happen when sending a RPC frame to a peer.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, thiserror::Error)]
 pub enum SendError {
-    /// Insufficient peers to send message to.
-    ///
-    /// This error is returned when there are not enough peers subscribed to the topic to
-    /// propagate the message.
-    #[error("insufficient peers")]
-    InsufficientPeers,
-
     /// Failed to fragment the message.
     #[error("failed to fragment message")]
     FragmentationFailed(#[from] FragmentationError),
@@ -57,33 +302,78 @@ pub enum SendError {
 
 /// Errors that can happen when publishing a message.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
 pub enum PublishError {
     /// Not subscribed to the topic.
     #[error("not subscribed to topic")]
     NotSubscribed,
 
-    /// Frame sending failed.
+    /// Insufficient peers to publish the message to.
     ///
-    /// This error is returned when the frame could not be sent to the peer.
-    #[error("failed to send frame: {0}")]
-    MessagePublishFailed(#[from] SendError),
+    /// This error is returned when there are no peers subscribed to the topic to propagate the
+    /// message to.
+    #[error("insufficient peers")]
+    InsufficientPeers,
+
+    /// The message exceeds the maximum frame size.
+    #[error("message too large")]
+    MessageTooLarge,
+
+    /// Failed to sign the message.
+    #[error("failed to sign the message")]
+    SigningError,
+
+    /// The message was already published.
+    #[error("duplicate message")]
+    Duplicate,
+
+    /// The message ID exceeds the maximum message ID length.
+    #[error("message ID too large")]
+    MessageIdTooLarge,
+
+    /// Failed to transform the message payload.
+    #[error("failed to transform the message data")]
+    TransformFailed,
+
+    /// The message was not queued to all the targeted peers before the timeout elapsed.
+    #[error("timed out waiting for the peers send queues")]
+    Timeout,
 }
 
 /// Errors that can happen when subscribing/unsubscribing to a topic.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
 pub enum SubscriptionError {
-    /// Failed to publish subscriptions.
-    #[error("failed to publish subscription: {0}")]
-    SubscriptionPublishFailed(#[from] SendError),
+    /// The subscription to the topic is not allowed.
+    #[error("subscription not allowed")]
+    NotAllowed,
+
+    /// Already subscribed to the topic.
+    #[error("already subscribed to topic")]
+    AlreadySubscribed,
 }
 
 pub struct Behaviour {
     /// Protocol behaviour configuration.
     config: Config,
 
+    /// The local node peer ID.
+    local_peer_id: PeerId,
+
+    /// The advertised pubsub protocol ID, built from the configured prefix.
+    protocol_id: StreamProtocol,
+
+    /// The advertised pubsub protocol ID exchanging zstd compressed RPC frames.
+    #[cfg(feature = "zstd")]
+    zstd_protocol_id: StreamProtocol,
+
     /// Events that need to be yielded to the swarm when polling.
     swarm_out_events: VecDeque<ToSwarm<Event, HandlerCommand>>,
 
+    /// Whether the events queue was reported as overloaded, and did not drain below the
+    /// threshold since.
+    queue_overloaded: bool,
+
     /// Connection manager.
     ///
     /// This is used to keep track of the connections and their state, and to manage the
@@ -96,27 +386,214 @@ pub struct Behaviour {
     /// appropriate subscribers.
     router: Router,
 
+    /// The function used to compute the ID of a message.
+    message_id_fn: Box<MessageIdFn>,
+
+    /// Cache of the IDs of the messages seen recently.
+    ///
+    /// This is used to drop duplicate messages. The IDs are scoped to the message topic if the
+    /// [`DedupScope::PerTopic`] deduplication scope is configured.
+    seen_cache: SeenCache,
+
     /// Message sequence number generator.
     message_seqno_generator: Box<dyn MessageSeqNumberGenerator + Send>,
 
-    /// Message author.
+    /// Sequence number generator of the anonymous messages.
+    anonymous_seqno_generator: Box<dyn MessageSeqNumberGenerator + Send>,
+
+    /// Sequence number generator of the messages published with a TTL.
+    ttl_seqno_generator: TtlSequenceNumber,
+
+    /// The author of the messages published without a signer, if any.
+    ///
+    /// This is the stable random author generated when the anonymous author is not rotated.
     message_author: Option<PeerId>,
+
+    /// Per-peer inbound message rate limiter.
+    ///
+    /// This is `None` if no rate limit is configured.
+    rate_limiter: Option<PeerRateLimiter>,
+
+    /// Blacklisted peers.
+    ///
+    /// Messages propagated or authored by these peers are dropped, and no messages are forwarded
+    /// to them.
+    blacklisted_peers: HashSet<PeerId>,
+
+    /// Whether the forwarding of the received messages is paused.
+    ///
+    /// While paused, the received messages are still deduplicated and delivered locally.
+    forwarding_paused: bool,
+
+    /// The number of violations (e.g., invalid messages) committed by each connected peer.
+    peer_violations: HashMap<PeerId, u32>,
+
+    /// The per-topic parameters overriding the global configuration.
+    topic_params: HashMap<TopicHash, TopicParams>,
+
+    /// The authors allowed to publish on each permissioned topic.
+    ///
+    /// Received messages on these topics authored by other peers are dropped.
+    topic_allowed_authors: HashMap<TopicHash, HashSet<PeerId>>,
+
+    /// The number of messages delivered locally on each topic since subscribing to it.
+    topic_message_counts: HashMap<TopicHash, u64>,
+
+    /// The parent topic of the subscribed topics, the messages are also delivered under.
+    topic_parents: HashMap<TopicHash, TopicHash>,
+
+    /// The kind of each connected peer.
+    peer_kinds: HashMap<PeerId, PeerKind>,
+
+    /// The endpoint of the first established connection with each connected peer.
+    peer_endpoints: HashMap<PeerId, ConnectedPoint>,
+
+    /// The statistics of the messages received from each peer.
+    peer_stats: HashMap<PeerId, PeerStats>,
+
+    /// The aggregate RPC frames traffic, across all peers.
+    bandwidth_stats: BandwidthStats,
+
+    /// The subscribed topics reported as having insufficient peers, not yet back to the minimum.
+    insufficient_peers_topics: HashSet<TopicHash>,
+
+    /// The local subscription changes not yet sent to the connected peers.
+    ///
+    /// The changes are batched and sent when the behaviour is polled.
+    pending_subscription_changes: Vec<SubscriptionAction>,
+
+    /// The peers sent the full set of local subscriptions since the pending subscription changes
+    /// were last sent, with the number of pending changes already reflected in it.
+    subscriptions_snapshot_peers: HashMap<PeerId, usize>,
+
+    /// The connected peers the local node received an RPC frame from, from the least to the most
+    /// recently active.
+    peers_by_activity: LinkedHashSet<PeerId>,
+
+    /// The number of frames sent over each connection, not yet taken from the connection handler
+    /// send queue.
+    pending_frames: HashMap<ConnectionId, usize>,
+
+    /// The messages published with [`Behaviour::publish_async`] waiting for capacity in the
+    /// send queues of some of the targeted peers, in publishing order.
+    pending_publishes: VecDeque<PendingPublish>,
+
+    /// The sender of the events to the [`EventStream`], if any.
+    event_sender: Option<mpsc::UnboundedSender<Event>>,
+
+    /// The channel the received messages are delivered to instead of [`Event::Message`] events,
+    /// if any.
+    message_sink: Option<mpsc::Sender<(MessageId, Message, PeerId)>>,
+
+    /// The number of messages dropped because the message sink was full.
+    message_sink_dropped: u64,
+
+    /// The number of received messages dropped because their ID exceeded the maximum length.
+    oversized_message_ids: u64,
+
+    /// The number of messages published by the local node.
+    messages_published: u64,
+
+    /// The number of received messages forwarded to at least one peer.
+    messages_forwarded: u64,
+
+    /// The number of received messages dropped.
+    messages_dropped: u64,
 }
 
 /// Public API.
 impl Behaviour {
     /// Create a new behaviour instance.
-    pub fn new(config: Config) -> Self {
+    ///
+    /// The local peer ID must be the swarm local peer ID: the behaviour does not derive it from a
+    /// key pair. The messages published with a [signer](crate::ConfigBuilder::signer) are
+    /// authored by the signer peer ID instead, which may differ from the local peer ID.
+    pub fn new(local_peer_id: PeerId, config: Config) -> Self {
+        let rate_limiter = config
+            .peer_message_rate()
+            .map(|(max_per_sec, burst)| PeerRateLimiter::new(max_per_sec, burst));
+        let anonymous_seqno_generator: Box<dyn MessageSeqNumberGenerator + Send> =
+            match config.anonymous_seqno() {
+                AnonymousSeqno::None => Box::new(NoSequenceNumber::new()),
+                AnonymousSeqno::RandomPerMessage => Box::new(RandomSequenceNumber::new()),
+            };
+        let message_author = (config.signer().is_none()
+            && config.random_anonymous_author()
+            && !config.rotate_anonymous_author())
+        .then(PeerId::random);
+        let message_seqno_generator = LinearSequenceNumber::new();
+        #[cfg(any(test, feature = "testing"))]
+        let message_seqno_generator = config
+            .fixed_seqno()
+            .map_or(message_seqno_generator, LinearSequenceNumber::with_base);
+
+        let router = Router::default();
+        #[cfg(any(test, feature = "testing"))]
+        let router = config
+            .forward_rng_seed()
+            .map_or(router, Router::with_rng_seed);
+
+        let protocol_id = format!(
+            "/{}/{FLOODSUB_PROTOCOL_VERSION}",
+            config.protocol_id_prefix()
+        );
+        #[cfg(feature = "zstd")]
+        let zstd_protocol_id =
+            new_protocol_id(format!("{protocol_id}/zstd"), FLOODSUB_ZSTD_PROTOCOL_ID);
+        let protocol_id = new_protocol_id(protocol_id, FLOODSUB_PROTOCOL_ID);
+
         Self {
-            config,
+            local_peer_id,
+            protocol_id,
+            #[cfg(feature = "zstd")]
+            zstd_protocol_id,
             swarm_out_events: Default::default(),
+            queue_overloaded: false,
             connections: Default::default(),
-            router: Default::default(),
-            message_seqno_generator: Box::new(LinearSequenceNumber::new()),
-            message_author: None,
+            router,
+            message_id_fn: Box::new(default_message_id_fn),
+            seen_cache: SeenCache::new(&config),
+            message_seqno_generator: Box::new(message_seqno_generator),
+            anonymous_seqno_generator,
+            ttl_seqno_generator: Default::default(),
+            message_author,
+            rate_limiter,
+            blacklisted_peers: Default::default(),
+            forwarding_paused: false,
+            peer_violations: Default::default(),
+            topic_params: Default::default(),
+            topic_allowed_authors: Default::default(),
+            topic_message_counts: Default::default(),
+            topic_parents: Default::default(),
+            peer_kinds: Default::default(),
+            peer_endpoints: Default::default(),
+            peer_stats: Default::default(),
+            bandwidth_stats: Default::default(),
+            insufficient_peers_topics: Default::default(),
+            pending_subscription_changes: Default::default(),
+            subscriptions_snapshot_peers: Default::default(),
+            peers_by_activity: Default::default(),
+            pending_frames: Default::default(),
+            pending_publishes: Default::default(),
+            event_sender: None,
+            message_sink: None,
+            message_sink_dropped: 0,
+            oversized_message_ids: 0,
+            messages_published: 0,
+            messages_forwarded: 0,
+            messages_dropped: 0,
+            config,
         }
     }
 
+    /// Get the local node peer ID, as given to [`Behaviour::new`].
+    ///
+    /// This is the propagation source of the locally published messages. It is also their author,
+    /// unless a [signer](crate::ConfigBuilder::signer) is configured.
+    pub fn local_peer_id(&self) -> &PeerId {
+        &self.local_peer_id
+    }
+
     /// Get a reference to the connection manager.
     pub fn connections(&self) -> &ConnectionManager {
         &self.connections
@@ -127,46 +604,401 @@ impl Behaviour {
         &self.router
     }
 
+    /// Get the number of peers with at least one established connection.
+    pub fn peer_count(&self) -> usize {
+        self.connections.active_peers_count()
+    }
+
+    /// Get the number of topics the local node is subscribed to, including the prefix
+    /// subscriptions.
+    pub fn subscribed_topic_count(&self) -> usize {
+        self.router.subscriptions_count()
+    }
+
+    /// Get the topics the given peer is subscribed to.
+    ///
+    /// Returns `None` if the peer is not subscribed to any topic. Peers are removed when their
+    /// last connection is closed.
+    pub fn peer_topics(&self, peer: &PeerId) -> Option<&BTreeSet<TopicHash>> {
+        self.router.peer_subscriptions(peer)
+    }
+
+    /// Get the kind of pubsub protocol the given peer supports.
+    ///
+    /// Returns `None` if the peer is not connected.
+    pub fn peer_kind(&self, peer: &PeerId) -> Option<PeerKind> {
+        self.peer_kinds.get(peer).copied()
+    }
+
+    /// Get the endpoint of the first established connection with the given peer, i.e., whether
+    /// the local node dialed the peer ([`ConnectedPoint::Dialer`]) or the peer dialed the local
+    /// node ([`ConnectedPoint::Listener`]).
+    ///
+    /// Returns `None` if the peer is not connected.
+    pub fn peer_endpoint(&self, peer: &PeerId) -> Option<ConnectedPoint> {
+        self.peer_endpoints.get(peer).cloned()
+    }
+
+    /// Get the number of events pending in the behaviour queue, to be yielded to the swarm (e.g.,
+    /// the messages to deliver to the application, or the RPC frames to send to the peers).
+    ///
+    /// See [`Config::event_queue_overload_threshold`].
+    pub fn pending_events_count(&self) -> usize {
+        self.swarm_out_events.len()
+    }
+
+    /// Get the pubsub protocol negotiated with the given peer (e.g., `/floodsub/1.0.0`).
+    ///
+    /// This is finer-grained than the [`PeerKind`]: it is the exact protocol string the
+    /// substreams with the peer were negotiated with. Returns `None` if the peer is not connected
+    /// or no substream was negotiated with it yet.
+    pub fn negotiated_protocol(&self, peer: &PeerId) -> Option<&str> {
+        self.connections.peer_negotiated_protocol(peer)
+    }
+
+    /// Get the statistics of the messages received from the given peer.
+    ///
+    /// The statistics are reset when the peer disconnects, unless
+    /// [`Config::retain_peer_stats`] is enabled. Returns `None` if there are no statistics for the
+    /// peer (e.g., the peer never connected).
+    pub fn peer_stats(&self, peer: &PeerId) -> Option<PeerStats> {
+        self.peer_stats.get(peer).copied()
+    }
+
+    /// Get the number of bytes of the RPC frames received from and sent to all the peers, since
+    /// the behaviour was created.
+    pub fn bandwidth_stats(&self) -> BandwidthStats {
+        self.bandwidth_stats
+    }
+
+    /// Get a snapshot of the behaviour state and counters, in a single call.
+    pub fn stats(&self) -> Stats {
+        Stats {
+            connected_peers: self.peer_count(),
+            subscribed_topics: self.subscribed_topic_count(),
+            seen_messages: self.seen_message_count(),
+            messages_published: self.messages_published,
+            messages_forwarded: self.messages_forwarded,
+            messages_dropped: self.messages_dropped,
+            bandwidth: self.bandwidth_stats,
+        }
+    }
+
+    /// Drain the messages ready to be delivered to the application, as `(message ID, message,
+    /// propagation source)` tuples.
+    ///
+    /// This is an alternative to handling the [`Event::Message`] events for applications polling
+    /// the messages on their own schedule: the drained messages are not emitted as events. The
+    /// messages went through the same deduplication, filtering and inbound data transform as the
+    /// ones emitted as events. The other events are left in place.
+    pub fn poll_messages(&mut self) -> impl Iterator<Item = (MessageId, Message, PeerId)> {
+        let mut messages = Vec::new();
+
+        for event in std::mem::take(&mut self.swarm_out_events) {
+            match event {
+                // The messages delivered under a parent topic were also delivered under their
+                // own topic.
+                ToSwarm::GenerateEvent(Event::Message { topic, message, .. })
+                    if &topic != message.topic_hash() => {}
+                ToSwarm::GenerateEvent(Event::Message {
+                    propagation_source,
+                    message_id,
+                    message,
+                    ..
+                }) => messages.push((message_id, message, propagation_source)),
+                event => self.swarm_out_events.push_back(event),
+            }
+        }
+
+        messages.into_iter()
+    }
+
+    /// Deliver the messages to the given channel, as `(message ID, message, propagation source)`
+    /// tuples, instead of emitting [`Event::Message`] events.
+    ///
+    /// This lets applications handle the messages in a dedicated task, apart from the other
+    /// events. The behaviour never waits for the channel: the messages delivered while it is full
+    /// are dropped, and counted in [`Behaviour::message_sink_dropped`]. Once the receiver is
+    /// dropped, the messages are emitted as events again. Setting another channel replaces the
+    /// previous one.
+    pub fn set_message_sink(&mut self, tx: mpsc::Sender<(MessageId, Message, PeerId)>) {
+        self.message_sink = Some(tx);
+    }
+
+    /// Get the number of messages dropped because the [message
+    /// sink](Behaviour::set_message_sink) was full.
+    pub fn message_sink_dropped(&self) -> u64 {
+        self.message_sink_dropped
+    }
+
+    /// Get the number of received messages dropped because their ID exceeded the
+    /// [maximum message ID length](Config::max_message_id_len).
+    pub fn oversized_message_ids(&self) -> u64 {
+        self.oversized_message_ids
+    }
+
+    /// Get a stream of the events emitted by the behaviour, for applications consuming them
+    /// without polling the swarm for events (e.g., while shutting down).
+    ///
+    /// The events queued for the swarm are moved to the stream, and the subsequent events are sent
+    /// to the stream instead of the swarm. The stream ends once the behaviour is dropped. Getting
+    /// another stream ends the previous one; dropping the stream returns the subsequent events to
+    /// the swarm.
+    pub fn event_stream(&mut self) -> EventStream {
+        let (sender, stream) = EventStream::new();
+        self.event_sender = Some(sender);
+
+        for event in std::mem::take(&mut self.swarm_out_events) {
+            match event {
+                ToSwarm::GenerateEvent(event) => {
+                    if let Some(event) = self.send_to_event_stream(event) {
+                        self.swarm_out_events
+                            .push_back(ToSwarm::GenerateEvent(event));
+                    }
+                }
+                event => self.swarm_out_events.push_back(event),
+            }
+        }
+
+        stream
+    }
+
+    /// Get the number of message IDs in the seen messages cache.
+    ///
+    /// With the [`DedupStrategy::BloomFilter`](crate::DedupStrategy::BloomFilter) strategy, this
+    /// is the number of IDs inserted in the filters, not accounting for false positives.
+    pub fn seen_message_count(&self) -> usize {
+        self.seen_cache.len()
+    }
+
+    /// Get the number of messages delivered locally on the topic since subscribing to it.
+    ///
+    /// The counter includes the own messages delivered locally (see
+    /// [`Config::allow_self_origin`]), and it is reset when unsubscribing from the topic.
+    pub fn topic_message_count(&self, topic: &TopicHash) -> u64 {
+        self.topic_message_counts.get(topic).copied().unwrap_or(0)
+    }
+
+    /// Clear the seen messages cache.
+    ///
+    /// The messages seen before clearing the cache are no longer considered duplicates, and they
+    /// will be accepted again if received (e.g., after rejoining the network).
+    pub fn clear_seen_cache(&mut self) {
+        self.seen_cache.clear();
+    }
+
+    /// Replace the function computing the message IDs, e.g., after a protocol upgrade
+    /// negotiation.
+    ///
+    /// The IDs in the seen messages cache were computed with the previous function, and they
+    /// would not match the IDs of the subsequent messages. The cache is therefore cleared: the
+    /// messages seen before the change are no longer considered duplicates.
+    pub fn set_message_id_fn(&mut self, message_id_fn: Box<MessageIdFn>) {
+        self.message_id_fn = message_id_fn;
+        self.clear_seen_cache();
+    }
+
+    /// Add a peer to the blacklist.
+    ///
+    /// Messages propagated or authored by a blacklisted peer are dropped, and no messages are
+    /// published or forwarded to it.
+    pub fn blacklist_peer(&mut self, peer: &PeerId) {
+        if self.blacklisted_peers.insert(*peer) {
+            log::debug!("Peer {peer} has been blacklisted");
+        }
+    }
+
+    /// Remove a peer from the blacklist.
+    pub fn remove_blacklisted_peer(&mut self, peer: &PeerId) {
+        if self.blacklisted_peers.remove(peer) {
+            log::debug!("Peer {peer} has been removed from the blacklist");
+        }
+    }
+
+    /// Pause the forwarding of the received messages (e.g., to shed load during a spike).
+    ///
+    /// While paused, the received messages are still deduplicated and delivered locally, and the
+    /// messages published by the local node are still sent to the peers, but no received message
+    /// is relayed. The messages received while paused are not forwarded on resume.
+    pub fn pause_forwarding(&mut self) {
+        if !self.forwarding_paused {
+            log::debug!("Message forwarding has been paused");
+            self.forwarding_paused = true;
+        }
+    }
+
+    /// Resume the forwarding of the received messages, paused with [`Behaviour::pause_forwarding`].
+    pub fn resume_forwarding(&mut self) {
+        if self.forwarding_paused {
+            log::debug!("Message forwarding has been resumed");
+            self.forwarding_paused = false;
+        }
+    }
+
+    /// Whether the forwarding of the received messages is paused.
+    #[must_use]
+    pub fn is_forwarding_paused(&self) -> bool {
+        self.forwarding_paused
+    }
+
+    /// Set the parameters of the given topic, overriding the global configuration defaults for
+    /// the topic messages, both published and received.
+    pub fn set_topic_params(&mut self, topic: &TopicHash, params: TopicParams) {
+        self.topic_params.insert(topic.clone(), params);
+    }
+
+    /// Remove the parameters of the given topic, restoring the global configuration defaults.
+    pub fn remove_topic_params(&mut self, topic: &TopicHash) {
+        self.topic_params.remove(topic);
+    }
+
+    /// Set the authors allowed to publish on the given topic, making it a permissioned topic.
+    ///
+    /// Received messages on the topic whose `source` is not in the set, or without `source`, are
+    /// dropped. The messages on topics without an allowed authors set are unaffected.
+    ///
+    /// The author is only authenticated by the message signature: combine the set with the
+    /// topic [`TopicParams::require_signatures`] parameter, so unsigned messages are dropped.
+    pub fn set_topic_allowed_authors(&mut self, topic: &TopicHash, authors: HashSet<PeerId>) {
+        self.topic_allowed_authors.insert(topic.clone(), authors);
+    }
+
+    /// Remove the allowed authors set of the given topic, accepting messages from any author.
+    pub fn remove_topic_allowed_authors(&mut self, topic: &TopicHash) {
+        self.topic_allowed_authors.remove(topic);
+    }
+
+    /// Check whether a message on the given topic, propagated by `source`, would be forwarded to
+    /// the given peer.
+    ///
+    /// This accounts for all the forwarding rules: the peer subscriptions (or the
+    /// [`Config::static_forward_peers`] set), the local node subscriptions for the received
+    /// messages (see [`Config::forward_unsubscribed`]), the blacklist, and the propagation source
+    /// (messages are never sent back to it), and whether the forwarding is paused (see
+    /// [`Behaviour::pause_forwarding`]). It does not account for the forward peers subset
+    /// selection (see [`Config::max_forward_peers_per_topic`]).
+    pub fn would_deliver_to(&self, peer: &PeerId, topic: &TopicHash, source: &PeerId) -> bool {
+        // Messages are never sent back to the propagation source.
+        if peer == source {
+            return false;
+        }
+
+        // No received messages are forwarded while the forwarding is paused.
+        if self.forwarding_paused && source != &self.local_peer_id {
+            return false;
+        }
+
+        // Messages propagated by blacklisted peers are dropped, and no messages are forwarded to
+        // blacklisted peers.
+        if self.blacklisted_peers.contains(source) || self.blacklisted_peers.contains(peer) {
+            return false;
+        }
+
+        // Received messages from topics that we are not subscribed to are dropped, unless they are
+        // forwarded. The local node publishes to any topic.
+        if source != &self.local_peer_id
+            && !self.config.forward_unsubscribed()
+            && !self.router.matches_subscription(topic)
+        {
+            return false;
+        }
+
+        // In static mesh mode, messages are forwarded to the connected static peers only.
+        if let Some(static_peers) = self.config.static_forward_peers() {
+            return static_peers.contains(peer)
+                && self.connections.peer_connections_count(peer) > 0;
+        }
+
+        self.router
+            .propagation_routes(topic)
+            .into_iter()
+            .any(|route| &route == peer)
+    }
+
+    /// Get the peers a message published to the given topic would be sent to, without publishing
+    /// anything.
+    ///
+    /// This accounts for the peer subscriptions (or the [`Config::static_forward_peers`] set) and
+    /// the blacklist. It does not account for the forward peers subset selection (see
+    /// [`Config::max_forward_peers_per_topic`] and [`Config::flood_factor`]), which may differ
+    /// for each message: the published message is then sent to a subset of these peers.
+    ///
+    /// If deterministic forwarding is enabled, the peers are sorted by `PeerId`.
+    pub fn would_publish_to(&self, topic: &TopicHash) -> Vec<PeerId> {
+        if let Some(static_peers) = self.config.static_forward_peers() {
+            return self.static_forward_peers(static_peers);
+        }
+
+        let mut peers = self.propagation_candidates(topic);
+        if self.config.deterministic_forwarding() {
+            peers.sort_unstable();
+        }
+
+        peers
+    }
+
     /// Subscribe to topic.
     ///
     /// Returns `Ok(true)` if the subscription was successful, `Ok(false)` if we were already
-    /// subscribed to the topic.
+    /// subscribed to the topic. Subscribing to an already subscribed topic is a no-op: no
+    /// subscription is sent to the peers.
     ///
-    /// Subscribing to a topic publishes immediately the subscription to the network. This requires
-    /// at least one active connection to the network, otherwise the subscription will fail with a
-    /// [`SubscriptionError::SubscriptionPublishFailed`].
+    /// The subscription is sent to the connected peers the next time the swarm is polled, batched
+    /// with the other pending subscription changes (see [`Config::max_subscriptions_per_rpc`]).
+    /// If there are no active connections, the subscription is sent to the peers when the
+    /// connections are established.
     pub fn subscribe<H: Hasher>(&mut self, topic: &Topic<H>) -> Result<bool, SubscriptionError> {
         log::debug!("Subscribing to topic {topic}");
 
         let topic = topic.hash();
 
         if self.router.is_subscribed(&topic) {
+            log::debug!("Already subscribed to topic {topic}");
             return Ok(false);
         }
 
         // TODO: Add a subscription filter.
 
         // Add the subscription to the router.
+        let keep_alive = self.keep_connections_alive();
         self.router.subscribe(topic.clone());
+        self.on_keep_alive_change(keep_alive);
+        self.check_topics_peers();
 
-        // If there are no active connections, as we cannot publish the subscription, return.
-        if self.connections.active_peers_count() == 0 {
+        // If there are no active connections, as we cannot publish the subscription, or the
+        // subscriptions are not advertised, return.
+        if self.connections.active_peers_count() == 0 || self.config.silent_observer() {
             return Ok(true);
         }
 
-        // Publish the subscription to the network.
-        let subscription = SubscriptionAction::subscribe(topic.clone());
-        let frame = Frame::new_with_subscriptions(vec![subscription]);
-
-        for peer in self.connections.active_peers() {
-            if let Err(err) = self.send_rpc_frame(&peer, frame.clone()) {
-                log::debug!("Failed to send topic {topic} subscription to peer {peer}: {err}");
-            }
-        }
+        // Queue the subscription to be published to the network.
+        self.pending_subscription_changes
+            .push(SubscriptionAction::subscribe(topic));
 
         Ok(true)
     }
 
+    /// Subscribe to the topic, also delivering its messages under the given parent topic.
+    ///
+    /// Each message received on the topic is emitted twice: as an [`Event::Message`] event with
+    /// the message topic, and as an aggregated [`Event::Message`] event whose `topic` is the
+    /// parent topic. This lets applications listen on a coarse topic while the publishers use
+    /// fine-grained ones. The parent topic is local to the node: it is neither subscribed to nor
+    /// advertised to the peers. The aggregated events are not delivered to the [message
+    /// sink](Behaviour::set_message_sink), nor returned by [`Behaviour::poll_messages`].
+    ///
+    /// Returns `Ok(true)` if the subscription was successful, `Ok(false)` if we were already
+    /// subscribed to the topic, in which case only its parent topic is replaced.
+    pub fn subscribe_with_parent<H: Hasher>(
+        &mut self,
+        topic: &Topic<H>,
+        parent: TopicHash,
+    ) -> Result<bool, SubscriptionError> {
+        let subscribed = self.subscribe(topic)?;
+        self.topic_parents.insert(topic.hash(), parent);
+        Ok(subscribed)
+    }
+
     /// Unsubscribe from topic.
     ///
     /// Returns `Ok(true)` if the unsubscription was successful, `Ok(false)` if we were not
@@ -183,64 +1015,482 @@ impl Behaviour {
         // TODO: Add a subscription filter.
 
         // Remove the subscription from the router.
+        let keep_alive = self.keep_connections_alive();
         self.router.unsubscribe(&topic);
-
-        // If there are no active connections, as we cannot publish the subscription, return.
-        if self.connections.active_peers_count() == 0 {
+        self.on_keep_alive_change(keep_alive);
+        self.check_topics_peers();
+        self.topic_message_counts.remove(&topic);
+        self.topic_parents.remove(&topic);
+
+        // If there are no active connections, as we cannot publish the subscription, or the
+        // subscriptions are not advertised, return.
+        if self.connections.active_peers_count() == 0 || self.config.silent_observer() {
             return Ok(true);
         }
 
-        // Publish the subscription to the network.
-        let subscription = SubscriptionAction::unsubscribe(topic.clone());
-        let frame = Frame::new_with_subscriptions(vec![subscription]);
+        // Queue the unsubscription to be published to the network.
+        self.pending_subscription_changes
+            .push(SubscriptionAction::unsubscribe(topic));
 
-        for peer in self.connections.active_peers() {
-            if let Err(err) = self.send_rpc_frame(&peer, frame.clone()) {
-                log::debug!("Failed to send topic {topic} unsubscription to peer {peer}: {err}");
+        Ok(true)
+    }
+
+    /// Subscribe to all the topics starting with the given prefix.
+    ///
+    /// Messages whose topic starts with the prefix are accepted and delivered to the application.
+    /// The prefix subscription is advertised to the peers as a subscription to the `<prefix>*`
+    /// topic, and peers running this implementation forward the matching messages to the local
+    /// node.
+    ///
+    /// Prefix subscriptions match the topic strings, so they only work with identity-hashed
+    /// topics (i.e., [`IdentTopic`](crate::IdentTopic)). Hashed topics (e.g.,
+    /// [`Sha256Topic`](crate::Sha256Topic)) never match a prefix.
+    ///
+    /// Returns `Ok(true)` if the subscription was successful, `Ok(false)` if we were already
+    /// subscribed to the prefix.
+    pub fn subscribe_prefix(&mut self, prefix: &str) -> Result<bool, SubscriptionError> {
+        self.subscribe(&prefix_topic(prefix))
+    }
+
+    /// Unsubscribe from a topic prefix.
+    ///
+    /// Returns `Ok(true)` if the unsubscription was successful, `Ok(false)` if we were not
+    /// subscribed to the prefix.
+    pub fn unsubscribe_prefix(&mut self, prefix: &str) -> Result<bool, SubscriptionError> {
+        self.unsubscribe(&prefix_topic(prefix))
+    }
+
+    /// Unsubscribe from all the topics, before shutting down the node.
+    ///
+    /// The unsubscriptions are sent to all the connected peers, so they promptly stop forwarding
+    /// messages to the local node instead of waiting for the connections to close. The swarm must
+    /// be polled afterwards for the unsubscriptions to be sent.
+    pub fn close(&mut self) {
+        log::debug!("Unsubscribing from all topics");
+
+        let topics = self.router.subscriptions().cloned().collect::<Vec<_>>();
+        if topics.is_empty() {
+            return;
+        }
+
+        // Remove the subscriptions from the router.
+        let keep_alive = self.keep_connections_alive();
+        for topic in &topics {
+            self.router.unsubscribe(topic);
+        }
+        self.on_keep_alive_change(keep_alive);
+        self.topic_message_counts.clear();
+        self.topic_parents.clear();
+
+        // If there are no active connections, as we cannot publish the unsubscriptions, or the
+        // subscriptions are not advertised, return.
+        if self.connections.active_peers_count() == 0 || self.config.silent_observer() {
+            return;
+        }
+
+        // Queue the unsubscriptions to be published to the network.
+        self.pending_subscription_changes
+            .extend(topics.into_iter().map(SubscriptionAction::unsubscribe));
+    }
+
+    /// Send the full set of local subscriptions to the given peer.
+    ///
+    /// This recovers the peer state when it missed earlier subscription frames (e.g., after a
+    /// protocol renegotiation). The subscriptions are split into RPC frames carrying at most
+    /// [`Config::max_subscriptions_per_rpc`] subscriptions each. The subscriptions are sent
+    /// automatically when the first connection with a peer is established.
+    ///
+    /// The sent subscriptions reflect all the subscription changes made so far. The changes made
+    /// afterwards are sent to the peer when the behaviour is polled, after these subscriptions.
+    ///
+    /// No subscriptions are sent if the node is a [silent observer](Config::silent_observer).
+    pub fn resend_subscriptions(&mut self, peer: &PeerId) {
+        if self.connections.peer_connections_count(peer) == 0 {
+            log::debug!("Not resending the subscriptions to peer {peer}: not connected");
+            return;
+        }
+
+        if self.config.silent_observer() {
+            log::debug!("Not resending the subscriptions to peer {peer}: silent observer");
+            return;
+        }
+
+        self.subscriptions_snapshot_peers
+            .insert(*peer, self.pending_subscription_changes.len());
+
+        let subscriptions = self
+            .router
+            .subscriptions()
+            .cloned()
+            .map(SubscriptionAction::subscribe)
+            .collect::<Vec<_>>();
+        if !subscriptions.is_empty() {
+            self.send_subscriptions(peer, &subscriptions);
+        }
+    }
+
+    /// Export the topics the local node is subscribed to, including the prefix subscriptions.
+    ///
+    /// The exported topics can be restored after a restart with
+    /// [`Behaviour::import_subscriptions`]. Only identity-hashed topics can be restored from their
+    /// hash (see [`IdentTopic`](crate::IdentTopic)).
+    pub fn export_subscriptions(&self) -> Vec<TopicHash> {
+        self.router.subscriptions().cloned().collect()
+    }
+
+    /// Subscribe to all the given topics, e.g., to restore the subscriptions exported with
+    /// [`Behaviour::export_subscriptions`].
+    ///
+    /// The subscriptions are sent to the connected peers like the ones made with
+    /// [`Behaviour::subscribe`]. Topics the local node is already subscribed to are skipped.
+    pub fn import_subscriptions(&mut self, topics: Vec<Topic<IdentityHash>>) {
+        for topic in topics {
+            if let Err(err) = self.subscribe(&topic) {
+                log::debug!("Failed to import the subscription to topic {topic}: {err}");
             }
         }
+    }
 
-        Ok(true)
+    /// Check whether the local node is subscribed to the topic.
+    pub fn is_subscribed<H: Hasher>(&self, topic: &Topic<H>) -> bool {
+        self.router.is_subscribed(&topic.hash())
     }
 
     /// Publish a message to the network.
+    ///
+    /// Returns the ID of the published message. If there are no peers subscribed to the topic,
+    /// publishing fails with [`PublishError::InsufficientPeers`], unless
+    /// [`Config::allow_publish_with_no_peers`] is enabled.
+    ///
+    /// The local node does not need to be subscribed to the topic. Messages to topics the local
+    /// node is not subscribed to are sent to the peers known to be subscribed to them, but they
+    /// are never delivered locally.
+    ///
+    /// Messages published without a [signer](crate::ConfigBuilder::signer) are anonymous: they
+    /// carry no source, and their sequence number follows the [`Config::anonymous_seqno`] policy.
     pub fn publish<H: Hasher>(
         &mut self,
         topic: &Topic<H>,
         data: impl Into<Vec<u8>>,
-    ) -> Result<(), PublishError> {
+    ) -> Result<MessageId, PublishError> {
         log::debug!("Publishing message to topic {topic}");
 
-        let topic = topic.hash();
+        let seqno = self.next_seqno();
+        self.publish_with_seqno(topic.hash(), Bytes::from(data.into()), seqno)
+    }
 
-        // Check if we are subscribed to the topic.
-        if !self.router.is_subscribed(&topic) {
-            return Err(PublishError::NotSubscribed);
+    /// Publish a message to the network, expiring after the given TTL.
+    ///
+    /// The message expiration time is encoded in its sequence number, so the message stays
+    /// compatible with peers unaware of it. Peers with the topic
+    /// [`TopicParams::drop_expired_messages`] parameter enabled drop the message, instead of
+    /// delivering and forwarding it, once the TTL has expired. The
+    /// expiration time has a resolution of one second, and relies on the peers clocks being
+    /// synchronized.
+    ///
+    /// See [`Behaviour::publish`].
+    pub fn publish_with_ttl<H: Hasher>(
+        &mut self,
+        topic: &Topic<H>,
+        data: impl Into<Vec<u8>>,
+        ttl: Duration,
+    ) -> Result<MessageId, PublishError> {
+        log::debug!("Publishing message to topic {topic} with TTL {ttl:?}");
+
+        let seqno = self.ttl_seqno_generator.next(ttl);
+        self.publish_with_seqno(topic.hash(), Bytes::from(data.into()), Some(seqno))
+    }
+
+    /// Publish the same payload to several topics, e.g., to cross-post it.
+    ///
+    /// Each topic gets its own message, built and published like the ones published with
+    /// [`Behaviour::publish`]. The messages share the payload buffer, unless the outbound data
+    /// transform changes it.
+    ///
+    /// Returns the outcome of the publishing to each topic, in the topics order: publishing to
+    /// a topic fails independently of the others (e.g., if its size limit is exceeded).
+    pub fn publish_multi<H: Hasher>(
+        &mut self,
+        topics: &[Topic<H>],
+        data: impl Into<Vec<u8>>,
+    ) -> Vec<Result<MessageId, PublishError>> {
+        let data = Bytes::from(data.into());
+
+        topics
+            .iter()
+            .map(|topic| {
+                log::debug!("Publishing message to topic {topic}");
+
+                let seqno = self.next_seqno();
+                self.publish_with_seqno(topic.hash(), data.clone(), seqno)
+            })
+            .collect()
+    }
+
+    /// Publish a message directly to the given peers, regardless of their subscriptions.
+    ///
+    /// The message is built like the ones published with [`Behaviour::publish`], but it is only
+    /// sent to the given peers, and it is never delivered locally. An
+    /// [`Event::DeliveryAttempted`] event is emitted for each target peer, reporting whether the
+    /// message was handed to a connection with the peer.
+    ///
+    /// Returns the ID of the published message.
+    pub fn publish_to_peers<H: Hasher>(
+        &mut self,
+        topic: &Topic<H>,
+        data: impl Into<Vec<u8>>,
+        peers: impl IntoIterator<Item = PeerId>,
+    ) -> Result<MessageId, PublishError> {
+        log::debug!("Publishing message to topic {topic} directly to peers");
+
+        let seqno = self.next_seqno();
+        let message = self.new_published_message(topic.hash(), Bytes::from(data.into()), seqno)?;
+        let message_id = self.check_published_message(&message)?;
+
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "publish",
+            topic = %message.topic_hash(),
+            message_id = %message_id
+        )
+        .entered();
+
+        // Mark the message as seen, so it is not processed again if a peer sends it back.
+        let seen_key = self.seen_cache_key(message.topic_hash(), &message_id);
+        self.seen_cache.put(&seen_key);
+        self.messages_published += 1;
+
+        let frame = Frame::new_with_messages(vec![message]);
+        for peer in peers {
+            let succeeded = self.connections.peer_connections_count(&peer) > 0
+                && !self.blacklisted_peers.contains(&peer)
+                && match self.send_rpc_frame(&peer, frame.clone()) {
+                    Ok(()) => true,
+                    Err(err) => {
+                        log::debug!("Failed to send message to peer {peer}: {err}");
+                        false
+                    }
+                };
+
+            self.emit_behaviour_event(Event::DeliveryAttempted {
+                message_id: message_id.clone(),
+                peer_id: peer,
+                succeeded,
+            });
         }
 
-        let propagation_peers: Vec<PeerId> =
-            self.router.propagation_routes(&topic).into_iter().collect();
+        Ok(message_id)
+    }
 
-        // Check if we have enough connections to publish the message.
-        if propagation_peers.is_empty() {
-            return Err(PublishError::MessagePublishFailed(
-                SendError::InsufficientPeers,
-            ));
-        }
-
-        // Build the message.
-        let data = data.into();
-        let author = self.message_author;
-        let seqno = self.message_seqno_generator.next();
-
-        let message = {
-            let mut msg = Message::new(topic, data);
-            msg.set_source(author);
-            msg.set_sequence_number(seqno);
-            msg
+    /// Publish a message to the network, waiting for capacity in the targeted peers send queues.
+    ///
+    /// The message is built and delivered locally like the ones published with
+    /// [`Behaviour::publish`], and it is immediately queued to the targeted peers with capacity
+    /// in their send queues. The peers having reached the
+    /// [`Config::max_pending_frames_per_peer`] limit are retried as their connection handlers
+    /// take frames from their queues. Without a limit, the message is queued to all the peers
+    /// immediately.
+    ///
+    /// The returned future resolves to the ID of the published message once it is queued to all
+    /// the targeted peers, or to [`PublishError::Timeout`] if the timeout elapses first.
+    pub fn publish_async<H: Hasher>(
+        &mut self,
+        topic: &Topic<H>,
+        data: impl Into<Vec<u8>>,
+        timeout: Duration,
+    ) -> PublishFuture {
+        log::debug!("Publishing message to topic {topic}, waiting for send queues capacity");
+
+        let seqno = self.next_seqno();
+        let published = self
+            .new_published_and_local_message(topic.hash(), Bytes::from(data.into()), seqno)
+            .and_then(|(message, local_message)| {
+                let message_id = self.check_published_message(&message)?;
+
+                #[cfg(feature = "tracing")]
+                let _span = tracing::debug_span!(
+                    "publish",
+                    topic = %message.topic_hash(),
+                    message_id = %message_id
+                )
+                .entered();
+
+                let peers = self.start_publish(&message_id, &message, local_message)?;
+                Ok((message_id, Frame::new_with_messages(vec![message]), peers))
+            });
+
+        match published {
+            Ok((message_id, frame, peers)) => {
+                let (pending, future) = PendingPublish::new(message_id, frame, peers, timeout);
+                self.pending_publishes.push_back(pending);
+                self.process_pending_publishes();
+                future
+            }
+            Err(err) => PublishFuture::ready(Err(err)),
+        }
+    }
+
+    /// Publish a pre-constructed message to the network.
+    ///
+    /// Unlike [`Behaviour::publish`], the message sequence number is not set, and the outbound
+    /// data transform is not applied. This is useful to replay messages or to bridge them from
+    /// another pubsub instance. The frame size limit and the duplicates check still apply.
+    ///
+    /// The signed messages are sent as is. If a [signer](crate::ConfigBuilder::signer) is
+    /// configured, the unsigned messages without source, or authored by the signer, are authored
+    /// by the signer and signed before being sent. The unsigned messages of other authors are sent
+    /// as is.
+    ///
+    /// Returns the ID of the published message.
+    pub fn publish_raw(&mut self, mut message: Message) -> Result<MessageId, PublishError> {
+        log::debug!("Publishing raw message to topic {}", message.topic_str());
+
+        // Sign the unsigned message, if a signer is configured and the message is its own.
+        if let Some(signer) = self.config.signer() {
+            let author = signer.author();
+            if message.signature().is_none() && message.source().unwrap_or(author) == author {
+                message.set_source(Some(author));
+
+                let omit_inlined_key = self.config.inline_public_key_if_possible();
+                signer.sign(&mut message, omit_inlined_key).map_err(|err| {
+                    log::debug!("Failed to sign raw message: {err}");
+                    PublishError::SigningError
+                })?;
+            }
+        }
+
+        // The message data is already transformed. Apply the inbound data transform to deliver
+        // the message to the local node, if configured.
+        let local_message = if self.delivers_published_locally(message.topic_hash()) {
+            let local_message = self
+                .config
+                .data_transform()
+                .inbound_transform(message.clone())
+                .map_err(|err| {
+                    log::debug!("Failed to transform raw message data: {err}");
+                    PublishError::TransformFailed
+                })?;
+            Some(local_message)
+        } else {
+            None
         };
 
-        // TODO: Sing the message.
+        self.publish_message(message, local_message)
+    }
+
+    /// Build, sign and publish a message with the given sequence number.
+    fn publish_with_seqno(
+        &mut self,
+        topic: TopicHash,
+        data: Bytes,
+        seqno: Option<u64>,
+    ) -> Result<MessageId, PublishError> {
+        let (message, local_message) = self.new_published_and_local_message(topic, data, seqno)?;
+        self.publish_message(message, local_message)
+    }
+
+    /// Build and sign a message with the given sequence number.
+    ///
+    /// Returns the message, and a copy of it with the original data if it must be delivered to
+    /// the local node.
+    fn new_published_and_local_message(
+        &mut self,
+        topic: TopicHash,
+        data: Bytes,
+        seqno: Option<u64>,
+    ) -> Result<(Message, Option<Message>), PublishError> {
+        // Keep the original data if the message must be delivered to the local node.
+        let local_data = self
+            .delivers_published_locally(&topic)
+            .then(|| data.clone());
+        let message = self.new_published_message(topic, data, seqno)?;
+
+        // Keep a copy of the message, with the original data, to deliver to the local node.
+        let local_message = local_data.map(|local_data| {
+            let mut local_message = message.clone();
+            local_message.set_data_bytes(local_data);
+            local_message
+        });
+
+        Ok((message, local_message))
+    }
+
+    /// Get the sequence number of the next published message.
+    ///
+    /// The messages without a stable author follow the anonymous sequence number policy.
+    fn next_seqno(&mut self) -> Option<u64> {
+        if self.config.signer().is_none() && self.message_author.is_none() {
+            self.anonymous_seqno_generator.next()
+        } else {
+            self.message_seqno_generator.next()
+        }
+    }
+
+    /// Build a message to publish: apply the outbound data transform, set the message author and
+    /// sequence number, and sign it, if a signer is configured.
+    fn new_published_message(
+        &self,
+        topic: TopicHash,
+        data: Bytes,
+        seqno: Option<u64>,
+    ) -> Result<Message, PublishError> {
+        // Apply the outbound data transform.
+        let data = self
+            .config
+            .data_transform()
+            .outbound_transform_bytes(&topic, data)
+            .map_err(|err| {
+                log::debug!("Failed to transform message data to topic {topic}: {err}");
+                PublishError::TransformFailed
+            })?;
+
+        // Build the message. If a signer is configured, the message is authored by the signer.
+        // Otherwise, the message carries the random anonymous author, if configured.
+        let signer = self.config.signer();
+        let author = signer
+            .map(Signer::author)
+            .or(self.message_author)
+            .or_else(|| {
+                (self.config.random_anonymous_author() && self.config.rotate_anonymous_author())
+                    .then(PeerId::random)
+            });
+
+        let mut message = Message::new(topic.clone(), Vec::new());
+        message.set_data_bytes(data);
+        message.set_source(author);
+        message.set_sequence_number(seqno);
+
+        // Sign the message, if a signer is configured.
+        if let Some(signer) = signer {
+            let omit_inlined_key = self.config.inline_public_key_if_possible();
+            signer.sign(&mut message, omit_inlined_key).map_err(|err| {
+                log::debug!("Failed to sign message to topic {topic}: {err}");
+                PublishError::SigningError
+            })?;
+        }
+
+        Ok(message)
+    }
+
+    /// Send a message, ready to be published, to the topic's subscribed peers.
+    ///
+    /// If given, the local message is delivered to the local node.
+    fn publish_message(
+        &mut self,
+        message: Message,
+        local_message: Option<Message>,
+    ) -> Result<MessageId, PublishError> {
+        let message_id = self.check_published_message(&message)?;
+
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "publish",
+            topic = %message.topic_hash(),
+            message_id = %message_id
+        )
+        .entered();
+
+        let propagation_peers = self.start_publish(&message_id, &message, local_message)?;
 
         let frame = Frame::new_with_messages(vec![message]);
         for peer in propagation_peers {
@@ -249,13 +1499,151 @@ impl Behaviour {
             }
         }
 
-        Ok(())
+        Ok(message_id)
+    }
+
+    /// Check there are enough peers to publish the message to, mark the message as seen and
+    /// deliver it to the local node, if configured.
+    ///
+    /// Returns the peers to propagate the message to.
+    fn start_publish(
+        &mut self,
+        message_id: &MessageId,
+        message: &Message,
+        local_message: Option<Message>,
+    ) -> Result<Vec<PeerId>, PublishError> {
+        let topic = message.topic();
+        let seen_key = self.seen_cache_key(&topic, message_id);
+
+        // Check if we have enough connections to publish the message.
+        let propagation_peers = self.forward_peers(&topic, message_id);
+        if propagation_peers.is_empty() && !self.config.allow_publish_with_no_peers() {
+            return Err(PublishError::InsufficientPeers);
+        }
+
+        // Mark the message as seen, so it is not processed again if a peer sends it back.
+        self.seen_cache.put(&seen_key);
+        self.messages_published += 1;
+
+        // Deliver the message, with the original data, to the local node, if configured.
+        if let Some(local_message) = local_message {
+            *self.topic_message_counts.entry(topic.clone()).or_default() += 1;
+            self.deliver_message(self.local_peer_id, None, message_id.clone(), local_message);
+        }
+
+        Ok(propagation_peers)
+    }
+
+    /// Queue the messages published with [`Behaviour::publish_async`] to the targeted peers with
+    /// capacity in their send queues, resolving the futures of the messages queued to all their
+    /// peers.
+    ///
+    /// The messages are processed in publishing order, so the messages sent to a peer keep their
+    /// order.
+    fn process_pending_publishes(&mut self) {
+        for mut pending in std::mem::take(&mut self.pending_publishes) {
+            if pending.is_canceled() {
+                continue;
+            }
+
+            let mut remaining_peers = Vec::new();
+            for peer in std::mem::take(&mut pending.peers) {
+                // Stop waiting for the peers disconnected in the meantime.
+                if self.connections.peer_connections_count(&peer) == 0 {
+                    continue;
+                }
+
+                if !self.has_send_capacity(&peer) {
+                    remaining_peers.push(peer);
+                    continue;
+                }
+
+                if let Err(err) = self.send_rpc_frame(&peer, pending.frame.clone()) {
+                    log::debug!("Failed to send message to peer {peer}: {err}");
+                }
+            }
+
+            if remaining_peers.is_empty() {
+                pending.complete();
+            } else {
+                pending.peers = remaining_peers;
+                self.pending_publishes.push_back(pending);
+            }
+        }
+    }
+
+    /// Whether the peer has not reached the [`Config::max_pending_frames_per_peer`] limit.
+    fn has_send_capacity(&self, peer: &PeerId) -> bool {
+        let Some(max_pending_frames) = self.config.max_pending_frames_per_peer() else {
+            return true;
+        };
+
+        let pending_frames = self
+            .connections
+            .peer_established_connections(peer)
+            .iter()
+            .filter_map(|connection| self.pending_frames.get(connection))
+            .sum::<usize>();
+        pending_frames < max_pending_frames
+    }
+
+    /// Check the message can be published: it fits in a frame, it is signed if the topic
+    /// requires it, and it was not already published.
+    ///
+    /// Returns the ID of the message.
+    fn check_published_message(&self, message: &Message) -> Result<MessageId, PublishError> {
+        let topic = message.topic_hash();
+
+        // Check the message fits in a frame, and the topic size limit.
+        if message.encoded_len() > self.max_transmit_size(topic) {
+            return Err(PublishError::MessageTooLarge);
+        }
+
+        // Check the message is signed, if the topic requires it.
+        if self.requires_signatures(topic) && message.signature().is_none() {
+            return Err(PublishError::SigningError);
+        }
+
+        // Check the message ID length, before it reaches the seen messages cache.
+        let message_id = (self.message_id_fn)(message);
+        if !self.is_valid_message_id(&message_id) {
+            return Err(PublishError::MessageIdTooLarge);
+        }
+
+        // Check the message was not already published.
+        let seen_key = self.seen_cache_key(topic, &message_id);
+        if self.seen_cache.contains_key(&seen_key) {
+            return Err(PublishError::Duplicate);
+        }
+
+        Ok(message_id)
     }
 }
 
 /// Connection handling.
 impl Behaviour {
     fn on_connection_established(&mut self, event: ConnectionEstablished) {
+        match event.endpoint {
+            ConnectedPoint::Dialer { address, .. } => {
+                self.connections.register_outbound(
+                    event.connection_id,
+                    event.peer_id,
+                    address.clone(),
+                );
+            }
+            ConnectedPoint::Listener {
+                local_addr,
+                send_back_addr,
+            } => {
+                self.connections.register_inbound(
+                    event.connection_id,
+                    event.peer_id,
+                    local_addr.clone(),
+                    send_back_addr.clone(),
+                );
+            }
+        }
+
         // Event's `other_established` is the number of connections existing before the connection
         // was established.
         self.connections
@@ -269,20 +1657,19 @@ impl Behaviour {
         );
 
         // If this is the first connection with the peer, send our subscriptions to the peer.
-        // The peer will be added to the router when the subscriptions are received.
+        // The peer will be added to the router when its subscriptions are received.
         if connections_count == 1 {
             log::debug!("Connection established with {}", event.peer_id);
 
-            let subscriptions = self
-                .router
-                .subscriptions()
-                .cloned()
-                .map(SubscriptionAction::subscribe);
-            let frame = Frame::new_with_subscriptions(subscriptions);
+            let active_peers = self.connections.active_peers_count();
+            self.on_active_peers_count_change(active_peers - 1, active_peers);
 
-            if let Err(err) = self.send_rpc_frame(&event.peer_id, frame) {
-                log::warn!("Failed to send subscriptions to {}: {}", event.peer_id, err);
-            }
+            self.peer_kinds.insert(event.peer_id, PeerKind::Floodsub);
+            self.peer_endpoints
+                .insert(event.peer_id, event.endpoint.clone());
+            self.peer_stats.entry(event.peer_id).or_default();
+
+            self.resend_subscriptions(&event.peer_id);
         }
     }
 
@@ -301,12 +1688,233 @@ impl Behaviour {
             "Peer connections count should match the remaining established connections"
         );
 
-        // If there are no more connections with the peer, remove the peer from the router.
-        if peer_connections == 0 {
-            log::debug!("No connections remaining for peer {}", event.peer_id);
+        // The frames sent over the closed connection are no longer pending. The frames still
+        // queued for it are sent over another connection with the peer, if any.
+        self.pending_frames.remove(&event.connection_id);
+        self.retarget_queued_frames(&event.peer_id, &event.connection_id);
+
+        // If there are no more connections with the peer, remove all the peer state.
+        if peer_connections == 0 {
+            log::debug!("No connections remaining for peer {}", event.peer_id);
+
+            let active_peers = self.connections.active_peers_count();
+            self.on_active_peers_count_change(active_peers + 1, active_peers);
+
+            self.router.remove_peer(&event.peer_id);
+            self.check_topics_peers();
+
+            if let Some(rate_limiter) = self.rate_limiter.as_mut() {
+                rate_limiter.remove_peer(&event.peer_id);
+            }
+
+            self.peer_violations.remove(&event.peer_id);
+            self.peer_kinds.remove(&event.peer_id);
+            self.peer_endpoints.remove(&event.peer_id);
+            self.peers_by_activity.remove(&event.peer_id);
+
+            if !self.config.retain_peer_stats() {
+                self.peer_stats.remove(&event.peer_id);
+            }
+
+            // Drop the frames still queued for the peer, they can no longer be delivered.
+            self.swarm_out_events.retain(|event_out| {
+                !matches!(
+                    event_out,
+                    ToSwarm::NotifyHandler { peer_id, .. } if peer_id == &event.peer_id
+                )
+            });
+
+            // Stop waiting for the peer capacity to complete the pending publishes.
+            self.process_pending_publishes();
+        }
+    }
+
+    /// Queue the frames queued for the closed connection with the peer to another connection
+    /// with the peer, if any. Otherwise, the frames are dropped.
+    fn retarget_queued_frames(&mut self, peer: &PeerId, closed: &ConnectionId) {
+        let remaining = self
+            .connections
+            .peer_established_connections(peer)
+            .first()
+            .copied();
+
+        let mut retargeted_frames = 0;
+        self.swarm_out_events.retain_mut(|event_out| {
+            let ToSwarm::NotifyHandler {
+                peer_id,
+                handler,
+                event,
+            } = event_out
+            else {
+                return true;
+            };
+            if peer_id != peer || !matches!(handler, NotifyHandler::One(id) if id == closed) {
+                return true;
+            }
+
+            match (remaining, event) {
+                (Some(connection), HandlerCommand::SendFrame(_)) => {
+                    *handler = NotifyHandler::One(connection);
+                    retargeted_frames += 1;
+                    true
+                }
+                _ => false,
+            }
+        });
+
+        if let Some(connection) = remaining.filter(|_| retargeted_frames > 0) {
+            *self.pending_frames.entry(connection).or_default() += retargeted_frames;
+        }
+    }
+
+    /// Account for the frames taken from the send queue of a connection, and queue the pending
+    /// published messages to the connection peer if it has capacity again.
+    fn on_frames_dequeued(&mut self, connection: &ConnectionId, count: usize) {
+        if let Some(pending_frames) = self.pending_frames.get_mut(connection) {
+            *pending_frames = pending_frames.saturating_sub(count);
+        }
+
+        self.process_pending_publishes();
+    }
+
+    /// Record the kind of the given peer, emitting a [`Event::GossipsubPeerDetected`] event the
+    /// first time a gossipsub peer is detected.
+    fn on_peer_kind_detected(&mut self, peer: &PeerId, kind: PeerKind) {
+        // The peer kind can be reported by the handler of any of the peer connections.
+        let Some(peer_kind) = self.peer_kinds.get_mut(peer) else {
+            return;
+        };
+
+        if *peer_kind == kind {
+            return;
+        }
+        *peer_kind = kind;
+
+        if kind == PeerKind::Gossipsub {
+            log::debug!("Gossipsub peer detected: {peer}");
+            self.emit_behaviour_event(Event::GossipsubPeerDetected { peer_id: *peer });
+        }
+    }
+
+    /// Emit a [`Event::PeerCountThreshold`] event for every configured threshold crossed when the
+    /// number of connected peers changes from `previous` to `current`.
+    fn on_active_peers_count_change(&mut self, previous: usize, current: usize) {
+        let crossed = self
+            .config
+            .peer_count_thresholds()
+            .iter()
+            .filter_map(|&threshold| {
+                if previous < threshold && current >= threshold {
+                    Some(true)
+                } else if previous >= threshold && current < threshold {
+                    Some(false)
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        for crossed_up in crossed {
+            self.emit_behaviour_event(Event::PeerCountThreshold {
+                count: current,
+                crossed_up,
+            });
+        }
+    }
+
+    /// Whether the connections should be kept alive regardless of the idle timeout.
+    ///
+    /// If no [`Config::idle_timeout`] is configured, the connections are kept alive as long as we
+    /// are subscribed to at least one topic.
+    fn keep_connections_alive(&self) -> bool {
+        self.config.idle_timeout().is_none() && self.router.subscriptions().next().is_some()
+    }
+
+    /// Notify all the connection handlers if the connections keep-alive status changed from
+    /// `previous`.
+    fn on_keep_alive_change(&mut self, previous: bool) {
+        let keep_alive = self.keep_connections_alive();
+        if keep_alive == previous {
+            return;
+        }
+
+        let connections = self.connections.peer_connections().collect::<Vec<_>>();
+        for (peer, connection) in connections {
+            self.emit_handler_event(
+                &peer,
+                HandlerCommand::KeepAlive(keep_alive),
+                NotifyHandler::One(connection),
+            );
+        }
+    }
+
+    /// Create a new connection handler for a connection with the given peer.
+    #[cfg_attr(not(feature = "debug"), allow(unused_variables))]
+    fn new_handler(&self, peer: PeerId) -> Handler {
+        let idle_timeout = self
+            .config
+            .idle_timeout()
+            .unwrap_or_else(|| self.config.connection_idle_timeout());
+
+        let mut handler = Handler::new(
+            self.protocol_id.clone(),
+            self.config.max_frame_size(),
+            self.config.max_inbound_substreams(),
+            idle_timeout,
+            self.config.negotiation_timeout(),
+        )
+        .with_substream_mode(self.config.substream_mode());
+        if self.keep_connections_alive() {
+            handler.on_behaviour_event(HandlerCommand::KeepAlive(true));
+        }
+
+        #[cfg(feature = "zstd")]
+        if self.config.compress_rpc_frames() {
+            handler = handler.with_zstd_protocol(self.zstd_protocol_id.clone());
+        }
+
+        #[cfg(feature = "debug")]
+        if let Some(on_raw_rpc) = self.config.on_raw_rpc() {
+            handler = handler.with_inbound_inspector(on_raw_rpc.for_peer(peer));
+        }
+
+        handler
+    }
+
+    /// Update the statistics of the given peer, if it is tracked.
+    fn update_peer_stats(&mut self, peer: &PeerId, update: impl FnOnce(&mut PeerStats)) {
+        if let Some(stats) = self.peer_stats.get_mut(peer) {
+            update(stats);
+        }
+    }
+
+    /// Record a violation committed by the peer.
+    ///
+    /// When the peer reaches the configured disconnect threshold, close all the connections with
+    /// the peer and emit a [`Event::PeerDisconnected`] event.
+    fn on_peer_violation(&mut self, peer: &PeerId, reason: DisconnectReason) {
+        let Some(threshold) = self.config.disconnect_threshold() else {
+            return;
+        };
+
+        let violations = self.peer_violations.entry(*peer).or_default();
+        *violations = violations.saturating_add(1);
 
-            self.router.remove_peer(&event.peer_id);
+        // Only request the disconnection once, when the threshold is reached.
+        if *violations != threshold {
+            return;
         }
+
+        log::debug!("Disconnecting peer {peer}: {violations} violations ({reason})");
+
+        self.swarm_out_events.push_back(ToSwarm::CloseConnection {
+            peer_id: *peer,
+            connection: CloseConnection::All,
+        });
+        self.emit_behaviour_event(Event::PeerDisconnected {
+            peer_id: *peer,
+            reason,
+        });
     }
 
     fn on_connection_address_change(&mut self, event: AddressChange) {
@@ -323,8 +1931,84 @@ impl Behaviour {
     /// This function will queue the event to be emitted to the application. It will be emitted
     /// when the swarm is polled.
     fn emit_behaviour_event(&mut self, event: Event) {
+        let Some(event) = self.send_to_event_stream(event) else {
+            return;
+        };
+
         self.swarm_out_events
             .push_back(ToSwarm::GenerateEvent(event));
+        self.check_queue_overload();
+    }
+
+    /// Deliver a message to the application: to the message sink, if set, otherwise as an
+    /// [`Event::Message`] event.
+    ///
+    /// The messages are dropped if the sink is full, so the behaviour never blocks. If the sink
+    /// was closed, it is removed and the messages are emitted as events again.
+    fn deliver_message(
+        &mut self,
+        propagation_source: PeerId,
+        propagation_endpoint: Option<Endpoint>,
+        message_id: MessageId,
+        message: Message,
+    ) {
+        let mut delivery = (message_id, message, propagation_source);
+        if let Some(sink) = self.message_sink.as_mut() {
+            match sink.try_send(delivery) {
+                Ok(()) => return,
+                Err(err) if err.is_full() => {
+                    let (message_id, ..) = err.into_inner();
+                    log::debug!("Dropped message {message_id}: the message sink is full");
+                    self.message_sink_dropped += 1;
+                    return;
+                }
+                Err(err) => {
+                    log::debug!("Message sink closed, delivering the messages as events");
+                    self.message_sink = None;
+                    delivery = err.into_inner();
+                }
+            }
+        }
+
+        let (message_id, message, propagation_source) = delivery;
+        let aggregated =
+            self.topic_parents
+                .get(message.topic_hash())
+                .map(|parent| Event::Message {
+                    propagation_source,
+                    propagation_endpoint,
+                    message_id: message_id.clone(),
+                    topic: parent.clone(),
+                    message: message.clone(),
+                });
+
+        self.emit_behaviour_event(Event::Message {
+            propagation_source,
+            propagation_endpoint,
+            message_id,
+            topic: message.topic(),
+            message,
+        });
+        if let Some(event) = aggregated {
+            self.emit_behaviour_event(event);
+        }
+    }
+
+    /// Send the event to the [`EventStream`], if any.
+    ///
+    /// Returns the event back if there is no stream, or if it was dropped.
+    fn send_to_event_stream(&mut self, event: Event) -> Option<Event> {
+        let Some(sender) = &self.event_sender else {
+            return Some(event);
+        };
+
+        match sender.unbounded_send(event) {
+            Ok(()) => None,
+            Err(err) => {
+                self.event_sender = None;
+                Some(err.into_inner())
+            }
+        }
     }
 
     /// Emit a event to the connection handlers.
@@ -337,6 +2021,37 @@ impl Behaviour {
             event,
             handler,
         });
+        self.check_queue_overload();
+    }
+
+    /// Emit an [`Event::QueueOverloaded`] event if the events queue crossed the configured
+    /// threshold.
+    ///
+    /// The event is queued in front of the pending events, so the application learns about the
+    /// overload on the next poll.
+    fn check_queue_overload(&mut self) {
+        let Some(threshold) = self.config.event_queue_overload_threshold() else {
+            return;
+        };
+
+        // The queue drained since the overload was reported.
+        let pending = self.swarm_out_events.len();
+        if pending <= threshold {
+            self.queue_overloaded = false;
+            return;
+        }
+
+        if self.queue_overloaded {
+            return;
+        }
+
+        log::debug!("Events queue overloaded: {pending} pending events");
+
+        self.queue_overloaded = true;
+        if let Some(event) = self.send_to_event_stream(Event::QueueOverloaded { pending }) {
+            self.swarm_out_events
+                .push_front(ToSwarm::GenerateEvent(event));
+        }
     }
 }
 
@@ -347,23 +2062,65 @@ impl Behaviour {
     /// This function is called when a peer sends us an RPC frame. The frame is validated and
     /// converted to the appropriate messages and subscriptions.
     fn on_received_rpc_frame(&mut self, src: &PeerId, frame: RpcProto) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("inbound_rpc", peer_id = %src).entered();
+
+        if !self.peers_by_activity.to_back(src) {
+            self.peers_by_activity.insert(*src);
+        }
+
         // First: Validate the RPC frame.
         if let Err(err) = validate_rpc_proto(&frame) {
             log::trace!("Received invalid RPC frame from {}: {}", src, err);
             return;
         }
 
-        // Second: Validate, sanitize and convert protobuf into messages.
-        let messages = frame.publish.into_iter().filter_map(|msg| {
-            if let Err(err) = validate_message_proto(&msg) {
-                log::trace!("Received invalid message from {}: {}", src, err);
-                return None;
+        // Reject the frames carrying too many subscriptions as a whole, before processing any of
+        // their entries.
+        if self
+            .config
+            .max_subscriptions_per_incoming_rpc()
+            .is_some_and(|max_subscriptions| frame.subscriptions.len() > max_subscriptions)
+        {
+            log::trace!(
+                "Rejected RPC frame from {src} carrying {} subscriptions",
+                frame.subscriptions.len()
+            );
+            self.update_peer_stats(src, |stats| stats.rejected_rpcs += 1);
+            self.on_peer_violation(src, DisconnectReason::InvalidMessages);
+            return;
+        }
+
+        // Gossipsub control messages (e.g., GRAFT, PRUNE, IHAVE, IWANT and, since gossipsub v1.2,
+        // IDONTWANT) sent by gossipsub peers are not supported by floodsub. Ignore them.
+        if frame.control.is_some() {
+            log::trace!("Ignoring control message received from {}", src);
+        }
+
+        // Second: Validate and convert protobuf into messages. The message protos are kept
+        // verbatim, so they are forwarded unchanged and their signatures remain valid.
+        let mut invalid_messages = 0;
+        let mut messages = Vec::with_capacity(frame.publish.len());
+        for msg in frame.publish {
+            match validate_message_proto(&msg) {
+                Ok(()) => messages.push(Message::from(msg)),
+                Err(err) => {
+                    log::trace!("Received invalid message from {}: {}", src, err);
+                    invalid_messages += 1;
+
+                    if matches!(err, MessageValidationError::EmptyTopic) {
+                        self.on_topicless_message(msg);
+                    }
+                }
             }
+        }
 
-            Some(Into::<Message>::into(msg))
-        });
+        for _ in 0..invalid_messages {
+            self.update_peer_stats(src, |stats| stats.invalid_messages += 1);
+            self.on_peer_violation(src, DisconnectReason::InvalidMessages);
+        }
 
-        self.handle_received_messages(src, messages);
+        self.handle_received_messages(src, messages.into_iter());
 
         // Third: Validate, sanitize and convert protobuf  into subscription actions.
         let subscriptions = frame.subscriptions.into_iter().filter_map(|sub| {
@@ -383,54 +2140,139 @@ impl Behaviour {
     /// This function is called when a peer sends us a message. The messages are filtered and
     /// forwarded to the appropriate subscribers.
     fn handle_received_messages(&mut self, src: &PeerId, messages: impl Iterator<Item = Message>) {
-        // Filter out messages from topics that we are not subscribed to.
-        let messages = messages.filter(|msg| self.router.is_subscribed(&msg.topic()));
+        self.seen_cache.clear_expired_entries();
+
+        // Filter out the messages that must be dropped (e.g., messages that we have already
+        // seen, or messages from topics that we are not subscribed to).
+        let mut accepted = Vec::new();
+        for msg in messages {
+            let message_id = (self.message_id_fn)(&msg);
+
+            if let Err(reason) = self.filter_received_message(src, &message_id, &msg) {
+                log::trace!("Dropped message {message_id} received from {src}: {reason}");
+                self.messages_dropped += 1;
+
+                match reason {
+                    DropReason::InvalidSignature | DropReason::InvalidTopic => {
+                        self.update_peer_stats(src, |stats| stats.invalid_messages += 1);
+                        self.on_peer_violation(src, DisconnectReason::InvalidMessages)
+                    }
+                    DropReason::RateLimited => {
+                        self.update_peer_stats(src, |stats| stats.rate_limit_hits += 1);
+                        self.on_peer_violation(src, DisconnectReason::RateLimited)
+                    }
+                    DropReason::Duplicate => {
+                        self.update_peer_stats(src, |stats| stats.duplicates += 1);
+                    }
+                    DropReason::MessageIdTooLarge => {
+                        self.oversized_message_ids += 1;
+                    }
+                    _ => {}
+                }
 
-        // Filter out messages that we have already seen.
-        // TODO: Filter seen messages (e.g., implement a seen cache)
+                if self.config.emit_dropped_events() {
+                    self.emit_behaviour_event(Event::MessageDropped { message_id, reason });
+                }
+                continue;
+            }
 
-        // Validate the messages.
-        // TODO: Add message validation logic (e.g., check the message signature).
+            accepted.push((message_id, msg));
+        }
 
-        let messages = messages.collect::<Vec<_>>();
+        // Apply the inbound data transform and emit the messages to the application. The raw
+        // messages, and not the transformed ones, are forwarded.
+        let mut messages = Vec::with_capacity(accepted.len());
+        for (message_id, msg) in accepted {
+            // Forward the messages from topics that we are not subscribed to, without delivering
+            // them to the application.
+            if !self.router.matches_subscription(msg.topic_hash()) {
+                log::trace!(
+                    "Forwarding message {message_id} from {src} to unsubscribed topic {}",
+                    msg.topic_str()
+                );
+                messages.push((message_id, msg));
+                continue;
+            }
 
-        // If there are no messages to forward, return.
-        if messages.is_empty() {
-            return;
-        }
+            let transformed = match self.config.data_transform().inbound_transform(msg.clone()) {
+                Ok(transformed) => transformed,
+                Err(err) => {
+                    log::debug!("Failed to transform message {message_id} from {src}: {err}");
+                    self.messages_dropped += 1;
+                    if self.config.emit_dropped_events() {
+                        self.emit_behaviour_event(Event::MessageDropped {
+                            message_id,
+                            reason: DropReason::TransformFailed,
+                        });
+                    }
+                    continue;
+                }
+            };
 
-        // Emit the messages to the application.
-        for msg in messages.iter() {
             log::trace!("Received message from {src} to topic {}", msg.topic_str());
-            self.emit_behaviour_event(Event::Message {
-                source: *src,
-                topic: msg.topic(),
-                message: msg.clone(),
-            })
+            self.update_peer_stats(src, |stats| stats.messages_delivered += 1);
+            *self.topic_message_counts.entry(msg.topic()).or_default() += 1;
+            let endpoint = self
+                .peer_endpoints
+                .get(src)
+                .map(ConnectedPoint::to_endpoint);
+            self.deliver_message(*src, endpoint, message_id.clone(), transformed);
+
+            messages.push((message_id, msg));
+        }
+
+        // If there are no messages to forward, or the forwarding is paused, return.
+        if messages.is_empty() || self.forwarding_paused {
+            return;
         }
 
         // Forward the messages to the appropriate subscribers. Group the messages that are
         // destined to the same topic.
-        let peer_frames = messages
+        let mut forwarded = 0;
+        let mut peer_frames = messages
             .into_iter()
-            .fold(HashMap::<PeerId, Vec<Message>>::new(), |mut mmap, msg| {
-                let next_hops = self
-                    .router
-                    .propagation_routes(&msg.topic())
-                    .into_iter()
-                    .filter(|peer| {
-                        // Don't send the message back to the propagation source.
-                        peer != src
-                    });
-
-                for peer in next_hops {
-                    mmap.entry(peer).or_default().push(msg.clone());
-                }
-
-                mmap
-            })
+            .fold(
+                HashMap::<PeerId, Vec<Message>>::new(),
+                |mut mmap, (message_id, msg)| {
+                    #[cfg(feature = "tracing")]
+                    let _span = tracing::debug_span!(
+                        "forward",
+                        peer_id = %src,
+                        topic = %msg.topic_hash(),
+                        message_id = %message_id
+                    )
+                    .entered();
+
+                    let next_hops = self
+                        .forward_peers(msg.topic_hash(), &message_id)
+                        .into_iter()
+                        .filter(|peer| {
+                            // Don't send the message back to the propagation source.
+                            peer != src
+                        });
+
+                    // Forward the received message as is. Its authorship fields (i.e., `from`,
+                    // `seqno`, `signature` and `key`) must not be altered, otherwise the
+                    // downstream peers' signature validation would fail.
+                    let mut next_hops = next_hops.peekable();
+                    if next_hops.peek().is_some() {
+                        forwarded += 1;
+                    }
+                    for peer in next_hops {
+                        mmap.entry(peer).or_default().push(msg.clone());
+                    }
+
+                    mmap
+                },
+            )
             .into_iter()
-            .map(|(peer, messages)| (peer, Frame::new_with_messages(messages)));
+            .map(|(peer, messages)| (peer, Frame::new_with_messages(messages)))
+            .collect::<Vec<_>>();
+        self.messages_forwarded += forwarded;
+
+        if self.config.deterministic_forwarding() {
+            peer_frames.sort_unstable_by_key(|(peer, _)| *peer);
+        }
 
         for (peer, frame) in peer_frames {
             if let Err(err) = self.send_rpc_frame(&peer, frame) {
@@ -439,6 +2281,132 @@ impl Behaviour {
         }
     }
 
+    /// Check whether a received message must be dropped.
+    ///
+    /// Returns the reason why the message must be dropped, if any. Messages passing all the checks
+    /// are inserted into the seen messages cache.
+    fn filter_received_message(
+        &mut self,
+        src: &PeerId,
+        message_id: &MessageId,
+        message: &Message,
+    ) -> Result<(), DropReason> {
+        // Drop messages propagated or authored by blacklisted peers.
+        if self.blacklisted_peers.contains(src)
+            || message
+                .source()
+                .is_some_and(|author| self.blacklisted_peers.contains(&author))
+        {
+            return Err(DropReason::Blacklisted);
+        }
+
+        // Drop messages published by the local node sent back by a peer (e.g., due to forwarding
+        // loops). If enabled, they were already delivered locally when published.
+        if message
+            .source()
+            .is_some_and(|author| self.is_local_author(&author))
+        {
+            return Err(DropReason::SelfOrigin);
+        }
+
+        // Drop messages exceeding the peer's rate limit budget.
+        if let Some(rate_limiter) = self.rate_limiter.as_mut() {
+            if !rate_limiter.try_acquire(src) {
+                log::trace!(
+                    "Peer {src} exceeded its message rate limit (total dropped: {})",
+                    rate_limiter.rejected_count(src)
+                );
+                return Err(DropReason::RateLimited);
+            }
+        }
+
+        // Drop messages whose topic fails the strict topic validation, if enabled.
+        if !self.is_valid_topic(message.topic_hash()) {
+            return Err(DropReason::InvalidTopic);
+        }
+
+        // Drop messages exceeding the maximum frame size, or the topic size limit.
+        if message.encoded_len() > self.max_transmit_size(message.topic_hash()) {
+            return Err(DropReason::TooLarge);
+        }
+
+        // Drop unsigned messages from topics requiring signatures.
+        if self.requires_signatures(message.topic_hash()) && message.signature().is_none() {
+            return Err(DropReason::InvalidSignature);
+        }
+
+        // Drop messages whose author key does not match the author (i.e., the `key` field is not
+        // the public key of the `from` peer ID).
+        if !signing::is_author_key(message) {
+            return Err(DropReason::InvalidSignature);
+        }
+
+        // Drop signed messages whose signature is invalid, e.g., signed messages re-routed to
+        // another topic than the one covered by the signature.
+        if !signing::is_valid_signature(message) {
+            return Err(DropReason::InvalidSignature);
+        }
+
+        // Drop messages from permissioned topics not authored by an allowed author.
+        if !self.is_allowed_author(message.topic_hash(), message.source().as_ref()) {
+            return Err(DropReason::AuthorNotAllowed);
+        }
+
+        // Drop messages whose TTL has expired, if enabled for their topic.
+        if self.drops_expired_messages(message.topic_hash())
+            && message.sequence_number().is_some_and(ttl::is_expired)
+        {
+            return Err(DropReason::Expired);
+        }
+
+        // Drop messages propagated by peers not subscribed to their topic, if enabled.
+        if self.config.only_accept_from_subscribed_peers()
+            && !self.router.is_peer_subscribed(src, message.topic_hash())
+        {
+            return Err(DropReason::PeerNotSubscribed);
+        }
+
+        // Drop messages from topics that we are not subscribed to, unless they are forwarded.
+        if !self.config.forward_unsubscribed()
+            && !self.router.matches_subscription(message.topic_hash())
+        {
+            return Err(DropReason::NotSubscribed);
+        }
+
+        // Drop messages whose ID exceeds the maximum length, before it reaches the seen messages
+        // cache.
+        if !self.is_valid_message_id(message_id) {
+            return Err(DropReason::MessageIdTooLarge);
+        }
+
+        // Drop messages that we have already seen.
+        let seen_key = self.seen_cache_key(message.topic_hash(), message_id);
+        if !self.seen_cache.put(&seen_key) {
+            return Err(DropReason::Duplicate);
+        }
+
+        Ok(())
+    }
+
+    /// Report the received message without topic as dropped, if configured.
+    ///
+    /// The message has no recipients: it is neither delivered nor forwarded.
+    fn on_topicless_message(&mut self, message: MessageProto) {
+        if !self.config.emit_dropped_events() {
+            return;
+        }
+
+        // The message ID can't be computed if the other message fields are invalid.
+        let Some(message) = Message::from_topicless_proto(message) else {
+            return;
+        };
+
+        self.emit_behaviour_event(Event::MessageDropped {
+            message_id: (self.message_id_fn)(&message),
+            reason: DropReason::NoTopic,
+        });
+    }
+
     /// Handle received subscriptions.
     ///
     /// This function will add or remove the peer topic subscriptions from the router.
@@ -447,16 +2415,299 @@ impl Behaviour {
         src: &PeerId,
         subscriptions: impl Iterator<Item = SubscriptionAction>,
     ) {
+        let mut subscribed_topics = Vec::new();
         for sub in subscriptions {
+            if !self.is_valid_topic(sub.topic_id()) {
+                log::trace!("Dropped subscription with invalid topic received from {src}");
+                continue;
+            }
+
             match sub {
                 SubscriptionAction::Subscribe(topic) => {
-                    self.router.add_peer_subscription(*src, topic);
+                    if self.router.add_peer_subscription(*src, topic.clone()) {
+                        subscribed_topics.push(topic);
+                    }
                 }
                 SubscriptionAction::Unsubscribe(topic) => {
                     self.router.remove_peer_subscription(src, &topic);
                 }
             }
         }
+
+        self.evict_peer_subscriptions();
+        self.check_topics_peers();
+        self.retransmit_recent_messages(src, &subscribed_topics);
+    }
+
+    /// Re-send the recent messages of the topics the peer just subscribed to, as provided by the
+    /// [`Config::retransmission_policy`], if any.
+    fn retransmit_recent_messages(&mut self, dst: &PeerId, topics: &[TopicHash]) {
+        let Some(policy) = self.config.retransmission_policy() else {
+            return;
+        };
+        if self.blacklisted_peers.contains(dst) {
+            return;
+        }
+
+        let messages = topics
+            .iter()
+            .flat_map(|topic| policy.messages_to_resend(topic))
+            .collect::<Vec<_>>();
+        if messages.is_empty() {
+            return;
+        }
+
+        log::debug!(
+            "Re-sending {} recent messages to peer {dst}",
+            messages.len()
+        );
+        if let Err(err) = self.send_rpc_frame(dst, Frame::new_with_messages(messages)) {
+            log::debug!("Failed to re-send the recent messages to peer {dst}: {err}");
+        }
+    }
+
+    /// Evict the subscriptions of the least recently active peers until the number of tracked
+    /// peer subscriptions is back within the [`Config::max_peer_subscriptions_total`] limit.
+    fn evict_peer_subscriptions(&mut self) {
+        let Some(max_subscriptions) = self.config.max_peer_subscriptions_total() else {
+            return;
+        };
+
+        while self.router.peer_subscriptions_count() > max_subscriptions {
+            let router = &self.router;
+            let Some((peer, topic)) = self.peers_by_activity.iter().find_map(|peer| {
+                let topic = router.peer_subscriptions(peer)?.first()?;
+                Some((*peer, topic.clone()))
+            }) else {
+                break;
+            };
+
+            log::debug!("Evicting the subscription of peer {peer} to topic {topic}");
+            self.router.remove_peer_subscription(&peer, &topic);
+        }
+    }
+
+    /// Emit an [`Event::InsufficientPeers`] event for every subscribed topic whose number of
+    /// subscribed peers dropped below the configured minimum.
+    ///
+    /// Prefix subscriptions are not checked, as the peers subscribe to the topics matching the
+    /// prefix and not to the prefix itself.
+    fn check_topics_peers(&mut self) {
+        let Some(min_peers) = self.config.min_peers_per_topic() else {
+            return;
+        };
+
+        let router = &self.router;
+        self.insufficient_peers_topics
+            .retain(|topic| router.is_subscribed(topic));
+
+        let mut insufficient = Vec::new();
+        for topic in router.subscriptions() {
+            if topic.as_str().ends_with(PREFIX_WILDCARD) {
+                continue;
+            }
+
+            let peers = router.subscription_peers(topic).map_or(0, BTreeSet::len);
+            if peers >= min_peers {
+                self.insufficient_peers_topics.remove(topic);
+            } else if self.insufficient_peers_topics.insert(topic.clone()) {
+                insufficient.push(topic.clone());
+            }
+        }
+
+        for topic in insufficient {
+            log::debug!("Topic {topic} has fewer than {min_peers} subscribed peers");
+            self.emit_behaviour_event(Event::InsufficientPeers { topic });
+        }
+    }
+
+    /// Whether the topic passes the strict topic validation: it does not exceed the maximum topic
+    /// length, nor contains control characters.
+    ///
+    /// All topics are valid if the strict topic validation is disabled.
+    fn is_valid_topic(&self, topic: &TopicHash) -> bool {
+        let Some(max_topic_length) = self.config.max_topic_length() else {
+            return true;
+        };
+
+        let topic = topic.as_str();
+        topic.len() <= max_topic_length && !topic.chars().any(char::is_control)
+    }
+
+    /// Whether the message ID does not exceed the [`Config::max_message_id_len`], if any.
+    fn is_valid_message_id(&self, message_id: &MessageId) -> bool {
+        let Some(max_message_id_len) = self.config.max_message_id_len() else {
+            return true;
+        };
+
+        message_id.as_ref().len() <= max_message_id_len
+    }
+
+    /// Whether the messages published to the given topic are delivered to the local node.
+    ///
+    /// This requires [`Config::allow_self_origin`] to be enabled, and the local node to be
+    /// subscribed to the topic.
+    fn delivers_published_locally(&self, topic: &TopicHash) -> bool {
+        self.config.allow_self_origin() && self.router.matches_subscription(topic)
+    }
+
+    /// Get the maximum size of the messages of the given topic.
+    ///
+    /// This is the topic's size limit, if set, otherwise the maximum frame size.
+    fn max_transmit_size(&self, topic: &TopicHash) -> usize {
+        self.topic_params
+            .get(topic)
+            .and_then(|params| params.max_transmit_size)
+            .unwrap_or(self.config.max_frame_size())
+    }
+
+    /// Get the seen messages cache key of a message, according to the configured deduplication
+    /// scope.
+    fn seen_cache_key(
+        &self,
+        topic: &TopicHash,
+        message_id: &MessageId,
+    ) -> (Option<TopicHash>, MessageId) {
+        let topic = match self.config.dedup_scope() {
+            DedupScope::Global => None,
+            DedupScope::PerTopic => Some(topic.clone()),
+        };
+        (topic, message_id.clone())
+    }
+
+    /// Whether the messages of the given topic must be signed.
+    fn requires_signatures(&self, topic: &TopicHash) -> bool {
+        self.topic_params
+            .get(topic)
+            .is_some_and(|params| params.require_signatures)
+    }
+
+    /// Whether the expired messages of the given topic are dropped.
+    fn drops_expired_messages(&self, topic: &TopicHash) -> bool {
+        self.topic_params
+            .get(topic)
+            .is_some_and(|params| params.drop_expired_messages)
+    }
+
+    /// Whether the peer is an author of the locally published messages: the local peer ID, the
+    /// signer peer ID, or the stable random anonymous author.
+    fn is_local_author(&self, author: &PeerId) -> bool {
+        author == &self.local_peer_id
+            || self.message_author.as_ref() == Some(author)
+            || self
+                .config
+                .signer()
+                .is_some_and(|signer| &signer.author() == author)
+    }
+
+    /// Whether the author is allowed to publish on the topic.
+    ///
+    /// All the authors are allowed on topics without an allowed authors set.
+    fn is_allowed_author(&self, topic: &TopicHash, author: Option<&PeerId>) -> bool {
+        match self.topic_allowed_authors.get(topic) {
+            Some(authors) => author.is_some_and(|author| authors.contains(author)),
+            None => true,
+        }
+    }
+
+    /// Get the peers to publish or forward a message to.
+    ///
+    /// If the number of forward peers per topic is capped, or the flood factor is below one, a
+    /// subset of the topic's subscribed peers is selected according to the configured strategy.
+    /// Blacklisted peers are excluded.
+    /// In static mesh mode (see [`Config::static_forward_peers`]), the static peers are used
+    /// instead of the topic's subscribed peers.
+    ///
+    /// If deterministic forwarding is enabled, the peers are sorted by `PeerId`.
+    fn forward_peers(&mut self, topic: &TopicHash, message_id: &MessageId) -> Vec<PeerId> {
+        if let Some(static_peers) = self.config.static_forward_peers() {
+            return self.static_forward_peers(static_peers);
+        }
+
+        let routes = self.propagation_candidates(topic);
+
+        let mut max_peers = self
+            .config
+            .max_forward_peers_per_topic()
+            .unwrap_or(usize::MAX);
+        if self.config.flood_factor() < 1.0 {
+            let fanout = flood_fanout(
+                routes.len(),
+                self.config.flood_factor(),
+                self.config.min_forward_peers(),
+            );
+            max_peers = max_peers.min(fanout);
+        }
+
+        let mut peers = self.router.select_forward_peers(
+            routes,
+            max_peers,
+            self.config.forward_subset_strategy(),
+            message_id,
+        );
+
+        if self.config.deterministic_forwarding() {
+            peers.sort_unstable();
+        }
+
+        peers
+    }
+
+    /// Get the non-blacklisted peers subscribed to the topic, before any subset selection.
+    fn propagation_candidates(&self, topic: &TopicHash) -> Vec<PeerId> {
+        self.router
+            .propagation_routes(topic)
+            .into_iter()
+            .filter(|peer| !self.blacklisted_peers.contains(peer))
+            .collect()
+    }
+
+    /// Get the connected, non-blacklisted peers of the static forward peers set.
+    ///
+    /// If deterministic forwarding is enabled, the peers are sorted by `PeerId`.
+    fn static_forward_peers(&self, static_peers: &[PeerId]) -> Vec<PeerId> {
+        let mut peers = static_peers
+            .iter()
+            .filter(|peer| self.connections.peer_connections_count(peer) > 0)
+            .filter(|peer| !self.blacklisted_peers.contains(peer))
+            .copied()
+            .collect::<Vec<_>>();
+
+        if self.config.deterministic_forwarding() {
+            peers.sort_unstable();
+        }
+
+        peers
+    }
+
+    /// Send the pending subscription changes to all the connected peers.
+    ///
+    /// The peers sent the full set of local subscriptions in the meantime only get the changes
+    /// made after it.
+    fn flush_subscription_changes(&mut self) {
+        let changes = std::mem::take(&mut self.pending_subscription_changes);
+        let snapshot_peers = std::mem::take(&mut self.subscriptions_snapshot_peers);
+        if changes.is_empty() {
+            return;
+        }
+
+        for peer in self.connections.active_peers() {
+            let reflected = snapshot_peers.get(&peer).copied().unwrap_or_default();
+            if reflected < changes.len() {
+                self.send_subscriptions(&peer, &changes[reflected..]);
+            }
+        }
+    }
+
+    /// Send the subscription changes to the peer, split into RPC frames carrying at most
+    /// [`Config::max_subscriptions_per_rpc`] changes each.
+    fn send_subscriptions(&mut self, dst: &PeerId, subscriptions: &[SubscriptionAction]) {
+        for chunk in subscriptions.chunks(self.config.max_subscriptions_per_rpc()) {
+            let frame = Frame::new_with_subscriptions(chunk.to_vec());
+            if let Err(err) = self.send_rpc_frame(dst, frame) {
+                log::debug!("Failed to send subscriptions to peer {dst}: {err}");
+            }
+        }
     }
 
     /// Send an RPC frame to a peer.
@@ -470,55 +2721,87 @@ impl Behaviour {
     ) -> Result<(), SendError> {
         let frames = fragment_rpc_message(frame.into(), self.config.max_frame_size())?;
 
-        // Send the RPC frame(s) to any active connection with the peer
+        // Send the RPC frame(s) over the first established connection with the peer, keeping
+        // track of the frames pending in its send queue. Without established connection, the
+        // frames are sent to any connection.
+        let handler = match self.connections.peer_established_connections(dst).first() {
+            Some(connection) => {
+                *self.pending_frames.entry(*connection).or_default() += frames.len();
+                NotifyHandler::One(*connection)
+            }
+            None => NotifyHandler::Any,
+        };
+
         for frame in frames {
-            self.emit_handler_event(dst, HandlerCommand::SendFrame(frame), NotifyHandler::Any);
+            self.bandwidth_stats.bytes_out += encoded_frame_len(&frame);
+            self.emit_handler_event(dst, HandlerCommand::SendFrame(frame), handler.clone());
         }
 
         Ok(())
     }
 }
 
+/// Test harness support.
+#[cfg(any(test, feature = "testing"))]
+impl Behaviour {
+    /// Inject the message as if it was received from the peer.
+    ///
+    /// The message goes through the whole inbound pipeline (i.e., validation, deduplication,
+    /// forwarding and event emission), without a connection to the peer being required.
+    pub fn inject_message_for_test(&mut self, from: PeerId, message: Message) {
+        let frame = Frame::new_with_messages([message]);
+        self.on_received_rpc_frame(&from, frame.into());
+    }
+
+    /// Queue the pending subscription changes and published messages, as when polled.
+    pub(crate) fn flush_pending(&mut self) {
+        self.flush_subscription_changes();
+        self.process_pending_publishes();
+    }
+
+    /// Take the queued swarm events matching the predicate, keeping the others queued in order.
+    pub(crate) fn take_swarm_out_events(
+        &mut self,
+        mut predicate: impl FnMut(&ToSwarm<Event, HandlerCommand>) -> bool,
+    ) -> Vec<ToSwarm<Event, HandlerCommand>> {
+        let mut taken = Vec::new();
+        for event in std::mem::take(&mut self.swarm_out_events) {
+            if predicate(&event) {
+                taken.push(event);
+            } else {
+                self.swarm_out_events.push_back(event);
+            }
+        }
+        taken
+    }
+}
+
 impl NetworkBehaviour for Behaviour {
     type ConnectionHandler = Handler;
     type ToSwarm = Event;
 
     fn handle_established_inbound_connection(
         &mut self,
-        connection_id: ConnectionId,
+        _connection_id: ConnectionId,
         peer: PeerId,
-        local_addr: &Multiaddr,
-        remote_addr: &Multiaddr,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
     ) -> Result<THandler<Self>, ConnectionDenied> {
-        self.connections.register_inbound(
-            connection_id,
-            peer,
-            local_addr.clone(),
-            remote_addr.clone(),
-        );
-
-        Ok(Handler::new(
-            FLOODSUB_PROTOCOL_ID,
-            self.config.max_frame_size(),
-            self.config.connection_idle_timeout(),
-        ))
+        // The connection is registered on `FromSwarm::ConnectionEstablished`, as another
+        // behaviour (e.g., a connection limits behaviour) can still deny it.
+        Ok(self.new_handler(peer))
     }
 
     fn handle_established_outbound_connection(
         &mut self,
-        connection_id: ConnectionId,
+        _connection_id: ConnectionId,
         peer: PeerId,
-        remote_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
         _role_override: Endpoint,
     ) -> Result<THandler<Self>, ConnectionDenied> {
-        self.connections
-            .register_outbound(connection_id, peer, remote_addr.clone());
-
-        Ok(Handler::new(
-            FLOODSUB_PROTOCOL_ID,
-            self.config.max_frame_size(),
-            self.config.connection_idle_timeout(),
-        ))
+        // The connection is registered on `FromSwarm::ConnectionEstablished`, as another
+        // behaviour (e.g., a connection limits behaviour) can still deny it.
+        Ok(self.new_handler(peer))
     }
 
     fn on_swarm_event(&mut self, event: FromSwarm<Self::ConnectionHandler>) {
@@ -537,10 +2820,20 @@ impl NetworkBehaviour for Behaviour {
         event: THandlerOutEvent<Self>,
     ) {
         match event {
-            HandlerEvent::FrameReceived(frame) => self.on_received_rpc_frame(&src, frame),
+            HandlerEvent::FrameReceived(frame) => {
+                self.bandwidth_stats.bytes_in += encoded_frame_len(&frame);
+                self.on_received_rpc_frame(&src, frame);
+            }
             HandlerEvent::Disabled(reason) => {
                 log::debug!("Connection handler {connection:?} for peer {src} disabled: {reason}");
             }
+            HandlerEvent::PeerKind(kind) => self.on_peer_kind_detected(&src, kind),
+            HandlerEvent::FramesDequeued(count) => self.on_frames_dequeued(&connection, count),
+            HandlerEvent::ProtocolNegotiated(protocol) => {
+                log::debug!("Negotiated protocol {protocol} with peer {src} on {connection:?}");
+                self.connections
+                    .on_protocol_negotiated(&connection, protocol);
+            }
         }
     }
 
@@ -549,6 +2842,9 @@ impl NetworkBehaviour for Behaviour {
         _cx: &mut Context<'_>,
         _params: &mut impl PollParameters,
     ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        self.flush_subscription_changes();
+        self.process_pending_publishes();
+
         if let Some(event) = self.swarm_out_events.pop_front() {
             return Poll::Ready(event);
         }
@@ -556,3 +2852,28 @@ impl NetworkBehaviour for Behaviour {
         Poll::Pending
     }
 }
+
+/// Get the length of the RPC frame on the wire, including its length prefix, before compression.
+fn encoded_frame_len(frame: &RpcProto) -> u64 {
+    let len = frame.encoded_len();
+    (prost::length_delimiter_len(len) + len) as u64
+}
+
+/// Get the topic advertising a subscription to the given topic prefix.
+fn prefix_topic(prefix: &str) -> Topic<IdentityHash> {
+    Topic::new(format!("{prefix}{PREFIX_WILDCARD}"))
+}
+
+/// Create the protocol ID advertised to the peers, reusing the static protocol ID if it matches
+/// the default one.
+fn new_protocol_id(protocol_id: String, default: &'static str) -> StreamProtocol {
+    if protocol_id == default {
+        return StreamProtocol::new(default);
+    }
+
+    // The prefix is validated when building the configuration.
+    StreamProtocol::try_from_owned(protocol_id).expect("valid protocol ID")
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,6966 @@
+use std::collections::{HashMap, HashSet};
+
+use assert_matches::assert_matches;
+use libp2p::core::{ConnectedPoint, Endpoint};
+use libp2p::identity::{Keypair, PeerId};
+use libp2p::swarm::behaviour::{ConnectionClosed, ConnectionEstablished};
+use libp2p::swarm::{CloseConnection, ConnectionId, FromSwarm, NetworkBehaviour, ToSwarm};
+use libp2p::{Multiaddr, StreamProtocol};
+
+use crate::config::{Config, ConfigBuilder};
+use crate::frame::{Frame, Message, SubscriptionAction};
+use crate::handler::{Command as HandlerCommand, Event as HandlerEvent, Handler, PeerKind};
+use crate::message_id::MessageId;
+use crate::proto::{MessageProto, RpcProto};
+use crate::IdentTopic;
+
+use super::{
+    Behaviour, DisconnectReason, DropReason, Event, PeerStats, PublishError, FLOODSUB_PROTOCOL_ID,
+};
+
+fn test_peer() -> PeerId {
+    PeerId::random()
+}
+
+fn test_topic() -> IdentTopic {
+    IdentTopic::new(format!("/test/topic-{}", rand::random::<u64>()))
+}
+
+fn test_message(topic: &IdentTopic, data: impl Into<Vec<u8>>) -> Message {
+    let mut message = Message::new(topic.hash(), data);
+    message.set_source(Some(test_peer()));
+    message.set_sequence_number(Some(rand::random()));
+    message
+}
+
+fn test_behaviour(config: Config) -> Behaviour {
+    Behaviour::new(test_peer(), config)
+}
+
+fn test_endpoint() -> ConnectedPoint {
+    ConnectedPoint::Listener {
+        local_addr: "/memory/1".parse::<Multiaddr>().unwrap(),
+        send_back_addr: "/memory/2".parse::<Multiaddr>().unwrap(),
+    }
+}
+
+/// Simulate the swarm establishing a new inbound connection with the given peer.
+fn connect_peer(behaviour: &mut Behaviour, peer: PeerId) -> ConnectionId {
+    let connection_id = ConnectionId::new_unchecked(rand::random());
+    let endpoint = test_endpoint();
+
+    behaviour
+        .handle_established_inbound_connection(
+            connection_id,
+            peer,
+            endpoint.get_remote_address(),
+            endpoint.get_remote_address(),
+        )
+        .expect("connection to be accepted");
+
+    let other_established = behaviour.connections.peer_connections_count(&peer);
+    behaviour.on_swarm_event(FromSwarm::ConnectionEstablished(ConnectionEstablished {
+        peer_id: peer,
+        connection_id,
+        endpoint: &endpoint,
+        failed_addresses: &[],
+        other_established,
+    }));
+
+    connection_id
+}
+
+/// Simulate the swarm closing the given connection with the peer.
+fn disconnect_peer(behaviour: &mut Behaviour, peer: PeerId, connection_id: ConnectionId) {
+    let endpoint = test_endpoint();
+    let handler = Handler::new(
+        StreamProtocol::new(FLOODSUB_PROTOCOL_ID),
+        behaviour.config.max_frame_size(),
+        behaviour.config.max_inbound_substreams(),
+        behaviour.config.connection_idle_timeout(),
+        behaviour.config.negotiation_timeout(),
+    );
+
+    let remaining_established = behaviour.connections.peer_connections_count(&peer) - 1;
+    behaviour.on_swarm_event(FromSwarm::ConnectionClosed(ConnectionClosed {
+        peer_id: peer,
+        connection_id,
+        endpoint: &endpoint,
+        handler,
+        remaining_established,
+    }));
+}
+
+/// Simulate the reception of the given RPC frame from the peer.
+fn receive_rpc_frame(
+    behaviour: &mut Behaviour,
+    peer: PeerId,
+    connection_id: ConnectionId,
+    frame: RpcProto,
+) {
+    behaviour.on_connection_handler_event(peer, connection_id, HandlerEvent::FrameReceived(frame));
+}
+
+/// Simulate the reception of an RPC frame with the given messages from the peer.
+fn receive_messages(
+    behaviour: &mut Behaviour,
+    peer: PeerId,
+    connection_id: ConnectionId,
+    messages: impl IntoIterator<Item = Message>,
+) {
+    let frame = Frame::new_with_messages(messages);
+    receive_rpc_frame(behaviour, peer, connection_id, frame.into());
+}
+
+/// Simulate the reception of an RPC frame with the given topic subscriptions from the peer.
+fn receive_subscriptions(
+    behaviour: &mut Behaviour,
+    peer: PeerId,
+    connection_id: ConnectionId,
+    topics: impl IntoIterator<Item = IdentTopic>,
+) {
+    let subscriptions = topics
+        .into_iter()
+        .map(|topic| SubscriptionAction::subscribe(topic.hash()));
+    let frame = Frame::new_with_subscriptions(subscriptions);
+    receive_rpc_frame(behaviour, peer, connection_id, frame.into());
+}
+
+/// Drain the events queued to be emitted to the application.
+fn drain_behaviour_events(behaviour: &mut Behaviour) -> Vec<Event> {
+    behaviour
+        .swarm_out_events
+        .drain(..)
+        .filter_map(|event| match event {
+            ToSwarm::GenerateEvent(event) => Some(event),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Drain the peers the queued RPC frames are sent to, in order.
+fn drain_frame_recipients(behaviour: &mut Behaviour) -> Vec<PeerId> {
+    behaviour
+        .swarm_out_events
+        .drain(..)
+        .filter_map(|event| match event {
+            ToSwarm::NotifyHandler {
+                peer_id,
+                event: HandlerCommand::SendFrame(_),
+                ..
+            } => Some(peer_id),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Drain the peers the queued RPC frames publishing messages are sent to, in order.
+fn drain_message_recipients(behaviour: &mut Behaviour) -> Vec<PeerId> {
+    behaviour
+        .swarm_out_events
+        .drain(..)
+        .filter_map(|event| match event {
+            ToSwarm::NotifyHandler {
+                peer_id,
+                event: HandlerCommand::SendFrame(frame),
+                ..
+            } if !frame.publish.is_empty() => Some(peer_id),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Drain the messages published in the queued RPC frames, as sent on the wire, in order.
+fn drain_published_messages(behaviour: &mut Behaviour) -> Vec<MessageProto> {
+    behaviour
+        .swarm_out_events
+        .drain(..)
+        .filter_map(|event| match event {
+            ToSwarm::NotifyHandler {
+                event: HandlerCommand::SendFrame(frame),
+                ..
+            } => Some(frame.publish),
+            _ => None,
+        })
+        .flatten()
+        .collect()
+}
+
+/// Drain the messages queued to be sent to each peer.
+fn drain_sent_messages(behaviour: &mut Behaviour) -> HashMap<PeerId, Vec<MessageProto>> {
+    let mut messages = HashMap::<PeerId, Vec<MessageProto>>::new();
+    for event in behaviour.swarm_out_events.drain(..) {
+        if let ToSwarm::NotifyHandler {
+            peer_id,
+            event: HandlerCommand::SendFrame(frame),
+            ..
+        } = event
+        {
+            messages.entry(peer_id).or_default().extend(frame.publish);
+        }
+    }
+    messages
+}
+
+mod subscriptions {
+    use super::*;
+
+    #[test]
+    fn is_subscribed_reflects_subscribe_and_unsubscribe() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let topic = test_topic();
+
+        //// When
+        let before_subscribe = behaviour.is_subscribed(&topic);
+
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+        let after_subscribe = behaviour.is_subscribed(&topic);
+
+        behaviour
+            .unsubscribe(&topic)
+            .expect("unsubscribe from topic");
+        let after_unsubscribe = behaviour.is_subscribed(&topic);
+
+        //// Then
+        assert!(!before_subscribe);
+        assert!(after_subscribe);
+        assert!(!after_unsubscribe);
+    }
+
+    #[test]
+    fn subscribe_to_already_subscribed_topic_sends_a_single_subscription() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let topic = test_topic();
+
+        let peer = test_peer();
+        connect_peer(&mut behaviour, peer);
+        behaviour.swarm_out_events.clear();
+
+        //// When
+        let first = behaviour.subscribe(&topic);
+        let second = behaviour.subscribe(&topic);
+        behaviour.flush_subscription_changes();
+
+        //// Then
+        assert_matches!(first, Ok(true));
+        assert_matches!(second, Ok(false));
+
+        let frames = behaviour
+            .swarm_out_events
+            .drain(..)
+            .filter_map(|event| match event {
+                ToSwarm::NotifyHandler {
+                    event: HandlerCommand::SendFrame(frame),
+                    ..
+                } => Some(frame),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].subscriptions.len(), 1);
+        assert_eq!(
+            frames[0].subscriptions[0].topic_id.as_deref(),
+            Some(topic.hash().as_str())
+        );
+    }
+}
+
+mod subscription_batching {
+    use super::*;
+
+    /// Drain the subscription frames queued for each peer.
+    fn drain_subscription_frames(behaviour: &mut Behaviour) -> HashMap<PeerId, Vec<RpcProto>> {
+        let mut frames = HashMap::<PeerId, Vec<RpcProto>>::new();
+        for event in behaviour.swarm_out_events.drain(..) {
+            if let ToSwarm::NotifyHandler {
+                peer_id,
+                event: HandlerCommand::SendFrame(frame),
+                ..
+            } = event
+            {
+                frames.entry(peer_id).or_default().push(frame);
+            }
+        }
+        frames
+    }
+
+    #[test]
+    fn subscription_changes_are_chunked_per_rpc() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .max_subscriptions_per_rpc(64)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let peer = test_peer();
+        connect_peer(&mut behaviour, peer);
+        behaviour.swarm_out_events.clear();
+
+        let topics = (0..500).map(|_| test_topic()).collect::<Vec<_>>();
+
+        //// When
+        for topic in &topics {
+            behaviour.subscribe(topic).expect("subscribe to topic");
+        }
+        behaviour.flush_subscription_changes();
+
+        //// Then
+        let frames = drain_subscription_frames(&mut behaviour);
+        let peer_frames = frames
+            .get(&peer)
+            .expect("subscription frames sent to the peer");
+        assert_eq!(peer_frames.len(), 8);
+        assert!(peer_frames
+            .iter()
+            .all(|frame| frame.subscriptions.len() <= 64));
+
+        let subscribed = peer_frames
+            .iter()
+            .flat_map(|frame| frame.subscriptions.iter())
+            .filter_map(|subscription| subscription.topic_id.clone())
+            .collect::<Vec<_>>();
+        let expected = topics
+            .iter()
+            .map(|topic| topic.hash().into_string())
+            .collect::<Vec<_>>();
+        assert_eq!(subscribed, expected);
+    }
+
+    #[test]
+    fn initial_subscriptions_are_chunked_per_rpc() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .max_subscriptions_per_rpc(64)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        for _ in 0..500 {
+            behaviour
+                .subscribe(&test_topic())
+                .expect("subscribe to topic");
+        }
+
+        //// When
+        let peer = test_peer();
+        connect_peer(&mut behaviour, peer);
+
+        //// Then
+        let frames = drain_subscription_frames(&mut behaviour);
+        let peer_frames = frames
+            .get(&peer)
+            .expect("subscription frames sent to the peer");
+        assert_eq!(peer_frames.len(), 8);
+        assert!(peer_frames
+            .iter()
+            .all(|frame| frame.subscriptions.len() <= 64));
+        assert_eq!(
+            peer_frames
+                .iter()
+                .map(|frame| frame.subscriptions.len())
+                .sum::<usize>(),
+            500
+        );
+    }
+
+    #[test]
+    fn subscription_changes_are_not_sent_until_polled() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        connect_peer(&mut behaviour, test_peer());
+        behaviour.swarm_out_events.clear();
+
+        //// When
+        behaviour
+            .subscribe(&test_topic())
+            .expect("subscribe to topic");
+
+        //// Then
+        assert!(drain_subscription_frames(&mut behaviour).is_empty());
+        assert_eq!(behaviour.pending_subscription_changes.len(), 1);
+    }
+}
+
+mod resend_subscriptions {
+    use super::*;
+
+    /// Collect the topics of the subscriptions sent to the given peer.
+    fn drain_sent_subscriptions(behaviour: &mut Behaviour, peer: PeerId) -> Vec<String> {
+        behaviour
+            .swarm_out_events
+            .drain(..)
+            .filter_map(|event| match event {
+                ToSwarm::NotifyHandler {
+                    peer_id,
+                    event: HandlerCommand::SendFrame(frame),
+                    ..
+                } if peer_id == peer => Some(frame.subscriptions),
+                _ => None,
+            })
+            .flatten()
+            .filter(|subscription| subscription.subscribe == Some(true))
+            .filter_map(|subscription| subscription.topic_id)
+            .collect()
+    }
+
+    #[test]
+    fn send_subscriptions_to_peer_connected_after_subscribing() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+        behaviour.flush_subscription_changes();
+        behaviour.swarm_out_events.clear();
+
+        let peer = test_peer();
+
+        //// When
+        connect_peer(&mut behaviour, peer);
+
+        //// Then
+        assert_eq!(
+            drain_sent_subscriptions(&mut behaviour, peer),
+            [topic.hash().into_string()]
+        );
+    }
+
+    #[test]
+    fn resend_all_subscriptions_to_peer() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic_a = test_topic();
+        let topic_b = test_topic();
+
+        let peer = test_peer();
+        let other_peer = test_peer();
+        connect_peer(&mut behaviour, peer);
+        connect_peer(&mut behaviour, other_peer);
+
+        behaviour.subscribe(&topic_a).expect("subscribe to topic");
+        behaviour.subscribe(&topic_b).expect("subscribe to topic");
+        behaviour.flush_subscription_changes();
+        behaviour.swarm_out_events.clear();
+
+        //// When
+        behaviour.resend_subscriptions(&peer);
+
+        //// Then
+        let mut expected = vec![topic_a.hash().into_string(), topic_b.hash().into_string()];
+        expected.sort();
+
+        assert!(!behaviour.swarm_out_events.iter().any(|event| matches!(
+            event,
+            ToSwarm::NotifyHandler { peer_id, .. } if peer_id == &other_peer
+        )));
+        assert_eq!(drain_sent_subscriptions(&mut behaviour, peer), expected);
+    }
+
+    #[test]
+    fn pending_changes_reflected_in_snapshot_are_not_sent_again() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        connect_peer(&mut behaviour, test_peer());
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        //// When
+        let peer = test_peer();
+        connect_peer(&mut behaviour, peer);
+        behaviour.flush_subscription_changes();
+
+        //// Then
+        assert_eq!(
+            drain_sent_subscriptions(&mut behaviour, peer),
+            [topic.hash().into_string()]
+        );
+    }
+
+    #[test]
+    fn changes_made_after_snapshot_are_sent_after_it() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        connect_peer(&mut behaviour, test_peer());
+
+        let topic_a = test_topic();
+        behaviour.subscribe(&topic_a).expect("subscribe to topic");
+
+        let peer = test_peer();
+        connect_peer(&mut behaviour, peer);
+
+        //// When
+        let topic_b = test_topic();
+        behaviour.subscribe(&topic_b).expect("subscribe to topic");
+        behaviour.flush_subscription_changes();
+
+        //// Then
+        assert_eq!(
+            drain_sent_subscriptions(&mut behaviour, peer),
+            [topic_a.hash().into_string(), topic_b.hash().into_string()]
+        );
+    }
+
+    #[test]
+    fn resend_subscriptions_to_disconnected_peer_is_noop() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        //// When
+        behaviour.resend_subscriptions(&test_peer());
+
+        //// Then
+        assert!(behaviour.swarm_out_events.is_empty());
+    }
+}
+
+mod subscriptions_export {
+    use super::*;
+
+    #[test]
+    fn round_trip_subscriptions_through_export_and_import() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topics = [test_topic(), test_topic(), test_topic()];
+        for topic in &topics {
+            behaviour.subscribe(topic).expect("subscribe to topic");
+        }
+        behaviour
+            .subscribe_prefix("/app/room/")
+            .expect("subscribe to prefix");
+
+        let exported = behaviour.export_subscriptions();
+
+        let mut restored = test_behaviour(Config::default());
+        let peer = test_peer();
+        connect_peer(&mut restored, peer);
+        restored.swarm_out_events.clear();
+
+        //// When
+        restored.import_subscriptions(
+            exported
+                .iter()
+                .map(|topic| IdentTopic::new(topic.as_str()))
+                .collect(),
+        );
+        restored.flush_subscription_changes();
+
+        //// Then
+        assert_eq!(exported.len(), 4);
+        assert_eq!(restored.export_subscriptions(), exported);
+        assert!(topics.iter().all(|topic| restored.is_subscribed(topic)));
+        assert!(restored.is_subscribed(&IdentTopic::new("/app/room/*")));
+
+        let advertised = restored
+            .swarm_out_events
+            .drain(..)
+            .filter_map(|event| match event {
+                ToSwarm::NotifyHandler {
+                    peer_id,
+                    event: HandlerCommand::SendFrame(frame),
+                    ..
+                } if peer_id == peer => Some(frame.subscriptions),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|subscription| subscription.topic_id)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            advertised,
+            exported
+                .iter()
+                .map(|topic| topic.as_str().to_string())
+                .collect::<Vec<_>>()
+        );
+    }
+}
+
+mod close {
+    use super::*;
+
+    #[test]
+    fn close_sends_unsubscriptions_to_connected_peers() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic_a = test_topic();
+        let topic_b = test_topic();
+        behaviour.subscribe(&topic_a).expect("subscribe to topic");
+        behaviour.subscribe(&topic_b).expect("subscribe to topic");
+
+        let peer_a = test_peer();
+        let peer_b = test_peer();
+        connect_peer(&mut behaviour, peer_a);
+        connect_peer(&mut behaviour, peer_b);
+        behaviour.swarm_out_events.clear();
+
+        //// When
+        behaviour.close();
+        behaviour.flush_subscription_changes();
+
+        //// Then
+        assert!(!behaviour.is_subscribed(&topic_a));
+        assert!(!behaviour.is_subscribed(&topic_b));
+
+        let mut unsubscriptions = HashMap::<PeerId, Vec<String>>::new();
+        for event in behaviour.swarm_out_events.drain(..) {
+            if let ToSwarm::NotifyHandler {
+                peer_id,
+                event: HandlerCommand::SendFrame(frame),
+                ..
+            } = event
+            {
+                for subscription in frame.subscriptions {
+                    assert_eq!(subscription.subscribe, Some(false));
+                    unsubscriptions
+                        .entry(peer_id)
+                        .or_default()
+                        .extend(subscription.topic_id);
+                }
+            }
+        }
+
+        for peer in [peer_a, peer_b] {
+            assert_matches!(unsubscriptions.get(&peer), Some(topics) => {
+                assert_eq!(topics.len(), 2);
+                assert!(topics.contains(&topic_a.hash().into_string()));
+                assert!(topics.contains(&topic_b.hash().into_string()));
+            });
+        }
+    }
+
+    #[test]
+    fn close_without_subscriptions_sends_nothing() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        connect_peer(&mut behaviour, test_peer());
+        behaviour.swarm_out_events.clear();
+
+        //// When
+        behaviour.close();
+
+        //// Then
+        assert!(behaviour.swarm_out_events.is_empty());
+    }
+}
+
+mod counts {
+    use super::*;
+
+    #[test]
+    fn counts_follow_connect_subscribe_and_disconnect() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic_a = test_topic();
+        let topic_b = test_topic();
+        let peer_a = test_peer();
+        let peer_b = test_peer();
+
+        //// When
+        let initial_counts = (behaviour.peer_count(), behaviour.subscribed_topic_count());
+
+        let connection_a = connect_peer(&mut behaviour, peer_a);
+        let connection_b = connect_peer(&mut behaviour, peer_b);
+        behaviour.subscribe(&topic_a).expect("subscribe to topic");
+        behaviour.subscribe(&topic_b).expect("subscribe to topic");
+        behaviour.subscribe(&topic_b).expect("subscribe to topic");
+        let connected_counts = (behaviour.peer_count(), behaviour.subscribed_topic_count());
+
+        disconnect_peer(&mut behaviour, peer_a, connection_a);
+        behaviour
+            .unsubscribe(&topic_a)
+            .expect("unsubscribe from topic");
+        let disconnected_counts = (behaviour.peer_count(), behaviour.subscribed_topic_count());
+
+        disconnect_peer(&mut behaviour, peer_b, connection_b);
+        behaviour.close();
+        let closed_counts = (behaviour.peer_count(), behaviour.subscribed_topic_count());
+
+        //// Then
+        assert_eq!(initial_counts, (0, 0));
+        assert_eq!(connected_counts, (2, 2));
+        assert_eq!(disconnected_counts, (1, 1));
+        assert_eq!(closed_counts, (0, 0));
+    }
+
+    #[test]
+    fn additional_connections_with_same_peer_count_once() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let peer = test_peer();
+
+        //// When
+        let connection_a = connect_peer(&mut behaviour, peer);
+        let connection_b = connect_peer(&mut behaviour, peer);
+        let count_with_two_connections = behaviour.peer_count();
+
+        disconnect_peer(&mut behaviour, peer, connection_a);
+        let count_with_one_connection = behaviour.peer_count();
+
+        disconnect_peer(&mut behaviour, peer, connection_b);
+
+        //// Then
+        assert_eq!(count_with_two_connections, 1);
+        assert_eq!(count_with_one_connection, 1);
+        assert_eq!(behaviour.peer_count(), 0);
+    }
+}
+
+mod peer_topics {
+    use super::*;
+
+    #[test]
+    fn peer_topics_include_all_peer_subscriptions() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic_a = test_topic();
+        let topic_b = test_topic();
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+
+        //// When
+        receive_subscriptions(
+            &mut behaviour,
+            peer,
+            connection,
+            [topic_a.clone(), topic_b.clone()],
+        );
+
+        //// Then
+        assert_matches!(behaviour.peer_topics(&peer), Some(topics) => {
+            assert_eq!(topics.len(), 2);
+            assert!(topics.contains(&topic_a.hash()));
+            assert!(topics.contains(&topic_b.hash()));
+        });
+    }
+
+    #[test]
+    fn peer_topics_are_removed_on_disconnection() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic = test_topic();
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic]);
+
+        //// When
+        disconnect_peer(&mut behaviour, peer, connection);
+
+        //// Then
+        assert!(behaviour.peer_topics(&peer).is_none());
+    }
+}
+
+mod peer_disconnection {
+    use super::*;
+
+    #[test]
+    fn drop_queued_frames_on_peer_disconnection() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+
+        behaviour
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+
+        //// When
+        disconnect_peer(&mut behaviour, peer, connection);
+
+        //// Then
+        assert!(!behaviour.swarm_out_events.iter().any(|event| matches!(
+            event,
+            ToSwarm::NotifyHandler { peer_id, .. } if peer_id == &peer
+        )));
+        assert!(behaviour.peer_topics(&peer).is_none());
+        assert!(!behaviour.would_deliver_to(&peer, &topic.hash(), &test_peer()));
+    }
+
+    #[test]
+    fn keep_peer_state_until_last_connection_is_closed() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection_a = connect_peer(&mut behaviour, peer);
+        let connection_b = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection_a, [topic.clone()]);
+
+        //// When
+        disconnect_peer(&mut behaviour, peer, connection_a);
+        let topics_after_first_close = behaviour.peer_topics(&peer).cloned();
+        let delivers_after_first_close =
+            behaviour.would_deliver_to(&peer, &topic.hash(), &test_peer());
+
+        disconnect_peer(&mut behaviour, peer, connection_b);
+        let topics_after_last_close = behaviour.peer_topics(&peer).cloned();
+        let delivers_after_last_close =
+            behaviour.would_deliver_to(&peer, &topic.hash(), &test_peer());
+
+        //// Then
+        assert_matches!(topics_after_first_close, Some(topics) => {
+            assert!(topics.contains(&topic.hash()));
+        });
+        assert!(delivers_after_first_close);
+
+        assert!(topics_after_last_close.is_none());
+        assert!(!delivers_after_last_close);
+    }
+}
+
+mod peer_kinds {
+    use super::*;
+
+    #[test]
+    fn connected_peers_are_floodsub_peers_by_default() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let peer = test_peer();
+
+        //// When
+        connect_peer(&mut behaviour, peer);
+
+        //// Then
+        assert_eq!(behaviour.peer_kind(&peer), Some(PeerKind::Floodsub));
+    }
+
+    #[test]
+    fn emit_gossipsub_peer_detected_event_once() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let peer = test_peer();
+        let connection_a = connect_peer(&mut behaviour, peer);
+        let connection_b = connect_peer(&mut behaviour, peer);
+        behaviour.swarm_out_events.clear();
+
+        //// When
+        for connection in [connection_a, connection_b] {
+            behaviour.on_connection_handler_event(
+                peer,
+                connection,
+                HandlerEvent::PeerKind(PeerKind::Gossipsub),
+            );
+        }
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_eq!(events.len(), 1);
+        assert_matches!(&events[0], Event::GossipsubPeerDetected { peer_id } if peer_id == &peer);
+        assert_eq!(behaviour.peer_kind(&peer), Some(PeerKind::Gossipsub));
+    }
+
+    #[test]
+    fn peer_kind_is_removed_on_disconnection() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        behaviour.on_connection_handler_event(
+            peer,
+            connection,
+            HandlerEvent::PeerKind(PeerKind::Gossipsub),
+        );
+
+        //// When
+        disconnect_peer(&mut behaviour, peer, connection);
+
+        //// Then
+        assert_eq!(behaviour.peer_kind(&peer), None);
+    }
+}
+
+mod negotiated_protocols {
+    use super::*;
+
+    #[test]
+    fn negotiated_protocol_is_unknown_until_reported() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let peer = test_peer();
+
+        //// When
+        connect_peer(&mut behaviour, peer);
+
+        //// Then
+        assert_eq!(behaviour.negotiated_protocol(&peer), None);
+    }
+
+    #[test]
+    fn report_the_negotiated_protocol() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+
+        //// When
+        behaviour.on_connection_handler_event(
+            peer,
+            connection,
+            HandlerEvent::ProtocolNegotiated(StreamProtocol::new(FLOODSUB_PROTOCOL_ID)),
+        );
+
+        //// Then
+        assert_eq!(
+            behaviour.negotiated_protocol(&peer),
+            Some(FLOODSUB_PROTOCOL_ID)
+        );
+    }
+
+    #[test]
+    fn negotiated_protocol_is_removed_on_disconnection() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        behaviour.on_connection_handler_event(
+            peer,
+            connection,
+            HandlerEvent::ProtocolNegotiated(StreamProtocol::new(FLOODSUB_PROTOCOL_ID)),
+        );
+
+        //// When
+        disconnect_peer(&mut behaviour, peer, connection);
+
+        //// Then
+        assert_eq!(behaviour.negotiated_protocol(&peer), None);
+    }
+}
+
+mod peer_stats {
+    use super::*;
+
+    #[test]
+    fn duplicate_count_increments_on_message_received_twice() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+
+        let message = test_message(&topic, b"test-payload".to_vec());
+
+        //// When
+        receive_messages(&mut behaviour, peer, connection, [message.clone()]);
+        receive_messages(&mut behaviour, peer, connection, [message]);
+
+        //// Then
+        assert_eq!(
+            behaviour.peer_stats(&peer),
+            Some(PeerStats {
+                messages_delivered: 1,
+                duplicates: 1,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn peer_stats_are_reset_on_disconnection() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [test_message(&topic, b"test-payload".to_vec())],
+        );
+
+        //// When
+        disconnect_peer(&mut behaviour, peer, connection);
+        let stats_after_disconnection = behaviour.peer_stats(&peer);
+
+        connect_peer(&mut behaviour, peer);
+        let stats_after_reconnection = behaviour.peer_stats(&peer);
+
+        //// Then
+        assert_eq!(stats_after_disconnection, None);
+        assert_eq!(stats_after_reconnection, Some(PeerStats::default()));
+    }
+
+    #[test]
+    fn peer_stats_are_retained_on_disconnection_if_enabled() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .retain_peer_stats(true)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [test_message(&topic, b"test-payload".to_vec())],
+        );
+
+        //// When
+        disconnect_peer(&mut behaviour, peer, connection);
+
+        //// Then
+        assert_matches!(behaviour.peer_stats(&peer), Some(stats) => {
+            assert_eq!(stats.messages_delivered, 1);
+        });
+    }
+
+    #[test]
+    fn rate_limit_hits_are_counted() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .peer_message_rate(1, 1)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+
+        //// When
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [
+                test_message(&topic, b"test-payload-1".to_vec()),
+                test_message(&topic, b"test-payload-2".to_vec()),
+            ],
+        );
+
+        //// Then
+        assert_matches!(behaviour.peer_stats(&peer), Some(stats) => {
+            assert_eq!(stats.messages_delivered, 1);
+            assert_eq!(stats.rate_limit_hits, 1);
+        });
+    }
+}
+
+mod publish {
+    use super::*;
+
+    #[test]
+    fn publish_without_subscribed_peers_fails_with_insufficient_peers() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        // A connected peer that is not subscribed to the topic.
+        connect_peer(&mut behaviour, test_peer());
+
+        //// When
+        let result = behaviour.publish(&topic, b"test-payload".to_vec());
+
+        //// Then
+        assert_eq!(result, Err(PublishError::InsufficientPeers));
+    }
+
+    #[test]
+    fn publish_to_unsubscribed_topic_sends_to_subscribed_peers() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .allow_self_origin(true)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+        behaviour.swarm_out_events.clear();
+
+        //// When
+        let result = behaviour.publish(&topic, b"test-payload".to_vec());
+
+        //// Then
+        assert_matches!(result, Ok(_));
+        assert!(!behaviour.is_subscribed(&topic));
+
+        let mut frames = Vec::new();
+        for event in behaviour.swarm_out_events.drain(..) {
+            match event {
+                ToSwarm::NotifyHandler {
+                    peer_id,
+                    event: HandlerCommand::SendFrame(frame),
+                    ..
+                } => frames.push((peer_id, frame)),
+                ToSwarm::GenerateEvent(event) => {
+                    panic!("Unexpected event: {event:?}")
+                }
+                _ => {}
+            }
+        }
+        assert_matches!(frames.as_slice(), [(frame_peer, frame)] => {
+            assert_eq!(frame_peer, &peer);
+            let message = Message::from(frame.publish[0].clone());
+            assert_eq!(message.data(), b"test-payload");
+        });
+    }
+
+    #[test]
+    fn publish_oversized_message_fails_with_message_too_large() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .max_frame_size(128)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+
+        //// When
+        let result = behaviour.publish(&topic, vec![0xff; 256]);
+
+        //// Then
+        assert_eq!(result, Err(PublishError::MessageTooLarge));
+    }
+
+    #[test]
+    fn publish_same_message_twice_fails_with_duplicate() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        behaviour.message_id_fn = Box::new(|message| MessageId::new(message.data()));
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+
+        behaviour
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+
+        //// When
+        let result = behaviour.publish(&topic, b"test-payload".to_vec());
+
+        //// Then
+        assert_eq!(result, Err(PublishError::Duplicate));
+    }
+
+    #[test]
+    fn publish_without_peers_succeeds_when_allowed() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .allow_publish_with_no_peers(true)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+        behaviour.swarm_out_events.clear();
+
+        //// When
+        let result = behaviour.publish(&topic, b"test-payload".to_vec());
+
+        //// Then
+        assert_matches!(result, Ok(_));
+        assert!(behaviour.swarm_out_events.is_empty());
+    }
+
+    #[test]
+    fn publish_without_peers_delivers_locally_when_self_origin_allowed() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .allow_publish_with_no_peers(true)
+            .allow_self_origin(true)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        //// When
+        let message_id = behaviour
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(events.as_slice(), [Event::Message { message, .. }] => {
+            assert_eq!((behaviour.message_id_fn)(message), message_id);
+        });
+    }
+
+    #[test]
+    fn publish_with_subscribed_peers_returns_the_message_id() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .allow_publish_with_no_peers(true)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+        behaviour.swarm_out_events.clear();
+
+        //// When
+        let message_id = behaviour
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+
+        //// Then
+        let frames = behaviour
+            .swarm_out_events
+            .drain(..)
+            .filter_map(|event| match event {
+                ToSwarm::NotifyHandler {
+                    peer_id,
+                    event: HandlerCommand::SendFrame(frame),
+                    ..
+                } => Some((peer_id, frame)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_matches!(frames.as_slice(), [(frame_peer, frame)] => {
+            assert_eq!(frame_peer, &peer);
+            let message = Message::from(frame.publish[0].clone());
+            assert_eq!((behaviour.message_id_fn)(&message), message_id);
+        });
+    }
+}
+
+mod publish_raw {
+    use super::*;
+
+    #[test]
+    fn publish_anonymous_message_as_is() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+        behaviour.swarm_out_events.clear();
+
+        let message = Message::new(topic.hash(), b"test-payload".to_vec());
+
+        //// When
+        let result = behaviour.publish_raw(message.clone());
+
+        //// Then
+        assert_eq!(result, Ok((behaviour.message_id_fn)(&message)));
+        assert_eq!(
+            drain_published_messages(&mut behaviour),
+            vec![message.into_proto()]
+        );
+    }
+
+    #[test]
+    fn publish_signed_message_as_is() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+        behaviour.swarm_out_events.clear();
+
+        let mut message = test_message(&topic, b"test-payload".to_vec());
+        message.set_signature(Some(b"test-signature".to_vec()));
+        message.set_key(Some(b"test-key".to_vec()));
+
+        //// When
+        let result = behaviour.publish_raw(message.clone());
+
+        //// Then
+        assert_matches!(result, Ok(_));
+        assert_eq!(
+            drain_published_messages(&mut behaviour),
+            vec![message.into_proto()]
+        );
+    }
+
+    #[test]
+    fn publish_same_raw_message_twice_fails_with_duplicate() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+
+        let message = test_message(&topic, b"test-payload".to_vec());
+        behaviour
+            .publish_raw(message.clone())
+            .expect("publish the message");
+
+        //// When
+        let result = behaviour.publish_raw(message);
+
+        //// Then
+        assert_eq!(result, Err(PublishError::Duplicate));
+    }
+
+    #[test]
+    fn publish_oversized_raw_message_fails_with_message_too_large() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .max_frame_size(128)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+
+        //// When
+        let result = behaviour.publish_raw(test_message(&topic, vec![0xff; 256]));
+
+        //// Then
+        assert_eq!(result, Err(PublishError::MessageTooLarge));
+    }
+}
+
+mod poll_messages {
+    use super::*;
+
+    #[test]
+    fn drain_published_and_received_messages() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .allow_self_origin(true)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+        behaviour.swarm_out_events.clear();
+
+        let mut published_ids = Vec::new();
+        for payload in [b"payload-1", b"payload-2", b"payload-3"] {
+            let message_id = behaviour
+                .publish(&topic, payload.to_vec())
+                .expect("publish the message");
+            published_ids.push(message_id);
+        }
+
+        let received = test_message(&topic, b"received-payload".to_vec());
+        let received_id = (behaviour.message_id_fn)(&received);
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [received.clone(), received],
+        );
+
+        //// When
+        let messages = behaviour.poll_messages().collect::<Vec<_>>();
+
+        //// Then
+        let local_peer_id = behaviour.local_peer_id;
+        assert_matches!(
+            messages.as_slice(),
+            [
+                (id_1, message_1, source_1),
+                (id_2, message_2, source_2),
+                (id_3, message_3, source_3),
+                (id_4, message_4, source_4),
+            ] => {
+                assert_eq!([id_1, id_2, id_3], [&published_ids[0], &published_ids[1], &published_ids[2]]);
+                assert_eq!(message_1.data(), b"payload-1");
+                assert_eq!(message_2.data(), b"payload-2");
+                assert_eq!(message_3.data(), b"payload-3");
+                assert_eq!([source_1, source_2, source_3], [&local_peer_id; 3]);
+
+                assert_eq!(id_4, &received_id);
+                assert_eq!(message_4.data(), b"received-payload");
+                assert_eq!(source_4, &peer);
+            }
+        );
+
+        // The frames to send are kept, and the messages are not emitted as events.
+        assert!(!behaviour.swarm_out_events.is_empty());
+        assert!(drain_behaviour_events(&mut behaviour).is_empty());
+        assert_eq!(behaviour.poll_messages().count(), 0);
+    }
+}
+
+mod message_ordering {
+    use crate::topic::TopicHash;
+
+    use super::*;
+
+    #[test]
+    fn messages_are_emitted_in_acceptance_order_per_topic() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic_a = test_topic();
+        let topic_b = test_topic();
+        behaviour.subscribe(&topic_a).expect("subscribe to topic");
+        behaviour.subscribe(&topic_b).expect("subscribe to topic");
+
+        let peer_1 = test_peer();
+        let connection_1 = connect_peer(&mut behaviour, peer_1);
+        let peer_2 = test_peer();
+        let connection_2 = connect_peer(&mut behaviour, peer_2);
+
+        let a_1 = test_message(&topic_a, b"a-1".to_vec());
+        let a_2 = test_message(&topic_a, b"a-2".to_vec());
+        let a_3 = test_message(&topic_a, b"a-3".to_vec());
+        let b_1 = test_message(&topic_b, b"b-1".to_vec());
+        let b_2 = test_message(&topic_b, b"b-2".to_vec());
+        let b_3 = test_message(&topic_b, b"b-3".to_vec());
+
+        //// When
+        receive_messages(&mut behaviour, peer_1, connection_1, [a_1.clone(), b_1]);
+        receive_messages(&mut behaviour, peer_2, connection_2, [a_2, a_1]);
+        receive_messages(&mut behaviour, peer_1, connection_1, [b_2, a_3]);
+        receive_messages(&mut behaviour, peer_2, connection_2, [b_3]);
+
+        //// Then
+        let mut topic_payloads = HashMap::<TopicHash, Vec<Vec<u8>>>::new();
+        for (_, message, _) in behaviour.poll_messages() {
+            topic_payloads
+                .entry(message.topic())
+                .or_default()
+                .push(message.data().to_vec());
+        }
+
+        assert_eq!(
+            topic_payloads[&topic_a.hash()],
+            [b"a-1".to_vec(), b"a-2".to_vec(), b"a-3".to_vec()]
+        );
+        assert_eq!(
+            topic_payloads[&topic_b.hash()],
+            [b"b-1".to_vec(), b"b-2".to_vec(), b"b-3".to_vec()]
+        );
+    }
+}
+
+mod peer_count_thresholds {
+    use super::*;
+
+    #[test]
+    fn emit_event_when_crossing_thresholds() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .peer_count_thresholds(vec![2, 3])
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let peer_a = test_peer();
+        let peer_b = test_peer();
+        let peer_c = test_peer();
+
+        //// When
+        connect_peer(&mut behaviour, peer_a);
+        let events_a = drain_behaviour_events(&mut behaviour);
+
+        connect_peer(&mut behaviour, peer_b);
+        let events_b = drain_behaviour_events(&mut behaviour);
+
+        let connection_c = connect_peer(&mut behaviour, peer_c);
+        let events_c = drain_behaviour_events(&mut behaviour);
+
+        disconnect_peer(&mut behaviour, peer_c, connection_c);
+        let events_closed = drain_behaviour_events(&mut behaviour);
+
+        //// Then
+        assert!(events_a.is_empty());
+        assert_matches!(
+            events_b.as_slice(),
+            [Event::PeerCountThreshold {
+                count: 2,
+                crossed_up: true
+            }]
+        );
+        assert_matches!(
+            events_c.as_slice(),
+            [Event::PeerCountThreshold {
+                count: 3,
+                crossed_up: true
+            }]
+        );
+        assert_matches!(
+            events_closed.as_slice(),
+            [Event::PeerCountThreshold {
+                count: 2,
+                crossed_up: false
+            }]
+        );
+    }
+
+    #[test]
+    fn additional_connections_with_same_peer_do_not_cross_thresholds() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .peer_count_thresholds(vec![2])
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let peer = test_peer();
+
+        //// When
+        connect_peer(&mut behaviour, peer);
+        connect_peer(&mut behaviour, peer);
+
+        //// Then
+        assert!(drain_behaviour_events(&mut behaviour).is_empty());
+    }
+}
+
+mod insufficient_peers {
+    use super::*;
+
+    fn min_peers_behaviour(min_peers: usize) -> Behaviour {
+        let config = ConfigBuilder::default()
+            .min_peers_per_topic(min_peers)
+            .build()
+            .expect("valid configuration");
+        test_behaviour(config)
+    }
+
+    #[test]
+    fn emit_event_when_topic_peers_drop_below_the_minimum() {
+        //// Given
+        let mut behaviour = min_peers_behaviour(1);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+        drain_behaviour_events(&mut behaviour);
+
+        //// When
+        disconnect_peer(&mut behaviour, peer, connection);
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(events.as_slice(), [Event::InsufficientPeers { topic: event_topic }] => {
+            assert_eq!(event_topic, &topic.hash());
+        });
+    }
+
+    #[test]
+    fn emit_event_when_subscribing_to_topic_without_peers() {
+        //// Given
+        let mut behaviour = min_peers_behaviour(1);
+
+        let topic = test_topic();
+
+        //// When
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(events.as_slice(), [Event::InsufficientPeers { topic: event_topic }] => {
+            assert_eq!(event_topic, &topic.hash());
+        });
+    }
+
+    #[test]
+    fn event_is_not_emitted_again_until_topic_reaches_the_minimum() {
+        //// Given
+        let mut behaviour = min_peers_behaviour(2);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer_a = test_peer();
+        let connection_a = connect_peer(&mut behaviour, peer_a);
+        let peer_b = test_peer();
+        let connection_b = connect_peer(&mut behaviour, peer_b);
+        drain_behaviour_events(&mut behaviour);
+
+        //// When
+        receive_subscriptions(&mut behaviour, peer_a, connection_a, [topic.clone()]);
+        let events_below_minimum = drain_behaviour_events(&mut behaviour);
+
+        receive_subscriptions(&mut behaviour, peer_b, connection_b, [topic.clone()]);
+        disconnect_peer(&mut behaviour, peer_b, connection_b);
+        let events_after_dropping_again = drain_behaviour_events(&mut behaviour);
+
+        //// Then
+        assert!(events_below_minimum.is_empty());
+        assert_matches!(
+            events_after_dropping_again.as_slice(),
+            [Event::InsufficientPeers { .. }]
+        );
+    }
+
+    #[test]
+    fn event_is_not_emitted_by_default() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic = test_topic();
+
+        //// When
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        //// Then
+        assert!(drain_behaviour_events(&mut behaviour).is_empty());
+    }
+}
+
+mod dropped_messages {
+    use super::*;
+
+    #[test]
+    fn emit_dropped_event_for_duplicate_message() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .emit_dropped_events(true)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        drain_behaviour_events(&mut behaviour);
+
+        let message = test_message(&topic, b"test-payload".to_vec());
+
+        //// When
+        receive_messages(&mut behaviour, peer, connection, [message.clone()]);
+        receive_messages(&mut behaviour, peer, connection, [message]);
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(
+            events.as_slice(),
+            [
+                Event::Message { .. },
+                Event::MessageDropped {
+                    reason: DropReason::Duplicate,
+                    ..
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn emit_dropped_event_for_oversized_message() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .max_frame_size(128)
+            .emit_dropped_events(true)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        drain_behaviour_events(&mut behaviour);
+
+        let message = test_message(&topic, vec![0xff; 256]);
+
+        //// When
+        receive_messages(&mut behaviour, peer, connection, [message]);
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(
+            events.as_slice(),
+            [Event::MessageDropped {
+                reason: DropReason::TooLarge,
+                ..
+            }]
+        );
+    }
+
+    #[test]
+    fn drop_message_whose_key_is_not_the_author_key() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .emit_dropped_events(true)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        drain_behaviour_events(&mut behaviour);
+
+        let author_key = Keypair::generate_secp256k1().public();
+        let other_key = Keypair::generate_secp256k1().public();
+
+        let mut valid_message = test_message(&topic, b"valid-payload".to_vec());
+        valid_message.set_source(Some(author_key.to_peer_id()));
+        valid_message.set_key(Some(author_key.encode_protobuf()));
+
+        // The `from` field is a different peer ID than the one derived from the `key` field.
+        let mut crafted_message = test_message(&topic, b"crafted-payload".to_vec());
+        crafted_message.set_source(Some(author_key.to_peer_id()));
+        crafted_message.set_key(Some(other_key.encode_protobuf()));
+
+        //// When
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [crafted_message, valid_message],
+        );
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(
+            events.as_slice(),
+            [
+                Event::MessageDropped {
+                    reason: DropReason::InvalidSignature,
+                    ..
+                },
+                Event::Message { message, .. },
+            ] if message.data() == b"valid-payload"
+        );
+        assert_matches!(behaviour.peer_stats(&peer), Some(stats) => {
+            assert_eq!(stats.invalid_messages, 1);
+        });
+    }
+
+    #[test]
+    fn drop_message_without_topic() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .emit_dropped_events(true)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        let other_peer = test_peer();
+        let other_connection = connect_peer(&mut behaviour, other_peer);
+        receive_subscriptions(&mut behaviour, other_peer, other_connection, [topic]);
+        behaviour.swarm_out_events.clear();
+
+        let message = MessageProto {
+            topic: String::new(),
+            from: Some(test_peer().to_bytes().into()),
+            seqno: Some(42u64.to_be_bytes().to_vec().into()),
+            data: Some(b"test-payload".to_vec().into()),
+            ..Default::default()
+        };
+        let frame = RpcProto {
+            publish: vec![message],
+            ..Default::default()
+        };
+
+        //// When
+        receive_rpc_frame(&mut behaviour, peer, connection, frame);
+
+        //// Then
+        assert!(!behaviour.swarm_out_events.iter().any(|event| matches!(
+            event,
+            ToSwarm::NotifyHandler {
+                event: HandlerCommand::SendFrame(frame),
+                ..
+            } if !frame.publish.is_empty()
+        )));
+
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(
+            events.as_slice(),
+            [Event::MessageDropped {
+                reason: DropReason::NoTopic,
+                ..
+            }]
+        );
+        assert_matches!(behaviour.peer_stats(&peer), Some(stats) => {
+            assert_eq!(stats.invalid_messages, 1);
+        });
+    }
+
+    #[test]
+    fn dropped_events_are_not_emitted_by_default() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        drain_behaviour_events(&mut behaviour);
+
+        let message = test_message(&topic, b"test-payload".to_vec());
+
+        //// When
+        receive_messages(&mut behaviour, peer, connection, [message.clone()]);
+        receive_messages(&mut behaviour, peer, connection, [message]);
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(events.as_slice(), [Event::Message { .. }]);
+    }
+}
+
+mod blacklist {
+    use super::*;
+
+    #[test]
+    fn drop_messages_from_blacklisted_peer() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .emit_dropped_events(true)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        drain_behaviour_events(&mut behaviour);
+
+        behaviour.blacklist_peer(&peer);
+
+        //// When
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [test_message(&topic, b"test-payload".to_vec())],
+        );
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(
+            events.as_slice(),
+            [Event::MessageDropped {
+                reason: DropReason::Blacklisted,
+                ..
+            }]
+        );
+    }
+}
+
+mod would_deliver_to {
+    use super::*;
+
+    #[test]
+    fn blacklisted_peer_would_not_receive_messages() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let source = test_peer();
+        let subscriber = test_peer();
+        let blacklisted = test_peer();
+
+        connect_peer(&mut behaviour, source);
+        let subscriber_conn = connect_peer(&mut behaviour, subscriber);
+        let blacklisted_conn = connect_peer(&mut behaviour, blacklisted);
+
+        receive_subscriptions(&mut behaviour, subscriber, subscriber_conn, [topic.clone()]);
+        receive_subscriptions(
+            &mut behaviour,
+            blacklisted,
+            blacklisted_conn,
+            [topic.clone()],
+        );
+
+        //// When
+        behaviour.blacklist_peer(&blacklisted);
+
+        //// Then
+        let topic = topic.hash();
+        assert!(behaviour.would_deliver_to(&subscriber, &topic, &source));
+        assert!(!behaviour.would_deliver_to(&blacklisted, &topic, &source));
+        assert!(!behaviour.would_deliver_to(&subscriber, &topic, &blacklisted));
+        assert!(!behaviour.would_deliver_to(&subscriber, &topic, &subscriber));
+    }
+
+    #[test]
+    fn removed_blacklisted_peer_would_receive_messages() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let source = test_peer();
+        let peer = test_peer();
+
+        connect_peer(&mut behaviour, source);
+        let peer_conn = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, peer_conn, [topic.clone()]);
+
+        behaviour.blacklist_peer(&peer);
+
+        //// When
+        behaviour.remove_blacklisted_peer(&peer);
+
+        //// Then
+        assert!(behaviour.would_deliver_to(&peer, &topic.hash(), &source));
+    }
+
+    /// Create a behaviour, not subscribed to the topic, connected to a source peer and to a peer
+    /// subscribed to the topic.
+    fn test_unsubscribed_node(config: Config, topic: &IdentTopic) -> (Behaviour, PeerId, PeerId) {
+        let mut behaviour = test_behaviour(config);
+
+        let source = test_peer();
+        let subscriber = test_peer();
+
+        connect_peer(&mut behaviour, source);
+        let subscriber_conn = connect_peer(&mut behaviour, subscriber);
+        receive_subscriptions(&mut behaviour, subscriber, subscriber_conn, [topic.clone()]);
+
+        (behaviour, source, subscriber)
+    }
+
+    #[test]
+    fn unsubscribed_topic_messages_would_be_forwarded() {
+        //// Given
+        let topic = test_topic();
+
+        //// When
+        let (behaviour, source, subscriber) = test_unsubscribed_node(Config::default(), &topic);
+
+        //// Then
+        assert!(behaviour.would_deliver_to(&subscriber, &topic.hash(), &source));
+    }
+
+    #[test]
+    fn unsubscribed_topic_messages_would_not_be_forwarded_if_forwarding_is_disabled() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .forward_unsubscribed(false)
+            .build()
+            .expect("valid configuration");
+        let topic = test_topic();
+
+        //// When
+        let (behaviour, source, subscriber) = test_unsubscribed_node(config, &topic);
+
+        //// Then
+        assert!(!behaviour.would_deliver_to(&subscriber, &topic.hash(), &source));
+    }
+
+    #[test]
+    fn unsubscribed_topic_messages_published_locally_would_be_delivered() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .forward_unsubscribed(false)
+            .build()
+            .expect("valid configuration");
+        let topic = test_topic();
+
+        //// When
+        let (behaviour, _source, subscriber) = test_unsubscribed_node(config, &topic);
+
+        //// Then
+        let local_peer_id = *behaviour.local_peer_id();
+        assert!(behaviour.would_deliver_to(&subscriber, &topic.hash(), &local_peer_id));
+    }
+}
+
+mod misbehaving_peers {
+    use super::*;
+
+    /// Create an RPC frame holding a message with an invalid (non 64-bit) sequence number.
+    fn invalid_message_frame(topic: &IdentTopic) -> RpcProto {
+        let message = MessageProto {
+            from: Some(test_peer().to_bytes().into()),
+            data: Some(b"test-payload".to_vec().into()),
+            seqno: Some(vec![0x01, 0x02, 0x03].into()),
+            topic: topic.hash().into_string(),
+            signature: None,
+            key: None,
+        };
+
+        RpcProto {
+            subscriptions: vec![],
+            publish: vec![message],
+            control: None,
+        }
+    }
+
+    #[test]
+    fn disconnect_peer_after_reaching_the_violations_threshold() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .disconnect_threshold(3)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        behaviour.swarm_out_events.clear();
+
+        //// When
+        for _ in 0..2 {
+            receive_rpc_frame(
+                &mut behaviour,
+                peer,
+                connection,
+                invalid_message_frame(&topic),
+            );
+        }
+        let events_below_threshold = behaviour.swarm_out_events.drain(..).collect::<Vec<_>>();
+
+        receive_rpc_frame(
+            &mut behaviour,
+            peer,
+            connection,
+            invalid_message_frame(&topic),
+        );
+        let events_at_threshold = behaviour.swarm_out_events.drain(..).collect::<Vec<_>>();
+
+        //// Then
+        assert!(events_below_threshold.is_empty());
+        assert_matches!(
+            events_at_threshold.as_slice(),
+            [
+                ToSwarm::CloseConnection {
+                    peer_id: close_peer,
+                    connection: CloseConnection::All,
+                },
+                ToSwarm::GenerateEvent(Event::PeerDisconnected {
+                    peer_id: disconnected_peer,
+                    reason: DisconnectReason::InvalidMessages,
+                }),
+            ] => {
+                assert_eq!(close_peer, &peer);
+                assert_eq!(disconnected_peer, &peer);
+            }
+        );
+    }
+
+    #[test]
+    fn misbehaving_peers_are_not_disconnected_by_default() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        behaviour.swarm_out_events.clear();
+
+        //// When
+        for _ in 0..10 {
+            receive_rpc_frame(
+                &mut behaviour,
+                peer,
+                connection,
+                invalid_message_frame(&topic),
+            );
+        }
+
+        //// Then
+        assert!(behaviour.swarm_out_events.is_empty());
+    }
+}
+
+mod self_origin {
+    use super::*;
+
+    /// Create a behaviour subscribed to the topic, connected to a peer also subscribed to it.
+    fn subscribed_behaviour(
+        config: Config,
+        topic: &IdentTopic,
+    ) -> (Behaviour, PeerId, ConnectionId) {
+        let mut behaviour = test_behaviour(config);
+        behaviour.subscribe(topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+        drain_behaviour_events(&mut behaviour);
+
+        (behaviour, peer, connection)
+    }
+
+    #[test]
+    fn deliver_published_message_locally_when_allowed() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .allow_self_origin(true)
+            .build()
+            .expect("valid configuration");
+        let topic = test_topic();
+        let (mut behaviour, _peer, _connection) = subscribed_behaviour(config, &topic);
+
+        //// When
+        behaviour
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+
+        //// Then
+        let local_peer_id = behaviour.local_peer_id;
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(
+            events.as_slice(),
+            [Event::Message { propagation_source, topic: message_topic, message, .. }] => {
+                assert_eq!(propagation_source, &local_peer_id);
+                assert_eq!(message_topic, &topic.hash());
+                assert_eq!(message.data(), b"test-payload");
+            }
+        );
+    }
+
+    #[test]
+    fn do_not_deliver_published_message_locally_by_default() {
+        //// Given
+        let topic = test_topic();
+        let (mut behaviour, _peer, _connection) = subscribed_behaviour(Config::default(), &topic);
+
+        //// When
+        behaviour
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+
+        //// Then
+        assert!(drain_behaviour_events(&mut behaviour).is_empty());
+    }
+
+    #[test]
+    fn publish_inserts_message_id_into_seen_cache() {
+        //// Given
+        let topic = test_topic();
+        let (mut behaviour, _peer, _connection) = subscribed_behaviour(Config::default(), &topic);
+
+        //// When
+        let message_id = behaviour
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+
+        //// Then
+        let seen_key = behaviour.seen_cache_key(&topic.hash(), &message_id);
+        assert!(behaviour.seen_cache.contains_key(&seen_key));
+    }
+
+    #[test]
+    fn drop_anonymous_published_message_sent_back_by_peer() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .allow_self_origin(true)
+            .emit_dropped_events(true)
+            .build()
+            .expect("valid configuration");
+        let topic = test_topic();
+        let (mut behaviour, peer, connection) = subscribed_behaviour(config, &topic);
+
+        behaviour
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+
+        let published_frame = behaviour
+            .swarm_out_events
+            .iter()
+            .find_map(|event| match event {
+                ToSwarm::NotifyHandler {
+                    event: HandlerCommand::SendFrame(frame),
+                    ..
+                } => Some(frame.clone()),
+                _ => None,
+            })
+            .expect("published frame");
+        drain_behaviour_events(&mut behaviour);
+
+        //// When
+        receive_rpc_frame(&mut behaviour, peer, connection, published_frame);
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(
+            events.as_slice(),
+            [Event::MessageDropped {
+                reason: DropReason::Duplicate,
+                ..
+            }]
+        );
+    }
+
+    #[test]
+    fn suppress_self_origin_message_sent_back_by_peer() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .emit_dropped_events(true)
+            .build()
+            .expect("valid configuration");
+        let topic = test_topic();
+        let (mut behaviour, peer, connection) = subscribed_behaviour(config, &topic);
+
+        let mut message = test_message(&topic, b"test-payload".to_vec());
+        message.set_source(Some(behaviour.local_peer_id));
+
+        //// When
+        receive_messages(&mut behaviour, peer, connection, [message]);
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(
+            events.as_slice(),
+            [Event::MessageDropped {
+                reason: DropReason::SelfOrigin,
+                ..
+            }]
+        );
+    }
+}
+
+mod local_peer_id {
+    use libp2p::identity::Keypair;
+
+    use crate::signing::SigningError;
+
+    use super::*;
+
+    #[test]
+    fn local_peer_id_is_the_given_peer_id() {
+        //// Given
+        let local_peer_id = test_peer();
+
+        //// When
+        let behaviour = Behaviour::new(local_peer_id, Config::default());
+
+        //// Then
+        assert_eq!(behaviour.local_peer_id(), &local_peer_id);
+    }
+
+    #[test]
+    fn suppress_message_authored_by_the_signer_sent_back_by_peer() {
+        //// Given
+        let keypair = Keypair::generate_secp256k1();
+        let signer_peer_id = keypair.public().to_peer_id();
+        let config = ConfigBuilder::default()
+            .emit_dropped_events(true)
+            .signer(
+                keypair.public(),
+                Box::new(move |payload| {
+                    keypair
+                        .sign(payload)
+                        .map_err(|err| SigningError::new(err.to_string()))
+                }),
+            )
+            .build()
+            .expect("valid configuration");
+        let topic = test_topic();
+        let mut behaviour = test_behaviour(config);
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        drain_behaviour_events(&mut behaviour);
+
+        let mut message = test_message(&topic, b"test-payload".to_vec());
+        message.set_source(Some(signer_peer_id));
+
+        //// When
+        receive_messages(&mut behaviour, peer, connection, [message]);
+
+        //// Then
+        assert_ne!(behaviour.local_peer_id(), &signer_peer_id);
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(
+            events.as_slice(),
+            [Event::MessageDropped {
+                reason: DropReason::SelfOrigin,
+                ..
+            }]
+        );
+    }
+}
+
+mod seen_cache {
+    use super::*;
+
+    #[test]
+    fn cleared_seen_message_is_accepted_again() {
+        //// Given
+        let topic = test_topic();
+
+        let mut behaviour = test_behaviour(Config::default());
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+        behaviour.swarm_out_events.clear();
+
+        behaviour
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+
+        // Capture the published frame to re-inject it later.
+        let published_frame = behaviour
+            .swarm_out_events
+            .drain(..)
+            .find_map(|event| match event {
+                ToSwarm::NotifyHandler {
+                    event: HandlerCommand::SendFrame(frame),
+                    ..
+                } => Some(frame),
+                _ => None,
+            })
+            .expect("published frame");
+        let seen_count_after_publish = behaviour.seen_message_count();
+
+        //// When
+        behaviour.clear_seen_cache();
+        let seen_count_after_clear = behaviour.seen_message_count();
+
+        receive_rpc_frame(&mut behaviour, peer, connection, published_frame);
+
+        //// Then
+        assert_eq!(seen_count_after_publish, 1);
+        assert_eq!(seen_count_after_clear, 0);
+        assert_eq!(behaviour.seen_message_count(), 1);
+
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(events.as_slice(), [Event::Message { message, .. }] => {
+            assert_eq!(message.data(), b"test-payload");
+        });
+    }
+}
+
+mod auto_cache_sizing {
+    use super::*;
+
+    /// Receive a burst of messages exceeding the seen messages cache capacity, then receive the
+    /// first message of the burst again.
+    ///
+    /// Returns the events emitted for the repeated message.
+    fn receive_burst_then_repeat_first(config: Config) -> Vec<Event> {
+        let topic = test_topic();
+
+        let mut behaviour = test_behaviour(config);
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+
+        let burst = (0..32)
+            .map(|i| test_message(&topic, format!("test-payload-{i}")))
+            .collect::<Vec<_>>();
+        let first = burst[0].clone();
+
+        receive_messages(&mut behaviour, peer, connection, burst);
+        drain_behaviour_events(&mut behaviour);
+
+        receive_messages(&mut behaviour, peer, connection, [first]);
+        drain_behaviour_events(&mut behaviour)
+    }
+
+    #[test]
+    fn burst_does_not_evict_seen_ids_within_the_cache_time() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .duplicate_cache_capacity(4)
+            .auto_cache_sizing(true)
+            .emit_dropped_events(true)
+            .build()
+            .expect("valid configuration");
+
+        //// When
+        let events = receive_burst_then_repeat_first(config);
+
+        //// Then
+        assert_matches!(
+            events.as_slice(),
+            [Event::MessageDropped {
+                reason: DropReason::Duplicate,
+                ..
+            }]
+        );
+    }
+
+    #[test]
+    fn burst_evicts_seen_ids_from_fixed_capacity_cache() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .duplicate_cache_capacity(4)
+            .emit_dropped_events(true)
+            .build()
+            .expect("valid configuration");
+
+        //// When
+        let events = receive_burst_then_repeat_first(config);
+
+        //// Then
+        assert_matches!(events.as_slice(), [Event::Message { .. }]);
+    }
+}
+
+mod dedup_scope {
+    use crate::config::DedupScope;
+
+    use super::*;
+
+    /// Create a behaviour using a content-hash message ID function, subscribed to the given
+    /// topics, with a peer subscribed to them.
+    fn content_hash_behaviour(
+        dedup_scope: DedupScope,
+        topics: [&IdentTopic; 2],
+    ) -> (Behaviour, PeerId, ConnectionId) {
+        let config = ConfigBuilder::default()
+            .dedup_scope(dedup_scope)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+        behaviour.message_id_fn = Box::new(|message: &Message| MessageId::new(message.data()));
+
+        for topic in topics {
+            behaviour.subscribe(topic).expect("subscribe to topic");
+        }
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, topics.map(Clone::clone));
+        behaviour.swarm_out_events.clear();
+
+        (behaviour, peer, connection)
+    }
+
+    #[test]
+    fn global_scope_drops_identical_payload_on_another_topic() {
+        //// Given
+        let topic_a = test_topic();
+        let topic_b = test_topic();
+        let (mut behaviour, peer, connection) =
+            content_hash_behaviour(DedupScope::Global, [&topic_a, &topic_b]);
+
+        //// When
+        let publish_result = behaviour.publish(&topic_a, b"test-payload".to_vec());
+        let republish_result = behaviour.publish(&topic_b, b"test-payload".to_vec());
+
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [
+                test_message(&topic_a, b"received-payload".to_vec()),
+                test_message(&topic_b, b"received-payload".to_vec()),
+            ],
+        );
+
+        //// Then
+        assert_matches!(publish_result, Ok(_));
+        assert_eq!(republish_result, Err(PublishError::Duplicate));
+
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(events.as_slice(), [Event::Message { topic, .. }] => {
+            assert_eq!(topic, &topic_a.hash());
+        });
+    }
+
+    #[test]
+    fn per_topic_scope_accepts_identical_payload_on_another_topic() {
+        //// Given
+        let topic_a = test_topic();
+        let topic_b = test_topic();
+        let (mut behaviour, peer, connection) =
+            content_hash_behaviour(DedupScope::PerTopic, [&topic_a, &topic_b]);
+
+        //// When
+        let publish_result = behaviour.publish(&topic_a, b"test-payload".to_vec());
+        let republish_result = behaviour.publish(&topic_b, b"test-payload".to_vec());
+
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [
+                test_message(&topic_a, b"received-payload".to_vec()),
+                test_message(&topic_b, b"received-payload".to_vec()),
+                test_message(&topic_b, b"received-payload".to_vec()),
+            ],
+        );
+
+        //// Then
+        assert_matches!(publish_result, Ok(_));
+        assert_matches!(republish_result, Ok(_));
+
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(
+            events.as_slice(),
+            [Event::Message { topic: first, .. }, Event::Message { topic: second, .. }] => {
+                assert_eq!(first, &topic_a.hash());
+                assert_eq!(second, &topic_b.hash());
+            }
+        );
+    }
+}
+
+mod dedup_strategy {
+    use crate::config::DedupStrategy;
+
+    use super::*;
+
+    #[test]
+    fn bloom_filter_drops_duplicate_messages() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .dedup_strategy(DedupStrategy::BloomFilter {
+                bits: 1 << 16,
+                hashes: 4,
+            })
+            .build()
+            .expect("valid configuration");
+        let topic = test_topic();
+        let mut behaviour = test_behaviour(config);
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        drain_behaviour_events(&mut behaviour);
+
+        let message = test_message(&topic, b"test-payload".to_vec());
+
+        //// When
+        receive_messages(&mut behaviour, peer, connection, [message.clone(), message]);
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(events.as_slice(), [Event::Message { .. }]);
+        assert_eq!(behaviour.seen_message_count(), 1);
+    }
+}
+
+mod message_expired {
+    use std::sync::{Arc, Mutex};
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    use super::*;
+
+    /// Create a behaviour reporting the expired message IDs, with a peer connected to it.
+    fn observed_behaviour(
+        builder: &mut ConfigBuilder,
+        topic: &IdentTopic,
+    ) -> (Behaviour, PeerId, ConnectionId, Arc<Mutex<Vec<MessageId>>>) {
+        let expired = Arc::new(Mutex::new(Vec::new()));
+        let observed = expired.clone();
+        let config = builder
+            .on_message_expired(Box::new(move |message_id| {
+                observed.lock().unwrap().push(message_id.clone());
+            }))
+            .build()
+            .expect("valid configuration");
+
+        let mut behaviour = test_behaviour(config);
+        behaviour.subscribe(topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        drain_behaviour_events(&mut behaviour);
+
+        (behaviour, peer, connection, expired)
+    }
+
+    #[test]
+    fn report_message_expired_after_ttl() {
+        //// Given
+        let topic = test_topic();
+        let (mut behaviour, peer, connection, expired) = observed_behaviour(
+            ConfigBuilder::default().duplicate_cache_time(Duration::from_millis(50)),
+            &topic,
+        );
+
+        let message = test_message(&topic, b"test-payload-1".to_vec());
+        let message_id = (behaviour.message_id_fn)(&message);
+        receive_messages(&mut behaviour, peer, connection, [message]);
+        let expired_before_ttl = expired.lock().unwrap().clone();
+
+        //// When
+        sleep(Duration::from_millis(60));
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [test_message(&topic, b"test-payload-2".to_vec())],
+        );
+
+        //// Then
+        assert!(expired_before_ttl.is_empty());
+        assert_eq!(*expired.lock().unwrap(), vec![message_id]);
+    }
+
+    #[test]
+    fn report_message_evicted_by_capacity() {
+        //// Given
+        let topic = test_topic();
+        let (mut behaviour, peer, connection, expired) =
+            observed_behaviour(ConfigBuilder::default().duplicate_cache_capacity(1), &topic);
+
+        let message = test_message(&topic, b"test-payload-1".to_vec());
+        let message_id = (behaviour.message_id_fn)(&message);
+
+        //// When
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [message, test_message(&topic, b"test-payload-2".to_vec())],
+        );
+
+        //// Then
+        assert_eq!(*expired.lock().unwrap(), vec![message_id]);
+    }
+}
+
+mod data_transform {
+    use base64::prelude::{Engine, BASE64_STANDARD};
+
+    use crate::topic::TopicHash;
+    use crate::transform::DataTransform;
+
+    use super::*;
+
+    /// A transform wrapping the messages data in base64.
+    #[derive(Debug)]
+    struct Base64Transform;
+
+    impl DataTransform for Base64Transform {
+        fn inbound_transform(&self, mut raw_message: Message) -> Result<Message, std::io::Error> {
+            let data = BASE64_STANDARD
+                .decode(raw_message.data())
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+            raw_message.set_data(data);
+            Ok(raw_message)
+        }
+
+        fn outbound_transform(
+            &self,
+            _topic: &TopicHash,
+            data: Vec<u8>,
+        ) -> Result<Vec<u8>, std::io::Error> {
+            Ok(BASE64_STANDARD.encode(data).into_bytes())
+        }
+    }
+
+    #[test]
+    fn deliver_inbound_transformed_message() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .data_transform(Base64Transform)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        drain_behaviour_events(&mut behaviour);
+
+        let message = test_message(&topic, BASE64_STANDARD.encode(b"test-payload"));
+
+        //// When
+        receive_messages(&mut behaviour, peer, connection, [message]);
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(events.as_slice(), [Event::Message { message, .. }] => {
+            assert_eq!(message.data(), b"test-payload");
+        });
+    }
+
+    #[test]
+    fn drop_message_failing_inbound_transform() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .data_transform(Base64Transform)
+            .emit_dropped_events(true)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        drain_behaviour_events(&mut behaviour);
+
+        let message = test_message(&topic, b"not base64!".to_vec());
+
+        //// When
+        receive_messages(&mut behaviour, peer, connection, [message]);
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(
+            events.as_slice(),
+            [Event::MessageDropped {
+                reason: DropReason::TransformFailed,
+                ..
+            }]
+        );
+    }
+
+    #[test]
+    fn publish_outbound_transformed_message() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .data_transform(Base64Transform)
+            .allow_self_origin(true)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+        behaviour.swarm_out_events.clear();
+
+        //// When
+        behaviour
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+
+        //// Then
+        let mut local_messages = Vec::new();
+        let mut sent_frames = Vec::new();
+        for event in behaviour.swarm_out_events.drain(..) {
+            match event {
+                ToSwarm::GenerateEvent(Event::Message { message, .. }) => {
+                    local_messages.push(message)
+                }
+                ToSwarm::NotifyHandler {
+                    event: HandlerCommand::SendFrame(frame),
+                    ..
+                } => sent_frames.push(frame),
+                _ => {}
+            }
+        }
+
+        assert_matches!(local_messages.as_slice(), [message] => {
+            assert_eq!(message.data(), b"test-payload");
+        });
+        assert_matches!(sent_frames.as_slice(), [frame] => {
+            let message = Message::from(frame.publish[0].clone());
+            assert_eq!(message.data(), BASE64_STANDARD.encode(b"test-payload").as_bytes());
+        });
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn check_max_frame_size_against_compressed_message() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .data_transform(crate::GzipTransform::default())
+            .max_frame_size(1024)
+            .allow_self_origin(true)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+        behaviour.swarm_out_events.clear();
+
+        let payload = b"test-payload".repeat(1000);
+
+        //// When
+        let result = behaviour.publish(&topic, payload.clone());
+
+        //// Then
+        assert!(result.is_ok());
+
+        let local_messages = behaviour
+            .swarm_out_events
+            .drain(..)
+            .filter_map(|event| match event {
+                ToSwarm::GenerateEvent(Event::Message { message, .. }) => Some(message),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_matches!(local_messages.as_slice(), [message] => {
+            assert_eq!(message.data(), payload);
+        });
+    }
+}
+
+mod deterministic_forwarding {
+    use super::*;
+
+    #[test]
+    fn publish_to_subscribed_peers_in_peer_id_order() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .deterministic_forwarding(true)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peers = [test_peer(), test_peer(), test_peer()];
+        for peer in peers {
+            let connection = connect_peer(&mut behaviour, peer);
+            receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+        }
+        behaviour.swarm_out_events.clear();
+
+        //// When
+        behaviour
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+
+        //// Then
+        let mut expected = peers.to_vec();
+        expected.sort();
+        assert_eq!(drain_frame_recipients(&mut behaviour), expected);
+    }
+
+    #[test]
+    fn forward_to_subscribed_peers_in_peer_id_order() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .deterministic_forwarding(true)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let source = test_peer();
+        let source_connection = connect_peer(&mut behaviour, source);
+
+        let peers = [test_peer(), test_peer(), test_peer()];
+        for peer in peers {
+            let connection = connect_peer(&mut behaviour, peer);
+            receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+        }
+        behaviour.swarm_out_events.clear();
+
+        let message = test_message(&topic, b"test-payload".to_vec());
+
+        //// When
+        receive_messages(&mut behaviour, source, source_connection, [message]);
+
+        //// Then
+        let mut expected = peers.to_vec();
+        expected.sort();
+        assert_eq!(drain_frame_recipients(&mut behaviour), expected);
+    }
+}
+
+mod static_forward_peers {
+    use super::*;
+
+    #[test]
+    fn forward_only_to_static_peers() {
+        //// Given
+        let static_peer_a = test_peer();
+        let static_peer_b = test_peer();
+        let config = ConfigBuilder::default()
+            .static_forward_peers(vec![static_peer_a, static_peer_b])
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let source = test_peer();
+        let source_connection = connect_peer(&mut behaviour, source);
+
+        // A static peer not subscribed to the topic.
+        connect_peer(&mut behaviour, static_peer_a);
+
+        // A static peer and a non-static peer, both subscribed to the topic.
+        let subscribed_peer = test_peer();
+        for peer in [static_peer_b, subscribed_peer] {
+            let connection = connect_peer(&mut behaviour, peer);
+            receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+        }
+        behaviour.swarm_out_events.clear();
+
+        let message = test_message(&topic, b"test-payload".to_vec());
+
+        //// When
+        receive_messages(&mut behaviour, source, source_connection, [message]);
+
+        //// Then
+        assert_eq!(
+            drain_frame_recipients(&mut behaviour)
+                .into_iter()
+                .collect::<HashSet<_>>(),
+            HashSet::from([static_peer_a, static_peer_b])
+        );
+        assert!(!behaviour.would_deliver_to(&subscribed_peer, &topic.hash(), &source));
+        assert!(behaviour.would_deliver_to(&static_peer_a, &topic.hash(), &source));
+    }
+
+    #[test]
+    fn publish_only_to_connected_static_peers() {
+        //// Given
+        let static_peer = test_peer();
+        let disconnected_static_peer = test_peer();
+        let config = ConfigBuilder::default()
+            .static_forward_peers(vec![static_peer, disconnected_static_peer])
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        connect_peer(&mut behaviour, static_peer);
+
+        let subscribed_peer = test_peer();
+        let connection = connect_peer(&mut behaviour, subscribed_peer);
+        receive_subscriptions(&mut behaviour, subscribed_peer, connection, [topic.clone()]);
+        behaviour.swarm_out_events.clear();
+
+        //// When
+        let result = behaviour.publish(&topic, b"test-payload".to_vec());
+
+        //// Then
+        assert_matches!(result, Ok(_));
+        assert_eq!(
+            drain_frame_recipients(&mut behaviour)
+                .into_iter()
+                .collect::<HashSet<_>>(),
+            HashSet::from([static_peer])
+        );
+    }
+
+    #[test]
+    fn publish_without_connected_static_peers_fails_with_insufficient_peers() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .static_forward_peers(vec![test_peer()])
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let subscribed_peer = test_peer();
+        let connection = connect_peer(&mut behaviour, subscribed_peer);
+        receive_subscriptions(&mut behaviour, subscribed_peer, connection, [topic.clone()]);
+
+        //// When
+        let result = behaviour.publish(&topic, b"test-payload".to_vec());
+
+        //// Then
+        assert_eq!(result, Err(PublishError::InsufficientPeers));
+    }
+}
+
+mod control_messages {
+    use bytes::Bytes;
+    use prost::Message as _;
+
+    use crate::proto::{
+        ControlGraftProto, ControlIHaveProto, ControlIWantProto, ControlMessageProto,
+        ControlPruneProto,
+    };
+
+    use super::*;
+
+    fn test_control(topic: &IdentTopic) -> ControlMessageProto {
+        ControlMessageProto {
+            ihave: vec![ControlIHaveProto {
+                topic_id: Some(topic.hash().into_string()),
+                message_ids: vec![Bytes::from_static(b"test-message-id")],
+            }],
+            iwant: vec![ControlIWantProto {
+                message_ids: vec![Bytes::from_static(b"test-message-id")],
+            }],
+            graft: vec![ControlGraftProto {
+                topic_id: Some(topic.hash().into_string()),
+            }],
+            prune: vec![ControlPruneProto {
+                topic_id: Some(topic.hash().into_string()),
+                peers: vec![],
+                backoff: Some(60),
+            }],
+        }
+    }
+
+    #[test]
+    fn deliver_message_received_along_control_messages() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        drain_behaviour_events(&mut behaviour);
+
+        let message = test_message(&topic, b"test-payload".to_vec());
+        let frame = RpcProto {
+            subscriptions: vec![],
+            publish: vec![message.into_proto()],
+            control: Some(test_control(&topic)),
+        };
+
+        //// When
+        receive_rpc_frame(&mut behaviour, peer, connection, frame);
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(events.as_slice(), [Event::Message { message, .. }] => {
+            assert_eq!(message.data(), b"test-payload");
+        });
+    }
+
+    #[test]
+    fn ignore_control_only_frame() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .disconnect_threshold(1)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        behaviour.swarm_out_events.clear();
+
+        let frame = RpcProto {
+            subscriptions: vec![],
+            publish: vec![],
+            control: Some(test_control(&topic)),
+        };
+
+        //// When
+        receive_rpc_frame(&mut behaviour, peer, connection, frame);
+
+        //// Then
+        assert!(behaviour.swarm_out_events.is_empty());
+    }
+
+    #[test]
+    fn decode_frame_with_unknown_control_fields() {
+        //// Given
+        let topic = test_topic();
+        let message = test_message(&topic, b"test-payload".to_vec());
+
+        // A control message with an unknown field (e.g., a gossipsub v1.2 IDONTWANT, tag 5).
+        let mut control = test_control(&topic).encode_to_vec();
+        control.extend_from_slice(&[(5 << 3) | 2, 3, (1 << 3) | 2, 1, 0xff]);
+
+        let mut encoded = RpcProto {
+            subscriptions: vec![],
+            publish: vec![message.clone().into_proto()],
+            control: None,
+        }
+        .encode_to_vec();
+        encoded.push((3 << 3) | 2);
+        prost::encoding::encode_varint(control.len() as u64, &mut encoded);
+        encoded.extend_from_slice(&control);
+
+        //// When
+        let frame = RpcProto::decode(encoded.as_slice());
+
+        //// Then
+        assert_matches!(frame, Ok(frame) => {
+            assert_eq!(frame.publish, vec![message.into_proto()]);
+            assert_eq!(frame.control, Some(test_control(&topic)));
+        });
+    }
+}
+
+mod prefix_subscriptions {
+    use super::*;
+
+    #[test]
+    fn deliver_message_matching_subscribed_prefix() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        behaviour
+            .subscribe_prefix("/app/room/")
+            .expect("subscribe to prefix");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        drain_behaviour_events(&mut behaviour);
+
+        let topic = IdentTopic::new("/app/room/123");
+        let message = test_message(&topic, b"test-payload".to_vec());
+
+        //// When
+        receive_messages(&mut behaviour, peer, connection, [message]);
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(events.as_slice(), [Event::Message { topic: message_topic, .. }] => {
+            assert_eq!(message_topic, &topic.hash());
+        });
+    }
+
+    #[test]
+    fn drop_message_not_matching_subscribed_prefix() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .forward_unsubscribed(false)
+            .emit_dropped_events(true)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+        behaviour
+            .subscribe_prefix("/app/room/")
+            .expect("subscribe to prefix");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        drain_behaviour_events(&mut behaviour);
+
+        let topic = IdentTopic::new("/app/other/123");
+        let message = test_message(&topic, b"test-payload".to_vec());
+
+        //// When
+        receive_messages(&mut behaviour, peer, connection, [message]);
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(
+            events.as_slice(),
+            [Event::MessageDropped {
+                reason: DropReason::NotSubscribed,
+                ..
+            }]
+        );
+    }
+
+    #[test]
+    fn advertise_prefix_subscription_to_peers() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let peer = test_peer();
+        connect_peer(&mut behaviour, peer);
+        behaviour.swarm_out_events.clear();
+
+        //// When
+        behaviour
+            .subscribe_prefix("/app/room/")
+            .expect("subscribe to prefix");
+        behaviour.flush_subscription_changes();
+
+        //// Then
+        let subscriptions = behaviour
+            .swarm_out_events
+            .drain(..)
+            .filter_map(|event| match event {
+                ToSwarm::NotifyHandler {
+                    event: HandlerCommand::SendFrame(frame),
+                    ..
+                } => Some(frame.subscriptions),
+                _ => None,
+            })
+            .flatten()
+            .collect::<Vec<_>>();
+        assert_matches!(subscriptions.as_slice(), [subscription] => {
+            assert_eq!(subscription.subscribe, Some(true));
+            assert_eq!(subscription.topic_id.as_deref(), Some("/app/room/*"));
+        });
+    }
+
+    #[test]
+    fn forward_message_to_peer_with_matching_prefix_subscription() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic = IdentTopic::new("/app/room/123");
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(
+            &mut behaviour,
+            peer,
+            connection,
+            [IdentTopic::new("/app/room/*")],
+        );
+
+        //// When
+        let result = behaviour.publish(&topic, b"test-payload".to_vec());
+
+        //// Then
+        assert_matches!(result, Ok(_));
+        assert!(behaviour.would_deliver_to(&peer, &topic.hash(), &test_peer()));
+    }
+}
+
+mod topic_params {
+    use crate::config::TopicParams;
+
+    use super::*;
+
+    /// Create a behaviour subscribed to the given topics, with a peer subscribed to them.
+    fn subscribed_behaviour(
+        config: Config,
+        topics: impl IntoIterator<Item = IdentTopic>,
+    ) -> (Behaviour, PeerId, ConnectionId) {
+        let topics = topics.into_iter().collect::<Vec<_>>();
+
+        let mut behaviour = test_behaviour(config);
+        for topic in &topics {
+            behaviour.subscribe(topic).expect("subscribe to topic");
+        }
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, topics);
+        drain_behaviour_events(&mut behaviour);
+
+        (behaviour, peer, connection)
+    }
+
+    #[test]
+    fn publish_fails_above_topic_size_limit() {
+        //// Given
+        let limited_topic = test_topic();
+        let other_topic = test_topic();
+        let (mut behaviour, _peer, _connection) = subscribed_behaviour(
+            Config::default(),
+            [limited_topic.clone(), other_topic.clone()],
+        );
+
+        behaviour.set_topic_params(
+            &limited_topic.hash(),
+            TopicParams {
+                max_transmit_size: Some(128),
+                ..Default::default()
+            },
+        );
+
+        //// When
+        let limited_result = behaviour.publish(&limited_topic, vec![0xff; 256]);
+        let other_result = behaviour.publish(&other_topic, vec![0xff; 256]);
+
+        //// Then
+        assert_eq!(limited_result, Err(PublishError::MessageTooLarge));
+        assert_matches!(other_result, Ok(_));
+    }
+
+    #[test]
+    fn drop_received_message_above_topic_size_limit() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .emit_dropped_events(true)
+            .build()
+            .expect("valid configuration");
+        let topic = test_topic();
+        let (mut behaviour, peer, connection) = subscribed_behaviour(config, [topic.clone()]);
+
+        behaviour.set_topic_params(
+            &topic.hash(),
+            TopicParams {
+                max_transmit_size: Some(128),
+                ..Default::default()
+            },
+        );
+
+        //// When
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [test_message(&topic, vec![0xff; 256])],
+        );
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(
+            events.as_slice(),
+            [Event::MessageDropped {
+                reason: DropReason::TooLarge,
+                ..
+            }]
+        );
+    }
+
+    #[test]
+    fn drop_unsigned_message_on_topic_requiring_signatures() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .emit_dropped_events(true)
+            .build()
+            .expect("valid configuration");
+        let topic = test_topic();
+        let (mut behaviour, peer, connection) = subscribed_behaviour(config, [topic.clone()]);
+
+        behaviour.set_topic_params(
+            &topic.hash(),
+            TopicParams {
+                require_signatures: true,
+                ..Default::default()
+            },
+        );
+
+        //// When
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [test_message(&topic, b"test-payload".to_vec())],
+        );
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(
+            events.as_slice(),
+            [Event::MessageDropped {
+                reason: DropReason::InvalidSignature,
+                ..
+            }]
+        );
+    }
+
+    #[test]
+    fn drop_message_from_author_not_allowed_on_topic() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .emit_dropped_events(true)
+            .build()
+            .expect("valid configuration");
+        let topic = test_topic();
+        let (mut behaviour, peer, connection) = subscribed_behaviour(config, [topic.clone()]);
+
+        let allowed_message = test_message(&topic, b"allowed-payload".to_vec());
+        let not_allowed_message = test_message(&topic, b"not-allowed-payload".to_vec());
+        behaviour.set_topic_allowed_authors(
+            &topic.hash(),
+            HashSet::from([allowed_message.source().expect("message author")]),
+        );
+
+        //// When
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [not_allowed_message, allowed_message],
+        );
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(
+            events.as_slice(),
+            [
+                Event::MessageDropped {
+                    reason: DropReason::AuthorNotAllowed,
+                    ..
+                },
+                Event::Message { message, .. },
+            ] if message.data() == b"allowed-payload"
+        );
+    }
+
+    #[test]
+    fn accept_messages_from_any_author_after_removing_allowed_authors() {
+        //// Given
+        let topic = test_topic();
+        let (mut behaviour, peer, connection) =
+            subscribed_behaviour(Config::default(), [topic.clone()]);
+
+        behaviour.set_topic_allowed_authors(&topic.hash(), HashSet::from([test_peer()]));
+
+        //// When
+        behaviour.remove_topic_allowed_authors(&topic.hash());
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [test_message(&topic, b"test-payload".to_vec())],
+        );
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(events.as_slice(), [Event::Message { .. }]);
+    }
+
+    #[test]
+    fn restore_global_defaults_after_removing_topic_params() {
+        //// Given
+        let topic = test_topic();
+        let (mut behaviour, _peer, _connection) =
+            subscribed_behaviour(Config::default(), [topic.clone()]);
+
+        behaviour.set_topic_params(
+            &topic.hash(),
+            TopicParams {
+                max_transmit_size: Some(128),
+                require_signatures: true,
+                drop_expired_messages: false,
+            },
+        );
+
+        //// When
+        behaviour.remove_topic_params(&topic.hash());
+
+        //// Then
+        assert_matches!(behaviour.publish(&topic, vec![0xff; 256]), Ok(_));
+    }
+}
+
+mod signing {
+    use libp2p::identity::Keypair;
+
+    use crate::config::TopicParams;
+    use crate::signing::{signature_payload, Signer, SigningError};
+
+    use super::*;
+
+    /// Create a behaviour subscribed to the topic, with a peer subscribed to it.
+    fn subscribed_behaviour(config: Config, topic: &IdentTopic) -> Behaviour {
+        let mut behaviour = test_behaviour(config);
+        behaviour.subscribe(topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+        behaviour.swarm_out_events.clear();
+
+        behaviour
+    }
+
+    /// Get the first message of the queued RPC frames.
+    fn published_message(behaviour: &mut Behaviour) -> Message {
+        drain_published_messages(behaviour)
+            .into_iter()
+            .map(Message::from)
+            .next()
+            .expect("published message")
+    }
+
+    #[test]
+    fn publish_message_signed_by_the_signer() {
+        //// Given
+        let keypair = Keypair::generate_secp256k1();
+        let public_key = keypair.public();
+        let config = ConfigBuilder::default()
+            .signer(
+                public_key.clone(),
+                Box::new(move |payload| {
+                    keypair
+                        .sign(payload)
+                        .map_err(|err| SigningError::new(err.to_string()))
+                }),
+            )
+            .build()
+            .expect("valid configuration");
+
+        let topic = test_topic();
+        let mut behaviour = subscribed_behaviour(config, &topic);
+
+        //// When
+        let result = behaviour.publish(&topic, b"test-payload".to_vec());
+
+        //// Then
+        assert_matches!(result, Ok(_));
+
+        let message = published_message(&mut behaviour);
+        assert_eq!(message.source(), Some(public_key.to_peer_id()));
+        assert_eq!(message.key(), Some(&public_key.encode_protobuf()[..]));
+        assert_matches!(message.signature(), Some(signature) => {
+            assert!(public_key.verify(&signature_payload(&message), signature));
+        });
+    }
+
+    #[test]
+    fn publish_signed_messages_with_reproducible_ids() {
+        //// Given
+        let keypair = common_test::secp256k1_keypair(common_test::keys::TEST_KEYPAIR_A);
+        let config = ConfigBuilder::default()
+            .signer(
+                keypair.public(),
+                Box::new(move |payload| {
+                    keypair
+                        .sign(payload)
+                        .map_err(|err| SigningError::new(err.to_string()))
+                }),
+            )
+            .fixed_seqno(1000)
+            .build()
+            .expect("valid configuration");
+
+        let topic = test_topic();
+        let mut behaviour = subscribed_behaviour(config, &topic);
+
+        //// When
+        let first = behaviour.publish(&topic, b"test-payload".to_vec());
+        let second = behaviour.publish(&topic, b"test-payload".to_vec());
+
+        //// Then
+        assert_matches!(first, Ok(message_id) => {
+            assert_eq!(message_id, MessageId::new(b"16Uiu2HAmFdwf4rPmUzWgKeQRz8WkxMLvqyrN6QFFSUpPm5TiCTqQ1001".to_vec()));
+        });
+        assert_matches!(second, Ok(message_id) => {
+            assert_eq!(message_id, MessageId::new(b"16Uiu2HAmFdwf4rPmUzWgKeQRz8WkxMLvqyrN6QFFSUpPm5TiCTqQ1002".to_vec()));
+        });
+    }
+
+    #[test]
+    fn publish_message_without_the_inlined_key() {
+        //// Given
+        let keypair = Keypair::generate_ed25519();
+        let public_key = keypair.public();
+        let config = ConfigBuilder::default()
+            .signer(
+                public_key.clone(),
+                Box::new(move |payload| {
+                    keypair
+                        .sign(payload)
+                        .map_err(|err| SigningError::new(err.to_string()))
+                }),
+            )
+            .inline_public_key_if_possible(true)
+            .build()
+            .expect("valid configuration");
+
+        let topic = test_topic();
+        let mut behaviour = subscribed_behaviour(config, &topic);
+
+        //// When
+        let result = behaviour.publish(&topic, b"test-payload".to_vec());
+
+        //// Then
+        assert_matches!(result, Ok(_));
+
+        let message = published_message(&mut behaviour);
+        assert_eq!(message.source(), Some(public_key.to_peer_id()));
+        assert_eq!(message.key(), None);
+        assert_matches!(message.signature(), Some(signature) => {
+            assert!(public_key.verify(&signature_payload(&message), signature));
+        });
+    }
+
+    #[test]
+    fn publish_fails_when_the_signer_fails() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .signer(
+                Keypair::generate_secp256k1().public(),
+                Box::new(|_| Err(SigningError::new("signer unavailable"))),
+            )
+            .build()
+            .expect("valid configuration");
+
+        let topic = test_topic();
+        let mut behaviour = subscribed_behaviour(config, &topic);
+
+        //// When
+        let result = behaviour.publish(&topic, b"test-payload".to_vec());
+
+        //// Then
+        assert_eq!(result, Err(PublishError::SigningError));
+    }
+
+    #[test]
+    fn publish_to_topic_requiring_signatures_succeeds_with_signer() {
+        //// Given
+        let keypair = Keypair::generate_secp256k1();
+        let config = ConfigBuilder::default()
+            .signer(
+                keypair.public(),
+                Box::new(move |payload| {
+                    keypair
+                        .sign(payload)
+                        .map_err(|err| SigningError::new(err.to_string()))
+                }),
+            )
+            .build()
+            .expect("valid configuration");
+
+        let topic = test_topic();
+        let mut behaviour = subscribed_behaviour(config, &topic);
+        behaviour.set_topic_params(
+            &topic.hash(),
+            TopicParams {
+                require_signatures: true,
+                ..Default::default()
+            },
+        );
+
+        //// When
+        let result = behaviour.publish(&topic, b"test-payload".to_vec());
+
+        //// Then
+        assert_matches!(result, Ok(_));
+    }
+
+    #[test]
+    fn drop_signed_message_rerouted_to_another_topic() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .emit_dropped_events(true)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        let signed_topic = test_topic();
+        let other_topic = test_topic();
+        behaviour
+            .subscribe(&signed_topic)
+            .expect("subscribe to topic");
+        behaviour
+            .subscribe(&other_topic)
+            .expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        drain_behaviour_events(&mut behaviour);
+
+        let keypair = Keypair::generate_secp256k1();
+        let signer = Signer::new(
+            keypair.public(),
+            Box::new(move |payload| {
+                keypair
+                    .sign(payload)
+                    .map_err(|err| SigningError::new(err.to_string()))
+            }),
+        );
+
+        let mut message = test_message(&signed_topic, b"test-payload".to_vec());
+        message.set_source(Some(signer.author()));
+        signer.sign(&mut message, false).expect("sign the message");
+
+        // The same signed message, with its topic rewritten to another topic.
+        let mut rerouted_proto = message.clone().into_proto();
+        rerouted_proto.topic = other_topic.hash().into_string();
+        let rerouted_message = Message::from(rerouted_proto);
+
+        //// When
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [rerouted_message, message],
+        );
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(
+            events.as_slice(),
+            [
+                Event::MessageDropped {
+                    reason: DropReason::InvalidSignature,
+                    ..
+                },
+                Event::Message { topic, .. },
+            ] if *topic == signed_topic.hash()
+        );
+        assert_eq!(behaviour.topic_message_count(&other_topic.hash()), 0);
+    }
+
+    #[test]
+    fn publish_raw_message_signed_by_the_signer() {
+        //// Given
+        let keypair = Keypair::generate_secp256k1();
+        let public_key = keypair.public();
+        let config = ConfigBuilder::default()
+            .signer(
+                public_key.clone(),
+                Box::new(move |payload| {
+                    keypair
+                        .sign(payload)
+                        .map_err(|err| SigningError::new(err.to_string()))
+                }),
+            )
+            .build()
+            .expect("valid configuration");
+
+        let topic = test_topic();
+        let mut behaviour = subscribed_behaviour(config, &topic);
+
+        //// When
+        let result = behaviour.publish_raw(Message::new(topic.hash(), b"test-payload".to_vec()));
+
+        //// Then
+        assert_matches!(result, Ok(_));
+
+        let message = published_message(&mut behaviour);
+        assert_eq!(message.source(), Some(public_key.to_peer_id()));
+        assert_matches!(message.signature(), Some(signature) => {
+            assert!(public_key.verify(&signature_payload(&message), signature));
+        });
+    }
+
+    #[test]
+    fn publish_raw_message_of_another_author_as_is() {
+        //// Given
+        let keypair = Keypair::generate_secp256k1();
+        let config = ConfigBuilder::default()
+            .signer(
+                keypair.public(),
+                Box::new(move |payload| {
+                    keypair
+                        .sign(payload)
+                        .map_err(|err| SigningError::new(err.to_string()))
+                }),
+            )
+            .build()
+            .expect("valid configuration");
+
+        let topic = test_topic();
+        let mut behaviour = subscribed_behaviour(config, &topic);
+
+        let message = test_message(&topic, b"test-payload".to_vec());
+
+        //// When
+        let result = behaviour.publish_raw(message.clone());
+
+        //// Then
+        assert_matches!(result, Ok(_));
+        assert_eq!(published_message(&mut behaviour), message);
+    }
+
+    #[test]
+    fn raw_message_published_with_signer_is_accepted_by_topic_requiring_signatures() {
+        use crate::testing::{assert_delivered, connect_behaviours, propagate_and_receive};
+
+        //// Given
+        let keypair = Keypair::generate_secp256k1();
+        let config = ConfigBuilder::default()
+            .signer(
+                keypair.public(),
+                Box::new(move |payload| {
+                    keypair
+                        .sign(payload)
+                        .map_err(|err| SigningError::new(err.to_string()))
+                }),
+            )
+            .build()
+            .expect("valid configuration");
+        let mut publisher = test_behaviour(config);
+        let mut receiver = test_behaviour(Config::default());
+
+        let topic = test_topic();
+        receiver.subscribe(&topic).expect("subscribe to topic");
+        receiver.set_topic_params(
+            &topic.hash(),
+            TopicParams {
+                require_signatures: true,
+                ..Default::default()
+            },
+        );
+
+        connect_behaviours(&mut publisher, &mut receiver);
+        propagate_and_receive(&mut publisher, &mut receiver);
+
+        //// When
+        publisher
+            .publish_raw(Message::new(topic.hash(), b"test-payload".to_vec()))
+            .expect("publish the message");
+        let messages = propagate_and_receive(&mut publisher, &mut receiver);
+
+        //// Then
+        assert_delivered(&messages, &topic.hash(), b"test-payload");
+    }
+}
+
+mod topic_validation {
+    use prost::Message as _;
+
+    use super::*;
+
+    /// A behaviour with the strict topic validation enabled.
+    fn validating_behaviour(max_topic_length: usize) -> Behaviour {
+        let config = ConfigBuilder::default()
+            .max_topic_length(max_topic_length)
+            .emit_dropped_events(true)
+            .build()
+            .expect("valid configuration");
+        test_behaviour(config)
+    }
+
+    #[test]
+    fn drop_message_with_overlong_topic() {
+        //// Given
+        let mut behaviour = validating_behaviour(64);
+
+        let topic = IdentTopic::new(format!("/test/{}", "a".repeat(128)));
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        drain_behaviour_events(&mut behaviour);
+
+        let message = test_message(&topic, b"test-payload".to_vec());
+
+        //// When
+        receive_messages(&mut behaviour, peer, connection, [message]);
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(
+            events.as_slice(),
+            [Event::MessageDropped {
+                reason: DropReason::InvalidTopic,
+                ..
+            }]
+        );
+    }
+
+    #[test]
+    fn drop_message_with_control_characters_in_topic() {
+        //// Given
+        let mut behaviour = validating_behaviour(64);
+
+        let topic = IdentTopic::new("/test/topic\n[INFO] injected log line");
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        drain_behaviour_events(&mut behaviour);
+
+        let message = test_message(&topic, b"test-payload".to_vec());
+
+        //// When
+        receive_messages(&mut behaviour, peer, connection, [message]);
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(
+            events.as_slice(),
+            [Event::MessageDropped {
+                reason: DropReason::InvalidTopic,
+                ..
+            }]
+        );
+    }
+
+    #[test]
+    fn drop_subscriptions_with_invalid_topic() {
+        //// Given
+        let mut behaviour = validating_behaviour(64);
+
+        let valid_topic = test_topic();
+        let overlong_topic = IdentTopic::new(format!("/test/{}", "a".repeat(128)));
+        let control_chars_topic = IdentTopic::new("/test/topic\u{1b}[31m");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+
+        //// When
+        receive_subscriptions(
+            &mut behaviour,
+            peer,
+            connection,
+            [valid_topic.clone(), overlong_topic, control_chars_topic],
+        );
+
+        //// Then
+        assert_matches!(behaviour.peer_topics(&peer), Some(topics) => {
+            assert_eq!(topics.len(), 1);
+            assert!(topics.contains(&valid_topic.hash()));
+        });
+    }
+
+    #[test]
+    fn accept_overlong_topic_by_default() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic = IdentTopic::new(format!("/test/{}", "a".repeat(1024)));
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        drain_behaviour_events(&mut behaviour);
+
+        let message = test_message(&topic, b"test-payload".to_vec());
+
+        //// When
+        receive_messages(&mut behaviour, peer, connection, [message]);
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(events.as_slice(), [Event::Message { .. }]);
+    }
+
+    #[test]
+    fn reject_frame_with_invalid_utf8_topic() {
+        //// Given
+        let frame = Frame::new_with_subscriptions([SubscriptionAction::subscribe(
+            IdentTopic::new("/test/topic-xx").hash(),
+        )]);
+        let mut bytes = RpcProto::from(frame).encode_to_vec();
+
+        // Replace the topic trailing characters with an invalid UTF-8 sequence.
+        let len = bytes.len();
+        bytes[len - 2..].copy_from_slice(&[0xff, 0xfe]);
+
+        //// When
+        let result = RpcProto::decode(bytes.as_slice());
+
+        //// Then
+        assert!(result.is_err());
+    }
+}
+
+mod message_ttl {
+    use std::time::Duration;
+
+    use instant::SystemTime;
+
+    use crate::config::TopicParams;
+    use crate::ttl;
+
+    use super::*;
+
+    /// Build a message to the topic with a TTL expiring at the given time.
+    fn test_message_with_expiration(topic: &IdentTopic, expires_at: SystemTime) -> Message {
+        let mut message = test_message(topic, b"test-payload".to_vec());
+        message.set_sequence_number(Some(ttl::ttl_seqno(expires_at, 1)));
+        message
+    }
+
+    /// Create a behaviour subscribed to the topic, connected to a source peer and to a peer
+    /// subscribed to the topic.
+    fn test_forwarding_node(
+        config: Config,
+        topic: &IdentTopic,
+    ) -> (Behaviour, (PeerId, ConnectionId), PeerId) {
+        let mut behaviour = test_behaviour(config);
+        behaviour.subscribe(topic).expect("subscribe to topic");
+
+        let source = test_peer();
+        let source_connection = connect_peer(&mut behaviour, source);
+
+        let subscriber = test_peer();
+        let subscriber_connection = connect_peer(&mut behaviour, subscriber);
+        receive_subscriptions(
+            &mut behaviour,
+            subscriber,
+            subscriber_connection,
+            [topic.clone()],
+        );
+
+        behaviour.swarm_out_events.clear();
+        (behaviour, (source, source_connection), subscriber)
+    }
+
+    /// Drop the expired messages of the topic.
+    fn enable_drop_expired_messages(behaviour: &mut Behaviour, topic: &IdentTopic) {
+        behaviour.set_topic_params(
+            &topic.hash(),
+            TopicParams {
+                drop_expired_messages: true,
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn expired_message_is_not_forwarded() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .emit_dropped_events(true)
+            .build()
+            .expect("valid configuration");
+        let topic = test_topic();
+        let (mut behaviour, (source, source_connection), subscriber) =
+            test_forwarding_node(config, &topic);
+        enable_drop_expired_messages(&mut behaviour, &topic);
+
+        let message =
+            test_message_with_expiration(&topic, SystemTime::now() - Duration::from_secs(10));
+
+        //// When
+        receive_messages(&mut behaviour, source, source_connection, [message]);
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(
+            events.as_slice(),
+            [Event::MessageDropped {
+                reason: DropReason::Expired,
+                ..
+            }]
+        );
+        assert!(drain_sent_messages(&mut behaviour)
+            .remove(&subscriber)
+            .unwrap_or_default()
+            .is_empty());
+    }
+
+    #[test]
+    fn unexpired_message_is_forwarded() {
+        //// Given
+        let topic = test_topic();
+        let (mut behaviour, (source, source_connection), subscriber) =
+            test_forwarding_node(Config::default(), &topic);
+        enable_drop_expired_messages(&mut behaviour, &topic);
+
+        let message =
+            test_message_with_expiration(&topic, SystemTime::now() + Duration::from_secs(60));
+
+        //// When
+        receive_messages(&mut behaviour, source, source_connection, [message.clone()]);
+
+        //// Then
+        assert_eq!(
+            drain_sent_messages(&mut behaviour)
+                .remove(&subscriber)
+                .unwrap_or_default(),
+            vec![message.into_proto()]
+        );
+    }
+
+    #[test]
+    fn expired_message_is_forwarded_if_not_enabled() {
+        //// Given
+        let topic = test_topic();
+        let (mut behaviour, (source, source_connection), subscriber) =
+            test_forwarding_node(Config::default(), &topic);
+
+        let message =
+            test_message_with_expiration(&topic, SystemTime::now() - Duration::from_secs(10));
+
+        //// When
+        receive_messages(&mut behaviour, source, source_connection, [message.clone()]);
+
+        //// Then
+        assert_eq!(
+            drain_sent_messages(&mut behaviour)
+                .remove(&subscriber)
+                .unwrap_or_default(),
+            vec![message.into_proto()]
+        );
+    }
+
+    #[test]
+    fn publish_with_ttl_encodes_the_expiration_time() {
+        //// Given
+        let topic = test_topic();
+        let (mut behaviour, _, subscriber) = test_forwarding_node(Config::default(), &topic);
+
+        //// When
+        behaviour
+            .publish_with_ttl(&topic, b"test-payload".to_vec(), Duration::from_secs(60))
+            .expect("publish the message");
+
+        //// Then
+        let messages = drain_sent_messages(&mut behaviour)
+            .remove(&subscriber)
+            .unwrap_or_default();
+        assert_matches!(messages.as_slice(), [message] => {
+            let seqno = message
+                .seqno
+                .as_deref()
+                .and_then(|seqno| seqno.try_into().ok())
+                .map(u64::from_be_bytes)
+                .expect("message with sequence number");
+            let expires_at = ttl::seqno_expiration(seqno).expect("seqno with expiration time");
+            assert!(expires_at > SystemTime::now() + Duration::from_secs(59));
+            assert!(expires_at <= SystemTime::now() + Duration::from_secs(61));
+        });
+    }
+}
+
+mod anonymous_seqno {
+    use libp2p::identity::Keypair;
+
+    use crate::config::AnonymousSeqno;
+    use crate::signing::SigningError;
+
+    use super::*;
+
+    /// Create a behaviour subscribed to the topic, with a peer subscribed to it.
+    fn subscribed_behaviour(config: Config, topic: &IdentTopic) -> Behaviour {
+        let mut behaviour = test_behaviour(config);
+        behaviour.subscribe(topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+        behaviour.swarm_out_events.clear();
+
+        behaviour
+    }
+
+    #[test]
+    fn anonymous_messages_carry_a_random_seqno_by_default() {
+        //// Given
+        let topic = test_topic();
+        let mut behaviour = subscribed_behaviour(Config::default(), &topic);
+
+        //// When
+        behaviour
+            .publish(&topic, b"test-payload-1".to_vec())
+            .expect("publish the message");
+        behaviour
+            .publish(&topic, b"test-payload-2".to_vec())
+            .expect("publish the message");
+
+        //// Then
+        let messages = drain_published_messages(&mut behaviour);
+        assert_matches!(messages.as_slice(), [first, second] => {
+            assert!(first.from.is_none());
+            assert!(first.seqno.is_some());
+            assert!(second.seqno.is_some());
+            assert_ne!(first.seqno, second.seqno);
+        });
+    }
+
+    #[test]
+    fn anonymous_messages_carry_no_seqno_with_none_policy() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .anonymous_seqno(AnonymousSeqno::None)
+            .build()
+            .expect("valid configuration");
+
+        let topic = test_topic();
+        let mut behaviour = subscribed_behaviour(config, &topic);
+
+        //// When
+        behaviour
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+
+        //// Then
+        let messages = drain_published_messages(&mut behaviour);
+        assert_matches!(messages.as_slice(), [message] => {
+            assert!(message.from.is_none());
+            assert!(message.seqno.is_none());
+        });
+    }
+
+    #[test]
+    fn signed_messages_carry_a_seqno_with_none_policy() {
+        //// Given
+        let keypair = Keypair::generate_secp256k1();
+        let public_key = keypair.public();
+        let config = ConfigBuilder::default()
+            .anonymous_seqno(AnonymousSeqno::None)
+            .signer(
+                public_key,
+                Box::new(move |payload| {
+                    keypair
+                        .sign(payload)
+                        .map_err(|err| SigningError::new(err.to_string()))
+                }),
+            )
+            .build()
+            .expect("valid configuration");
+
+        let topic = test_topic();
+        let mut behaviour = subscribed_behaviour(config, &topic);
+
+        //// When
+        behaviour
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+
+        //// Then
+        let messages = drain_published_messages(&mut behaviour);
+        assert_matches!(messages.as_slice(), [message] => {
+            assert!(message.from.is_some());
+            assert!(message.seqno.is_some());
+        });
+    }
+}
+
+mod publish_to_peers {
+    use super::*;
+
+    #[test]
+    fn emit_delivery_attempted_event_per_target_peer() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let topic = test_topic();
+
+        let connected_peer = test_peer();
+        connect_peer(&mut behaviour, connected_peer);
+        let disconnected_peer = test_peer();
+        drain_behaviour_events(&mut behaviour);
+
+        //// When
+        let message_id = behaviour
+            .publish_to_peers(
+                &topic,
+                b"test-payload".to_vec(),
+                [connected_peer, disconnected_peer],
+            )
+            .expect("publish the message");
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_eq!(events.len(), 2);
+        assert_matches!(&events[0], Event::DeliveryAttempted { message_id: id, peer_id, succeeded } => {
+            assert_eq!(id, &message_id);
+            assert_eq!(peer_id, &connected_peer);
+            assert!(succeeded);
+        });
+        assert_matches!(&events[1], Event::DeliveryAttempted { message_id: id, peer_id, succeeded } => {
+            assert_eq!(id, &message_id);
+            assert_eq!(peer_id, &disconnected_peer);
+            assert!(!succeeded);
+        });
+    }
+
+    #[test]
+    fn send_message_to_target_peers_only() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let target_peer = test_peer();
+        connect_peer(&mut behaviour, target_peer);
+
+        let subscribed_peer = test_peer();
+        let connection = connect_peer(&mut behaviour, subscribed_peer);
+        receive_subscriptions(&mut behaviour, subscribed_peer, connection, [topic.clone()]);
+        behaviour.swarm_out_events.clear();
+
+        //// When
+        behaviour
+            .publish_to_peers(&topic, b"test-payload".to_vec(), [target_peer])
+            .expect("publish the message");
+
+        //// Then
+        let recipients = behaviour
+            .swarm_out_events
+            .drain(..)
+            .filter_map(|event| match event {
+                ToSwarm::NotifyHandler {
+                    peer_id,
+                    event: HandlerCommand::SendFrame(frame),
+                    ..
+                } if !frame.publish.is_empty() => Some(peer_id),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(recipients, vec![target_peer]);
+    }
+
+    #[test]
+    fn blacklisted_target_peer_delivery_fails() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let topic = test_topic();
+
+        let peer = test_peer();
+        connect_peer(&mut behaviour, peer);
+        behaviour.blacklist_peer(&peer);
+        drain_behaviour_events(&mut behaviour);
+
+        //// When
+        behaviour
+            .publish_to_peers(&topic, b"test-payload".to_vec(), [peer])
+            .expect("publish the message");
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(
+            events.as_slice(),
+            [Event::DeliveryAttempted {
+                succeeded: false,
+                ..
+            }]
+        );
+    }
+}
+
+mod forward_unsubscribed {
+    use super::*;
+
+    /// Create a behaviour, not subscribed to the topic, connected to a source peer and to a peer
+    /// subscribed to the topic.
+    fn test_relay_node(
+        config: Config,
+        topic: &IdentTopic,
+    ) -> (Behaviour, (PeerId, ConnectionId), PeerId) {
+        let mut behaviour = test_behaviour(config);
+
+        let source = test_peer();
+        let source_connection = connect_peer(&mut behaviour, source);
+
+        let subscriber = test_peer();
+        let subscriber_connection = connect_peer(&mut behaviour, subscriber);
+        receive_subscriptions(
+            &mut behaviour,
+            subscriber,
+            subscriber_connection,
+            [topic.clone()],
+        );
+
+        behaviour.swarm_out_events.clear();
+        (behaviour, (source, source_connection), subscriber)
+    }
+
+    #[test]
+    fn forward_unsubscribed_topic_message_without_delivering_it() {
+        //// Given
+        let topic = test_topic();
+        let (mut behaviour, (source, source_connection), subscriber) =
+            test_relay_node(Config::default(), &topic);
+
+        let message = test_message(&topic, b"test-payload".to_vec());
+
+        //// When
+        receive_messages(&mut behaviour, source, source_connection, [message]);
+
+        //// Then
+        assert!(!behaviour
+            .swarm_out_events
+            .iter()
+            .any(|event| matches!(event, ToSwarm::GenerateEvent(_))));
+        assert_eq!(drain_message_recipients(&mut behaviour), vec![subscriber]);
+    }
+
+    #[test]
+    fn drop_unsubscribed_topic_message_if_forwarding_is_disabled() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .forward_unsubscribed(false)
+            .emit_dropped_events(true)
+            .build()
+            .expect("valid configuration");
+        let topic = test_topic();
+        let (mut behaviour, (source, source_connection), _subscriber) =
+            test_relay_node(config, &topic);
+
+        let message = test_message(&topic, b"test-payload".to_vec());
+
+        //// When
+        receive_messages(&mut behaviour, source, source_connection, [message]);
+
+        //// Then
+        assert!(!behaviour.swarm_out_events.iter().any(|event| matches!(
+            event,
+            ToSwarm::NotifyHandler {
+                event: HandlerCommand::SendFrame(_),
+                ..
+            }
+        )));
+        assert_matches!(
+            drain_behaviour_events(&mut behaviour).as_slice(),
+            [Event::MessageDropped {
+                reason: DropReason::NotSubscribed,
+                ..
+            }]
+        );
+    }
+
+    #[test]
+    fn forwarded_unsubscribed_topic_message_is_deduplicated() {
+        //// Given
+        let topic = test_topic();
+        let (mut behaviour, (source, source_connection), _subscriber) =
+            test_relay_node(Config::default(), &topic);
+
+        let message = test_message(&topic, b"test-payload".to_vec());
+        receive_messages(&mut behaviour, source, source_connection, [message.clone()]);
+        behaviour.swarm_out_events.clear();
+
+        //// When
+        receive_messages(&mut behaviour, source, source_connection, [message]);
+
+        //// Then
+        assert!(drain_message_recipients(&mut behaviour).is_empty());
+    }
+}
+
+#[cfg(feature = "tracing")]
+mod tracing_spans {
+    use std::fmt::Debug;
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id};
+    use tracing::Subscriber;
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::Layer;
+
+    use super::*;
+
+    /// A span captured by the [`SpanCapture`] layer: its name and its fields.
+    #[derive(Debug, Clone)]
+    struct CapturedSpan {
+        name: &'static str,
+        fields: HashMap<&'static str, String>,
+    }
+
+    /// A layer capturing the created spans.
+    #[derive(Clone, Default)]
+    struct SpanCapture(Arc<Mutex<Vec<CapturedSpan>>>);
+
+    impl SpanCapture {
+        fn spans(&self, name: &str) -> Vec<CapturedSpan> {
+            let spans = self.0.lock().unwrap();
+            spans
+                .iter()
+                .filter(|span| span.name == name)
+                .cloned()
+                .collect()
+        }
+    }
+
+    struct FieldsVisitor<'a>(&'a mut HashMap<&'static str, String>);
+
+    impl Visit for FieldsVisitor<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0.insert(field.name(), format!("{value:?}"));
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for SpanCapture {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+            let mut fields = HashMap::new();
+            attrs.record(&mut FieldsVisitor(&mut fields));
+            self.0.lock().unwrap().push(CapturedSpan {
+                name: attrs.metadata().name(),
+                fields,
+            });
+        }
+    }
+
+    #[test]
+    fn publish_creates_span_with_message_id() {
+        //// Given
+        let capture = SpanCapture::default();
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+
+        let config = ConfigBuilder::default()
+            .allow_publish_with_no_peers(true)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+        let topic = test_topic();
+
+        //// When
+        let message_id = tracing::subscriber::with_default(subscriber, || {
+            behaviour
+                .publish(&topic, b"test-payload".to_vec())
+                .expect("publish the message")
+        });
+
+        //// Then
+        let spans = capture.spans("publish");
+        assert_matches!(spans.as_slice(), [span] => {
+            assert_eq!(span.fields.get("message_id"), Some(&message_id.to_string()));
+            assert_eq!(span.fields.get("topic"), Some(&topic.hash().to_string()));
+        });
+    }
+
+    #[test]
+    fn forwarded_message_creates_inbound_rpc_and_forward_spans() {
+        //// Given
+        let capture = SpanCapture::default();
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+
+        let mut behaviour = test_behaviour(Config::default());
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let source = test_peer();
+        let source_connection = connect_peer(&mut behaviour, source);
+
+        let message = test_message(&topic, b"test-payload".to_vec());
+        let message_id = (behaviour.message_id_fn)(&message);
+
+        //// When
+        tracing::subscriber::with_default(subscriber, || {
+            receive_messages(&mut behaviour, source, source_connection, [message]);
+        });
+
+        //// Then
+        assert_matches!(capture.spans("inbound_rpc").as_slice(), [span] => {
+            assert_eq!(span.fields.get("peer_id"), Some(&source.to_string()));
+        });
+        assert_matches!(capture.spans("forward").as_slice(), [span] => {
+            assert_eq!(span.fields.get("peer_id"), Some(&source.to_string()));
+            assert_eq!(span.fields.get("message_id"), Some(&message_id.to_string()));
+        });
+    }
+}
+
+mod message_id_fn {
+    use crate::message_id::{default_message_id_fn, sha256_message_id_fn};
+
+    use super::*;
+
+    #[test]
+    fn received_messages_use_the_replaced_message_id_fn() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+
+        let first = test_message(&topic, b"test-payload-1".to_vec());
+        let second = test_message(&topic, b"test-payload-2".to_vec());
+
+        receive_messages(&mut behaviour, peer, connection, [first.clone()]);
+
+        //// When
+        behaviour.set_message_id_fn(Box::new(sha256_message_id_fn));
+
+        receive_messages(&mut behaviour, peer, connection, [second.clone()]);
+
+        //// Then
+        let message_ids = drain_behaviour_events(&mut behaviour)
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::Message { message_id, .. } => Some(message_id),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            message_ids,
+            vec![default_message_id_fn(&first), sha256_message_id_fn(&second)]
+        );
+    }
+
+    #[test]
+    fn replacing_the_message_id_fn_clears_the_seen_cache() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+
+        let message = test_message(&topic, b"test-payload".to_vec());
+        receive_messages(&mut behaviour, peer, connection, [message]);
+        let seen_count_before = behaviour.seen_message_count();
+
+        //// When
+        behaviour.set_message_id_fn(Box::new(sha256_message_id_fn));
+
+        //// Then
+        assert_eq!(seen_count_before, 1);
+        assert_eq!(behaviour.seen_message_count(), 0);
+    }
+}
+
+mod publish_async {
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use futures::FutureExt;
+    use libp2p::swarm::NotifyHandler;
+
+    use super::*;
+
+    #[test]
+    fn complete_once_peer_send_queue_capacity_frees_up() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .max_pending_frames_per_peer(1)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+        let topic = test_topic();
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+
+        // Fill the peer send queue.
+        behaviour
+            .publish(&topic, b"test-payload-1".to_vec())
+            .expect("publish the message");
+        drain_message_recipients(&mut behaviour);
+
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut future =
+            behaviour.publish_async(&topic, b"test-payload-2".to_vec(), Duration::from_secs(60));
+        assert!(future.poll_unpin(&mut cx).is_pending());
+        assert!(drain_message_recipients(&mut behaviour).is_empty());
+
+        //// When
+        behaviour.on_connection_handler_event(peer, connection, HandlerEvent::FramesDequeued(1));
+
+        //// Then
+        assert_eq!(drain_message_recipients(&mut behaviour), vec![peer]);
+        assert_matches!(future.poll_unpin(&mut cx), Poll::Ready(Ok(_)));
+    }
+
+    #[test]
+    fn complete_immediately_without_pending_frames_limit() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let topic = test_topic();
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+
+        behaviour
+            .publish(&topic, b"test-payload-1".to_vec())
+            .expect("publish the message");
+        drain_message_recipients(&mut behaviour);
+
+        //// When
+        let future =
+            behaviour.publish_async(&topic, b"test-payload-2".to_vec(), Duration::from_secs(60));
+
+        //// Then
+        assert_eq!(drain_message_recipients(&mut behaviour), vec![peer]);
+        assert_matches!(futures::executor::block_on(future), Ok(_));
+    }
+
+    #[test]
+    fn complete_when_waited_peer_disconnects() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .max_pending_frames_per_peer(1)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+        let topic = test_topic();
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+
+        behaviour
+            .publish(&topic, b"test-payload-1".to_vec())
+            .expect("publish the message");
+
+        let future =
+            behaviour.publish_async(&topic, b"test-payload-2".to_vec(), Duration::from_secs(60));
+
+        //// When
+        disconnect_peer(&mut behaviour, peer, connection);
+
+        //// Then
+        assert_matches!(futures::executor::block_on(future), Ok(_));
+    }
+
+    #[test]
+    fn complete_once_the_connection_with_pending_frames_closes() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .max_pending_frames_per_peer(1)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+        let topic = test_topic();
+
+        let peer = test_peer();
+        let first_connection = connect_peer(&mut behaviour, peer);
+        let second_connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, first_connection, [topic.clone()]);
+
+        // Fill the send queue of the first connection.
+        behaviour
+            .publish(&topic, b"test-payload-1".to_vec())
+            .expect("publish the message");
+        drain_message_recipients(&mut behaviour);
+
+        //// When
+        disconnect_peer(&mut behaviour, peer, first_connection);
+
+        let future =
+            behaviour.publish_async(&topic, b"test-payload-2".to_vec(), Duration::from_secs(60));
+
+        //// Then
+        assert_matches!(
+            behaviour.swarm_out_events.iter().collect::<Vec<_>>().as_slice(),
+            [ToSwarm::NotifyHandler {
+                handler: NotifyHandler::One(connection),
+                event: HandlerCommand::SendFrame(_),
+                ..
+            }] if *connection == second_connection
+        );
+        assert_matches!(futures::executor::block_on(future), Ok(_));
+    }
+
+    #[test]
+    fn send_queued_frames_over_the_remaining_connection() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let topic = test_topic();
+
+        let peer = test_peer();
+        let first_connection = connect_peer(&mut behaviour, peer);
+        let second_connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, first_connection, [topic.clone()]);
+        behaviour.swarm_out_events.clear();
+
+        behaviour
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+
+        //// When
+        disconnect_peer(&mut behaviour, peer, first_connection);
+
+        //// Then
+        assert_matches!(
+            behaviour.swarm_out_events.iter().collect::<Vec<_>>().as_slice(),
+            [ToSwarm::NotifyHandler {
+                handler: NotifyHandler::One(connection),
+                event: HandlerCommand::SendFrame(_),
+                ..
+            }] if *connection == second_connection
+        );
+    }
+
+    #[test]
+    fn fail_with_timeout_if_capacity_does_not_free_up() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .max_pending_frames_per_peer(1)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+        let topic = test_topic();
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+
+        behaviour
+            .publish(&topic, b"test-payload-1".to_vec())
+            .expect("publish the message");
+
+        //// When
+        let future = behaviour.publish_async(
+            &topic,
+            b"test-payload-2".to_vec(),
+            Duration::from_millis(10),
+        );
+
+        //// Then
+        assert_matches!(
+            futures::executor::block_on(future),
+            Err(PublishError::Timeout)
+        );
+    }
+
+    #[test]
+    fn fail_immediately_without_peers() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let topic = test_topic();
+
+        //// When
+        let future =
+            behaviour.publish_async(&topic, b"test-payload".to_vec(), Duration::from_secs(60));
+
+        //// Then
+        assert_matches!(
+            futures::executor::block_on(future),
+            Err(PublishError::InsufficientPeers)
+        );
+    }
+}
+
+mod max_subscriptions_per_incoming_rpc {
+    use super::*;
+
+    fn test_config(max_subscriptions: usize) -> Config {
+        ConfigBuilder::default()
+            .max_subscriptions_per_incoming_rpc(max_subscriptions)
+            .build()
+            .expect("valid configuration")
+    }
+
+    #[test]
+    fn reject_rpc_with_too_many_subscriptions() {
+        //// Given
+        let mut behaviour = test_behaviour(test_config(4));
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+
+        let mut frame: RpcProto = Frame::new_with_subscriptions(
+            (0..5).map(|_| SubscriptionAction::subscribe(test_topic().hash())),
+        )
+        .into();
+        frame
+            .publish
+            .push(test_message(&topic, b"test-payload").into_proto());
+
+        //// When
+        receive_rpc_frame(&mut behaviour, peer, connection, frame);
+
+        //// Then
+        let peer_topics_count = behaviour
+            .peer_topics(&peer)
+            .map(|topics| topics.len())
+            .unwrap_or_default();
+        assert_eq!(peer_topics_count, 0);
+        assert!(drain_behaviour_events(&mut behaviour).is_empty());
+        assert_matches!(behaviour.peer_stats(&peer), Some(stats) => {
+            assert_eq!(stats.rejected_rpcs, 1);
+        });
+    }
+
+    #[test]
+    fn accept_rpc_with_subscriptions_within_the_limit() {
+        //// Given
+        let mut behaviour = test_behaviour(test_config(4));
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        let topics = [test_topic(), test_topic(), test_topic(), test_topic()];
+
+        //// When
+        receive_subscriptions(&mut behaviour, peer, connection, topics.clone());
+
+        //// Then
+        assert_matches!(behaviour.peer_topics(&peer), Some(peer_topics) => {
+            assert_eq!(peer_topics.len(), 4);
+        });
+        assert_matches!(behaviour.peer_stats(&peer), Some(stats) => {
+            assert_eq!(stats.rejected_rpcs, 0);
+        });
+    }
+}
+
+mod max_peer_subscriptions_total {
+    use super::*;
+
+    fn test_config(max_subscriptions: usize) -> Config {
+        ConfigBuilder::default()
+            .max_peer_subscriptions_total(max_subscriptions)
+            .build()
+            .expect("valid configuration")
+    }
+
+    fn peer_subscriptions_count(behaviour: &Behaviour, peer: &PeerId) -> usize {
+        behaviour
+            .peer_topics(peer)
+            .map(|topics| topics.len())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn evict_least_recently_active_peer_subscriptions() {
+        //// Given
+        let mut behaviour = test_behaviour(test_config(4));
+
+        let old_peer = test_peer();
+        let old_connection = connect_peer(&mut behaviour, old_peer);
+        receive_subscriptions(
+            &mut behaviour,
+            old_peer,
+            old_connection,
+            [test_topic(), test_topic(), test_topic()],
+        );
+
+        let recent_peer = test_peer();
+        let recent_connection = connect_peer(&mut behaviour, recent_peer);
+        let recent_topics = [test_topic(), test_topic()];
+
+        //// When
+        receive_subscriptions(
+            &mut behaviour,
+            recent_peer,
+            recent_connection,
+            recent_topics.clone(),
+        );
+
+        //// Then
+        assert_eq!(behaviour.router.peer_subscriptions_count(), 4);
+        assert_eq!(peer_subscriptions_count(&behaviour, &old_peer), 2);
+        assert_matches!(behaviour.peer_topics(&recent_peer), Some(topics) => {
+            assert!(recent_topics.iter().all(|topic| topics.contains(&topic.hash())));
+        });
+    }
+
+    #[test]
+    fn peer_activity_protects_its_subscriptions() {
+        //// Given
+        let mut behaviour = test_behaviour(test_config(4));
+
+        let peer_a = test_peer();
+        let connection_a = connect_peer(&mut behaviour, peer_a);
+        receive_subscriptions(
+            &mut behaviour,
+            peer_a,
+            connection_a,
+            [test_topic(), test_topic()],
+        );
+
+        let peer_b = test_peer();
+        let connection_b = connect_peer(&mut behaviour, peer_b);
+        receive_subscriptions(
+            &mut behaviour,
+            peer_b,
+            connection_b,
+            [test_topic(), test_topic()],
+        );
+
+        //// When
+        receive_subscriptions(&mut behaviour, peer_a, connection_a, [test_topic()]);
+
+        //// Then
+        assert_eq!(behaviour.router.peer_subscriptions_count(), 4);
+        assert_eq!(peer_subscriptions_count(&behaviour, &peer_a), 3);
+        assert_eq!(peer_subscriptions_count(&behaviour, &peer_b), 1);
+    }
+
+    #[test]
+    fn keep_all_subscriptions_without_limit() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+
+        //// When
+        receive_subscriptions(
+            &mut behaviour,
+            peer,
+            connection,
+            (0..100).map(|_| test_topic()),
+        );
+
+        //// Then
+        assert_eq!(peer_subscriptions_count(&behaviour, &peer), 100);
+    }
+}
+
+mod anonymous_author {
+    use super::*;
+
+    /// Create a behaviour with a peer subscribed to the topic.
+    fn subscribed_behaviour(config: Config, topic: &IdentTopic) -> Behaviour {
+        let mut behaviour = test_behaviour(config);
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+        behaviour.swarm_out_events.clear();
+
+        behaviour
+    }
+
+    /// Publish two messages to the topic, and collect the `from` fields sent on the wire.
+    fn publish_two_messages(behaviour: &mut Behaviour, topic: &IdentTopic) -> Vec<Option<PeerId>> {
+        behaviour
+            .publish(topic, b"test-payload-1".to_vec())
+            .expect("publish the message");
+        behaviour
+            .publish(topic, b"test-payload-2".to_vec())
+            .expect("publish the message");
+
+        behaviour
+            .swarm_out_events
+            .drain(..)
+            .filter_map(|event| match event {
+                ToSwarm::NotifyHandler {
+                    event: HandlerCommand::SendFrame(frame),
+                    ..
+                } => Some(frame.publish),
+                _ => None,
+            })
+            .flatten()
+            .map(|message| Message::from(message).source())
+            .collect()
+    }
+
+    #[test]
+    fn rotated_author_differs_across_messages() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .random_anonymous_author(true)
+            .build()
+            .expect("valid configuration");
+
+        let topic = test_topic();
+        let mut behaviour = subscribed_behaviour(config, &topic);
+
+        //// When
+        let authors = publish_two_messages(&mut behaviour, &topic);
+
+        //// Then
+        assert_matches!(authors.as_slice(), [Some(first), Some(second)] => {
+            assert_ne!(first, second);
+            assert_ne!(first, &behaviour.local_peer_id);
+            assert_ne!(second, &behaviour.local_peer_id);
+        });
+    }
+
+    #[test]
+    fn stable_author_is_kept_across_messages() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .random_anonymous_author(true)
+            .rotate_anonymous_author(false)
+            .build()
+            .expect("valid configuration");
+
+        let topic = test_topic();
+        let mut behaviour = subscribed_behaviour(config, &topic);
+
+        //// When
+        let authors = publish_two_messages(&mut behaviour, &topic);
+
+        //// Then
+        assert_matches!(authors.as_slice(), [Some(first), Some(second)] => {
+            assert_eq!(first, second);
+            assert_ne!(first, &behaviour.local_peer_id);
+        });
+    }
+
+    #[test]
+    fn anonymous_messages_carry_no_author_by_default() {
+        //// Given
+        let topic = test_topic();
+        let mut behaviour = subscribed_behaviour(Config::default(), &topic);
+
+        //// When
+        let authors = publish_two_messages(&mut behaviour, &topic);
+
+        //// Then
+        assert_eq!(authors, vec![None, None]);
+    }
+}
+
+mod publish_multi {
+    use bytes::Bytes;
+
+    use crate::config::TopicParams;
+
+    use super::*;
+
+    #[test]
+    fn publish_payload_to_each_topic_subscribers() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topics = [test_topic(), test_topic(), test_topic()];
+        let subscribers = topics
+            .iter()
+            .map(|topic| {
+                let peer = test_peer();
+                let connection = connect_peer(&mut behaviour, peer);
+                receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+                peer
+            })
+            .collect::<Vec<_>>();
+        behaviour.swarm_out_events.clear();
+
+        //// When
+        let results = behaviour.publish_multi(&topics, b"test-payload".to_vec());
+
+        //// Then
+        let message_ids = results
+            .into_iter()
+            .map(|result| result.expect("publish the message"))
+            .collect::<HashSet<_>>();
+        assert_eq!(message_ids.len(), 3);
+
+        let sent = drain_sent_messages(&mut behaviour);
+        for (topic, subscriber) in topics.iter().zip(&subscribers) {
+            assert_matches!(sent.get(subscriber).map(Vec::as_slice), Some([message]) => {
+                assert_eq!(message.topic, topic.hash().as_str());
+                assert_eq!(message.data.as_deref(), Some(&b"test-payload"[..]));
+            });
+        }
+
+        let payloads = sent
+            .values()
+            .flatten()
+            .filter_map(|message| message.data.as_ref())
+            .map(|data| data.as_ptr())
+            .collect::<HashSet<_>>();
+        assert_eq!(payloads.len(), 1, "messages share the payload buffer");
+    }
+
+    #[test]
+    fn report_failures_per_topic() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let topics = [test_topic(), test_topic()];
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, topics.clone());
+
+        behaviour.set_topic_params(
+            &topics[1].hash(),
+            TopicParams {
+                max_transmit_size: Some(16),
+                ..Default::default()
+            },
+        );
+
+        //// When
+        let results = behaviour.publish_multi(&topics, vec![0xff; 64]);
+
+        //// Then
+        assert_matches!(
+            results.as_slice(),
+            [Ok(_), Err(PublishError::MessageTooLarge)]
+        );
+    }
+}
+
+mod forwarding_pause {
+    use crate::testing::{assert_delivered, connect_behaviours, propagate, propagate_and_receive};
+
+    use super::*;
+
+    /// Create a chain of three behaviours subscribed to the topic: `origin -> middle -> last`.
+    fn test_chain(topic: &IdentTopic) -> (Behaviour, Behaviour, Behaviour) {
+        let mut origin = test_behaviour(Config::default());
+        let mut middle = test_behaviour(Config::default());
+        let mut last = test_behaviour(Config::default());
+        for behaviour in [&mut origin, &mut middle, &mut last] {
+            behaviour.subscribe(topic).expect("subscribe to topic");
+        }
+
+        connect_behaviours(&mut origin, &mut middle);
+        connect_behaviours(&mut middle, &mut last);
+        propagate(&mut origin, &mut middle);
+        propagate(&mut middle, &mut last);
+
+        (origin, middle, last)
+    }
+
+    #[test]
+    fn paused_node_delivers_but_does_not_forward_messages() {
+        //// Given
+        let topic = test_topic();
+        let (mut origin, mut middle, mut last) = test_chain(&topic);
+
+        middle.pause_forwarding();
+
+        //// When
+        origin
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+        let middle_messages = propagate_and_receive(&mut origin, &mut middle);
+        let last_messages = propagate_and_receive(&mut middle, &mut last);
+
+        //// Then
+        assert!(middle.is_forwarding_paused());
+        assert_delivered(&middle_messages, &topic.hash(), b"test-payload");
+        assert!(last_messages.is_empty());
+        assert!(!middle.would_deliver_to(
+            last.local_peer_id(),
+            &topic.hash(),
+            origin.local_peer_id()
+        ));
+    }
+
+    #[test]
+    fn resumed_node_forwards_messages() {
+        //// Given
+        let topic = test_topic();
+        let (mut origin, mut middle, mut last) = test_chain(&topic);
+
+        middle.pause_forwarding();
+        origin
+            .publish(&topic, b"paused-payload".to_vec())
+            .expect("publish the message");
+        propagate(&mut origin, &mut middle);
+        let paused_messages = propagate_and_receive(&mut middle, &mut last);
+
+        //// When
+        middle.resume_forwarding();
+        origin
+            .publish(&topic, b"resumed-payload".to_vec())
+            .expect("publish the message");
+        propagate(&mut origin, &mut middle);
+        let resumed_messages = propagate_and_receive(&mut middle, &mut last);
+
+        //// Then
+        assert!(paused_messages.is_empty());
+        assert_eq!(resumed_messages.len(), 1);
+        assert_delivered(&resumed_messages, &topic.hash(), b"resumed-payload");
+    }
+
+    #[test]
+    fn paused_node_still_publishes_own_messages() {
+        //// Given
+        let topic = test_topic();
+        let (_, mut middle, mut last) = test_chain(&topic);
+
+        middle.pause_forwarding();
+
+        //// When
+        middle
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+        let last_messages = propagate_and_receive(&mut middle, &mut last);
+
+        //// Then
+        assert_delivered(&last_messages, &topic.hash(), b"test-payload");
+    }
+}
+
+mod queue_overload {
+    use super::*;
+
+    /// Create a behaviour subscribed to the given topic, with a peer subscribed to it.
+    fn subscribed_behaviour(config: Config, topic: &IdentTopic) -> Behaviour {
+        let mut behaviour = test_behaviour(config);
+        behaviour.subscribe(topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+        behaviour.swarm_out_events.clear();
+
+        behaviour
+    }
+
+    fn overload_events(events: &[Event]) -> Vec<usize> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                Event::QueueOverloaded { pending } => Some(*pending),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn emit_overload_event_once_threshold_is_crossed() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .event_queue_overload_threshold(8)
+            .build()
+            .expect("valid configuration");
+        let topic = test_topic();
+        let mut behaviour = subscribed_behaviour(config, &topic);
+
+        //// When
+        for _ in 0..16 {
+            behaviour
+                .publish(&topic, b"test-payload".to_vec())
+                .expect("publish the message");
+        }
+
+        //// Then
+        assert!(behaviour.pending_events_count() > 8);
+        assert_matches!(
+            behaviour.swarm_out_events.front(),
+            Some(ToSwarm::GenerateEvent(Event::QueueOverloaded {
+                pending: 9
+            }))
+        );
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_eq!(overload_events(&events), vec![9]);
+    }
+
+    #[test]
+    fn emit_overload_event_again_after_the_queue_drains() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .event_queue_overload_threshold(8)
+            .build()
+            .expect("valid configuration");
+        let topic = test_topic();
+        let mut behaviour = subscribed_behaviour(config, &topic);
+
+        for _ in 0..16 {
+            behaviour
+                .publish(&topic, b"test-payload".to_vec())
+                .expect("publish the message");
+        }
+        drain_behaviour_events(&mut behaviour);
+
+        //// When
+        for _ in 0..16 {
+            behaviour
+                .publish(&topic, b"test-payload".to_vec())
+                .expect("publish the message");
+        }
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_eq!(overload_events(&events), vec![9]);
+    }
+
+    #[test]
+    fn no_overload_event_without_threshold() {
+        //// Given
+        let topic = test_topic();
+        let mut behaviour = subscribed_behaviour(Config::default(), &topic);
+
+        //// When
+        for _ in 0..16 {
+            behaviour
+                .publish(&topic, b"test-payload".to_vec())
+                .expect("publish the message");
+        }
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert!(overload_events(&events).is_empty());
+    }
+}
+
+mod peer_endpoints {
+    use super::*;
+
+    #[test]
+    fn report_the_endpoint_of_received_messages() {
+        //// Given
+        let topic = test_topic();
+        let mut behaviour = test_behaviour(Config::default());
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        drain_behaviour_events(&mut behaviour);
+
+        //// When
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [test_message(&topic, b"test-payload".to_vec())],
+        );
+
+        //// Then
+        assert_eq!(behaviour.peer_endpoint(&peer), Some(test_endpoint()));
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(
+            events.as_slice(),
+            [Event::Message {
+                propagation_endpoint: Some(Endpoint::Listener),
+                ..
+            }]
+        );
+    }
+
+    #[test]
+    fn published_messages_have_no_endpoint() {
+        //// Given
+        let config = ConfigBuilder::default()
+            .allow_self_origin(true)
+            .allow_publish_with_no_peers(true)
+            .build()
+            .expect("valid configuration");
+        let topic = test_topic();
+        let mut behaviour = test_behaviour(config);
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        //// When
+        behaviour
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(
+            events.as_slice(),
+            [Event::Message {
+                propagation_endpoint: None,
+                ..
+            }]
+        );
+    }
+
+    #[test]
+    fn peer_endpoint_is_removed_on_disconnection() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+
+        //// When
+        disconnect_peer(&mut behaviour, peer, connection);
+
+        //// Then
+        assert_eq!(behaviour.peer_endpoint(&peer), None);
+    }
+}
+
+mod flood_factor {
+    use super::*;
+
+    /// Create a behaviour with the given number of peers subscribed to the topic.
+    fn test_behaviour_with_subscribers(
+        config: Config,
+        topic: &IdentTopic,
+        count: usize,
+    ) -> Behaviour {
+        let mut behaviour = test_behaviour(config);
+        for _ in 0..count {
+            let peer = test_peer();
+            let connection = connect_peer(&mut behaviour, peer);
+            receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+        }
+        behaviour.swarm_out_events.clear();
+
+        behaviour
+    }
+
+    /// Take the peers the published messages were sent to.
+    fn take_message_recipients(behaviour: &mut Behaviour) -> Vec<PeerId> {
+        behaviour.flush_pending();
+        drain_message_recipients(behaviour)
+    }
+
+    #[test]
+    fn send_message_to_a_fraction_of_the_subscribed_peers() {
+        //// Given
+        let topic = test_topic();
+        let config = ConfigBuilder::default()
+            .flood_factor(0.2)
+            .min_forward_peers(1)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour_with_subscribers(config, &topic, 100);
+
+        //// When
+        let recipients = (0..20)
+            .map(|seqno| {
+                behaviour
+                    .publish(&topic, format!("test-payload-{seqno}").into_bytes())
+                    .expect("publish the message");
+                take_message_recipients(&mut behaviour)
+            })
+            .collect::<Vec<_>>();
+
+        //// Then
+        assert!(recipients
+            .iter()
+            .all(|message_recipients| message_recipients.len() == 20));
+        // The recipients are selected at random for each message.
+        let distinct_recipients = recipients.iter().flatten().collect::<HashSet<_>>();
+        assert!(distinct_recipients.len() > 20);
+    }
+
+    #[test]
+    fn seeded_behaviours_send_message_to_the_same_peers() {
+        //// Given
+        let topic = test_topic();
+        let config = ConfigBuilder::default()
+            .flood_factor(0.2)
+            .min_forward_peers(1)
+            .forward_rng_seed(42)
+            .build()
+            .expect("valid configuration");
+
+        let peers = (0..50).map(|_| test_peer()).collect::<Vec<_>>();
+        let mut behaviours = [
+            test_behaviour(config.clone()),
+            test_behaviour(config.clone()),
+        ];
+        for behaviour in &mut behaviours {
+            for peer in &peers {
+                let connection = connect_peer(behaviour, *peer);
+                receive_subscriptions(behaviour, *peer, connection, [topic.clone()]);
+            }
+            behaviour.swarm_out_events.clear();
+        }
+
+        //// When
+        let [recipients_a, recipients_b] = behaviours.map(|mut behaviour| {
+            behaviour
+                .publish(&topic, b"test-payload".to_vec())
+                .expect("publish the message");
+            take_message_recipients(&mut behaviour)
+        });
+
+        //// Then
+        assert_eq!(recipients_a.len(), 10);
+        assert_eq!(recipients_a, recipients_b);
+    }
+
+    #[test]
+    fn send_message_to_at_least_the_minimum_peers() {
+        //// Given
+        let topic = test_topic();
+        let config = ConfigBuilder::default()
+            .flood_factor(0.01)
+            .min_forward_peers(6)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour_with_subscribers(config, &topic, 50);
+
+        //// When
+        behaviour
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+
+        //// Then
+        assert_eq!(take_message_recipients(&mut behaviour).len(), 6);
+    }
+
+    #[test]
+    fn send_message_to_all_subscribed_peers_by_default() {
+        //// Given
+        let topic = test_topic();
+        let mut behaviour = test_behaviour_with_subscribers(Config::default(), &topic, 50);
+
+        //// When
+        behaviour
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+
+        //// Then
+        assert_eq!(take_message_recipients(&mut behaviour).len(), 50);
+    }
+}
+
+mod gossipsub_idontwant {
+    use bytes::Bytes;
+    use prost::Message as _;
+
+    use super::*;
+
+    /// The gossipsub v1.2 RPC frame, with the `IDONTWANT` control messages.
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct GossipsubRpcProto {
+        #[prost(message, repeated, tag = "2")]
+        publish: Vec<MessageProto>,
+        #[prost(message, optional, tag = "3")]
+        control: Option<GossipsubControlMessageProto>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct GossipsubControlMessageProto {
+        #[prost(message, repeated, tag = "5")]
+        idontwant: Vec<ControlIDontWantProto>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct ControlIDontWantProto {
+        #[prost(bytes = "bytes", repeated, tag = "1")]
+        message_ids: Vec<Bytes>,
+    }
+
+    /// Encode a gossipsub v1.2 RPC frame with the messages and an `IDONTWANT` control message, and
+    /// decode it as a floodsub RPC frame.
+    fn decode_frame_with_idontwant(messages: Vec<MessageProto>) -> RpcProto {
+        let frame = GossipsubRpcProto {
+            publish: messages,
+            control: Some(GossipsubControlMessageProto {
+                idontwant: vec![ControlIDontWantProto {
+                    message_ids: vec![Bytes::from_static(b"test-message-id")],
+                }],
+            }),
+        };
+
+        RpcProto::decode(frame.encode_to_vec().as_slice()).expect("decode the frame")
+    }
+
+    #[test]
+    fn deliver_message_received_along_with_idontwant() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        drain_behaviour_events(&mut behaviour);
+
+        let message = test_message(&topic, b"test-payload".to_vec());
+        let messages = RpcProto::from(Frame::new_with_messages([message])).publish;
+
+        //// When
+        let frame = decode_frame_with_idontwant(messages);
+        receive_rpc_frame(&mut behaviour, peer, connection, frame);
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_matches!(events.as_slice(), [Event::Message { message, .. }] => {
+            assert_eq!(message.data(), b"test-payload");
+        });
+    }
+
+    #[test]
+    fn decode_idontwant_only_frame_as_control_frame() {
+        //// When
+        let frame = decode_frame_with_idontwant(Vec::new());
+
+        //// Then
+        assert!(frame.publish.is_empty());
+        assert_eq!(frame.control, Some(Default::default()));
+    }
+}
+
+mod publish_dry_run {
+    use super::*;
+
+    #[test]
+    fn would_publish_to_the_subscribed_peers() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let topic = test_topic();
+
+        let peer_a = test_peer();
+        let connection_a = connect_peer(&mut behaviour, peer_a);
+        receive_subscriptions(&mut behaviour, peer_a, connection_a, [topic.clone()]);
+
+        let peer_b = test_peer();
+        let connection_b = connect_peer(&mut behaviour, peer_b);
+        receive_subscriptions(&mut behaviour, peer_b, connection_b, [topic.clone()]);
+
+        let unsubscribed_peer = test_peer();
+        connect_peer(&mut behaviour, unsubscribed_peer);
+        behaviour.swarm_out_events.clear();
+
+        //// When
+        let peers = behaviour.would_publish_to(&topic.hash());
+
+        //// Then
+        assert_eq!(
+            peers.into_iter().collect::<HashSet<_>>(),
+            HashSet::from([peer_a, peer_b])
+        );
+        assert!(behaviour.swarm_out_events.is_empty());
+    }
+
+    #[test]
+    fn would_not_publish_to_blacklisted_peers() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let topic = test_topic();
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+
+        let blacklisted_peer = test_peer();
+        let connection = connect_peer(&mut behaviour, blacklisted_peer);
+        receive_subscriptions(
+            &mut behaviour,
+            blacklisted_peer,
+            connection,
+            [topic.clone()],
+        );
+        behaviour.blacklist_peer(&blacklisted_peer);
+
+        //// When
+        let peers = behaviour.would_publish_to(&topic.hash());
+
+        //// Then
+        assert_eq!(peers, vec![peer]);
+    }
+
+    #[test]
+    fn would_publish_to_the_connected_static_peers() {
+        //// Given
+        let topic = test_topic();
+        let static_peer = test_peer();
+        let disconnected_static_peer = test_peer();
+        let config = ConfigBuilder::default()
+            .static_forward_peers(vec![static_peer, disconnected_static_peer])
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+
+        connect_peer(&mut behaviour, static_peer);
+        let subscribed_peer = test_peer();
+        let connection = connect_peer(&mut behaviour, subscribed_peer);
+        receive_subscriptions(&mut behaviour, subscribed_peer, connection, [topic.clone()]);
+
+        //// When
+        let peers = behaviour.would_publish_to(&topic.hash());
+
+        //// Then
+        assert_eq!(peers, vec![static_peer]);
+    }
+
+    #[test]
+    fn would_publish_to_the_peers_a_publish_targets() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let topic = test_topic();
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+        behaviour.swarm_out_events.clear();
+
+        let dry_run_peers = behaviour.would_publish_to(&topic.hash());
+
+        //// When
+        behaviour
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+
+        //// Then
+        let recipients = behaviour
+            .swarm_out_events
+            .drain(..)
+            .filter_map(|event| match event {
+                ToSwarm::NotifyHandler {
+                    peer_id,
+                    event: HandlerCommand::SendFrame(frame),
+                    ..
+                } if !frame.publish.is_empty() => Some(peer_id),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(recipients, dry_run_peers);
+    }
+}
+
+mod topic_message_count {
+    use super::*;
+    use crate::testing::{connect_behaviours, propagate};
+
+    #[test]
+    fn count_the_messages_published_to_the_subscriber() {
+        //// Given
+        let topic = test_topic();
+
+        let mut publisher = test_behaviour(Config::default());
+        let mut subscriber = test_behaviour(Config::default());
+        subscriber.subscribe(&topic).expect("subscribe to topic");
+
+        connect_behaviours(&mut publisher, &mut subscriber);
+        propagate(&mut publisher, &mut subscriber);
+
+        //// When
+        for i in 0..5 {
+            publisher
+                .publish(&topic, format!("test-payload-{i}").into_bytes())
+                .expect("publish the message");
+        }
+        propagate(&mut publisher, &mut subscriber);
+
+        //// Then
+        assert_eq!(subscriber.topic_message_count(&topic.hash()), 5);
+        assert_eq!(subscriber.topic_message_count(&test_topic().hash()), 0);
+    }
+
+    #[test]
+    fn do_not_count_duplicate_messages() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        let message = test_message(&topic, b"test-payload");
+
+        //// When
+        receive_messages(&mut behaviour, peer, connection, [message.clone()]);
+        receive_messages(&mut behaviour, peer, connection, [message]);
+
+        //// Then
+        assert_eq!(behaviour.topic_message_count(&topic.hash()), 1);
+    }
+
+    #[test]
+    fn reset_the_count_on_unsubscribe() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [
+                test_message(&topic, b"test-payload-1"),
+                test_message(&topic, b"test-payload-2"),
+            ],
+        );
+        assert_eq!(behaviour.topic_message_count(&topic.hash()), 2);
+
+        //// When
+        behaviour
+            .unsubscribe(&topic)
+            .expect("unsubscribe from topic");
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        //// Then
+        assert_eq!(behaviour.topic_message_count(&topic.hash()), 0);
+    }
+}
+
+mod event_stream {
+    use futures::StreamExt;
+
+    use crate::testing::{connect_behaviours, propagate};
+
+    use super::*;
+
+    #[test]
+    fn consume_published_messages_through_the_event_stream() {
+        //// Given
+        let topic = test_topic();
+
+        let mut publisher = test_behaviour(Config::default());
+        let mut subscriber = test_behaviour(Config::default());
+        subscriber.subscribe(&topic).expect("subscribe to topic");
+        let stream = subscriber.event_stream();
+
+        connect_behaviours(&mut publisher, &mut subscriber);
+        propagate(&mut publisher, &mut subscriber);
+
+        //// When
+        for i in 0..3 {
+            publisher
+                .publish(&topic, format!("test-payload-{i}").into_bytes())
+                .expect("publish the message");
+        }
+        let (_, subscriber_events) = propagate(&mut publisher, &mut subscriber);
+        drop(subscriber);
+
+        //// Then
+        assert!(subscriber_events.is_empty());
+
+        // The stream ends once the behaviour is dropped.
+        let events = futures::executor::block_on(stream.collect::<Vec<_>>());
+        let payloads = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Message { message, .. } => Some(message.data()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            payloads,
+            vec![
+                &b"test-payload-0"[..],
+                &b"test-payload-1"[..],
+                &b"test-payload-2"[..],
+            ]
+        );
+    }
+
+    #[test]
+    fn move_the_queued_events_to_the_event_stream() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [test_message(&topic, b"test-payload")],
+        );
+
+        //// When
+        let mut stream = behaviour.event_stream();
+
+        //// Then
+        assert!(drain_behaviour_events(&mut behaviour).is_empty());
+        assert_matches!(
+            futures::executor::block_on(stream.next()),
+            Some(Event::Message { message, .. }) if message.data() == b"test-payload"
+        );
+    }
+
+    #[test]
+    fn return_the_events_to_the_swarm_once_the_stream_is_dropped() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+
+        let stream = behaviour.event_stream();
+
+        //// When
+        drop(stream);
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [test_message(&topic, b"test-payload")],
+        );
+
+        //// Then
+        assert_matches!(
+            drain_behaviour_events(&mut behaviour).as_slice(),
+            [Event::Message { message, .. }] if message.data() == b"test-payload"
+        );
+    }
+}
+
+mod message_sink {
+    use futures::channel::mpsc;
+
+    use super::*;
+
+    /// Create a behaviour subscribed to the topic, with a connected peer.
+    fn subscribed_behaviour(topic: &IdentTopic) -> (Behaviour, PeerId, ConnectionId) {
+        let mut behaviour = test_behaviour(Config::default());
+        behaviour.subscribe(topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+
+        (behaviour, peer, connection)
+    }
+
+    #[test]
+    fn receive_messages_through_the_channel() {
+        //// Given
+        let topic = test_topic();
+        let (mut behaviour, peer, connection) = subscribed_behaviour(&topic);
+
+        let (tx, mut rx) = mpsc::channel(8);
+        behaviour.set_message_sink(tx);
+
+        //// When
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            (0..3).map(|i| test_message(&topic, format!("test-payload-{i}"))),
+        );
+
+        //// Then
+        let mut received = Vec::new();
+        while let Ok((_, message, source)) = rx.try_recv() {
+            assert_eq!(source, peer);
+            received.push(message.data().to_vec());
+        }
+        assert_eq!(
+            received,
+            vec![
+                b"test-payload-0".to_vec(),
+                b"test-payload-1".to_vec(),
+                b"test-payload-2".to_vec(),
+            ]
+        );
+        assert!(drain_behaviour_events(&mut behaviour).is_empty());
+    }
+
+    #[test]
+    fn drop_and_count_messages_when_the_channel_is_full() {
+        //// Given
+        let topic = test_topic();
+        let (mut behaviour, peer, connection) = subscribed_behaviour(&topic);
+
+        // The channel holds a single message: one slot per sender, and no buffer.
+        let (tx, mut rx) = mpsc::channel(0);
+        behaviour.set_message_sink(tx);
+
+        //// When
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            (0..3).map(|i| test_message(&topic, format!("test-payload-{i}"))),
+        );
+
+        //// Then
+        assert_eq!(behaviour.message_sink_dropped(), 2);
+        assert_matches!(rx.try_recv(), Ok((_, message, _)) => {
+            assert_eq!(message.data(), b"test-payload-0");
+        });
+        assert!(drain_behaviour_events(&mut behaviour).is_empty());
+    }
+
+    #[test]
+    fn emit_message_events_once_the_channel_is_closed() {
+        //// Given
+        let topic = test_topic();
+        let (mut behaviour, peer, connection) = subscribed_behaviour(&topic);
+
+        let (tx, rx) = mpsc::channel(8);
+        behaviour.set_message_sink(tx);
+        drop(rx);
+
+        //// When
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [test_message(&topic, b"test-payload")],
+        );
+
+        //// Then
+        assert_eq!(behaviour.message_sink_dropped(), 0);
+        assert_matches!(
+            drain_behaviour_events(&mut behaviour).as_slice(),
+            [Event::Message { message, .. }] if message.data() == b"test-payload"
+        );
+    }
+}
+
+mod unsigned_messages {
+    use super::*;
+
+    /// Create a behaviour subscribed to the topic, connected to a peer.
+    fn subscribed_behaviour(topic: &IdentTopic) -> (Behaviour, PeerId, ConnectionId) {
+        let mut behaviour = test_behaviour(Config::default());
+        behaviour.subscribe(topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        behaviour.swarm_out_events.clear();
+
+        (behaviour, peer, connection)
+    }
+
+    #[test]
+    fn accept_message_with_seqno_but_no_signature() {
+        //// Given
+        let topic = test_topic();
+        let (mut behaviour, peer, connection) = subscribed_behaviour(&topic);
+
+        let message = test_message(&topic, b"test-payload");
+        assert!(message.signature().is_none());
+
+        //// When
+        receive_messages(&mut behaviour, peer, connection, [message.clone()]);
+
+        //// Then
+        assert_matches!(
+            drain_behaviour_events(&mut behaviour).as_slice(),
+            [Event::Message { message: received, .. }] if received == &message
+        );
+    }
+
+    #[test]
+    fn accept_message_without_source_and_seqno() {
+        //// Given
+        let topic = test_topic();
+        let (mut behaviour, peer, connection) = subscribed_behaviour(&topic);
+
+        let message = Message::new(topic.hash(), b"test-payload".to_vec());
+
+        //// When
+        receive_messages(&mut behaviour, peer, connection, [message.clone()]);
+
+        //// Then
+        assert_matches!(
+            drain_behaviour_events(&mut behaviour).as_slice(),
+            [Event::Message { message: received, .. }] if received == &message
+        );
+    }
+
+    #[test]
+    fn deliver_distinct_messages_without_seqno_once_each() {
+        //// Given
+        let topic = test_topic();
+        let (mut behaviour, peer, connection) = subscribed_behaviour(&topic);
+
+        let message1 = Message::new(topic.hash(), b"test-payload-1".to_vec());
+        let message2 = Message::new(topic.hash(), b"test-payload-2".to_vec());
+
+        //// When
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [message1.clone(), message2.clone(), message1],
+        );
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        let received = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Message { message, .. } => Some(message.data()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            received,
+            [b"test-payload-1".as_slice(), b"test-payload-2".as_slice()]
+        );
+    }
+}
+
+mod inject_message_for_test {
+    use super::*;
+
+    #[test]
+    fn deliver_the_injected_message() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let message = test_message(&topic, b"test-payload");
+
+        //// When
+        behaviour.inject_message_for_test(peer, message.clone());
+
+        //// Then
+        assert_matches!(
+            drain_behaviour_events(&mut behaviour).as_slice(),
+            [Event::Message { propagation_source, message: received, .. }]
+                if propagation_source == &peer && received == &message
+        );
+    }
+
+    #[test]
+    fn deduplicate_the_injected_messages() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let message = test_message(&topic, b"test-payload");
+
+        //// When
+        behaviour.inject_message_for_test(test_peer(), message.clone());
+        behaviour.inject_message_for_test(test_peer(), message);
+
+        //// Then
+        let events = drain_behaviour_events(&mut behaviour);
+        assert_eq!(
+            events
+                .iter()
+                .filter(|event| matches!(event, Event::Message { .. }))
+                .count(),
+            1
+        );
+    }
+}
+
+mod unacknowledged_subscriptions {
+    use super::*;
+    use crate::testing::{connect_behaviours, propagate_and_receive};
+
+    #[test]
+    fn deliver_message_received_before_the_subscription_is_sent() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+        assert!(!behaviour.pending_subscription_changes.is_empty());
+
+        //// When
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [test_message(&topic, b"test-payload")],
+        );
+
+        //// Then
+        assert_matches!(
+            drain_behaviour_events(&mut behaviour).as_slice(),
+            [Event::Message { message, .. }] if message.data() == b"test-payload"
+        );
+    }
+
+    #[test]
+    fn deliver_message_published_right_after_subscribing() {
+        //// Given
+        let topic = test_topic();
+
+        let mut subscriber = test_behaviour(Config::default());
+        let config = ConfigBuilder::default()
+            .static_forward_peers(vec![*subscriber.local_peer_id()])
+            .build()
+            .expect("valid configuration");
+        let mut publisher = test_behaviour(config);
+
+        connect_behaviours(&mut publisher, &mut subscriber);
+        propagate_and_receive(&mut publisher, &mut subscriber);
+
+        //// When
+        subscriber.subscribe(&topic).expect("subscribe to topic");
+        publisher
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+        let messages = propagate_and_receive(&mut publisher, &mut subscriber);
+
+        //// Then
+        assert_matches!(messages.as_slice(), [message] => {
+            assert_eq!(message.data(), b"test-payload");
+        });
+    }
+}
+
+mod silent_observer {
+    use super::*;
+    use crate::testing::{connect_behaviours, propagate, propagate_and_receive};
+
+    fn silent_observer_config() -> Config {
+        ConfigBuilder::default()
+            .silent_observer(true)
+            .build()
+            .expect("valid configuration")
+    }
+
+    #[test]
+    fn do_not_advertise_the_subscriptions() {
+        //// Given
+        let topic = test_topic();
+        let other_topic = test_topic();
+
+        let mut observer = test_behaviour(silent_observer_config());
+        let mut peer = test_behaviour(Config::default());
+        observer.subscribe(&topic).expect("subscribe to topic");
+
+        //// When
+        connect_behaviours(&mut observer, &mut peer);
+        observer
+            .subscribe(&other_topic)
+            .expect("subscribe to topic");
+        observer.resend_subscriptions(peer.local_peer_id());
+        propagate(&mut observer, &mut peer);
+
+        //// Then
+        assert!(observer.pending_subscription_changes.is_empty());
+        assert_eq!(
+            peer.peer_topics(observer.local_peer_id())
+                .map(|topics| topics.len())
+                .unwrap_or_default(),
+            0
+        );
+    }
+
+    #[test]
+    fn receive_messages_flooded_by_a_static_forward_peer() {
+        //// Given
+        let topic = test_topic();
+
+        let mut observer = test_behaviour(silent_observer_config());
+        observer.subscribe(&topic).expect("subscribe to topic");
+
+        let config = ConfigBuilder::default()
+            .static_forward_peers(vec![*observer.local_peer_id()])
+            .build()
+            .expect("valid configuration");
+        let mut upstream = test_behaviour(config);
+
+        connect_behaviours(&mut upstream, &mut observer);
+        propagate(&mut upstream, &mut observer);
+
+        //// When
+        upstream
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+        let messages = propagate_and_receive(&mut upstream, &mut observer);
+
+        //// Then
+        assert_eq!(
+            upstream
+                .peer_topics(observer.local_peer_id())
+                .map(|topics| topics.len())
+                .unwrap_or_default(),
+            0
+        );
+        assert_matches!(messages.as_slice(), [message] => {
+            assert_eq!(message.data(), b"test-payload");
+        });
+    }
+}
+
+mod denied_connections {
+    use libp2p::swarm::behaviour::ListenFailure;
+    use libp2p::swarm::{ConnectionDenied, ListenError};
+
+    use super::*;
+
+    /// Simulate the swarm reporting the inbound connection as denied (e.g., by a connection
+    /// limits behaviour).
+    fn deny_connection(behaviour: &mut Behaviour, connection_id: ConnectionId) {
+        let endpoint = test_endpoint();
+        let error = ListenError::Denied {
+            cause: ConnectionDenied::new(std::io::Error::other("connection limit reached")),
+        };
+        behaviour.on_swarm_event(FromSwarm::ListenFailure(ListenFailure {
+            local_addr: endpoint.get_remote_address(),
+            send_back_addr: endpoint.get_remote_address(),
+            error: &error,
+            connection_id,
+        }));
+    }
+
+    /// Assert the behaviour holds no state for the peer.
+    fn assert_no_peer_state(behaviour: &Behaviour, peer: &PeerId) {
+        assert_eq!(behaviour.connections.peer_connections().count(), 0);
+        assert_eq!(behaviour.connections.active_peers_count(), 0);
+        assert!(!behaviour.peer_kinds.contains_key(peer));
+        assert!(!behaviour.peer_endpoints.contains_key(peer));
+        assert!(!behaviour.peer_stats.contains_key(peer));
+        assert!(behaviour.peer_topics(peer).is_none());
+    }
+
+    #[test]
+    fn no_peer_state_for_denied_pending_connection() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let peer = test_peer();
+        let connection_id = ConnectionId::new_unchecked(rand::random());
+        let endpoint = test_endpoint();
+
+        //// When
+        behaviour
+            .handle_pending_inbound_connection(
+                connection_id,
+                endpoint.get_remote_address(),
+                endpoint.get_remote_address(),
+            )
+            .expect("connection to be accepted");
+        deny_connection(&mut behaviour, connection_id);
+
+        //// Then
+        assert_no_peer_state(&behaviour, &peer);
+    }
+
+    #[test]
+    fn no_peer_state_for_established_connection_denied_by_another_behaviour() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let peer = test_peer();
+        let connection_id = ConnectionId::new_unchecked(rand::random());
+        let endpoint = test_endpoint();
+
+        //// When
+        behaviour
+            .handle_established_inbound_connection(
+                connection_id,
+                peer,
+                endpoint.get_remote_address(),
+                endpoint.get_remote_address(),
+            )
+            .expect("connection to be accepted");
+        deny_connection(&mut behaviour, connection_id);
+
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        //// Then
+        assert_no_peer_state(&behaviour, &peer);
+        assert!(behaviour.pending_subscription_changes.is_empty());
+        assert!(!behaviour
+            .swarm_out_events
+            .iter()
+            .any(|event| matches!(event, ToSwarm::NotifyHandler { .. })));
+    }
+}
+
+mod max_message_id_len {
+    use super::*;
+
+    /// Create a behaviour subscribed to the topic, with a peer subscribed to it, using a message
+    /// ID function returning 1 MiB IDs.
+    fn subscribed_behaviour(topic: &IdentTopic) -> (Behaviour, PeerId, ConnectionId) {
+        let config = ConfigBuilder::default()
+            .max_message_id_len(128)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+        behaviour.set_message_id_fn(Box::new(|message| {
+            let mut id = message.data().to_vec();
+            id.resize(1024 * 1024, 0);
+            MessageId::new(id)
+        }));
+        behaviour.subscribe(topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+        behaviour.swarm_out_events.clear();
+
+        (behaviour, peer, connection)
+    }
+
+    #[test]
+    fn drop_received_message_with_oversized_id() {
+        //// Given
+        let topic = test_topic();
+        let (mut behaviour, peer, connection) = subscribed_behaviour(&topic);
+
+        //// When
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [test_message(&topic, b"test-payload")],
+        );
+
+        //// Then
+        assert!(drain_behaviour_events(&mut behaviour).is_empty());
+        assert_eq!(behaviour.oversized_message_ids(), 1);
+        assert_eq!(behaviour.seen_message_count(), 0);
+    }
+
+    #[test]
+    fn reject_published_message_with_oversized_id() {
+        //// Given
+        let topic = test_topic();
+        let (mut behaviour, _, _) = subscribed_behaviour(&topic);
+
+        //// When
+        let result = behaviour.publish(&topic, b"test-payload".to_vec());
+
+        //// Then
+        assert_matches!(result, Err(PublishError::MessageIdTooLarge));
+        assert_eq!(behaviour.seen_message_count(), 0);
+    }
+
+    #[test]
+    fn accept_received_message_with_id_within_the_limit() {
+        //// Given
+        let topic = test_topic();
+        let (mut behaviour, peer, connection) = subscribed_behaviour(&topic);
+        behaviour.set_message_id_fn(Box::new(crate::message_id::sha256_message_id_fn));
+
+        //// When
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [test_message(&topic, b"test-payload")],
+        );
+
+        //// Then
+        assert_matches!(
+            drain_behaviour_events(&mut behaviour).as_slice(),
+            [Event::Message { message, .. }] if message.data() == b"test-payload"
+        );
+        assert_eq!(behaviour.oversized_message_ids(), 0);
+    }
+}
+
+mod topic_parents {
+    use super::*;
+    use crate::testing::{connect_behaviours, propagate};
+
+    #[test]
+    fn deliver_child_topic_messages_under_the_parent_topic() {
+        //// Given
+        let parent = test_topic();
+        let child = IdentTopic::new(format!("{parent}/child"));
+
+        let mut publisher = test_behaviour(Config::default());
+        let mut subscriber = test_behaviour(Config::default());
+        subscriber
+            .subscribe_with_parent(&child, parent.hash())
+            .expect("subscribe to topic");
+
+        connect_behaviours(&mut publisher, &mut subscriber);
+        propagate(&mut publisher, &mut subscriber);
+
+        //// When
+        publisher
+            .publish(&child, b"test-payload".to_vec())
+            .expect("publish the message");
+        let (_, events) = propagate(&mut publisher, &mut subscriber);
+
+        //// Then
+        assert_matches!(events.as_slice(), [
+            Event::Message { topic: first_topic, message: first, .. },
+            Event::Message { topic: second_topic, message: second, .. },
+        ] => {
+            assert_eq!(first_topic, &child.hash());
+            assert_eq!(second_topic, &parent.hash());
+            assert_eq!(first.topic_hash(), &child.hash());
+            assert_eq!(second.topic_hash(), &child.hash());
+            assert_eq!(second.data(), b"test-payload");
+        });
+        assert!(!subscriber.is_subscribed(&parent));
+    }
+
+    #[test]
+    fn do_not_deliver_under_the_parent_topic_after_unsubscribing() {
+        //// Given
+        let parent = test_topic();
+        let child = test_topic();
+
+        let mut behaviour = test_behaviour(Config::default());
+        behaviour
+            .subscribe_with_parent(&child, parent.hash())
+            .expect("subscribe to topic");
+        behaviour
+            .unsubscribe(&child)
+            .expect("unsubscribe from topic");
+        behaviour.subscribe(&child).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+
+        //// When
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [test_message(&child, b"test-payload")],
+        );
+
+        //// Then
+        assert_matches!(
+            drain_behaviour_events(&mut behaviour).as_slice(),
+            [Event::Message { topic, .. }] if topic == &child.hash()
+        );
+    }
+
+    #[test]
+    fn poll_messages_skips_the_aggregated_messages() {
+        //// Given
+        let parent = test_topic();
+        let child = test_topic();
+
+        let mut behaviour = test_behaviour(Config::default());
+        behaviour
+            .subscribe_with_parent(&child, parent.hash())
+            .expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [test_message(&child, b"test-payload")],
+        );
+
+        //// When
+        let messages = behaviour.poll_messages().collect::<Vec<_>>();
+
+        //// Then
+        assert_matches!(messages.as_slice(), [(_, message, _)] => {
+            assert_eq!(message.data(), b"test-payload");
+        });
+        assert!(drain_behaviour_events(&mut behaviour).is_empty());
+    }
+}
+
+mod bandwidth_stats {
+    use super::*;
+    use crate::testing::{connect_behaviours, propagate, propagate_and_receive};
+
+    #[test]
+    fn count_the_published_message_bytes() {
+        //// Given
+        let topic = test_topic();
+
+        let mut publisher = test_behaviour(Config::default());
+        let mut subscriber = test_behaviour(Config::default());
+        subscriber.subscribe(&topic).expect("subscribe to topic");
+
+        connect_behaviours(&mut publisher, &mut subscriber);
+        propagate(&mut publisher, &mut subscriber);
+
+        let publisher_before = publisher.bandwidth_stats();
+        let subscriber_before = subscriber.bandwidth_stats();
+
+        //// When
+        publisher
+            .publish(&topic, vec![0xAB; 1024])
+            .expect("publish the message");
+        let messages = propagate_and_receive(&mut publisher, &mut subscriber);
+
+        //// Then
+        let message = messages.first().expect("message delivered");
+        let bytes_out = publisher.bandwidth_stats().bytes_out - publisher_before.bytes_out;
+        let bytes_in = subscriber.bandwidth_stats().bytes_in - subscriber_before.bytes_in;
+        assert!(bytes_out >= message.encoded_len() as u64);
+        assert_eq!(bytes_in, bytes_out);
+    }
+
+    #[test]
+    fn count_the_subscription_frames_bytes() {
+        //// Given
+        let topic = test_topic();
+
+        let mut a = test_behaviour(Config::default());
+        let mut b = test_behaviour(Config::default());
+        a.subscribe(&topic).expect("subscribe to topic");
+        b.subscribe(&topic).expect("subscribe to topic");
+
+        //// When
+        connect_behaviours(&mut a, &mut b);
+        propagate(&mut a, &mut b);
+
+        //// Then
+        let a_stats = a.bandwidth_stats();
+        let b_stats = b.bandwidth_stats();
+        assert!(a_stats.bytes_out > 0);
+        assert!(b_stats.bytes_out > 0);
+        assert_eq!(a_stats.bytes_out, b_stats.bytes_in);
+        assert_eq!(b_stats.bytes_out, a_stats.bytes_in);
+    }
+}
+
+mod retransmission_policy {
+    use std::time::Duration;
+
+    use instant::Instant;
+
+    use super::*;
+    use crate::config::RetransmissionPolicy;
+    use crate::testing::{connect_behaviours, propagate_and_receive};
+
+    /// Create a behaviour re-sending the given recent messages to the subscribing peers.
+    fn caching_behaviour(recent: Vec<(Instant, Message)>, max_messages: usize) -> Behaviour {
+        let policy = RetransmissionPolicy::new(
+            Box::new(move |_| recent.clone()),
+            max_messages,
+            Duration::from_secs(60),
+        );
+        let config = ConfigBuilder::default()
+            .retransmission_policy(policy)
+            .build()
+            .expect("valid configuration");
+        test_behaviour(config)
+    }
+
+    #[test]
+    fn late_peer_receives_the_recent_messages_on_connect() {
+        //// Given
+        let topic = test_topic();
+        let now = Instant::now();
+        let recent = (0..3)
+            .map(|i| (now, test_message(&topic, format!("test-payload-{i}"))))
+            .collect();
+        let mut publisher = caching_behaviour(recent, 10);
+
+        let mut late_peer = test_behaviour(Config::default());
+        late_peer.subscribe(&topic).expect("subscribe to topic");
+
+        //// When
+        connect_behaviours(&mut publisher, &mut late_peer);
+        let messages = propagate_and_receive(&mut publisher, &mut late_peer);
+
+        //// Then
+        let payloads = messages.iter().map(Message::data).collect::<Vec<_>>();
+        assert_eq!(
+            payloads,
+            [
+                b"test-payload-0".as_slice(),
+                b"test-payload-1".as_slice(),
+                b"test-payload-2".as_slice()
+            ]
+        );
+    }
+
+    #[test]
+    fn resend_only_the_most_recent_messages_within_the_max_age() {
+        //// Given
+        let topic = test_topic();
+        let now = Instant::now();
+        let recent = vec![
+            (
+                now - Duration::from_secs(120),
+                test_message(&topic, b"test-payload-expired"),
+            ),
+            (
+                now - Duration::from_secs(3),
+                test_message(&topic, b"test-payload-0"),
+            ),
+            (
+                now - Duration::from_secs(1),
+                test_message(&topic, b"test-payload-2"),
+            ),
+            (
+                now - Duration::from_secs(2),
+                test_message(&topic, b"test-payload-1"),
+            ),
+            (
+                now,
+                test_message(&test_topic(), b"test-payload-other-topic"),
+            ),
+        ];
+        let mut publisher = caching_behaviour(recent, 2);
+
+        let mut late_peer = test_behaviour(Config::default());
+        late_peer.subscribe(&topic).expect("subscribe to topic");
+
+        //// When
+        connect_behaviours(&mut publisher, &mut late_peer);
+        let messages = propagate_and_receive(&mut publisher, &mut late_peer);
+
+        //// Then
+        let payloads = messages.iter().map(Message::data).collect::<Vec<_>>();
+        assert_eq!(
+            payloads,
+            [b"test-payload-1".as_slice(), b"test-payload-2".as_slice()]
+        );
+    }
+
+    #[test]
+    fn do_not_resend_on_repeated_subscriptions() {
+        //// Given
+        let topic = test_topic();
+        let recent = vec![(Instant::now(), test_message(&topic, b"test-payload"))];
+        let mut behaviour = caching_behaviour(recent, 10);
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+        behaviour.swarm_out_events.clear();
+
+        //// When
+        receive_subscriptions(&mut behaviour, peer, connection, [topic]);
+
+        //// Then
+        assert!(behaviour.swarm_out_events.is_empty());
+    }
+}
+
+mod only_accept_from_subscribed_peers {
+    use super::*;
+
+    /// Create a behaviour subscribed to the topic, only accepting messages from subscribed peers.
+    fn subscribed_behaviour(topic: &IdentTopic) -> Behaviour {
+        let config = ConfigBuilder::default()
+            .only_accept_from_subscribed_peers(true)
+            .emit_dropped_events(true)
+            .build()
+            .expect("valid configuration");
+        let mut behaviour = test_behaviour(config);
+        behaviour.subscribe(topic).expect("subscribe to topic");
+        behaviour
+    }
+
+    #[test]
+    fn drop_message_from_peer_not_subscribed_to_the_topic() {
+        //// Given
+        let topic = test_topic();
+        let mut behaviour = subscribed_behaviour(&topic);
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [test_topic()]);
+
+        //// When
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [test_message(&topic, b"test-payload")],
+        );
+
+        //// Then
+        assert_matches!(
+            drain_behaviour_events(&mut behaviour).as_slice(),
+            [Event::MessageDropped {
+                reason: DropReason::PeerNotSubscribed,
+                ..
+            }]
+        );
+        assert_eq!(behaviour.seen_message_count(), 0);
+    }
+
+    #[test]
+    fn accept_message_from_peer_subscribed_to_the_topic() {
+        //// Given
+        let topic = test_topic();
+        let mut behaviour = subscribed_behaviour(&topic);
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+
+        //// When
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [test_message(&topic, b"test-payload")],
+        );
+
+        //// Then
+        assert_matches!(
+            drain_behaviour_events(&mut behaviour).as_slice(),
+            [Event::Message { message, .. }] if message.data() == b"test-payload"
+        );
+    }
+
+    #[test]
+    fn accept_message_from_unsubscribed_peer_by_default() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+
+        //// When
+        receive_messages(
+            &mut behaviour,
+            peer,
+            connection,
+            [test_message(&topic, b"test-payload")],
+        );
+
+        //// Then
+        assert_matches!(
+            drain_behaviour_events(&mut behaviour).as_slice(),
+            [Event::Message { .. }]
+        );
+    }
+}
+
+mod stats {
+    use super::*;
+
+    #[test]
+    fn snapshot_reflects_a_subscribe_and_a_publish() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let topic = test_topic();
+
+        let peer = test_peer();
+        let connection = connect_peer(&mut behaviour, peer);
+        receive_subscriptions(&mut behaviour, peer, connection, [topic.clone()]);
+        let before = behaviour.stats();
+
+        //// When
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+        behaviour
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+
+        //// Then
+        let stats = behaviour.stats();
+        assert_eq!(before.subscribed_topics, 0);
+        assert_eq!(before.messages_published, 0);
+        assert_eq!(stats.connected_peers, 1);
+        assert_eq!(stats.subscribed_topics, 1);
+        assert_eq!(stats.seen_messages, 1);
+        assert_eq!(stats.messages_published, 1);
+        assert!(stats.bandwidth.bytes_out > before.bandwidth.bytes_out);
+        assert_eq!(stats.bandwidth, behaviour.bandwidth_stats());
+    }
+
+    #[test]
+    fn count_the_forwarded_and_dropped_messages() {
+        //// Given
+        let mut behaviour = test_behaviour(Config::default());
+        let topic = test_topic();
+        behaviour.subscribe(&topic).expect("subscribe to topic");
+
+        let source = test_peer();
+        let source_connection = connect_peer(&mut behaviour, source);
+
+        let subscribed_peer = test_peer();
+        let connection = connect_peer(&mut behaviour, subscribed_peer);
+        receive_subscriptions(&mut behaviour, subscribed_peer, connection, [topic.clone()]);
+
+        let message = test_message(&topic, b"test-payload");
+
+        //// When
+        receive_messages(&mut behaviour, source, source_connection, [message.clone()]);
+        receive_messages(&mut behaviour, source, source_connection, [message]);
+
+        //// Then
+        let stats = behaviour.stats();
+        assert_eq!(stats.messages_forwarded, 1);
+        assert_eq!(stats.messages_dropped, 1);
+        assert_eq!(stats.messages_published, 0);
+        assert!(stats.bandwidth.bytes_in > 0);
+    }
+}
@@ -1,5 +1,357 @@
+use std::sync::Arc;
 use std::time::Duration;
 
+use instant::Instant;
+use libp2p::identity::{PeerId, PublicKey};
+
+#[cfg(feature = "debug")]
+use common::codec::ProstCodecInspector;
+
+use crate::frame::Message;
+use crate::message_id::MessageId;
+use crate::signing::{Signer, SignerFn};
+use crate::topic::TopicHash;
+use crate::transform::{DataTransform, IdentityTransform};
+
+/// The strategy used to select the subset of subscribed peers a message is forwarded to when the
+/// number of forward peers per topic is capped.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ForwardSubsetStrategy {
+    /// Select a random subset of the peers for each message.
+    #[default]
+    Random,
+
+    /// Select the subset deterministically from the message ID.
+    ///
+    /// The same message ID and set of peers always result in the same selection.
+    HashBased,
+}
+
+/// How the RPC frames are sent to a peer over the outbound substreams.
+///
+/// Some pubsub implementations keep a long-lived substream per connection, while others open a
+/// fresh substream per RPC frame. The mode only applies to the outbound substreams: the inbound
+/// substreams of the remote peers are read in both modes, whatever the remote implementation does.
+/// In both modes, only the failed substream negotiations count towards the maximum substream
+/// attempts of a connection.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum SubstreamMode {
+    /// Send all the RPC frames over a single long-lived outbound substream per connection.
+    ///
+    /// This is the most efficient mode, as the substream protocol is negotiated once.
+    #[default]
+    Persistent,
+
+    /// Open a fresh outbound substream per RPC frame, closing it once the frame is sent.
+    ///
+    /// This interoperates with the implementations reading a single RPC frame per substream, at
+    /// the cost of a protocol negotiation per frame. When the remote opens a new substream while
+    /// the previous one is not read to the end yet, the previous one is drained before being
+    /// closed, so the default
+    /// [`max_inbound_substreams`](ConfigBuilder::max_inbound_substreams) is enough on the remote.
+    PerMessage,
+}
+
+/// The scope of the inbound and outbound messages deduplication.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum DedupScope {
+    /// Messages with the same ID are duplicates, regardless of their topic.
+    #[default]
+    Global,
+
+    /// Messages with the same ID are duplicates only if they belong to the same topic.
+    ///
+    /// This lets identical messages flow on different topics when the message ID does not depend
+    /// on the topic (e.g., a content-hash message ID function).
+    PerTopic,
+}
+
+/// The data structure used to remember the seen message IDs, i.e., to deduplicate the messages.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum DedupStrategy {
+    /// An exact cache of the seen message IDs, bounded by the
+    /// [`duplicate_cache_capacity`](Config::duplicate_cache_capacity) and expiring the IDs after
+    /// the [`duplicate_cache_time`](Config::duplicate_cache_time).
+    #[default]
+    Lru,
+
+    /// A pair of Bloom filters of `bits` bits each, setting `hashes` bits per message ID.
+    ///
+    /// The filters use a fixed amount of memory regardless of the number of message IDs, at the
+    /// cost of false positives: a legitimate message may be taken for an already seen one, and
+    /// dropped as a duplicate. See [`DedupStrategy::false_positive_rate`] to size the filters.
+    ///
+    /// The filters are rotated every [`duplicate_cache_time`](Config::duplicate_cache_time), so
+    /// a message ID is remembered for one to two periods. The
+    /// [`duplicate_cache_capacity`](Config::duplicate_cache_capacity) does not apply.
+    BloomFilter {
+        /// The number of bits of each filter.
+        bits: usize,
+
+        /// The number of bits set per message ID.
+        hashes: u32,
+    },
+}
+
+impl DedupStrategy {
+    /// The expected rate of messages wrongly dropped as duplicates, once the given number of
+    /// message IDs were inserted in a Bloom filter.
+    ///
+    /// This is zero for the [`DedupStrategy::Lru`] strategy, which has no false positives.
+    #[must_use]
+    pub fn false_positive_rate(&self, items: usize) -> f64 {
+        match *self {
+            Self::Lru => 0.0,
+            Self::BloomFilter { bits, hashes } => {
+                let hashes = f64::from(hashes);
+                let fill = -hashes * items as f64 / bits as f64;
+                (1.0 - fill.exp()).powf(hashes)
+            }
+        }
+    }
+}
+
+/// The sequence number policy of the anonymous messages, i.e., the messages published without a
+/// [signer](ConfigBuilder::signer).
+///
+/// Gossipsub nodes in `Permissive` validation mode accept messages with or without sequence
+/// number, while nodes in `Anonymous` validation mode reject the messages carrying one. Nodes in
+/// `Strict` validation mode reject anonymous messages regardless of the policy.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum AnonymousSeqno {
+    /// The anonymous messages carry no sequence number.
+    ///
+    /// Without sequence number, the default message ID function falls back to the content-based
+    /// [`sha256_message_id_fn`](crate::sha256_message_id_fn), so republishing the same data to the
+    /// same topic is dropped as a duplicate.
+    None,
+
+    /// Each anonymous message carries a random sequence number.
+    #[default]
+    RandomPerMessage,
+}
+
+/// A callback invoked with the ID of each message evicted from the seen messages cache.
+pub type MessageExpiredFn = dyn Fn(&MessageId) + Send + Sync + 'static;
+
+/// The observer of the messages evicted from the seen messages cache.
+#[derive(Clone)]
+pub(crate) struct MessageExpiredObserver(Arc<MessageExpiredFn>);
+
+impl std::fmt::Debug for MessageExpiredObserver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("MessageExpiredObserver")
+            .finish_non_exhaustive()
+    }
+}
+
+impl MessageExpiredObserver {
+    /// Notify the observer the message was evicted from the seen messages cache.
+    pub(crate) fn notify(&self, message_id: &MessageId) {
+        (self.0)(message_id)
+    }
+}
+
+/// A callback returning the recent messages of the topic kept by the application, along with the
+/// time each message was published or received.
+pub type RetransmissionFn = dyn Fn(&TopicHash) -> Vec<(Instant, Message)> + Send + Sync + 'static;
+
+/// The policy re-sending the recent messages of a topic to the peers subscribing to it, so the
+/// late-joining peers catch up.
+///
+/// Floodsub keeps no message history: the recent messages are provided by the application
+/// callback, and bounded by count and age.
+///
+/// See [`ConfigBuilder::retransmission_policy`].
+#[derive(Clone)]
+pub struct RetransmissionPolicy {
+    /// The callback returning the recent messages of a topic.
+    recent_messages: Arc<RetransmissionFn>,
+
+    /// The maximum number of messages re-sent to a peer per topic.
+    max_messages: usize,
+
+    /// The maximum age of the re-sent messages.
+    max_age: Duration,
+}
+
+impl std::fmt::Debug for RetransmissionPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetransmissionPolicy")
+            .field("max_messages", &self.max_messages)
+            .field("max_age", &self.max_age)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RetransmissionPolicy {
+    /// Create a policy re-sending, to each peer subscribing to a topic, at most the
+    /// `max_messages` most recent messages returned by the callback, published or received at
+    /// most `max_age` ago.
+    pub fn new(
+        recent_messages: Box<RetransmissionFn>,
+        max_messages: usize,
+        max_age: Duration,
+    ) -> Self {
+        Self {
+            recent_messages: Arc::from(recent_messages),
+            max_messages,
+            max_age,
+        }
+    }
+
+    /// The maximum number of messages re-sent to a peer per topic.
+    pub fn max_messages(&self) -> usize {
+        self.max_messages
+    }
+
+    /// The maximum age of the re-sent messages.
+    pub fn max_age(&self) -> Duration {
+        self.max_age
+    }
+
+    /// Get the recent messages of the topic to re-send, oldest first, within the policy bounds.
+    ///
+    /// The messages returned by the callback for other topics are ignored.
+    pub(crate) fn messages_to_resend(&self, topic: &TopicHash) -> Vec<Message> {
+        let now = Instant::now();
+        let mut messages = (self.recent_messages)(topic)
+            .into_iter()
+            .filter(|(at, message)| {
+                message.topic_hash() == topic && now.saturating_duration_since(*at) <= self.max_age
+            })
+            .collect::<Vec<_>>();
+        messages.sort_by_key(|(at, _)| *at);
+
+        let skipped = messages.len().saturating_sub(self.max_messages);
+        messages
+            .into_iter()
+            .skip(skipped)
+            .map(|(_, message)| message)
+            .collect()
+    }
+}
+
+/// A callback observing the raw bytes of each inbound RPC frame, before decoding it, along with
+/// the peer that sent it.
+#[cfg(feature = "debug")]
+pub type RawRpcFn = dyn Fn(&PeerId, &[u8]) + Send + Sync + 'static;
+
+/// The observer of the raw inbound RPC frames.
+#[cfg(feature = "debug")]
+#[derive(Clone)]
+pub(crate) struct RawRpcObserver(Arc<RawRpcFn>);
+
+#[cfg(feature = "debug")]
+impl std::fmt::Debug for RawRpcObserver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("RawRpcObserver").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "debug")]
+impl RawRpcObserver {
+    /// Get a callback observing the raw RPC frames received from the given peer.
+    pub(crate) fn for_peer(&self, peer: PeerId) -> Arc<ProstCodecInspector> {
+        let observer = self.0.clone();
+        Arc::new(move |bytes| observer(&peer, bytes))
+    }
+}
+
+/// Errors that can happen when building a configuration.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// The maximum frame size is zero.
+    #[error("the maximum frame size must be greater than zero")]
+    ZeroMaxFrameSize,
+
+    /// The duplicate cache time is zero.
+    #[error("the duplicate cache time must be greater than zero")]
+    ZeroDuplicateCacheTime,
+
+    /// The duplicate cache capacity is zero.
+    #[error("the duplicate cache capacity must be greater than zero")]
+    ZeroDuplicateCacheCapacity,
+
+    /// The maximum number of subscription changes per RPC frame is zero.
+    #[error("the maximum subscriptions per RPC must be greater than zero")]
+    ZeroMaxSubscriptionsPerRpc,
+
+    /// The maximum number of concurrent inbound substreams per connection is zero.
+    #[error("the maximum inbound substreams must be greater than zero")]
+    ZeroMaxInboundSubstreams,
+
+    /// The maximum number of pending RPC frames per peer is zero.
+    #[error("the maximum pending frames per peer must be greater than zero")]
+    ZeroMaxPendingFramesPerPeer,
+
+    /// The maximum number of tracked peer subscriptions is zero.
+    #[error("the maximum total peer subscriptions must be greater than zero")]
+    ZeroMaxPeerSubscriptionsTotal,
+
+    /// The Bloom filter deduplication strategy has zero bits or hashes.
+    #[error("the bloom filter bits and hashes must be greater than zero")]
+    ZeroBloomFilterSize,
+
+    /// The event queue overload threshold is zero.
+    #[error("the event queue overload threshold must be greater than zero")]
+    ZeroEventQueueOverloadThreshold,
+
+    /// The maximum number of subscriptions per received RPC frame is zero.
+    #[error("the maximum subscriptions per incoming RPC must be greater than zero")]
+    ZeroMaxSubscriptionsPerIncomingRpc,
+
+    /// The maximum message ID length is zero.
+    #[error("the maximum message ID length must be greater than zero")]
+    ZeroMaxMessageIdLen,
+
+    /// The per-peer message rate or burst is zero.
+    #[error("the peer message rate and burst must be greater than zero")]
+    ZeroPeerMessageRate,
+
+    /// The maximum number of messages re-sent by the retransmission policy is zero.
+    #[error("the retransmission policy maximum messages must be greater than zero")]
+    ZeroRetransmissionMaxMessages,
+
+    /// The flood factor is not between zero and one.
+    #[error("the flood factor must be between zero and one")]
+    InvalidFloodFactor,
+
+    /// The protocol ID prefix is empty, or starts or ends with a slash.
+    #[error("the protocol ID prefix must be non-empty, without leading or trailing slashes")]
+    InvalidProtocolIdPrefix,
+}
+
+/// The per-topic parameters overriding the global [`Config`] defaults.
+///
+/// See [`Behaviour::set_topic_params`](crate::Behaviour::set_topic_params).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct TopicParams {
+    /// The maximum size of the topic messages, overriding the
+    /// [`max_frame_size`](Config::max_frame_size).
+    ///
+    /// Default is `None` (use the global limit).
+    pub max_transmit_size: Option<usize>,
+
+    /// Whether the topic messages must be signed. Unsigned received messages are dropped, and
+    /// publishing unsigned messages fails.
+    ///
+    /// Default is `false`.
+    pub require_signatures: bool,
+
+    /// Whether the received topic messages whose TTL has expired are dropped, instead of being
+    /// delivered and forwarded.
+    ///
+    /// The TTL of the messages published with
+    /// [`Behaviour::publish_with_ttl`](crate::Behaviour::publish_with_ttl) is carried in their
+    /// sequence number, which other sequence numbers (e.g., random ones) may be mistaken for.
+    /// Only enable it on topics whose messages are all published with a TTL.
+    ///
+    /// Default is `false`.
+    pub drop_expired_messages: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct Config {
     /// The maximum size of a RPC frame.
@@ -7,6 +359,150 @@ pub struct Config {
 
     /// The idle timeout of a connection.
     connection_idle_timeout: Duration,
+
+    /// The idle timeout of the pubsub substreams.
+    idle_timeout: Option<Duration>,
+
+    /// The timeout of the pubsub substreams protocol negotiation.
+    negotiation_timeout: Duration,
+
+    /// The prefix of the advertised pubsub protocol IDs.
+    protocol_id_prefix: String,
+
+    /// The per-peer inbound message rate limit, as `(max_per_sec, burst)`.
+    peer_message_rate: Option<(u32, u32)>,
+
+    /// The connected peers count thresholds to notify about.
+    peer_count_thresholds: Vec<usize>,
+
+    /// The maximum number of peers a message is forwarded to per topic.
+    max_forward_peers_per_topic: Option<usize>,
+
+    /// The forward peers subset selection strategy.
+    forward_subset_strategy: ForwardSubsetStrategy,
+
+    /// How the RPC frames are sent over the outbound substreams.
+    substream_mode: SubstreamMode,
+
+    /// The fraction of a topic's subscribed peers a message is published or forwarded to.
+    flood_factor: f64,
+
+    /// The minimum number of peers a message is published or forwarded to, if the flood factor
+    /// is below one.
+    min_forward_peers: usize,
+
+    /// The time a message ID is kept in the seen messages cache.
+    duplicate_cache_time: Duration,
+
+    /// The maximum number of message IDs kept in the seen messages cache.
+    duplicate_cache_capacity: usize,
+
+    /// Whether the seen messages cache capacity adapts to the recent message rate.
+    auto_cache_sizing: bool,
+
+    /// Whether to emit an event when an inbound message is dropped.
+    emit_dropped_events: bool,
+
+    /// The number of violations after which a misbehaving peer is disconnected.
+    disconnect_threshold: Option<u32>,
+
+    /// Whether to deliver the locally published messages to the local node.
+    allow_self_origin: bool,
+
+    /// Whether publishing succeeds when there are no peers subscribed to the topic.
+    allow_publish_with_no_peers: bool,
+
+    /// The transform applied to the messages data.
+    data_transform: Arc<dyn DataTransform>,
+
+    /// Whether the messages are sent to the recipient peers in `PeerId` order.
+    deterministic_forwarding: bool,
+
+    /// The maximum number of subscription changes carried by a single RPC frame.
+    max_subscriptions_per_rpc: usize,
+
+    /// The fixed set of peers the messages are forwarded to, if any.
+    static_forward_peers: Option<Vec<PeerId>>,
+
+    /// The scope of the messages deduplication.
+    dedup_scope: DedupScope,
+
+    /// The data structure used to remember the seen message IDs.
+    dedup_strategy: DedupStrategy,
+
+    /// The observer of the messages evicted from the seen messages cache, if any.
+    on_message_expired: Option<MessageExpiredObserver>,
+
+    /// The policy re-sending the recent messages to the peers subscribing to a topic, if any.
+    retransmission_policy: Option<RetransmissionPolicy>,
+
+    /// The signer of the locally published messages, if any.
+    signer: Option<Signer>,
+
+    /// Whether the `key` field of the signed messages is omitted when the public key is embedded
+    /// in the author peer ID.
+    inline_public_key_if_possible: bool,
+
+    /// The base of the sequence numbers of the messages with a stable author, if fixed.
+    #[cfg(any(test, feature = "testing"))]
+    fixed_seqno: Option<u64>,
+
+    /// The seed of the random forward peers selection, if fixed.
+    #[cfg(any(test, feature = "testing"))]
+    forward_rng_seed: Option<u64>,
+
+    /// The maximum number of concurrent inbound pubsub substreams per connection.
+    max_inbound_substreams: usize,
+
+    /// The maximum length of the inbound topic strings, if the topic validation is enabled.
+    max_topic_length: Option<usize>,
+
+    /// The observer of the raw inbound RPC frames, if any.
+    #[cfg(feature = "debug")]
+    on_raw_rpc: Option<RawRpcObserver>,
+
+    /// Whether the RPC frames are compressed with zstd, with the peers supporting it.
+    #[cfg(feature = "zstd")]
+    compress_rpc_frames: bool,
+
+    /// Whether the peer statistics are kept after the peer disconnects.
+    retain_peer_stats: bool,
+
+    /// The minimum number of subscribed peers of each subscribed topic, if any.
+    min_peers_per_topic: Option<usize>,
+
+    /// The sequence number policy of the anonymous messages.
+    anonymous_seqno: AnonymousSeqno,
+
+    /// Whether the messages from topics the local node is not subscribed to are forwarded.
+    forward_unsubscribed: bool,
+
+    /// Whether the local subscriptions are kept from the peers.
+    silent_observer: bool,
+
+    /// Whether the messages are only accepted from the peers subscribed to their topic.
+    only_accept_from_subscribed_peers: bool,
+
+    /// The maximum number of RPC frames queued for sending to each peer, if any.
+    max_pending_frames_per_peer: Option<usize>,
+
+    /// The maximum number of tracked peer subscriptions, across all peers, if any.
+    max_peer_subscriptions_total: Option<usize>,
+
+    /// The maximum number of subscriptions carried by a received RPC frame.
+    max_subscriptions_per_incoming_rpc: Option<usize>,
+
+    /// The maximum length of the message IDs, if any.
+    max_message_id_len: Option<usize>,
+
+    /// Whether the messages published without a signer carry a random author.
+    random_anonymous_author: bool,
+
+    /// Whether a new random author is generated for each anonymous message.
+    rotate_anonymous_author: bool,
+
+    /// The number of pending behaviour events above which the event queue is overloaded, if any.
+    event_queue_overload_threshold: Option<usize>,
 }
 
 impl Default for Config {
@@ -14,6 +510,56 @@ impl Default for Config {
         Self {
             max_frame_size: 65537,
             connection_idle_timeout: Duration::from_secs(120),
+            idle_timeout: None,
+            negotiation_timeout: Duration::from_secs(10),
+            protocol_id_prefix: "floodsub".to_string(),
+            peer_message_rate: None,
+            peer_count_thresholds: Vec::new(),
+            max_forward_peers_per_topic: None,
+            forward_subset_strategy: ForwardSubsetStrategy::default(),
+            substream_mode: SubstreamMode::default(),
+            flood_factor: 1.0,
+            min_forward_peers: 6,
+            duplicate_cache_time: Duration::from_secs(60),
+            duplicate_cache_capacity: 10_000,
+            auto_cache_sizing: false,
+            emit_dropped_events: false,
+            disconnect_threshold: None,
+            allow_self_origin: false,
+            allow_publish_with_no_peers: false,
+            data_transform: Arc::new(IdentityTransform),
+            deterministic_forwarding: false,
+            max_subscriptions_per_rpc: 128,
+            static_forward_peers: None,
+            dedup_scope: DedupScope::default(),
+            dedup_strategy: DedupStrategy::default(),
+            on_message_expired: None,
+            retransmission_policy: None,
+            signer: None,
+            inline_public_key_if_possible: false,
+            #[cfg(any(test, feature = "testing"))]
+            fixed_seqno: None,
+            #[cfg(any(test, feature = "testing"))]
+            forward_rng_seed: None,
+            max_inbound_substreams: 1,
+            max_topic_length: None,
+            #[cfg(feature = "debug")]
+            on_raw_rpc: None,
+            #[cfg(feature = "zstd")]
+            compress_rpc_frames: false,
+            retain_peer_stats: false,
+            min_peers_per_topic: None,
+            anonymous_seqno: AnonymousSeqno::default(),
+            forward_unsubscribed: true,
+            silent_observer: false,
+            only_accept_from_subscribed_peers: false,
+            max_pending_frames_per_peer: None,
+            max_peer_subscriptions_total: None,
+            max_subscriptions_per_incoming_rpc: None,
+            max_message_id_len: None,
+            random_anonymous_author: false,
+            rotate_anonymous_author: true,
+            event_queue_overload_threshold: None,
         }
     }
 }
@@ -32,7 +578,1128 @@ impl Config {
     /// The time a connection is maintained to a peer without being in the mesh and without
     /// send/receiving a message from. Connections that idle beyond this timeout are disconnected.
     /// Default is 120 seconds.
+    ///
+    /// This timeout only applies when no [`idle_timeout`](Self::idle_timeout) is configured and
+    /// the local node is not subscribed to any topic.
     pub fn connection_idle_timeout(&self) -> Duration {
         self.connection_idle_timeout
     }
+
+    /// The time the pubsub substreams of a connection are kept open without send/receiving a
+    /// message.
+    ///
+    /// If set, connections that idle beyond this timeout are disconnected, regardless of the
+    /// local node subscriptions. If `None`, connections are kept alive as long as the local node
+    /// is subscribed to at least one topic, and the
+    /// [`connection_idle_timeout`](Self::connection_idle_timeout) applies otherwise.
+    ///
+    /// Default is `None`.
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+
+    /// The maximum time allowed for the protocol negotiation of an inbound or outbound pubsub
+    /// substream. Substreams of peers stalling the negotiation beyond this timeout are dropped.
+    ///
+    /// Default is 10 seconds.
+    pub fn negotiation_timeout(&self) -> Duration {
+        self.negotiation_timeout
+    }
+
+    /// The prefix of the advertised pubsub protocol IDs, i.e., the protocol ID is
+    /// `/<prefix>/1.0.0`.
+    ///
+    /// Peers with different prefixes do not negotiate pubsub substreams with each other. This
+    /// isolates application-specific networks from the public floodsub network.
+    ///
+    /// Default is `floodsub`.
+    pub fn protocol_id_prefix(&self) -> &str {
+        &self.protocol_id_prefix
+    }
+
+    /// The per-peer inbound message rate limit, as a `(max_per_sec, burst)` pair.
+    ///
+    /// Each peer is assigned a token bucket holding up to `burst` tokens and refilled at
+    /// `max_per_sec` tokens per second. Every inbound message consumes one token; messages received
+    /// when the bucket is empty are dropped.
+    ///
+    /// Default is `None` (no rate limiting).
+    pub fn peer_message_rate(&self) -> Option<(u32, u32)> {
+        self.peer_message_rate
+    }
+
+    /// The connected peers count thresholds.
+    ///
+    /// An [`Event::PeerCountThreshold`](crate::Event::PeerCountThreshold) event is emitted every
+    /// time the number of connected peers crosses one of these thresholds, in either direction.
+    ///
+    /// Default is no thresholds.
+    pub fn peer_count_thresholds(&self) -> &[usize] {
+        &self.peer_count_thresholds
+    }
+
+    /// The maximum number of subscribed peers a message is published or forwarded to.
+    ///
+    /// If a topic has more subscribed peers than this limit, a subset of them is selected according
+    /// to the [`forward_subset_strategy`](Self::forward_subset_strategy).
+    ///
+    /// Default is `None` (flood to all subscribed peers).
+    pub fn max_forward_peers_per_topic(&self) -> Option<usize> {
+        self.max_forward_peers_per_topic
+    }
+
+    /// The strategy used to select the subset of forward peers.
+    ///
+    /// Default is [`ForwardSubsetStrategy::Random`].
+    pub fn forward_subset_strategy(&self) -> ForwardSubsetStrategy {
+        self.forward_subset_strategy
+    }
+
+    /// How the RPC frames are sent to the peers over the outbound substreams.
+    ///
+    /// See [`SubstreamMode`] for the interoperability implications of each mode.
+    ///
+    /// Default is [`SubstreamMode::Persistent`].
+    pub fn substream_mode(&self) -> SubstreamMode {
+        self.substream_mode
+    }
+
+    /// The fraction (from 0 to 1) of a topic's subscribed peers a message is published or
+    /// forwarded to.
+    ///
+    /// Below one, a message is sent to a subset of the subscribed peers, selected according to
+    /// the [`forward_subset_strategy`](Self::forward_subset_strategy), of at least
+    /// [`min_forward_peers`](Self::min_forward_peers) peers. This reduces the bandwidth used in
+    /// dense networks, at the cost of some redundancy. The
+    /// [`max_forward_peers_per_topic`](Self::max_forward_peers_per_topic) cap still applies.
+    ///
+    /// Default is `1.0` (flood to all subscribed peers).
+    pub fn flood_factor(&self) -> f64 {
+        self.flood_factor
+    }
+
+    /// The minimum number of subscribed peers a message is published or forwarded to, if the
+    /// [`flood_factor`](Self::flood_factor) is below one.
+    ///
+    /// Default is `6`.
+    pub fn min_forward_peers(&self) -> usize {
+        self.min_forward_peers
+    }
+
+    /// The time a message ID is kept in the seen messages cache. Messages received again within
+    /// this period are considered duplicates and dropped.
+    ///
+    /// Default is 60 seconds.
+    pub fn duplicate_cache_time(&self) -> Duration {
+        self.duplicate_cache_time
+    }
+
+    /// The maximum number of message IDs kept in the seen messages cache. When full, the oldest
+    /// entries are evicted.
+    ///
+    /// Default is 10000 entries.
+    pub fn duplicate_cache_capacity(&self) -> usize {
+        self.duplicate_cache_capacity
+    }
+
+    /// Whether the seen messages cache capacity adapts to the recent message rate, so the IDs seen
+    /// within the [`duplicate_cache_time`](Config::duplicate_cache_time) are never evicted.
+    ///
+    /// When the cache is full, its capacity grows instead of evicting IDs not expired yet, and it
+    /// shrinks back as the IDs expire, down to the
+    /// [`duplicate_cache_capacity`](Config::duplicate_cache_capacity). The memory used by the
+    /// cache is then bounded by the message rate only. This applies to the [`DedupStrategy::Lru`]
+    /// strategy.
+    ///
+    /// Default is `false`.
+    pub fn auto_cache_sizing(&self) -> bool {
+        self.auto_cache_sizing
+    }
+
+    /// Whether to emit an [`Event::MessageDropped`](crate::Event::MessageDropped) event every
+    /// time an inbound message is dropped before being delivered to the application.
+    ///
+    /// Default is `false`.
+    pub fn emit_dropped_events(&self) -> bool {
+        self.emit_dropped_events
+    }
+
+    /// The number of violations (e.g., invalid messages, rate limit violations) after which a
+    /// misbehaving peer is disconnected and an
+    /// [`Event::PeerDisconnected`](crate::Event::PeerDisconnected) event is emitted.
+    ///
+    /// Default is `None` (misbehaving peers are never disconnected).
+    pub fn disconnect_threshold(&self) -> Option<u32> {
+        self.disconnect_threshold
+    }
+
+    /// Whether the locally published messages are also delivered to the local node as an
+    /// [`Event::Message`](crate::Event::Message) event, with the local peer ID as propagation source.
+    ///
+    /// Regardless of this setting, locally published messages sent back by a peer are dropped.
+    ///
+    /// Default is `false`.
+    pub fn allow_self_origin(&self) -> bool {
+        self.allow_self_origin
+    }
+
+    /// Whether publishing a message succeeds when there are no peers subscribed to the topic. If
+    /// disabled, publishing fails with
+    /// [`PublishError::InsufficientPeers`](crate::PublishError::InsufficientPeers) instead, so the
+    /// application can detect that the node is isolated.
+    ///
+    /// Default is `false`.
+    pub fn allow_publish_with_no_peers(&self) -> bool {
+        self.allow_publish_with_no_peers
+    }
+
+    /// The transform applied to the published messages data, and to the received messages before
+    /// delivering them to the application.
+    ///
+    /// Default is [`IdentityTransform`](crate::IdentityTransform).
+    pub fn data_transform(&self) -> &dyn DataTransform {
+        self.data_transform.as_ref()
+    }
+
+    /// Whether the published and forwarded messages are sent to the recipient peers sorted by
+    /// `PeerId`, instead of in the (unspecified) order of the internal peer sets.
+    ///
+    /// This is intended for tests asserting the delivery order. It adds a sort of the recipient
+    /// peers to every published or forwarded message, so it should stay disabled in production.
+    ///
+    /// Default is `false`.
+    pub fn deterministic_forwarding(&self) -> bool {
+        self.deterministic_forwarding
+    }
+
+    /// The maximum number of subscription changes carried by a single RPC frame.
+    ///
+    /// The subscription changes are batched and sent to the peers when the swarm is polled. Larger
+    /// batches (e.g., when subscribing to many topics at startup) are split into multiple RPC
+    /// frames, each carrying at most this number of subscription changes.
+    ///
+    /// Default is 128.
+    pub fn max_subscriptions_per_rpc(&self) -> usize {
+        self.max_subscriptions_per_rpc
+    }
+
+    /// The fixed set of peers the published and forwarded messages are sent to ("static mesh"
+    /// mode).
+    ///
+    /// When set, the messages are sent to the connected peers of this set only, regardless of the
+    /// peers subscriptions, and the subscription-based forwarding (including the
+    /// [`max_forward_peers_per_topic`](Self::max_forward_peers_per_topic) cap) is disabled. This
+    /// gives a predictable topology in test harnesses and private networks.
+    ///
+    /// Default is `None` (subscription-based forwarding).
+    pub fn static_forward_peers(&self) -> Option<&[PeerId]> {
+        self.static_forward_peers.as_deref()
+    }
+
+    /// The scope of the messages deduplication, i.e., whether the seen messages cache is keyed by
+    /// the message ID only, or by the message topic and ID.
+    ///
+    /// Default is [`DedupScope::Global`].
+    pub fn dedup_scope(&self) -> DedupScope {
+        self.dedup_scope
+    }
+
+    /// The data structure used to remember the seen message IDs.
+    ///
+    /// The [`DedupStrategy::BloomFilter`] strategy bounds the memory used by nodes seeing many
+    /// messages, but it occasionally drops legitimate messages as duplicates.
+    ///
+    /// Default is [`DedupStrategy::Lru`].
+    pub fn dedup_strategy(&self) -> DedupStrategy {
+        self.dedup_strategy
+    }
+
+    /// The observer of the messages evicted from the seen messages cache.
+    ///
+    /// When set, the observer is invoked with the ID of each message evicted from the cache,
+    /// either because it expired (see [`Config::duplicate_cache_time`]) or because the cache was
+    /// full (see [`Config::duplicate_cache_capacity`]), so applications can forget the state they
+    /// keep per message ID. The expired entries are evicted as new messages are received.
+    ///
+    /// With the [`DedupScope::PerTopic`] scope, the observer is invoked once per topic the ID was
+    /// seen on. It is never invoked with the [`DedupStrategy::BloomFilter`] strategy, which
+    /// can't enumerate the IDs it forgets.
+    ///
+    /// Default is `None`.
+    pub(crate) fn on_message_expired(&self) -> Option<&MessageExpiredObserver> {
+        self.on_message_expired.as_ref()
+    }
+
+    /// The policy re-sending the recent messages of a topic to the peers subscribing to it.
+    ///
+    /// When set, each time a connected peer subscribes to a topic, the recent messages of the
+    /// topic provided by the application are sent to the peer, letting the late-joining peers
+    /// catch up. The peers deduplicate the messages they already received.
+    ///
+    /// Default is `None` (no retransmission).
+    pub fn retransmission_policy(&self) -> Option<&RetransmissionPolicy> {
+        self.retransmission_policy.as_ref()
+    }
+
+    /// The signer of the locally published messages.
+    ///
+    /// When set, the messages published with [`Behaviour::publish`](crate::Behaviour::publish)
+    /// are authored by the peer ID derived from the signer public key, and signed by the signer
+    /// callback.
+    ///
+    /// Default is `None` (the published messages are not signed).
+    pub(crate) fn signer(&self) -> Option<&Signer> {
+        self.signer.as_ref()
+    }
+
+    /// Whether the signed messages rely on the author peer ID to carry the public key, omitting
+    /// the `key` field, when the key is small enough to be embedded in the peer ID (e.g., Ed25519
+    /// or Secp256k1 keys).
+    ///
+    /// This reduces the size of the messages, as gossipsub does. The receivers must derive the
+    /// public key from the `from` field. The `key` field is always set for larger keys (e.g., RSA
+    /// keys).
+    ///
+    /// Default is `false` (the `key` field is always set).
+    pub fn inline_public_key_if_possible(&self) -> bool {
+        self.inline_public_key_if_possible
+    }
+
+    /// The base of the sequence numbers of the published messages with a stable author (e.g.,
+    /// signed messages), if fixed.
+    ///
+    /// The sequence numbers count up from the base (the first message gets `base + 1`) instead of
+    /// the current time, so the message IDs are reproducible across test runs.
+    ///
+    /// Default is `None` (count up from the current time).
+    #[cfg(any(test, feature = "testing"))]
+    pub fn fixed_seqno(&self) -> Option<u64> {
+        self.fixed_seqno
+    }
+
+    /// The seed of the random number generator selecting the forward peers subset with the
+    /// [`ForwardSubsetStrategy::Random`] strategy, if fixed.
+    ///
+    /// A fixed seed makes the selected subsets reproducible across test runs.
+    ///
+    /// Default is `None` (seeded from the system entropy).
+    #[cfg(any(test, feature = "testing"))]
+    pub fn forward_rng_seed(&self) -> Option<u64> {
+        self.forward_rng_seed
+    }
+
+    /// The maximum number of concurrent inbound pubsub substreams a peer can open on a connection.
+    ///
+    /// Peers are expected to open a single long-lived substream. When the limit is reached, the
+    /// oldest substream is superseded by the newly opened one and read until the remote closes it.
+    /// The substreams opened while as many superseded substreams are still being read are refused,
+    /// preventing a peer from exhausting the connection handler resources.
+    ///
+    /// Default is 1.
+    pub fn max_inbound_substreams(&self) -> usize {
+        self.max_inbound_substreams
+    }
+
+    /// The maximum length, in bytes, of the topic strings received from the peers.
+    ///
+    /// When set, the strict topic validation is enabled: the received messages and subscriptions
+    /// whose topic exceeds this length or contains control characters are dropped. This prevents
+    /// log injection, and oversized topic strings from bloating the subscriptions table. Topic
+    /// strings that are not valid UTF-8 are always rejected when decoding the RPC frames.
+    ///
+    /// Default is `None` (no topic validation).
+    pub fn max_topic_length(&self) -> Option<usize> {
+        self.max_topic_length
+    }
+
+    /// The observer of the raw inbound RPC frames.
+    ///
+    /// When set, the observer is invoked with the exact wire bytes of each RPC frame received from
+    /// a peer, before decoding it, to debug the interoperability with other implementations.
+    ///
+    /// Default is `None`.
+    #[cfg(feature = "debug")]
+    pub(crate) fn on_raw_rpc(&self) -> Option<&RawRpcObserver> {
+        self.on_raw_rpc.as_ref()
+    }
+
+    /// Whether the whole RPC frames are compressed with zstd, with the peers supporting it.
+    ///
+    /// When enabled, the compressed variant of the floodsub protocol (`/floodsub/1.0.0/zstd`) is
+    /// negotiated in preference to the uncompressed one. The substreams with peers not supporting it fall
+    /// back to the uncompressed protocol.
+    ///
+    /// Default is `false`.
+    #[cfg(feature = "zstd")]
+    pub fn compress_rpc_frames(&self) -> bool {
+        self.compress_rpc_frames
+    }
+
+    /// Whether the statistics of a peer (see [`Behaviour::peer_stats`](crate::Behaviour::peer_stats))
+    /// are kept after its last connection is closed, and resumed if the peer reconnects.
+    ///
+    /// Default is `false` (the statistics are reset when the peer disconnects).
+    pub fn retain_peer_stats(&self) -> bool {
+        self.retain_peer_stats
+    }
+
+    /// The minimum number of peers subscribed to each topic the local node is subscribed to.
+    ///
+    /// An [`Event::InsufficientPeers`](crate::Event::InsufficientPeers) event is emitted when a
+    /// subscribed topic has fewer subscribed peers than this minimum, as the messages propagation
+    /// may be unreliable (e.g., the topic is partitioned). The event is emitted again only after
+    /// the topic has reached the minimum and dropped below it again.
+    ///
+    /// Default is `None` (no minimum).
+    pub fn min_peers_per_topic(&self) -> Option<usize> {
+        self.min_peers_per_topic
+    }
+
+    /// The sequence number policy of the messages published without a signer.
+    ///
+    /// The signed messages always carry a sequence number, as do the messages published with
+    /// [`Behaviour::publish_with_ttl`](crate::Behaviour::publish_with_ttl), whose TTL is encoded
+    /// in it.
+    ///
+    /// Default is [`AnonymousSeqno::RandomPerMessage`].
+    pub fn anonymous_seqno(&self) -> AnonymousSeqno {
+        self.anonymous_seqno
+    }
+
+    /// Whether the received messages from topics the local node is not subscribed to are
+    /// forwarded to the peers subscribed to them.
+    ///
+    /// These messages are never delivered to the application. Disabling the forwarding turns the
+    /// node into a leaf relaying only the topics it is subscribed to, reducing its bandwidth
+    /// usage.
+    ///
+    /// Default is `true`.
+    pub fn forward_unsubscribed(&self) -> bool {
+        self.forward_unsubscribed
+    }
+
+    /// Whether the local node never advertises its subscriptions to the peers.
+    ///
+    /// The node still subscribes locally, and the messages to the subscribed topics forwarded to
+    /// it are delivered to the application, without revealing the node's interest in them. As
+    /// floodsub peers only forward the messages to the peers subscribed to their topic, a silent
+    /// observer only receives messages from an upstream flooding all the messages to it (e.g., a
+    /// node with the observer in its [static forward peers](Self::static_forward_peers)).
+    ///
+    /// Default is `false`.
+    pub fn silent_observer(&self) -> bool {
+        self.silent_observer
+    }
+
+    /// Whether the received messages are only accepted from the peers that advertised a
+    /// subscription to their topic (directly or through a matching prefix subscription).
+    ///
+    /// This reduces the acceptance of spoofed forwards in trust-minimized setups, from peers
+    /// relaying topics they never declared an interest in. It also drops the messages forwarded
+    /// before the peer subscriptions are received, and the messages flooded by
+    /// [static forward peers](Self::static_forward_peers) not subscribed to the topic.
+    ///
+    /// Default is `false`.
+    pub fn only_accept_from_subscribed_peers(&self) -> bool {
+        self.only_accept_from_subscribed_peers
+    }
+
+    /// The maximum number of RPC frames queued for sending to each peer, i.e., handed to the peer
+    /// connection handlers and not yet sent.
+    ///
+    /// The messages published with [`Behaviour::publish_async`](crate::Behaviour::publish_async)
+    /// are only queued for the peers below this limit, waiting for the queues of the other peers
+    /// to have capacity. The other frames (e.g., the messages published with
+    /// [`Behaviour::publish`](crate::Behaviour::publish) or forwarded) are always queued.
+    ///
+    /// Default is `None` (no limit).
+    pub fn max_pending_frames_per_peer(&self) -> Option<usize> {
+        self.max_pending_frames_per_peer
+    }
+
+    /// The maximum number of topic subscriptions tracked for the connected peers, across all
+    /// peers.
+    ///
+    /// This bounds the memory used by the peer subscriptions table when facing many peers, each
+    /// subscribing to many topics. When a received subscription exceeds the limit, the
+    /// subscriptions of the least recently active peers (i.e., the peers the local node received
+    /// an RPC frame from the longest ago) are evicted.
+    ///
+    /// Default is `None` (no limit).
+    pub fn max_peer_subscriptions_total(&self) -> Option<usize> {
+        self.max_peer_subscriptions_total
+    }
+
+    /// The maximum number of subscriptions carried by a received RPC frame.
+    ///
+    /// The frames exceeding the limit are rejected as a whole, including their messages, without
+    /// processing any of their entries. This keeps a single frame from flooding the peer
+    /// subscriptions table. See [`PeerStats::rejected_rpcs`](crate::PeerStats::rejected_rpcs).
+    ///
+    /// Default is `None` (no limit).
+    pub fn max_subscriptions_per_incoming_rpc(&self) -> Option<usize> {
+        self.max_subscriptions_per_incoming_rpc
+    }
+
+    /// The maximum length, in bytes, of the IDs computed by the message ID function.
+    ///
+    /// The received messages whose ID exceeds the limit are dropped before reaching the seen
+    /// messages cache, and counted in
+    /// [`Behaviour::oversized_message_ids`](crate::Behaviour::oversized_message_ids). Publishing
+    /// such a message fails. This bounds the memory used by the seen messages cache, whatever the
+    /// message ID function.
+    ///
+    /// Default is `None` (no limit).
+    pub fn max_message_id_len(&self) -> Option<usize> {
+        self.max_message_id_len
+    }
+
+    /// Whether the messages published without a signer carry a random author, as gossipsub's
+    /// `MessageAuthenticity::RandomAuthor` mode, instead of no author at all.
+    ///
+    /// The random author can't be linked to the local node peer ID. See
+    /// [`Config::rotate_anonymous_author`].
+    ///
+    /// Default is `false`.
+    pub fn random_anonymous_author(&self) -> bool {
+        self.random_anonymous_author
+    }
+
+    /// Whether a new random author is generated for each published message, if
+    /// [`Config::random_anonymous_author`] is enabled.
+    ///
+    /// Rotating the author makes the messages published by the local node unlinkable to each
+    /// other. The messages then follow the [`Config::anonymous_seqno`] policy. Otherwise, a
+    /// random author is generated once, and the messages carry a linear sequence number, like the
+    /// signed messages. The message author is part of the default message ID.
+    ///
+    /// Default is `true`, as gossipsub's `MessageAuthenticity::RandomAuthor` mode.
+    pub fn rotate_anonymous_author(&self) -> bool {
+        self.rotate_anonymous_author
+    }
+
+    /// The number of events pending in the behaviour queue above which an
+    /// [`Event::QueueOverloaded`](crate::Event::QueueOverloaded) event is emitted.
+    ///
+    /// A growing queue signals the swarm is not polled fast enough to keep up with the published
+    /// and received messages. The event is not emitted again until the queue drains below the
+    /// threshold and crosses it again.
+    ///
+    /// Default is `None` (no overload detection).
+    pub fn event_queue_overload_threshold(&self) -> Option<usize> {
+        self.event_queue_overload_threshold
+    }
+}
+
+/// The builder struct for constructing a floodsub configuration.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl From<Config> for ConfigBuilder {
+    fn from(config: Config) -> Self {
+        Self { config }
+    }
+}
+
+impl ConfigBuilder {
+    /// The maximum byte size for each pubsub frame (default is 65536 bytes).
+    pub fn max_frame_size(&mut self, max_frame_size: usize) -> &mut Self {
+        self.config.max_frame_size = max_frame_size;
+        self
+    }
+
+    /// The time a connection is maintained to a peer without send/receiving a message from.
+    /// Default is 120 seconds.
+    pub fn connection_idle_timeout(&mut self, connection_idle_timeout: Duration) -> &mut Self {
+        self.config.connection_idle_timeout = connection_idle_timeout;
+        self
+    }
+
+    /// Close the connections whose pubsub substreams idle beyond the given timeout, regardless of
+    /// the local node subscriptions (default is `None`, keep the connections alive while
+    /// subscribed to at least one topic).
+    pub fn idle_timeout(&mut self, idle_timeout: Duration) -> &mut Self {
+        self.config.idle_timeout = Some(idle_timeout);
+        self
+    }
+
+    /// The maximum time allowed for the pubsub substreams protocol negotiation (default is 10
+    /// seconds).
+    pub fn negotiation_timeout(&mut self, negotiation_timeout: Duration) -> &mut Self {
+        self.config.negotiation_timeout = negotiation_timeout;
+        self
+    }
+
+    /// The prefix of the advertised pubsub protocol IDs, advertising `/<prefix>/1.0.0` (default
+    /// is `floodsub`).
+    pub fn protocol_id_prefix(&mut self, protocol_id_prefix: impl Into<String>) -> &mut Self {
+        self.config.protocol_id_prefix = protocol_id_prefix.into();
+        self
+    }
+
+    /// Limit the inbound message rate of each peer to `max_per_sec` messages per second, allowing
+    /// bursts of up to `burst` messages.
+    pub fn peer_message_rate(&mut self, max_per_sec: u32, burst: u32) -> &mut Self {
+        self.config.peer_message_rate = Some((max_per_sec, burst));
+        self
+    }
+
+    /// Emit an event every time the number of connected peers crosses one of the given
+    /// thresholds.
+    pub fn peer_count_thresholds(&mut self, thresholds: Vec<usize>) -> &mut Self {
+        self.config.peer_count_thresholds = thresholds;
+        self
+    }
+
+    /// Cap the number of subscribed peers a message is published or forwarded to.
+    pub fn max_forward_peers_per_topic(&mut self, max_peers: usize) -> &mut Self {
+        self.config.max_forward_peers_per_topic = Some(max_peers);
+        self
+    }
+
+    /// The strategy used to select the subset of forward peers (default is
+    /// [`ForwardSubsetStrategy::Random`]).
+    pub fn forward_subset_strategy(&mut self, strategy: ForwardSubsetStrategy) -> &mut Self {
+        self.config.forward_subset_strategy = strategy;
+        self
+    }
+
+    /// How the RPC frames are sent over the outbound substreams (default is
+    /// [`SubstreamMode::Persistent`]).
+    pub fn substream_mode(&mut self, substream_mode: SubstreamMode) -> &mut Self {
+        self.config.substream_mode = substream_mode;
+        self
+    }
+
+    /// Send the messages to the given fraction (from 0 to 1) of the topic's subscribed peers
+    /// only (default is `1.0`, flood to all subscribed peers).
+    pub fn flood_factor(&mut self, flood_factor: f64) -> &mut Self {
+        self.config.flood_factor = flood_factor;
+        self
+    }
+
+    /// The minimum number of peers a message is sent to, if the flood factor is below one
+    /// (default is `6`).
+    pub fn min_forward_peers(&mut self, min_forward_peers: usize) -> &mut Self {
+        self.config.min_forward_peers = min_forward_peers;
+        self
+    }
+
+    /// The time a message ID is kept in the seen messages cache (default is 60 seconds).
+    pub fn duplicate_cache_time(&mut self, cache_time: Duration) -> &mut Self {
+        self.config.duplicate_cache_time = cache_time;
+        self
+    }
+
+    /// The maximum number of message IDs kept in the seen messages cache (default is 10000).
+    pub fn duplicate_cache_capacity(&mut self, capacity: usize) -> &mut Self {
+        self.config.duplicate_cache_capacity = capacity;
+        self
+    }
+
+    /// Adapt the seen messages cache capacity to the recent message rate, instead of evicting the
+    /// IDs not expired yet when full (default is `false`).
+    pub fn auto_cache_sizing(&mut self, auto_cache_sizing: bool) -> &mut Self {
+        self.config.auto_cache_sizing = auto_cache_sizing;
+        self
+    }
+
+    /// Emit an event every time an inbound message is dropped (default is `false`).
+    pub fn emit_dropped_events(&mut self, emit_dropped_events: bool) -> &mut Self {
+        self.config.emit_dropped_events = emit_dropped_events;
+        self
+    }
+
+    /// Disconnect the peers reaching the given number of violations (default is `None`, never
+    /// disconnect misbehaving peers).
+    pub fn disconnect_threshold(&mut self, threshold: u32) -> &mut Self {
+        self.config.disconnect_threshold = Some(threshold);
+        self
+    }
+
+    /// Deliver the locally published messages to the local node (default is `false`).
+    pub fn allow_self_origin(&mut self, allow_self_origin: bool) -> &mut Self {
+        self.config.allow_self_origin = allow_self_origin;
+        self
+    }
+
+    /// Let publishing succeed when there are no peers subscribed to the topic (default is
+    /// `false`).
+    pub fn allow_publish_with_no_peers(&mut self, allow_publish_with_no_peers: bool) -> &mut Self {
+        self.config.allow_publish_with_no_peers = allow_publish_with_no_peers;
+        self
+    }
+
+    /// The transform applied to the messages data (default is
+    /// [`IdentityTransform`](crate::IdentityTransform)).
+    pub fn data_transform(&mut self, data_transform: impl DataTransform + 'static) -> &mut Self {
+        self.config.data_transform = Arc::new(data_transform);
+        self
+    }
+
+    /// Send the messages to the recipient peers sorted by `PeerId` (default is `false`). Intended
+    /// for tests only.
+    pub fn deterministic_forwarding(&mut self, deterministic_forwarding: bool) -> &mut Self {
+        self.config.deterministic_forwarding = deterministic_forwarding;
+        self
+    }
+
+    /// The maximum number of subscription changes carried by a single RPC frame (default is 128).
+    pub fn max_subscriptions_per_rpc(&mut self, max_subscriptions_per_rpc: usize) -> &mut Self {
+        self.config.max_subscriptions_per_rpc = max_subscriptions_per_rpc;
+        self
+    }
+
+    /// Send the published and forwarded messages to the given fixed set of peers only, disabling
+    /// the subscription-based forwarding (default is `None`).
+    pub fn static_forward_peers(&mut self, peers: Vec<PeerId>) -> &mut Self {
+        self.config.static_forward_peers = Some(peers);
+        self
+    }
+
+    /// The scope of the messages deduplication (default is [`DedupScope::Global`]).
+    pub fn dedup_scope(&mut self, dedup_scope: DedupScope) -> &mut Self {
+        self.config.dedup_scope = dedup_scope;
+        self
+    }
+
+    /// The data structure used to remember the seen message IDs (default is
+    /// [`DedupStrategy::Lru`]).
+    pub fn dedup_strategy(&mut self, dedup_strategy: DedupStrategy) -> &mut Self {
+        self.config.dedup_strategy = dedup_strategy;
+        self
+    }
+
+    /// Invoke the given callback with the ID of each message evicted from the seen messages
+    /// cache (default is `None`).
+    pub fn on_message_expired(&mut self, callback: Box<MessageExpiredFn>) -> &mut Self {
+        self.config.on_message_expired = Some(MessageExpiredObserver(Arc::from(callback)));
+        self
+    }
+
+    /// Re-send the recent messages of a topic to the peers subscribing to it, according to the
+    /// given policy (default is `None`).
+    pub fn retransmission_policy(&mut self, policy: RetransmissionPolicy) -> &mut Self {
+        self.config.retransmission_policy = Some(policy);
+        self
+    }
+
+    /// Sign the locally published messages with the given signer callback, delegating the
+    /// signature generation (e.g., to a hardware security module) instead of holding the private
+    /// key in memory. The public key must be the signer key pair public key.
+    pub fn signer(&mut self, public_key: PublicKey, signer: Box<SignerFn>) -> &mut Self {
+        self.config.signer = Some(Signer::new(public_key, signer));
+        self
+    }
+
+    /// Omit the `key` field of the signed messages when the public key is embedded in the author
+    /// peer ID (default is `false`).
+    pub fn inline_public_key_if_possible(
+        &mut self,
+        inline_public_key_if_possible: bool,
+    ) -> &mut Self {
+        self.config.inline_public_key_if_possible = inline_public_key_if_possible;
+        self
+    }
+
+    /// Count the sequence numbers of the published messages with a stable author up from the
+    /// given base, instead of the current time, for reproducible message IDs in tests (default is
+    /// `None`).
+    #[cfg(any(test, feature = "testing"))]
+    pub fn fixed_seqno(&mut self, base: u64) -> &mut Self {
+        self.config.fixed_seqno = Some(base);
+        self
+    }
+
+    /// Seed the random forward peers selection with the given seed, instead of the system
+    /// entropy, for reproducible forward peers subsets in tests (default is `None`).
+    #[cfg(any(test, feature = "testing"))]
+    pub fn forward_rng_seed(&mut self, seed: u64) -> &mut Self {
+        self.config.forward_rng_seed = Some(seed);
+        self
+    }
+
+    /// The maximum number of concurrent inbound pubsub substreams per connection (default is 1).
+    pub fn max_inbound_substreams(&mut self, max_inbound_substreams: usize) -> &mut Self {
+        self.config.max_inbound_substreams = max_inbound_substreams;
+        self
+    }
+
+    /// Drop the received messages and subscriptions whose topic exceeds the given length, or
+    /// contains control characters (default is `None`, no topic validation).
+    pub fn max_topic_length(&mut self, max_topic_length: usize) -> &mut Self {
+        self.config.max_topic_length = Some(max_topic_length);
+        self
+    }
+
+    /// Invoke the given callback with the raw bytes of each inbound RPC frame, before decoding it
+    /// (default is `None`).
+    #[cfg(feature = "debug")]
+    pub fn on_raw_rpc(&mut self, callback: Box<RawRpcFn>) -> &mut Self {
+        self.config.on_raw_rpc = Some(RawRpcObserver(Arc::from(callback)));
+        self
+    }
+
+    /// Compress the whole RPC frames with zstd, with the peers supporting it (default is
+    /// `false`).
+    #[cfg(feature = "zstd")]
+    pub fn compress_rpc_frames(&mut self, compress_rpc_frames: bool) -> &mut Self {
+        self.config.compress_rpc_frames = compress_rpc_frames;
+        self
+    }
+
+    /// Keep the peer statistics after the peer disconnects (default is `false`).
+    pub fn retain_peer_stats(&mut self, retain_peer_stats: bool) -> &mut Self {
+        self.config.retain_peer_stats = retain_peer_stats;
+        self
+    }
+
+    /// Emit an event when a subscribed topic has fewer subscribed peers than the given minimum
+    /// (default is `None`).
+    pub fn min_peers_per_topic(&mut self, min_peers_per_topic: usize) -> &mut Self {
+        self.config.min_peers_per_topic = Some(min_peers_per_topic);
+        self
+    }
+
+    /// The sequence number policy of the messages published without a signer (default is
+    /// [`AnonymousSeqno::RandomPerMessage`]).
+    pub fn anonymous_seqno(&mut self, anonymous_seqno: AnonymousSeqno) -> &mut Self {
+        self.config.anonymous_seqno = anonymous_seqno;
+        self
+    }
+
+    /// Forward the received messages from topics the local node is not subscribed to (default is
+    /// `true`).
+    pub fn forward_unsubscribed(&mut self, forward_unsubscribed: bool) -> &mut Self {
+        self.config.forward_unsubscribed = forward_unsubscribed;
+        self
+    }
+
+    /// Never advertise the local subscriptions to the peers (default is `false`).
+    pub fn silent_observer(&mut self, silent_observer: bool) -> &mut Self {
+        self.config.silent_observer = silent_observer;
+        self
+    }
+
+    /// Only accept the received messages from the peers subscribed to their topic (default is
+    /// `false`).
+    pub fn only_accept_from_subscribed_peers(
+        &mut self,
+        only_accept_from_subscribed_peers: bool,
+    ) -> &mut Self {
+        self.config.only_accept_from_subscribed_peers = only_accept_from_subscribed_peers;
+        self
+    }
+
+    /// The maximum number of RPC frames queued for sending to each peer (default is `None`).
+    pub fn max_pending_frames_per_peer(&mut self, max_pending_frames_per_peer: usize) -> &mut Self {
+        self.config.max_pending_frames_per_peer = Some(max_pending_frames_per_peer);
+        self
+    }
+
+    /// The maximum number of topic subscriptions tracked for the connected peers, across all
+    /// peers (default is `None`).
+    pub fn max_peer_subscriptions_total(
+        &mut self,
+        max_peer_subscriptions_total: usize,
+    ) -> &mut Self {
+        self.config.max_peer_subscriptions_total = Some(max_peer_subscriptions_total);
+        self
+    }
+
+    /// The maximum number of subscriptions carried by a received RPC frame, above which the frame
+    /// is rejected (default is `None`).
+    pub fn max_subscriptions_per_incoming_rpc(
+        &mut self,
+        max_subscriptions_per_incoming_rpc: usize,
+    ) -> &mut Self {
+        self.config.max_subscriptions_per_incoming_rpc = Some(max_subscriptions_per_incoming_rpc);
+        self
+    }
+
+    /// The maximum length of the message IDs, above which the messages are dropped (default is
+    /// `None`).
+    pub fn max_message_id_len(&mut self, max_message_id_len: usize) -> &mut Self {
+        self.config.max_message_id_len = Some(max_message_id_len);
+        self
+    }
+
+    /// Publish the messages without a signer with a random author (default is `false`).
+    pub fn random_anonymous_author(&mut self, random_anonymous_author: bool) -> &mut Self {
+        self.config.random_anonymous_author = random_anonymous_author;
+        self
+    }
+
+    /// Generate a new random author for each anonymous message (default is `true`).
+    pub fn rotate_anonymous_author(&mut self, rotate_anonymous_author: bool) -> &mut Self {
+        self.config.rotate_anonymous_author = rotate_anonymous_author;
+        self
+    }
+
+    /// The number of pending behaviour events above which the event queue is overloaded
+    /// (default is `None`).
+    pub fn event_queue_overload_threshold(
+        &mut self,
+        event_queue_overload_threshold: usize,
+    ) -> &mut Self {
+        self.config.event_queue_overload_threshold = Some(event_queue_overload_threshold);
+        self
+    }
+
+    /// Constructs a [`Config`] from the given configuration.
+    ///
+    /// Returns an error if the configuration is invalid.
+    pub fn build(&self) -> Result<Config, ConfigError> {
+        if self.config.max_frame_size == 0 {
+            return Err(ConfigError::ZeroMaxFrameSize);
+        }
+
+        if self.config.duplicate_cache_time.is_zero() {
+            return Err(ConfigError::ZeroDuplicateCacheTime);
+        }
+
+        if self.config.duplicate_cache_capacity == 0 {
+            return Err(ConfigError::ZeroDuplicateCacheCapacity);
+        }
+
+        if self.config.max_subscriptions_per_rpc == 0 {
+            return Err(ConfigError::ZeroMaxSubscriptionsPerRpc);
+        }
+
+        if self.config.max_inbound_substreams == 0 {
+            return Err(ConfigError::ZeroMaxInboundSubstreams);
+        }
+
+        if self.config.max_pending_frames_per_peer == Some(0) {
+            return Err(ConfigError::ZeroMaxPendingFramesPerPeer);
+        }
+
+        if self.config.max_peer_subscriptions_total == Some(0) {
+            return Err(ConfigError::ZeroMaxPeerSubscriptionsTotal);
+        }
+
+        if self.config.max_subscriptions_per_incoming_rpc == Some(0) {
+            return Err(ConfigError::ZeroMaxSubscriptionsPerIncomingRpc);
+        }
+
+        if self.config.max_message_id_len == Some(0) {
+            return Err(ConfigError::ZeroMaxMessageIdLen);
+        }
+
+        if let Some((max_per_sec, burst)) = self.config.peer_message_rate {
+            if max_per_sec == 0 || burst == 0 {
+                return Err(ConfigError::ZeroPeerMessageRate);
+            }
+        }
+
+        if let Some(policy) = &self.config.retransmission_policy {
+            if policy.max_messages == 0 {
+                return Err(ConfigError::ZeroRetransmissionMaxMessages);
+            }
+        }
+
+        if let DedupStrategy::BloomFilter { bits, hashes } = self.config.dedup_strategy {
+            if bits == 0 || hashes == 0 {
+                return Err(ConfigError::ZeroBloomFilterSize);
+            }
+        }
+
+        if self.config.event_queue_overload_threshold == Some(0) {
+            return Err(ConfigError::ZeroEventQueueOverloadThreshold);
+        }
+
+        if !(0.0..=1.0).contains(&self.config.flood_factor) {
+            return Err(ConfigError::InvalidFloodFactor);
+        }
+
+        let prefix = &self.config.protocol_id_prefix;
+        if prefix.is_empty() || prefix.starts_with('/') || prefix.ends_with('/') {
+            return Err(ConfigError::InvalidProtocolIdPrefix);
+        }
+
+        Ok(self.config.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    #[test]
+    fn build_default_config() {
+        //// When
+        let result = ConfigBuilder::default().build();
+
+        //// Then
+        assert_matches!(result, Ok(_));
+    }
+
+    #[test]
+    fn reject_zero_max_frame_size() {
+        //// When
+        let result = ConfigBuilder::default().max_frame_size(0).build();
+
+        //// Then
+        assert_matches!(result, Err(ConfigError::ZeroMaxFrameSize));
+    }
+
+    #[test]
+    fn reject_zero_duplicate_cache_time() {
+        //// When
+        let result = ConfigBuilder::default()
+            .duplicate_cache_time(Duration::ZERO)
+            .build();
+
+        //// Then
+        assert_matches!(result, Err(ConfigError::ZeroDuplicateCacheTime));
+    }
+
+    #[test]
+    fn reject_zero_duplicate_cache_capacity() {
+        //// When
+        let result = ConfigBuilder::default().duplicate_cache_capacity(0).build();
+
+        //// Then
+        assert_matches!(result, Err(ConfigError::ZeroDuplicateCacheCapacity));
+    }
+
+    #[test]
+    fn reject_zero_peer_message_rate() {
+        //// When
+        let zero_rate = ConfigBuilder::default().peer_message_rate(0, 10).build();
+        let zero_burst = ConfigBuilder::default().peer_message_rate(10, 0).build();
+
+        //// Then
+        assert_matches!(zero_rate, Err(ConfigError::ZeroPeerMessageRate));
+        assert_matches!(zero_burst, Err(ConfigError::ZeroPeerMessageRate));
+    }
+
+    #[test]
+    fn reject_zero_max_subscriptions_per_rpc() {
+        //// When
+        let result = ConfigBuilder::default()
+            .max_subscriptions_per_rpc(0)
+            .build();
+
+        //// Then
+        assert_matches!(result, Err(ConfigError::ZeroMaxSubscriptionsPerRpc));
+    }
+
+    #[test]
+    fn reject_zero_max_inbound_substreams() {
+        //// When
+        let result = ConfigBuilder::default().max_inbound_substreams(0).build();
+
+        //// Then
+        assert_matches!(result, Err(ConfigError::ZeroMaxInboundSubstreams));
+    }
+
+    #[test]
+    fn reject_zero_max_pending_frames_per_peer() {
+        //// When
+        let result = ConfigBuilder::default()
+            .max_pending_frames_per_peer(0)
+            .build();
+
+        //// Then
+        assert_matches!(result, Err(ConfigError::ZeroMaxPendingFramesPerPeer));
+    }
+
+    #[test]
+    fn reject_zero_max_peer_subscriptions_total() {
+        //// When
+        let result = ConfigBuilder::default()
+            .max_peer_subscriptions_total(0)
+            .build();
+
+        //// Then
+        assert_matches!(result, Err(ConfigError::ZeroMaxPeerSubscriptionsTotal));
+    }
+
+    #[test]
+    fn reject_zero_max_subscriptions_per_incoming_rpc() {
+        //// When
+        let result = ConfigBuilder::default()
+            .max_subscriptions_per_incoming_rpc(0)
+            .build();
+
+        //// Then
+        assert_matches!(result, Err(ConfigError::ZeroMaxSubscriptionsPerIncomingRpc));
+    }
+
+    #[test]
+    fn reject_zero_max_message_id_len() {
+        //// When
+        let result = ConfigBuilder::default().max_message_id_len(0).build();
+
+        //// Then
+        assert_matches!(result, Err(ConfigError::ZeroMaxMessageIdLen));
+    }
+
+    #[test]
+    fn reject_zero_retransmission_max_messages() {
+        //// Given
+        let policy =
+            RetransmissionPolicy::new(Box::new(|_| Vec::new()), 0, Duration::from_secs(60));
+
+        //// When
+        let result = ConfigBuilder::default()
+            .retransmission_policy(policy)
+            .build();
+
+        //// Then
+        assert_matches!(result, Err(ConfigError::ZeroRetransmissionMaxMessages));
+    }
+
+    #[test]
+    fn reject_empty_bloom_filter() {
+        //// When
+        let result = ConfigBuilder::default()
+            .dedup_strategy(DedupStrategy::BloomFilter { bits: 0, hashes: 4 })
+            .build();
+
+        //// Then
+        assert_matches!(result, Err(ConfigError::ZeroBloomFilterSize));
+    }
+
+    #[test]
+    fn reject_zero_event_queue_overload_threshold() {
+        //// When
+        let result = ConfigBuilder::default()
+            .event_queue_overload_threshold(0)
+            .build();
+
+        //// Then
+        assert_matches!(result, Err(ConfigError::ZeroEventQueueOverloadThreshold));
+    }
+
+    #[test]
+    fn reject_flood_factor_out_of_range() {
+        //// When
+        let above_one = ConfigBuilder::default().flood_factor(1.5).build();
+        let negative = ConfigBuilder::default().flood_factor(-0.1).build();
+        let nan = ConfigBuilder::default().flood_factor(f64::NAN).build();
+
+        //// Then
+        assert_matches!(above_one, Err(ConfigError::InvalidFloodFactor));
+        assert_matches!(negative, Err(ConfigError::InvalidFloodFactor));
+        assert_matches!(nan, Err(ConfigError::InvalidFloodFactor));
+    }
+
+    #[test]
+    fn reject_invalid_protocol_id_prefix() {
+        //// When
+        let empty = ConfigBuilder::default().protocol_id_prefix("").build();
+        let leading_slash = ConfigBuilder::default().protocol_id_prefix("/acme").build();
+        let trailing_slash = ConfigBuilder::default().protocol_id_prefix("acme/").build();
+
+        //// Then
+        assert_matches!(empty, Err(ConfigError::InvalidProtocolIdPrefix));
+        assert_matches!(leading_slash, Err(ConfigError::InvalidProtocolIdPrefix));
+        assert_matches!(trailing_slash, Err(ConfigError::InvalidProtocolIdPrefix));
+    }
 }
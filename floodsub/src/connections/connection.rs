@@ -1,4 +1,4 @@
-use libp2p::Multiaddr;
+use libp2p::{Multiaddr, StreamProtocol};
 
 /// The direction of a connection.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -45,6 +45,9 @@ pub struct Connection {
 
     /// The connection remote address.
     remote_addr: Multiaddr,
+
+    /// The pubsub protocol negotiated on the connection substreams, if any.
+    negotiated_protocol: Option<StreamProtocol>,
 }
 
 impl Connection {
@@ -55,6 +58,7 @@ impl Connection {
         Self {
             local_addr: Some(local_addr),
             remote_addr,
+            negotiated_protocol: None,
             state: ConnectionState::Connecting,
             direction: ConnectionDirection::Inbound,
         }
@@ -67,6 +71,7 @@ impl Connection {
         Self {
             local_addr: None,
             remote_addr,
+            negotiated_protocol: None,
             state: ConnectionState::Connecting,
             direction: ConnectionDirection::Outbound,
         }
@@ -82,6 +87,11 @@ impl Connection {
         self.remote_addr = remote_addr;
     }
 
+    /// Update connection negotiated protocol.
+    pub(crate) fn set_negotiated_protocol(&mut self, protocol: StreamProtocol) {
+        self.negotiated_protocol = Some(protocol);
+    }
+
     /// Whether the connection is inbound.
     #[must_use]
     pub fn is_inbound(&self) -> bool {
@@ -99,4 +109,12 @@ impl Connection {
     pub fn is_established(&self) -> bool {
         self.state == ConnectionState::Established
     }
+
+    /// The pubsub protocol negotiated on the connection substreams (e.g., `/floodsub/1.0.0`).
+    ///
+    /// This is `None` until a substream is negotiated on the connection.
+    #[must_use]
+    pub fn negotiated_protocol(&self) -> Option<&str> {
+        self.negotiated_protocol.as_ref().map(AsRef::as_ref)
+    }
 }
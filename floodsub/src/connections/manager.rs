@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use libp2p::identity::PeerId;
 use libp2p::swarm::ConnectionId;
-use libp2p::Multiaddr;
+use libp2p::{Multiaddr, StreamProtocol};
 
 use crate::connections::connection::{Connection, ConnectionState};
 
@@ -131,6 +131,19 @@ impl ConnectionManager {
             .collect::<Vec<_>>()
     }
 
+    /// Get the pubsub protocol negotiated with the given peer, on any of its established
+    /// connections.
+    ///
+    /// Returns `None` if no substream was negotiated with the peer yet.
+    #[must_use]
+    pub fn peer_negotiated_protocol(&self, peer: &PeerId) -> Option<&str> {
+        self.peer_established_connections
+            .get(peer)?
+            .iter()
+            .filter_map(|connection| self.connections.get(connection))
+            .find_map(Connection::negotiated_protocol)
+    }
+
     /// Get then number of peers with at least one established connection.
     #[must_use]
     pub fn active_peers_count(&self) -> usize {
@@ -140,12 +153,30 @@ impl ConnectionManager {
 
 /// Internal API.
 impl ConnectionManager {
+    /// Get an iterator over all the registered connections, including those that are not yet
+    /// established, and their associated peer.
+    pub(crate) fn peer_connections(&self) -> impl Iterator<Item = (PeerId, ConnectionId)> + '_ {
+        self.peer_connections
+            .iter()
+            .flat_map(|(peer, conns)| conns.iter().map(move |conn| (*peer, *conn)))
+    }
+
+    /// Get the established connections with the given peer, in establishment order.
+    pub(crate) fn peer_established_connections(&self, peer: &PeerId) -> &[ConnectionId] {
+        self.peer_established_connections
+            .get(peer)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
     /// Register a new inbound connection with the given peer.
     ///
     /// The connection is registered with the given connection ID and the given local and remote
     /// addresses. The connection is registered with the `ConnectionState::Connecting` state.
     ///
-    /// To be called at [`NetworkBehaviour::handle_established_inbound_connection`].
+    /// To be called when the [`FromSwarm::ConnectionEstablished`] event is received, and not at
+    /// [`NetworkBehaviour::handle_established_inbound_connection`], as the connection can still
+    /// be denied by another behaviour.
     pub(crate) fn register_inbound(
         &mut self,
         connection: ConnectionId,
@@ -162,7 +193,9 @@ impl ConnectionManager {
     /// The connection is registered with the given connection ID and the given remote address. The
     /// connection is registered with the `ConnectionState::Connecting` state.
     ///
-    /// To be called at [`NetworkBehaviour::handle_established_outbound_connection`].
+    /// To be called when the [`FromSwarm::ConnectionEstablished`] event is received, and not at
+    /// [`NetworkBehaviour::handle_established_outbound_connection`], as the connection can still
+    /// be denied by another behaviour.
     pub(crate) fn register_outbound(
         &mut self,
         connection: ConnectionId,
@@ -187,6 +220,19 @@ impl ConnectionManager {
         self.deregister_connection(peer, connection);
     }
 
+    /// When a substream protocol is negotiated on a connection, record it.
+    ///
+    /// To be called when the connection handler reports the negotiated protocol.
+    pub(crate) fn on_protocol_negotiated(
+        &mut self,
+        connection: &ConnectionId,
+        protocol: StreamProtocol,
+    ) {
+        if let Some(conn) = self.connections.get_mut(connection) {
+            conn.set_negotiated_protocol(protocol);
+        }
+    }
+
     /// When a connection address is updated, update the connection's remote address.
     ///
     /// To be called when the [`FromSwarm::AddressChange`] event is received.
@@ -0,0 +1,41 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::channel::mpsc;
+use futures::stream::{FusedStream, Stream};
+use futures::StreamExt;
+
+use crate::behaviour::Event;
+
+/// The stream of the events emitted by a behaviour, returned by
+/// [`crate::Behaviour::event_stream`].
+///
+/// While the stream is alive, the behaviour events are sent to it instead of being returned to
+/// the swarm. Once the behaviour is dropped, the stream yields the events still pending, then
+/// ends. Dropping the stream returns the subsequent events to the swarm.
+#[must_use = "streams do nothing unless polled"]
+pub struct EventStream {
+    receiver: mpsc::UnboundedReceiver<Event>,
+}
+
+impl EventStream {
+    /// Create an event stream, and the sender of the events to it.
+    pub(crate) fn new() -> (mpsc::UnboundedSender<Event>, Self) {
+        let (sender, receiver) = mpsc::unbounded();
+        (sender, Self { receiver })
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Event;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_next_unpin(cx)
+    }
+}
+
+impl FusedStream for EventStream {
+    fn is_terminated(&self) -> bool {
+        self.receiver.is_terminated()
+    }
+}
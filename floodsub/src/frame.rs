@@ -1,4 +1,4 @@
-pub use message::Message;
+pub use message::{DecodeError, Message};
 pub use rpc::Frame;
 pub use subopts::SubscriptionAction;
 
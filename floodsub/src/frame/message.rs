@@ -1,12 +1,30 @@
 use bytes::Bytes;
 use libp2p::identity::PeerId;
+use prost::Message as _;
 
-use crate::proto::{validate_message_proto, MessageProto};
+use crate::proto::{
+    validate_message_proto, validate_message_proto_fields, MessageProto, MessageValidationError,
+};
 use crate::topic::TopicHash;
 
+/// An error decoding a [`Message`] from its protobuf wire encoding.
+#[derive(Debug, thiserror::Error)]
+pub enum DecodeError {
+    /// The bytes are not a valid protobuf encoded message.
+    #[error("invalid protobuf encoding: {0}")]
+    InvalidProtobuf(#[from] prost::DecodeError),
+
+    /// The decoded message is not a valid pubsub message.
+    #[error("invalid message: {0}")]
+    InvalidMessage(#[from] MessageValidationError),
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct Message {
     proto: MessageProto,
+
+    /// The message topic hash, cached from the protobuf `topic` field.
+    topic: TopicHash,
 }
 
 impl Message {
@@ -19,12 +37,12 @@ impl Message {
             from: None,
             data: Some(data.into()),
             seqno: None,
-            topic: topic.into_string(),
+            topic: topic.as_str().to_owned(),
             signature: None,
             key: None,
         };
 
-        Self { proto }
+        Self { proto, topic }
     }
 
     #[must_use]
@@ -38,6 +56,45 @@ impl Message {
         rpc
     }
 
+    /// Decode a message from its protobuf wire encoding, as produced by
+    /// [`Message::to_protobuf_bytes`].
+    pub fn from_protobuf_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let proto = MessageProto::decode(bytes)?;
+        validate_message_proto(&proto)?;
+        Ok(Self::from(proto))
+    }
+
+    /// Encode the message as it is sent on the wire.
+    ///
+    /// The encoding covers all the message fields, including the signature and the author key,
+    /// so a message decoded with [`Message::from_protobuf_bytes`] can be re-published verbatim
+    /// (e.g., by a bridge or after being persisted).
+    #[must_use]
+    pub fn to_protobuf_bytes(&self) -> Vec<u8> {
+        self.proto.encode_to_vec()
+    }
+
+    /// The size of the message protobuf wire encoding, without encoding it.
+    ///
+    /// This is the size checked against the maximum transmit size when publishing or receiving
+    /// the message.
+    #[must_use]
+    pub fn encoded_len(&self) -> usize {
+        self.proto.encoded_len()
+    }
+
+    /// Convert a message proto without topic, e.g., to compute the ID of a dropped topic-less
+    /// message.
+    ///
+    /// Returns `None` if any of the other message fields is invalid.
+    pub(crate) fn from_topicless_proto(proto: MessageProto) -> Option<Self> {
+        debug_assert!(proto.topic.is_empty(), "message proto with topic");
+        validate_message_proto_fields(&proto).ok()?;
+
+        let topic = TopicHash::from_raw(String::new());
+        Some(Self { proto, topic })
+    }
+
     #[must_use]
     pub fn into_proto(self) -> MessageProto {
         self.proto
@@ -48,11 +105,13 @@ impl Message {
         &self.proto
     }
 
+    /// The message author. An empty `from` field is interpreted as not present.
     #[must_use]
     pub fn source(&self) -> Option<PeerId> {
         self.proto
             .from
             .as_ref()
+            .filter(|bytes| !bytes.is_empty())
             .map(|bytes| PeerId::from_bytes(bytes).expect("valid peer id"))
     }
 
@@ -60,19 +119,44 @@ impl Message {
         self.proto.from = source.map(|peer_id| peer_id.to_bytes().into());
     }
 
+    /// The message payload. A non-present data field is interpreted as an empty payload.
     #[must_use]
     pub fn data(&self) -> &[u8] {
-        self.proto.data.as_ref().unwrap()
+        self.proto.data.as_deref().unwrap_or_default()
+    }
+
+    pub fn set_data(&mut self, data: impl Into<Vec<u8>>) {
+        self.proto.data = Some(data.into().into());
+    }
+
+    /// Set the message payload, sharing the given buffer: no payload bytes are copied.
+    pub fn set_data_bytes(&mut self, data: Bytes) {
+        self.proto.data = Some(data);
+    }
+
+    /// Returns the message payload as [`Bytes`].
+    ///
+    /// This is a cheap, reference-counted clone of the underlying buffer: no payload bytes are
+    /// copied. Prefer this over `data().to_vec()` when the payload must outlive the message (e.g.,
+    /// when re-publishing it).
+    #[must_use]
+    pub fn data_bytes(&self) -> Bytes {
+        self.proto.data.clone().unwrap_or_default()
     }
 
+    /// The message sequence number. An empty `seqno` field is interpreted as not present.
     #[must_use]
     pub fn sequence_number(&self) -> Option<u64> {
-        self.proto.seqno.as_ref().map(|bytes| {
-            // From pubsub spec: https://github.com/libp2p/specs/tree/master/pubsub#the-message
-            // seqno field must be a 64-bit big-endian serialized unsigned integer
-            let be_bytes = bytes[..].try_into().unwrap();
-            u64::from_be_bytes(be_bytes)
-        })
+        self.proto
+            .seqno
+            .as_ref()
+            .filter(|bytes| !bytes.is_empty())
+            .map(|bytes| {
+                // From pubsub spec: https://github.com/libp2p/specs/tree/master/pubsub#the-message
+                // seqno field must be a 64-bit big-endian serialized unsigned integer
+                let be_bytes = bytes[..].try_into().unwrap();
+                u64::from_be_bytes(be_bytes)
+            })
     }
 
     pub fn set_sequence_number(&mut self, seq_no: Option<u64>) {
@@ -86,21 +170,32 @@ impl Message {
 
     #[must_use]
     pub fn topic(&self) -> TopicHash {
-        TopicHash::from_raw(self.topic_str())
+        self.topic.clone()
     }
 
+    /// The message topic hash.
+    #[must_use]
+    pub fn topic_hash(&self) -> &TopicHash {
+        &self.topic
+    }
+
+    /// The message signature. An empty `signature` field is interpreted as not present.
     #[must_use]
     pub fn signature(&self) -> Option<&[u8]> {
-        self.proto.signature.as_ref().map(|bytes| bytes.as_ref())
+        self.proto
+            .signature
+            .as_deref()
+            .filter(|bytes| !bytes.is_empty())
     }
 
     pub fn set_signature(&mut self, signature: Option<impl Into<Vec<u8>>>) {
         self.proto.signature = signature.map(|bytes| bytes.into().into());
     }
 
+    /// The message author public key. An empty `key` field is interpreted as not present.
     #[must_use]
     pub fn key(&self) -> Option<&[u8]> {
-        self.proto.key.as_ref().map(|bytes| bytes.as_ref())
+        self.proto.key.as_deref().filter(|bytes| !bytes.is_empty())
     }
 
     pub fn set_key(&mut self, key: Option<impl Into<Vec<u8>>>) {
@@ -109,50 +204,22 @@ impl Message {
 }
 
 impl From<MessageProto> for Message {
-    /// Convert from a [`MessageProto`] into a [`Message`]. Additionally. sanitize the protobuf
-    /// message by removing optional fields when empty.
-    #[must_use]
-    fn from(mut proto: MessageProto) -> Self {
-        // A non-present data field should be interpreted as an empty payload.
-        if proto.data.is_none() {
-            proto.data = Some(Bytes::new());
-        }
-
-        // An empty from field should be interpreted as not present.
-        if let Some(from) = proto.from.as_ref() {
-            if from.is_empty() {
-                proto.from = None;
-            }
-        }
-
-        // An empty seqno field should be interpreted as not present.
-        if let Some(seq_no) = proto.seqno.as_ref() {
-            if seq_no.is_empty() {
-                proto.seqno = None;
-            }
-        }
-
-        // An empty signature field should be interpreted as not present.
-        if let Some(signature) = proto.signature.as_ref() {
-            if signature.is_empty() {
-                proto.signature = None;
-            }
-        }
-
-        // An empty key field should be interpreted as not present.
-        if let Some(key) = proto.key.as_ref() {
-            if key.is_empty() {
-                proto.key = None;
-            }
-        }
-
-        // Assert proto validity after sanitizing (development builds only)
+    /// Convert from a [`MessageProto`] into a [`Message`].
+    ///
+    /// The protobuf message is kept verbatim, so a forwarded message is byte-for-byte equal to the
+    /// received one (e.g., the `from`, `seqno` and `signature` fields covered by the message
+    /// signature are preserved). Empty optional fields are interpreted as not present by the
+    /// accessors instead.
+    #[must_use]
+    fn from(proto: MessageProto) -> Self {
+        // Assert proto validity (development builds only)
         debug_assert!(
             validate_message_proto(&proto).is_ok(),
             "invalid message proto: {proto:?}",
         );
 
-        Self { proto }
+        let topic = TopicHash::from_raw(proto.topic.clone());
+        Self { proto, topic }
     }
 }
 
@@ -161,3 +228,207 @@ impl From<Message> for MessageProto {
         message.into_proto()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use libp2p::identity::Keypair;
+
+    use crate::proto::RpcProto;
+
+    use super::*;
+
+    #[test]
+    fn data_bytes_shares_the_payload_allocation() {
+        //// Given
+        let message = Message::new(TopicHash::from_raw("/test/topic"), b"test-payload".to_vec());
+
+        //// When
+        let data = message.data_bytes();
+
+        //// Then
+        assert_eq!(data[..], message.data()[..]);
+        assert_eq!(data.as_ptr(), message.data().as_ptr());
+    }
+
+    #[test]
+    fn from_proto_keeps_the_proto_verbatim() {
+        //// Given
+        let proto = MessageProto {
+            from: Some(PeerId::random().to_bytes().into()),
+            data: None,
+            seqno: Some(42u64.to_be_bytes().to_vec().into()),
+            topic: "/test/topic".to_string(),
+            signature: Some(Bytes::new()),
+            key: Some(Bytes::new()),
+        };
+
+        //// When
+        let message = Message::from(proto.clone());
+
+        //// Then
+        assert_eq!(message.into_proto(), proto);
+    }
+
+    #[test]
+    fn empty_proto_fields_are_interpreted_as_not_present() {
+        //// Given
+        let proto = MessageProto {
+            from: Some(Bytes::new()),
+            data: None,
+            seqno: Some(Bytes::new()),
+            topic: "/test/topic".to_string(),
+            signature: Some(Bytes::new()),
+            key: Some(Bytes::new()),
+        };
+
+        //// When
+        let message = Message::from(proto);
+
+        //// Then
+        assert!(message.source().is_none());
+        assert!(message.data().is_empty());
+        assert!(message.data_bytes().is_empty());
+        assert!(message.sequence_number().is_none());
+        assert!(message.signature().is_none());
+        assert!(message.key().is_none());
+    }
+
+    #[test]
+    fn topic_hash_matches_the_proto_topic() {
+        //// Given
+        let proto = MessageProto {
+            from: None,
+            data: Some(b"test-payload".to_vec().into()),
+            seqno: None,
+            topic: "/test/topic".to_string(),
+            signature: None,
+            key: None,
+        };
+
+        //// When
+        let message = Message::from(proto);
+
+        //// Then
+        assert_eq!(message.topic_hash(), &TopicHash::from_raw("/test/topic"));
+        assert_eq!(message.topic_hash().as_str(), message.topic_str());
+    }
+
+    #[test]
+    fn round_trip_signed_message_through_protobuf_bytes() {
+        //// Given
+        let keypair = Keypair::generate_secp256k1();
+
+        let mut message = Message::new_with_sequence_number(
+            TopicHash::from_raw("/test/topic"),
+            b"test-payload".to_vec(),
+            Some(42),
+        );
+        message.set_source(Some(keypair.public().to_peer_id()));
+        message.set_signature(Some(
+            keypair.sign(b"test-payload").expect("sign the payload"),
+        ));
+        message.set_key(Some(keypair.public().encode_protobuf()));
+
+        //// When
+        let bytes = message.to_protobuf_bytes();
+        let decoded = Message::from_protobuf_bytes(&bytes);
+
+        //// Then
+        assert_matches!(decoded, Ok(decoded) => {
+            assert_eq!(decoded, message);
+            assert_eq!(decoded.signature(), message.signature());
+            assert_eq!(decoded.key(), message.key());
+        });
+    }
+
+    #[test]
+    fn round_trip_anonymous_message_through_protobuf_bytes() {
+        //// Given
+        let message = Message::new(TopicHash::from_raw("/test/topic"), b"test-payload".to_vec());
+
+        //// When
+        let bytes = message.to_protobuf_bytes();
+        let decoded = Message::from_protobuf_bytes(&bytes);
+
+        //// Then
+        assert_matches!(decoded, Ok(decoded) => {
+            assert_eq!(decoded, message);
+            assert!(decoded.source().is_none());
+            assert!(decoded.sequence_number().is_none());
+        });
+    }
+
+    #[test]
+    fn protobuf_bytes_match_the_wire_encoding() {
+        //// Given
+        let mut message = Message::new_with_sequence_number(
+            TopicHash::from_raw("/test/topic"),
+            b"test-payload".to_vec(),
+            Some(42),
+        );
+        message.set_source(Some(PeerId::random()));
+
+        let rpc = RpcProto {
+            publish: vec![message.as_proto().clone()],
+            ..Default::default()
+        };
+
+        //// When
+        let bytes = message.to_protobuf_bytes();
+
+        //// Then
+        assert!(rpc.encode_to_vec().ends_with(&bytes));
+    }
+
+    #[test]
+    fn encoded_len_matches_the_protobuf_bytes_length() {
+        //// Given
+        let mut message = Message::new_with_sequence_number(
+            TopicHash::from_raw("/test/topic"),
+            b"test-payload".to_vec(),
+            Some(42),
+        );
+        message.set_source(Some(PeerId::random()));
+        message.set_signature(Some(b"test-signature".to_vec()));
+
+        //// When
+        let encoded_len = message.encoded_len();
+
+        //// Then
+        assert_eq!(encoded_len, message.to_protobuf_bytes().len());
+    }
+
+    #[test]
+    fn reject_invalid_protobuf_bytes() {
+        //// When
+        let result = Message::from_protobuf_bytes(&[0xff, 0xff, 0xff]);
+
+        //// Then
+        assert_matches!(result, Err(DecodeError::InvalidProtobuf(_)));
+    }
+
+    #[test]
+    fn reject_invalid_message_protobuf_bytes() {
+        //// Given
+        let proto = MessageProto {
+            from: None,
+            data: Some(b"test-payload".to_vec().into()),
+            seqno: Some(vec![0x01, 0x02].into()),
+            topic: "/test/topic".to_string(),
+            signature: None,
+            key: None,
+        };
+
+        //// When
+        let result = Message::from_protobuf_bytes(&proto.encode_to_vec());
+
+        //// Then
+        assert_matches!(
+            result,
+            Err(DecodeError::InvalidMessage(
+                MessageValidationError::InvalidSequenceNumber
+            ))
+        );
+    }
+}
@@ -1,5 +1,7 @@
 use std::convert::Infallible;
 use std::pin::Pin;
+#[cfg(feature = "debug")]
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::{Duration, Instant};
 
@@ -9,30 +11,51 @@ use futures::prelude::*;
 use libp2p::core::upgrade::DeniedUpgrade;
 use libp2p::swarm::handler::{
     ConnectionEvent, DialUpgradeError, FullyNegotiatedInbound, FullyNegotiatedOutbound,
+    ProtocolsChange,
 };
 use libp2p::swarm::{
     ConnectionHandler, ConnectionHandlerEvent, KeepAlive, Stream, StreamUpgradeError,
     SubstreamProtocol,
 };
-use smallvec::SmallVec;
+use libp2p::StreamProtocol;
 
-use common::codec::ProstCodec;
+#[cfg(feature = "debug")]
+use common::codec::ProstCodecInspector;
 use common::upgrade::{SimpleUpgrade, SimpleUpgradeOutput};
 
+use crate::config::SubstreamMode;
 use crate::proto::RpcProto;
 
-type ProtocolId = &'static str;
-type Codec = ProstCodec<RpcProto>;
+use self::codec::RpcCodec;
+use self::send_queue::SendQueue;
+
+mod codec;
+mod send_queue;
+
+type ProtocolId = StreamProtocol;
+type Codec = RpcCodec;
 type Upgrade = SimpleUpgrade<ProtocolId>;
 type UpgradeOutput = SimpleUpgradeOutput<ProtocolId, Stream>;
 
+/// The prefix of the gossipsub protocol IDs (e.g., `/meshsub/1.1.0`).
+const GOSSIPSUB_PROTOCOL_PREFIX: &str = "/meshsub/";
+
+/// The kind of pubsub protocol a peer supports.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, strum_macros::Display)]
+pub enum PeerKind {
+    /// The peer supports the floodsub protocol only.
+    Floodsub,
+    /// The peer supports the gossipsub protocol, and speaks floodsub for interoperability.
+    Gossipsub,
+}
+
 #[derive(Debug)]
 pub enum Command {
     /// A RPC frame to send.
     SendFrame(RpcProto),
 
-    /// Keep the connection alive.
-    KeepAlive,
+    /// Set whether to keep the connection alive, regardless of the idle timeout.
+    KeepAlive(bool),
 }
 
 #[derive(Debug)]
@@ -42,6 +65,15 @@ pub enum Event {
 
     /// The handler has been disabled.
     Disabled(DisabledHandlerReason),
+
+    /// The kind of the remote peer has been detected from the protocols it supports.
+    PeerKind(PeerKind),
+
+    /// The given number of RPC frames left the send queue, either sent or dropped.
+    FramesDequeued(usize),
+
+    /// The protocol of the first substream negotiated on the connection.
+    ProtocolNegotiated(ProtocolId),
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, strum_macros::Display)]
@@ -62,6 +94,9 @@ pub struct DisabledHandler {
 
     /// Reason was emitted to the user.
     reason_emitted: bool,
+
+    /// The number of dropped RPC frames not yet reported to the behaviour.
+    dropped_frames: usize,
 }
 
 impl DisabledHandler {
@@ -69,6 +104,7 @@ impl DisabledHandler {
         Self {
             reason,
             reason_emitted: false,
+            dropped_frames: 0,
         }
     }
 }
@@ -110,11 +146,21 @@ impl ConnectionHandler for DisabledHandler {
             )));
         }
 
+        if self.dropped_frames > 0 {
+            let dropped_frames = std::mem::take(&mut self.dropped_frames);
+            return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
+                Event::FramesDequeued(dropped_frames),
+            ));
+        }
+
         Poll::Pending
     }
 
     fn on_behaviour_event(&mut self, event: Self::FromBehaviour) {
-        log::debug!("Ignoring incoming message because handler is disabled: {event:?}")
+        log::debug!("Ignoring incoming message because handler is disabled: {event:?}");
+        if let Command::SendFrame(_) = event {
+            self.dropped_frames += 1;
+        }
     }
 
     fn on_connection_event(
@@ -133,6 +179,8 @@ impl ConnectionHandler for DisabledHandler {
 enum InboundSubstreamState {
     /// Waiting for a message from the remote. The idle state for an inbound substream.
     WaitingInput(Framed<Stream, Codec>),
+    /// Superseded by a newer substream, the substream is read until the remote closes it.
+    Draining(Framed<Stream, Codec>),
     /// The substream is being closed.
     Closing(Framed<Stream, Codec>),
     /// An error occurred during processing.
@@ -147,6 +195,9 @@ enum OutboundSubstreamState {
     PendingSend(Framed<Stream, Codec>, RpcProto),
     /// Waiting to flush the substream so that the data arrives to the remote.
     PendingFlush(Framed<Stream, Codec>),
+    /// The substream is being closed, once its RPC frame is sent (see
+    /// [`SubstreamMode::PerMessage`]).
+    Closing(Framed<Stream, Codec>),
     /// An error occurred during processing.
     Poisoned,
 }
@@ -163,11 +214,20 @@ pub struct SimpleHandler {
     /// The single long-lived outbound substream.
     outbound_substream: Option<OutboundSubstreamState>,
 
-    /// The single long-lived inbound substream.
-    inbound_substream: Option<InboundSubstreamState>,
+    /// The long-lived inbound substreams opened by the remote.
+    inbound_substreams: Vec<InboundSubstreamState>,
 
-    /// Queue of values that we want to send to the remote.
-    send_queue: SmallVec<[RpcProto; 16]>,
+    /// The maximum number of concurrent inbound substreams. When the limit is reached, the oldest
+    /// substream is superseded by the newly opened one and drained. Substreams opened by the
+    /// remote while as many substreams are draining are refused.
+    max_inbound_substreams: usize,
+
+    /// Queue of values that we want to send to the remote, prioritizing the subscriptions and
+    /// control frames over the published messages.
+    send_queue: SendQueue,
+
+    /// The number of frames that left the send queue, not yet reported to the behaviour.
+    dequeued_frames: usize,
 
     /// Flag indicating that an outbound substream is being established to prevent duplicate
     /// requests.
@@ -179,38 +239,140 @@ pub struct SimpleHandler {
     /// The amount of time we keep an idle connection alive.
     idle_timeout: Duration,
 
+    /// The maximum time allowed for the substreams protocol negotiation.
+    negotiation_timeout: Duration,
+
     /// Keep connection alive.
     keep_alive: bool,
+
+    /// The kind of the remote peer, if detected.
+    peer_kind: Option<PeerKind>,
+
+    /// Whether the peer kind was reported to the behaviour.
+    peer_kind_reported: bool,
+
+    /// The protocol of the first substream negotiated on the connection, if any.
+    negotiated_protocol: Option<ProtocolId>,
+
+    /// Whether the negotiated protocol was reported to the behaviour.
+    negotiated_protocol_reported: bool,
+
+    /// The inspector of the raw frames received on the inbound substreams, if any.
+    #[cfg(feature = "debug")]
+    inbound_inspector: Option<Arc<ProstCodecInspector>>,
+
+    /// The protocol exchanging zstd compressed RPC frames, if supported.
+    #[cfg(feature = "zstd")]
+    zstd_protocol: Option<ProtocolId>,
+
+    /// How the RPC frames are sent over the outbound substreams.
+    substream_mode: SubstreamMode,
 }
 
 impl SimpleHandler {
     pub(crate) fn new(
         listen_protocol: Upgrade,
         max_frame_size: usize,
+        max_inbound_substreams: usize,
         idle_timeout: Duration,
+        negotiation_timeout: Duration,
     ) -> Self {
         Self {
             upgrade: listen_protocol,
             max_frame_size,
             outbound_substream: None,
-            inbound_substream: None,
-            send_queue: SmallVec::new(),
+            inbound_substreams: Vec::new(),
+            max_inbound_substreams,
+            send_queue: SendQueue::default(),
+            dequeued_frames: 0,
             outbound_substream_establishing: false,
             last_io_activity: Instant::now(),
             idle_timeout,
+            negotiation_timeout,
             keep_alive: false,
+            peer_kind: None,
+            peer_kind_reported: false,
+            negotiated_protocol: None,
+            negotiated_protocol_reported: false,
+            #[cfg(feature = "debug")]
+            inbound_inspector: None,
+            #[cfg(feature = "zstd")]
+            zstd_protocol: None,
+            substream_mode: SubstreamMode::default(),
         }
     }
 
+    /// Create the codec of a substream negotiated with the given protocol.
+    #[cfg_attr(not(feature = "zstd"), allow(unused_variables))]
+    fn new_codec(&self, protocol: &ProtocolId) -> Codec {
+        #[cfg(feature = "zstd")]
+        if self.zstd_protocol.as_ref() == Some(protocol) {
+            return Codec::zstd(self.max_frame_size);
+        }
+
+        Codec::plain(self.max_frame_size)
+    }
+
     fn on_fully_negotiated_inbound(&mut self, protocol: UpgradeOutput) {
-        let UpgradeOutput { socket, .. } = protocol;
+        let UpgradeOutput { socket, info } = protocol;
+        self.negotiated_protocol.get_or_insert_with(|| info.clone());
+
+        // Substreams negotiated concurrently are not refused by the listen protocol. Drop the
+        // ones exceeding the limit.
+        if !self.accepts_inbound_substream() {
+            log::debug!("Refusing inbound substream: maximum number of inbound substreams reached");
+            return;
+        }
+
+        // Peers opening a substream per RPC frame open the next one before the previous one is
+        // read to the end. Keep reading the superseded substream until the remote closes it.
+        if self.open_inbound_substreams() >= self.max_inbound_substreams {
+            if let Some(idx) = self
+                .inbound_substreams
+                .iter()
+                .position(|substream| matches!(substream, InboundSubstreamState::WaitingInput(_)))
+            {
+                if let InboundSubstreamState::WaitingInput(substream) = std::mem::replace(
+                    &mut self.inbound_substreams[idx],
+                    InboundSubstreamState::Poisoned,
+                ) {
+                    self.inbound_substreams[idx] = InboundSubstreamState::Draining(substream);
+                }
+            }
+        }
 
-        let codec = Codec::new(self.max_frame_size);
+        let codec = self.new_codec(&info);
+        #[cfg(feature = "debug")]
+        let codec = match &self.inbound_inspector {
+            Some(inspector) => codec.with_inspector(inspector.clone()),
+            None => codec,
+        };
         let stream = Framed::new(socket, codec);
 
-        // new inbound substream. Replace the current one, if it exists.
         log::trace!("new inbound substream request");
-        self.inbound_substream = Some(InboundSubstreamState::WaitingInput(stream));
+        self.inbound_substreams
+            .push(InboundSubstreamState::WaitingInput(stream));
+    }
+
+    /// Whether a new inbound substream can be accepted without exceeding the maximum number of
+    /// concurrent inbound substreams, either open or draining. The substreams being closed,
+    /// already read to the end, are not counted.
+    fn accepts_inbound_substream(&self) -> bool {
+        let draining_substreams = self
+            .inbound_substreams
+            .iter()
+            .filter(|substream| matches!(substream, InboundSubstreamState::Draining(_)))
+            .count();
+        self.open_inbound_substreams() < self.max_inbound_substreams
+            || draining_substreams < self.max_inbound_substreams
+    }
+
+    /// The number of inbound substreams waiting for input from the remote.
+    fn open_inbound_substreams(&self) -> usize {
+        self.inbound_substreams
+            .iter()
+            .filter(|substream| matches!(substream, InboundSubstreamState::WaitingInput(_)))
+            .count()
     }
 
     fn on_fully_negotiated_outbound(
@@ -220,9 +382,10 @@ impl SimpleHandler {
             <Self as ConnectionHandler>::OutboundOpenInfo,
         >,
     ) {
-        let UpgradeOutput { socket, .. } = protocol;
+        let UpgradeOutput { socket, info } = protocol;
+        let codec = self.new_codec(&info);
+        self.negotiated_protocol.get_or_insert(info);
 
-        let codec = Codec::new(self.max_frame_size);
         let stream = Framed::new(socket, codec);
 
         assert!(
@@ -231,6 +394,21 @@ impl SimpleHandler {
         );
         self.outbound_substream = Some(OutboundSubstreamState::WaitingOutput(stream));
     }
+
+    fn on_remote_protocols_change(&mut self, change: ProtocolsChange) {
+        // The remote protocols are only known if a protocol advertising them (e.g., identify) is
+        // running on the connection. Peers are assumed to be floodsub peers otherwise.
+        let ProtocolsChange::Added(mut protocols) = change else {
+            return;
+        };
+
+        if self.peer_kind.is_none()
+            && protocols.any(|protocol| protocol.as_ref().starts_with(GOSSIPSUB_PROTOCOL_PREFIX))
+        {
+            log::debug!("The remote peer supports the gossipsub protocol");
+            self.peer_kind = Some(PeerKind::Gossipsub);
+        }
+    }
 }
 
 impl ConnectionHandler for SimpleHandler {
@@ -243,7 +421,12 @@ impl ConnectionHandler for SimpleHandler {
     type OutboundOpenInfo = ();
 
     fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol, Self::InboundOpenInfo> {
+        if !self.accepts_inbound_substream() {
+            return SubstreamProtocol::new(either::Either::Right(DeniedUpgrade), ());
+        }
+
         SubstreamProtocol::new(either::Either::Left(self.upgrade.clone()), ())
+            .with_timeout(self.negotiation_timeout)
     }
 
     fn connection_keep_alive(&self) -> KeepAlive {
@@ -254,7 +437,9 @@ impl ConnectionHandler for SimpleHandler {
         if matches!(
             self.outbound_substream,
             Some(
-                OutboundSubstreamState::PendingSend(_, _) | OutboundSubstreamState::PendingFlush(_),
+                OutboundSubstreamState::PendingSend(_, _)
+                    | OutboundSubstreamState::PendingFlush(_)
+                    | OutboundSubstreamState::Closing(_),
             )
         ) {
             return KeepAlive::Yes;
@@ -274,6 +459,24 @@ impl ConnectionHandler for SimpleHandler {
             Self::Error,
         >,
     > {
+        // Report the peer kind, once detected.
+        if let (Some(peer_kind), false) = (self.peer_kind, self.peer_kind_reported) {
+            self.peer_kind_reported = true;
+            return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(Event::PeerKind(
+                peer_kind,
+            )));
+        }
+
+        // Report the negotiated protocol, once a substream is negotiated.
+        if let (Some(protocol), false) =
+            (&self.negotiated_protocol, self.negotiated_protocol_reported)
+        {
+            self.negotiated_protocol_reported = true;
+            return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
+                Event::ProtocolNegotiated(protocol.clone()),
+            ));
+        }
+
         // determine if we need to create the outbound stream
         if !self.send_queue.is_empty()
             && self.outbound_substream.is_none()
@@ -283,23 +486,36 @@ impl ConnectionHandler for SimpleHandler {
 
             // Send a request to open a new outbound substream.
             return Poll::Ready(ConnectionHandlerEvent::OutboundSubstreamRequest {
-                protocol: SubstreamProtocol::new(self.upgrade.clone(), ()),
+                protocol: SubstreamProtocol::new(self.upgrade.clone(), ())
+                    .with_timeout(self.negotiation_timeout),
             });
         }
 
-        // Process the inbound substream.
-        loop {
+        // Process the inbound substreams.
+        let mut idx = 0;
+        while idx < self.inbound_substreams.len() {
+            let draining = matches!(
+                self.inbound_substreams[idx],
+                InboundSubstreamState::Draining(_)
+            );
+            let reading = |substream| {
+                if draining {
+                    InboundSubstreamState::Draining(substream)
+                } else {
+                    InboundSubstreamState::WaitingInput(substream)
+                }
+            };
             match std::mem::replace(
-                &mut self.inbound_substream,
-                Some(InboundSubstreamState::Poisoned),
+                &mut self.inbound_substreams[idx],
+                InboundSubstreamState::Poisoned,
             ) {
                 // inbound idle state
-                Some(InboundSubstreamState::WaitingInput(mut substream)) => {
+                InboundSubstreamState::WaitingInput(mut substream)
+                | InboundSubstreamState::Draining(mut substream) => {
                     match substream.poll_next_unpin(cx) {
                         Poll::Ready(Some(Ok(message))) => {
                             self.last_io_activity = Instant::now();
-                            self.inbound_substream =
-                                Some(InboundSubstreamState::WaitingInput(substream));
+                            self.inbound_substreams[idx] = reading(substream);
                             return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
                                 Event::FrameReceived(message),
                             ));
@@ -309,23 +525,22 @@ impl ConnectionHandler for SimpleHandler {
                             // Close this side of the stream. If the
                             // peer is still around, they will re-establish their
                             // outbound stream i.e. our inbound stream.
-                            self.inbound_substream =
-                                Some(InboundSubstreamState::Closing(substream));
+                            self.inbound_substreams[idx] =
+                                InboundSubstreamState::Closing(substream);
                         }
                         // peer closed the stream
                         Poll::Ready(None) => {
                             log::debug!("Inbound stream closed by remote");
-                            self.inbound_substream =
-                                Some(InboundSubstreamState::Closing(substream));
+                            self.inbound_substreams[idx] =
+                                InboundSubstreamState::Closing(substream);
                         }
                         Poll::Pending => {
-                            self.inbound_substream =
-                                Some(InboundSubstreamState::WaitingInput(substream));
-                            break;
+                            self.inbound_substreams[idx] = reading(substream);
+                            idx += 1;
                         }
                     }
                 }
-                Some(InboundSubstreamState::Closing(mut substream)) => {
+                InboundSubstreamState::Closing(mut substream) => {
                     match Sink::poll_close(Pin::new(&mut substream), cx) {
                         Poll::Ready(res) => {
                             if let Err(e) = res {
@@ -334,21 +549,16 @@ impl ConnectionHandler for SimpleHandler {
                                 // substream.
                                 log::debug!("Inbound substream error while closing: {e}");
                             }
-                            self.inbound_substream = None;
-                            break;
+                            self.inbound_substreams.swap_remove(idx);
                         }
                         Poll::Pending => {
-                            self.inbound_substream =
-                                Some(InboundSubstreamState::Closing(substream));
-                            break;
+                            self.inbound_substreams[idx] =
+                                InboundSubstreamState::Closing(substream);
+                            idx += 1;
                         }
                     }
                 }
-                None => {
-                    self.inbound_substream = None;
-                    break;
-                }
-                Some(InboundSubstreamState::Poisoned) => {
+                InboundSubstreamState::Poisoned => {
                     unreachable!("Error occurred during inbound stream processing")
                 }
             }
@@ -363,7 +573,7 @@ impl ConnectionHandler for SimpleHandler {
                 // outbound idle state
                 Some(OutboundSubstreamState::WaitingOutput(substream)) => {
                     if let Some(message) = self.send_queue.pop() {
-                        self.send_queue.shrink_to_fit();
+                        self.dequeued_frames += 1;
                         self.outbound_substream =
                             Some(OutboundSubstreamState::PendingSend(substream, message));
                         continue;
@@ -404,8 +614,14 @@ impl ConnectionHandler for SimpleHandler {
                     match Sink::poll_flush(Pin::new(&mut substream), cx) {
                         Poll::Ready(Ok(())) => {
                             self.last_io_activity = Instant::now();
-                            self.outbound_substream =
-                                Some(OutboundSubstreamState::WaitingOutput(substream))
+                            self.outbound_substream = match self.substream_mode {
+                                SubstreamMode::Persistent => {
+                                    Some(OutboundSubstreamState::WaitingOutput(substream))
+                                }
+                                SubstreamMode::PerMessage => {
+                                    Some(OutboundSubstreamState::Closing(substream))
+                                }
+                            };
                         }
                         Poll::Ready(Err(e)) => {
                             log::debug!("Failed to flush outbound stream: {e}");
@@ -419,6 +635,27 @@ impl ConnectionHandler for SimpleHandler {
                         }
                     }
                 }
+                Some(OutboundSubstreamState::Closing(mut substream)) => {
+                    match Sink::poll_close(Pin::new(&mut substream), cx) {
+                        Poll::Ready(res) => {
+                            if let Err(e) = res {
+                                log::debug!("Outbound substream error while closing: {e}");
+                            }
+                            self.outbound_substream = None;
+
+                            // Open a fresh substream for the next queued frame, if any.
+                            if !self.send_queue.is_empty() {
+                                cx.waker().wake_by_ref();
+                            }
+                            break;
+                        }
+                        Poll::Pending => {
+                            self.outbound_substream =
+                                Some(OutboundSubstreamState::Closing(substream));
+                            break;
+                        }
+                    }
+                }
                 None => {
                     self.outbound_substream = None;
                     break;
@@ -429,14 +666,22 @@ impl ConnectionHandler for SimpleHandler {
             }
         }
 
+        // Report the frames that left the send queue.
+        if self.dequeued_frames > 0 {
+            let dequeued_frames = std::mem::take(&mut self.dequeued_frames);
+            return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
+                Event::FramesDequeued(dequeued_frames),
+            ));
+        }
+
         Poll::Pending
     }
 
     fn on_behaviour_event(&mut self, event: Self::FromBehaviour) {
         match event {
             Command::SendFrame(msg) => self.send_queue.push(msg),
-            Command::KeepAlive => {
-                self.keep_alive = true;
+            Command::KeepAlive(keep_alive) => {
+                self.keep_alive = keep_alive;
             }
         }
     }
@@ -466,6 +711,9 @@ impl ConnectionHandler for SimpleHandler {
             ConnectionEvent::FullyNegotiatedOutbound(fully_negotiated_outbound) => {
                 self.on_fully_negotiated_outbound(fully_negotiated_outbound)
             }
+            ConnectionEvent::RemoteProtocolsChange(change) => {
+                self.on_remote_protocols_change(change)
+            }
             ConnectionEvent::DialUpgradeError(DialUpgradeError {
                 error: StreamUpgradeError::Timeout,
                 ..
@@ -511,15 +759,59 @@ pub struct Handler {
 
 impl Handler {
     // TODO: Make generic, decouple from Frame frame, from ProtocolUpgrade, etc.
-    pub fn new(protocol_id: ProtocolId, max_frame_size: usize, idle_timeout: Duration) -> Self {
+    pub fn new(
+        protocol_id: ProtocolId,
+        max_frame_size: usize,
+        max_inbound_substreams: usize,
+        idle_timeout: Duration,
+        negotiation_timeout: Duration,
+    ) -> Self {
         let upgrade = Upgrade::new(protocol_id);
         Self {
             inbound_substream_attempts: 0,
             outbound_substream_attempts: 0,
-            inner: HandlerState::Enabled(SimpleHandler::new(upgrade, max_frame_size, idle_timeout)),
+            inner: HandlerState::Enabled(SimpleHandler::new(
+                upgrade,
+                max_frame_size,
+                max_inbound_substreams,
+                idle_timeout,
+                negotiation_timeout,
+            )),
         }
     }
 
+    /// Invoke the given inspector with the raw bytes of each frame received from the remote,
+    /// before decoding it.
+    #[cfg(feature = "debug")]
+    pub(crate) fn with_inbound_inspector(mut self, inspector: Arc<ProstCodecInspector>) -> Self {
+        if let HandlerState::Enabled(handler) = &mut self.inner {
+            handler.inbound_inspector = Some(inspector);
+        }
+        self
+    }
+
+    /// Support the given protocol, exchanging zstd compressed RPC frames, in preference to the
+    /// uncompressed protocol. Peers not supporting it fall back to the uncompressed protocol.
+    #[cfg(feature = "zstd")]
+    pub(crate) fn with_zstd_protocol(mut self, protocol_id: ProtocolId) -> Self {
+        if let HandlerState::Enabled(handler) = &mut self.inner {
+            handler.upgrade = handler
+                .upgrade
+                .clone()
+                .with_preferred_protocol(protocol_id.clone());
+            handler.zstd_protocol = Some(protocol_id);
+        }
+        self
+    }
+
+    /// Set how the RPC frames are sent over the outbound substreams.
+    pub(crate) fn with_substream_mode(mut self, substream_mode: SubstreamMode) -> Self {
+        if let HandlerState::Enabled(handler) = &mut self.inner {
+            handler.substream_mode = substream_mode;
+        }
+        self
+    }
+
     #[cfg(test)]
     pub fn is_enabled(&self) -> bool {
         matches!(self.inner, HandlerState::Enabled(_))
@@ -530,9 +822,13 @@ impl Handler {
         matches!(self.inner, HandlerState::Disabled(_))
     }
 
-    #[cfg(test)]
-    pub fn disable(&mut self, reason: DisabledHandlerReason) {
-        self.inner = HandlerState::Disabled(DisabledHandler::with_reason(reason));
+    /// Disable the handler. The frames still queued for sending are dropped.
+    fn disable(&mut self, reason: DisabledHandlerReason) {
+        let mut disabled = DisabledHandler::with_reason(reason);
+        if let HandlerState::Enabled(handler) = &self.inner {
+            disabled.dropped_frames = handler.send_queue.len() + handler.dequeued_frames;
+        }
+        self.inner = HandlerState::Disabled(disabled);
     }
 }
 
@@ -592,26 +888,30 @@ impl ConnectionHandler for Handler {
             Self::OutboundOpenInfo,
         >,
     ) {
-        if event.is_inbound() {
+        // Peers in per-message mode open substreams over and over, whatever our own mode, so only
+        // the failed negotiations count towards the maximum attempts.
+        let counts_as_attempt = !matches!(
+            event,
+            ConnectionEvent::FullyNegotiatedInbound(_)
+                | ConnectionEvent::FullyNegotiatedOutbound(_)
+        );
+
+        if event.is_inbound() && counts_as_attempt {
             self.inbound_substream_attempts += 1;
 
             if self.inbound_substream_attempts >= MAX_SUBSTREAM_ATTEMPTS {
                 log::warn!("The maximum number of inbound substreams attempts has been exceeded");
-                self.inner = HandlerState::Disabled(DisabledHandler::with_reason(
-                    DisabledHandlerReason::MaxSubstreamAttempts,
-                ));
+                self.disable(DisabledHandlerReason::MaxSubstreamAttempts);
                 return;
             }
         }
 
-        if event.is_outbound() {
+        if event.is_outbound() && counts_as_attempt {
             self.outbound_substream_attempts += 1;
 
             if self.outbound_substream_attempts >= MAX_SUBSTREAM_ATTEMPTS {
                 log::warn!("The maximum number of outbound substream attempts has been exceeded");
-                self.inner = HandlerState::Disabled(DisabledHandler::with_reason(
-                    DisabledHandlerReason::MaxSubstreamAttempts,
-                ));
+                self.disable(DisabledHandlerReason::MaxSubstreamAttempts);
                 return;
             }
         }
@@ -623,9 +923,7 @@ impl ConnectionHandler for Handler {
         {
             // The protocol is not supported
             log::debug!("The remote peer does not support the protocol on this connection");
-            self.inner = HandlerState::Disabled(DisabledHandler::with_reason(
-                DisabledHandlerReason::ProtocolUnsupported,
-            ));
+            self.disable(DisabledHandlerReason::ProtocolUnsupported);
         }
 
         match &mut self.inner {
@@ -643,7 +941,13 @@ mod tests {
     use super::*;
 
     fn test_handler() -> Handler {
-        Handler::new("/test/0.0.1", 1024, Duration::from_secs(60))
+        Handler::new(
+            StreamProtocol::new("/test/0.0.1"),
+            1024,
+            1,
+            Duration::from_secs(60),
+            Duration::from_secs(10),
+        )
     }
 
     #[test]
@@ -763,10 +1067,103 @@ mod tests {
         let mut handler = test_handler();
 
         //// When
-        handler.on_behaviour_event(Command::KeepAlive);
+        handler.on_behaviour_event(Command::KeepAlive(true));
 
         //// Then
         let keep_alive = handler.connection_keep_alive();
         assert_matches!(keep_alive, KeepAlive::Yes);
     }
+
+    #[test]
+    fn enabled_handler_on_release_keep_alive_command() {
+        //// Given
+        let mut handler = test_handler();
+        handler.on_behaviour_event(Command::KeepAlive(true));
+
+        //// When
+        handler.on_behaviour_event(Command::KeepAlive(false));
+
+        //// Then
+        let keep_alive = handler.connection_keep_alive();
+        assert_matches!(keep_alive, KeepAlive::Until(_));
+    }
+
+    #[test]
+    fn enabled_handler_listen_protocol_timeout() {
+        //// Given
+        let handler = Handler::new(
+            StreamProtocol::new("/test/0.0.1"),
+            1024,
+            1,
+            Duration::from_secs(60),
+            Duration::from_millis(500),
+        );
+
+        //// When
+        let protocol = handler.listen_protocol();
+
+        //// Then
+        assert_eq!(protocol.timeout(), &Duration::from_millis(500));
+    }
+
+    #[test]
+    fn enabled_handler_retries_outbound_substream_after_negotiation_timeout() {
+        //// Given
+        let mut handler = Handler::new(
+            StreamProtocol::new("/test/0.0.1"),
+            1024,
+            1,
+            Duration::from_secs(60),
+            Duration::from_millis(500),
+        );
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+
+        handler.on_behaviour_event(Command::SendFrame(RpcProto::default()));
+        assert_matches!(
+            handler.poll(&mut cx),
+            Poll::Ready(ConnectionHandlerEvent::OutboundSubstreamRequest { protocol }) => {
+                assert_eq!(protocol.timeout(), &Duration::from_millis(500));
+            }
+        );
+
+        //// When
+        handler.on_connection_event(ConnectionEvent::DialUpgradeError(DialUpgradeError {
+            info: (),
+            error: StreamUpgradeError::Timeout,
+        }));
+
+        //// Then
+        assert!(handler.is_enabled());
+        assert_matches!(
+            handler.poll(&mut cx),
+            Poll::Ready(ConnectionHandlerEvent::OutboundSubstreamRequest { .. })
+        );
+    }
+
+    #[test]
+    fn disabled_handler_reports_dropped_queued_frames() {
+        //// Given
+        let mut handler = test_handler();
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+
+        handler.on_behaviour_event(Command::SendFrame(RpcProto::default()));
+        handler.on_behaviour_event(Command::SendFrame(RpcProto::default()));
+
+        //// When
+        handler.disable(DisabledHandlerReason::ProtocolUnsupported);
+        handler.on_behaviour_event(Command::SendFrame(RpcProto::default()));
+
+        //// Then
+        assert_matches!(
+            handler.poll(&mut cx),
+            Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(Event::Disabled(_)))
+        );
+        assert_matches!(
+            handler.poll(&mut cx),
+            Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
+                Event::FramesDequeued(3)
+            ))
+        );
+        assert_matches!(handler.poll(&mut cx), Poll::Pending);
+    }
 }
@@ -0,0 +1,244 @@
+#[cfg(feature = "debug")]
+use std::sync::Arc;
+
+use asynchronous_codec::{Decoder, Encoder};
+use bytes::BytesMut;
+#[cfg(feature = "zstd")]
+use common::codec::LengthDelimited;
+#[cfg(feature = "debug")]
+use common::codec::ProstCodecInspector;
+use common::codec::{ProstCodec, ProstCodecError};
+#[cfg(feature = "zstd")]
+use prost::Message as _;
+
+use crate::proto::RpcProto;
+
+/// The codec of the RPC frames exchanged on a pubsub substream, selected by the negotiated
+/// protocol.
+pub(crate) enum RpcCodec {
+    /// The RPC frames are exchanged uncompressed.
+    Plain(ProstCodec<RpcProto>),
+
+    /// The RPC frames are compressed with zstd as a whole.
+    #[cfg(feature = "zstd")]
+    Zstd(ZstdCodec),
+}
+
+impl RpcCodec {
+    /// Create a codec exchanging uncompressed RPC frames of up to `max_frame_size` bytes.
+    pub(crate) fn plain(max_frame_size: usize) -> Self {
+        Self::Plain(ProstCodec::new(max_frame_size))
+    }
+
+    /// Create a codec exchanging zstd compressed RPC frames of up to `max_frame_size` bytes, both
+    /// compressed and decompressed.
+    #[cfg(feature = "zstd")]
+    pub(crate) fn zstd(max_frame_size: usize) -> Self {
+        Self::Zstd(ZstdCodec::new(max_frame_size))
+    }
+
+    /// Set a callback invoked with the raw bytes of every received RPC frame, after decompressing
+    /// it if compressed, before decoding it.
+    #[cfg(feature = "debug")]
+    pub(crate) fn with_inspector(self, inspector: Arc<ProstCodecInspector>) -> Self {
+        match self {
+            Self::Plain(codec) => Self::Plain(codec.with_inspector(inspector)),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(mut codec) => {
+                codec.inspector = Some(inspector);
+                Self::Zstd(codec)
+            }
+        }
+    }
+}
+
+impl Encoder for RpcCodec {
+    type Item = RpcProto;
+    type Error = ProstCodecError;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        match self {
+            Self::Plain(codec) => codec.encode(item, dst),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(codec) => codec.encode(item, dst),
+        }
+    }
+}
+
+impl Decoder for RpcCodec {
+    type Item = RpcProto;
+    type Error = ProstCodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self {
+            Self::Plain(codec) => codec.decode(src),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(codec) => codec.decode(src),
+        }
+    }
+}
+
+/// A codec compressing the whole encoded RPC frames with zstd, prefixed with the compressed
+/// length as an [`unsigned_varint`].
+#[cfg(feature = "zstd")]
+pub(crate) struct ZstdCodec {
+    /// The maximum size of a frame, compressed or decompressed.
+    max_frame_size: usize,
+
+    /// The decoder of the length-prefixed compressed frames.
+    decoder: LengthDelimited,
+
+    /// The inspector of the received frames, once decompressed, if any.
+    #[cfg(feature = "debug")]
+    inspector: Option<Arc<ProstCodecInspector>>,
+}
+
+#[cfg(feature = "zstd")]
+impl ZstdCodec {
+    fn new(max_frame_size: usize) -> Self {
+        Self {
+            max_frame_size,
+            decoder: LengthDelimited::new(max_frame_size),
+            #[cfg(feature = "debug")]
+            inspector: None,
+        }
+    }
+
+    /// Fail if the frame size exceeds the maximum frame size.
+    fn check_frame_size(&self, size: usize) -> Result<(), std::io::Error> {
+        if size > self.max_frame_size {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "frame size {size} exceeds the maximum {}",
+                    self.max_frame_size
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Encoder for ZstdCodec {
+    type Item = RpcProto;
+    type Error = ProstCodecError;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let encoded = item.encode_to_vec();
+        self.check_frame_size(encoded.len())?;
+
+        let compressed = zstd::bulk::compress(&encoded, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+        self.check_frame_size(compressed.len())?;
+
+        let mut prefix = unsigned_varint::encode::usize_buffer();
+        dst.extend_from_slice(unsigned_varint::encode::usize(
+            compressed.len(),
+            &mut prefix,
+        ));
+        dst.extend_from_slice(&compressed);
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Decoder for ZstdCodec {
+    type Item = RpcProto;
+    type Error = ProstCodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let Some(compressed) = self.decoder.decode(src)? else {
+            return Ok(None);
+        };
+
+        // The decompression fails if the frame exceeds the maximum frame size once decompressed.
+        let frame = zstd::bulk::decompress(&compressed, self.max_frame_size)?;
+
+        #[cfg(feature = "debug")]
+        if let Some(inspector) = &self.inspector {
+            inspector(&frame);
+        }
+
+        Ok(Some(RpcProto::decode(frame.as_slice())?))
+    }
+}
+
+#[cfg(all(test, feature = "zstd"))]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::proto::MessageProto;
+
+    use super::*;
+
+    fn test_frame(payload: &[u8]) -> RpcProto {
+        RpcProto {
+            publish: vec![MessageProto {
+                topic: "/test/topic".to_string(),
+                data: Some(Bytes::copy_from_slice(payload)),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn zstd_codec_round_trip_frame() {
+        //// Given
+        let mut codec = RpcCodec::zstd(4096);
+        let frame = test_frame(&b"test-payload".repeat(100));
+
+        let mut buffer = BytesMut::new();
+        codec
+            .encode(frame.clone(), &mut buffer)
+            .expect("encode the frame");
+
+        //// When
+        let decoded = codec.decode(&mut buffer);
+
+        //// Then
+        assert!(buffer.is_empty());
+        assert_eq!(decoded.unwrap(), Some(frame));
+    }
+
+    #[test]
+    fn zstd_codec_compresses_the_whole_frame() {
+        //// Given
+        let frame = test_frame(&b"test-payload".repeat(100));
+
+        let mut plain = BytesMut::new();
+        RpcCodec::plain(4096)
+            .encode(frame.clone(), &mut plain)
+            .expect("encode the frame");
+
+        //// When
+        let mut compressed = BytesMut::new();
+        RpcCodec::zstd(4096)
+            .encode(frame, &mut compressed)
+            .expect("encode the frame");
+
+        //// Then
+        assert!(compressed.len() < plain.len());
+    }
+
+    #[test]
+    fn zstd_codec_rejects_frame_exceeding_max_size_once_decompressed() {
+        //// Given
+        let frame = test_frame(&[0; 2048]);
+
+        let mut buffer = BytesMut::new();
+        RpcCodec::zstd(4096)
+            .encode(frame, &mut buffer)
+            .expect("encode the frame");
+
+        let mut codec = RpcCodec::zstd(1024);
+
+        //// When
+        let decoded = codec.decode(&mut buffer);
+
+        //// Then
+        assert!(decoded.is_err());
+    }
+}
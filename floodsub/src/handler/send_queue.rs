@@ -0,0 +1,128 @@
+use std::collections::VecDeque;
+
+use crate::proto::RpcProto;
+
+/// The outbound frames queue of a connection handler.
+///
+/// Frames carrying subscription changes or control messages are sent before any queued published
+/// message, so the remote peer learns about the subscription changes promptly even when there is
+/// a backlog of messages to send. Frames of the same priority are sent in the order they were
+/// queued.
+#[derive(Debug, Default)]
+pub(crate) struct SendQueue {
+    /// The frames carrying subscriptions or control messages.
+    control: VecDeque<RpcProto>,
+
+    /// The frames carrying published messages only.
+    publish: VecDeque<RpcProto>,
+}
+
+impl SendQueue {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.control.is_empty() && self.publish.is_empty()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.control.len() + self.publish.len()
+    }
+
+    /// Queue the frame, according to its priority.
+    pub(crate) fn push(&mut self, frame: RpcProto) {
+        if is_control_frame(&frame) {
+            self.control.push_back(frame);
+        } else {
+            self.publish.push_back(frame);
+        }
+    }
+
+    /// Take the next frame to send.
+    pub(crate) fn pop(&mut self) -> Option<RpcProto> {
+        let frame = self
+            .control
+            .pop_front()
+            .or_else(|| self.publish.pop_front());
+
+        if self.is_empty() {
+            self.control.shrink_to_fit();
+            self.publish.shrink_to_fit();
+        }
+
+        frame
+    }
+}
+
+/// Whether the frame carries subscription changes or control messages.
+fn is_control_frame(frame: &RpcProto) -> bool {
+    !frame.subscriptions.is_empty() || frame.control.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::proto::{MessageProto, SubOptsProto};
+
+    use super::*;
+
+    fn publish_frame(seqno: u64) -> RpcProto {
+        RpcProto {
+            publish: vec![MessageProto {
+                topic: "/test/topic".to_string(),
+                data: Some(Bytes::from_static(b"test-payload")),
+                seqno: Some(Bytes::copy_from_slice(&seqno.to_be_bytes())),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn subscription_frame(topic: &str) -> RpcProto {
+        RpcProto {
+            subscriptions: vec![SubOptsProto {
+                subscribe: Some(true),
+                topic_id: Some(topic.to_string()),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn subscription_frame_is_sent_before_queued_messages() {
+        //// Given
+        let mut queue = SendQueue::default();
+        for seqno in 0..1024 {
+            queue.push(publish_frame(seqno));
+        }
+
+        //// When
+        queue.push(subscription_frame("/test/new-topic"));
+
+        //// Then
+        assert_eq!(queue.pop(), Some(subscription_frame("/test/new-topic")));
+    }
+
+    #[test]
+    fn frames_of_the_same_priority_are_sent_in_order() {
+        //// Given
+        let mut queue = SendQueue::default();
+
+        //// When
+        queue.push(publish_frame(1));
+        queue.push(subscription_frame("/test/topic-a"));
+        queue.push(publish_frame(2));
+        queue.push(subscription_frame("/test/topic-b"));
+
+        //// Then
+        let frames = std::iter::from_fn(|| queue.pop()).collect::<Vec<_>>();
+        assert_eq!(
+            frames,
+            vec![
+                subscription_frame("/test/topic-a"),
+                subscription_frame("/test/topic-b"),
+                publish_frame(1),
+                publish_frame(2),
+            ]
+        );
+        assert!(queue.is_empty());
+    }
+}
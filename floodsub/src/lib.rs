@@ -1,19 +1,48 @@
-pub use behaviour::{Behaviour, Event, PublishError, SendError, SubscriptionError};
-pub use config::Config;
-pub use frame::Message;
-pub use topic::{Hasher, Topic, TopicHash};
+pub use behaviour::{
+    BandwidthStats, Behaviour, DisconnectReason, DropReason, Event, PeerStats, PublishError,
+    SendError, Stats, SubscriptionError,
+};
+#[cfg(feature = "debug")]
+pub use config::RawRpcFn;
+pub use config::{
+    AnonymousSeqno, Config, ConfigBuilder, ConfigError, DedupScope, DedupStrategy,
+    ForwardSubsetStrategy, MessageExpiredFn, RetransmissionFn, RetransmissionPolicy, SubstreamMode,
+    TopicParams,
+};
+pub use event_stream::EventStream;
+pub use frame::{DecodeError, Message};
+pub use handler::PeerKind;
+pub use message_id::{sha256_message_id_fn, MessageId, MessageIdFn};
+pub use pending_publish::PublishFuture;
+pub use proto::MessageValidationError;
+pub use signing::{SignerFn, SigningError};
+pub use topic::{Hasher, Topic, TopicError, TopicHash, MAX_TOPIC_LENGTH};
+#[cfg(feature = "gzip")]
+pub use transform::GzipTransform;
+#[cfg(feature = "zstd")]
+pub use transform::ZstdTransform;
+pub use transform::{DataTransform, IdentityTransform};
 
 mod behaviour;
 mod config;
 mod connections;
+mod event_stream;
 mod frame;
 mod handler;
 mod message_cache;
 mod message_id;
+mod pending_publish;
 mod proto;
+mod rate_limiter;
 mod router;
+mod seen_cache;
 mod seqno;
+mod signing;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 mod topic;
+mod transform;
+mod ttl;
 
 pub type IdentTopic = Topic<topic::IdentityHash>;
 pub type Sha256Topic = Topic<topic::Sha256Hash>;
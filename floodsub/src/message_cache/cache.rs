@@ -18,6 +18,13 @@ pub struct MessageCache<K, V> {
     /// Maximum number of messages in the cache.
     capacity: usize,
 
+    /// The capacity the cache was created with, below which an adaptive capacity never shrinks.
+    min_capacity: usize,
+
+    /// Whether the capacity adapts to the number of non-expired messages, instead of evicting
+    /// them when the cache is full.
+    adaptive_capacity: bool,
+
     /// Time-to-live of messages in the cache.
     ttl: Duration,
 
@@ -44,10 +51,29 @@ impl<K, V> MessageCache<K, V> {
     pub fn with_capacity_and_ttl(capacity: usize, ttl: Duration) -> Self {
         Self {
             capacity,
+            min_capacity: capacity,
+            adaptive_capacity: false,
             ttl,
             cache: LinkedHashMap::with_capacity(capacity),
         }
     }
+
+    /// Adapt the capacity of the cache to the number of non-expired messages.
+    ///
+    /// When the cache is full, the capacity is doubled instead of evicting the oldest message if
+    /// it has not expired yet. When clearing the expired messages, the capacity is halved while
+    /// the cache is less than a quarter full, down to the initial capacity.
+    #[must_use]
+    pub fn with_adaptive_capacity(mut self) -> Self {
+        self.adaptive_capacity = true;
+        self
+    }
+
+    /// Returns the current maximum number of messages in the cache.
+    #[cfg(test)]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
 }
 
 impl<K, V> MessageCache<K, V>
@@ -60,7 +86,15 @@ where
     /// was already in the cache.
     ///
     /// If the source is `None`, then the message is assumed to have been sent by us.
+    #[cfg(test)]
     pub fn put(&mut self, id: &K, message: V) -> bool {
+        self.put_and_evict(id, message).0
+    }
+
+    /// Inserts a message in the cache, as [`MessageCache::put`] does.
+    ///
+    /// Also returns the ID of the oldest message, if it was evicted because the cache was full.
+    pub fn put_and_evict(&mut self, id: &K, message: V) -> (bool, Option<K>) {
         let result = match self.cache.raw_entry_mut().from_key(id) {
             RawEntryMut::Occupied(mut entry) => {
                 // If the entry has expired but it is still present, update the timestamp
@@ -83,12 +117,18 @@ where
             }
         };
 
-        // If the cache is full, remove the oldest message.
-        if self.cache.len() > self.capacity {
-            self.cache.pop_front();
-        }
+        // If the cache is full, grow it if the oldest message has not expired yet and the capacity
+        // is adaptive. Otherwise, remove the oldest message.
+        let evicted = if self.cache.len() <= self.capacity {
+            None
+        } else if self.adaptive_capacity && !self.is_oldest_expired() {
+            self.capacity = self.capacity.saturating_mul(2);
+            None
+        } else {
+            self.cache.pop_front().map(|(id, _)| id)
+        };
 
-        result
+        (result, evicted)
     }
 
     /// Returns an iterator over all the entries of the cache (expired and not-expired).
@@ -134,11 +174,18 @@ where
             .map(|entry| entry.message)
     }
 
+    /// Remove all messages from the cache, expired and not-expired.
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
     /// Remove all expired messages from the cache.
     ///
     /// An entry is considered expired if the elapsed time since the insertion of the entry is
     /// greater than the time-to-live of the cache, then the entry is considered expired.
-    pub fn clear_expired_entries(&mut self) {
+    ///
+    /// Returns the IDs of the removed messages.
+    pub fn clear_expired_entries(&mut self) -> Vec<K> {
         let mut to_remove = Vec::new();
 
         for (id, entry) in self.cache.iter() {
@@ -149,8 +196,34 @@ where
             to_remove.push(id.clone());
         }
 
-        for id in to_remove {
-            self.cache.remove(&id);
+        for id in &to_remove {
+            self.cache.remove(id);
+        }
+
+        if self.adaptive_capacity {
+            self.shrink_capacity();
+        }
+
+        to_remove
+    }
+
+    /// Whether the oldest message of the cache has expired.
+    fn is_oldest_expired(&self) -> bool {
+        self.cache
+            .front()
+            .is_some_and(|(_, entry)| entry.timestamp.elapsed() > self.ttl)
+    }
+
+    /// Halve the capacity while the cache is less than a quarter full, down to the initial
+    /// capacity.
+    fn shrink_capacity(&mut self) {
+        let capacity = self.capacity;
+        while self.capacity / 2 >= self.min_capacity && self.cache.len() < self.capacity / 4 {
+            self.capacity /= 2;
+        }
+
+        if self.capacity < capacity {
+            self.cache.shrink_to_fit();
         }
     }
 }
@@ -119,11 +119,13 @@ fn insert_over_capacity() {
     cache.put(&id2, msg2);
 
     // Insert messages to go over capacity and discard the previous
-    cache.put(&id3, msg3);
-    cache.put(&id4, msg4);
+    let (_, evicted3) = cache.put_and_evict(&id3, msg3);
+    let (_, evicted4) = cache.put_and_evict(&id4, msg4);
 
     //// Then
     assert_eq!(cache.len(), 2, "cache should contain 2 messages");
+    assert_eq!(evicted3, Some(id1));
+    assert_eq!(evicted4, Some(id2));
 
     let cache_content_ids = cache.iter().map(|(id, _)| id).collect::<Vec<_>>();
     assert_eq!(cache_content_ids, vec![&id3, &id4]);
@@ -266,11 +268,12 @@ fn remove_all_expired_entries_at_once() {
     cache.put(&id5, msg5);
 
     // Remove expired entries
-    cache.clear_expired_entries();
+    let removed = cache.clear_expired_entries();
 
     //// Then
     // Assert cache contents
     assert_eq!(cache.len(), 2, "cache should contain 2 messages");
+    assert_eq!(removed, vec![id1, id2, id3]);
 
     let cache_content_ids = cache.iter().map(|(id, _)| id).collect::<Vec<_>>();
     assert_eq!(cache_content_ids, vec![&id4, &id5]);
@@ -320,3 +323,97 @@ fn insert_an_already_expired_message_should_update_the_timestamp() {
         ]
     );
 }
+
+#[test]
+fn clear_all_entries() {
+    //// Given
+    let (id1, msg1) = test_message(b"test-message1");
+    let (id2, msg2) = test_message(b"test-message2");
+
+    let mut cache = MessageCache::default();
+    cache.put(&id1, msg1.clone());
+    cache.put(&id2, msg2.clone());
+
+    //// When
+    cache.clear();
+
+    //// Then
+    assert_eq!(cache.len(), 0, "cache should be empty");
+    assert!(
+        cache.put(&id1, msg1),
+        "message 1 should have been inserted again"
+    );
+}
+
+#[test]
+fn adaptive_capacity_grows_instead_of_evicting_non_expired_entries() {
+    //// Given
+    let (id1, msg1) = test_message(b"test-message1");
+    let (id2, msg2) = test_message(b"test-message2");
+    let (id3, msg3) = test_message(b"test-message3");
+    let (id4, msg4) = test_message(b"test-message4");
+
+    let capacity = 2;
+    let ttl = Duration::from_secs(5);
+    let mut cache = MessageCache::with_capacity_and_ttl(capacity, ttl).with_adaptive_capacity();
+
+    //// When
+    cache.put(&id1, msg1);
+    cache.put(&id2, msg2);
+    let (_, evicted3) = cache.put_and_evict(&id3, msg3);
+    let (_, evicted4) = cache.put_and_evict(&id4, msg4);
+
+    //// Then
+    assert_eq!(evicted3, None);
+    assert_eq!(evicted4, None);
+    assert_eq!(cache.len(), 4, "cache should contain 4 messages");
+    assert_eq!(cache.capacity(), 4);
+}
+
+#[test]
+fn adaptive_capacity_evicts_expired_entries_when_full() {
+    //// Given
+    let (id1, msg1) = test_message(b"test-message1");
+    let (id2, msg2) = test_message(b"test-message2");
+    let (id3, msg3) = test_message(b"test-message3");
+
+    let capacity = 2;
+    let ttl = Duration::from_millis(100);
+    let mut cache = MessageCache::with_capacity_and_ttl(capacity, ttl).with_adaptive_capacity();
+
+    cache.put(&id1, msg1);
+    cache.put(&id2, msg2);
+    sleep(ttl + Duration::from_millis(20));
+
+    //// When
+    let (_, evicted3) = cache.put_and_evict(&id3, msg3);
+
+    //// Then
+    assert_eq!(evicted3, Some(id1));
+    assert_eq!(cache.capacity(), 2);
+}
+
+#[test]
+fn adaptive_capacity_shrinks_as_entries_expire() {
+    //// Given
+    let ids = (0..16u8)
+        .map(|i| MessageId::new(vec![i]))
+        .collect::<Vec<_>>();
+
+    let capacity = 2;
+    let ttl = Duration::from_millis(100);
+    let mut cache = MessageCache::with_capacity_and_ttl(capacity, ttl).with_adaptive_capacity();
+
+    for id in &ids {
+        cache.put(id, ());
+    }
+    let grown_capacity = cache.capacity();
+    sleep(ttl + Duration::from_millis(20));
+
+    //// When
+    cache.clear_expired_entries();
+
+    //// Then
+    assert_eq!(grown_capacity, 16);
+    assert_eq!(cache.capacity(), 2);
+}
@@ -1,5 +1,6 @@
 use bytes::Bytes;
 use libp2p::identity::PeerId;
+use sha2::{Digest, Sha256};
 
 use crate::Message;
 
@@ -23,6 +24,12 @@ macro_rules! declare_message_id_type {
             }
         }
 
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
         impl From<Vec<u8>> for $name {
             fn from(value: Vec<u8>) -> Self {
                 Self(value)
@@ -60,9 +67,16 @@ declare_message_id_type!(MessageId);
 
 pub type MessageIdFn = dyn Fn(&Message) -> MessageId + Send + Sync + 'static;
 
+/// The default message ID function.
+///
+/// The ID is the source followed by the sequence number. If the source is not provided, the zero
+/// peer ID is used instead. Messages without a sequence number cannot be told apart by their
+/// source, so their ID falls back to the content-based [`sha256_message_id_fn`].
 pub fn default_message_id_fn(msg: &Message) -> MessageId {
-    // default message id is: source + sequence number
-    // NOTE: If either the peer_id or source is not provided, we set to 0;
+    let Some(seqno) = msg.sequence_number() else {
+        return sha256_message_id_fn(msg);
+    };
+
     let mut source_string = if let Some(peer_id) = msg.source().as_ref() {
         peer_id.to_base58()
     } else {
@@ -70,10 +84,34 @@ pub fn default_message_id_fn(msg: &Message) -> MessageId {
             .expect("Valid peer id")
             .to_base58()
     };
-    source_string.push_str(&msg.sequence_number().unwrap_or_default().to_string());
+    source_string.push_str(&seqno.to_string());
     MessageId::new(source_string.into_bytes())
 }
 
+/// A message ID function computing the 32-byte SHA-256 digest of the message topic, source,
+/// sequence number and data.
+///
+/// Unlike the [`default_message_id_fn`], the ID covers the message payload and topic, so distinct
+/// messages from the same source and sequence number get distinct IDs. Each field is prefixed with
+/// its length, so different field splits of the same bytes do not collide.
+pub fn sha256_message_id_fn(msg: &Message) -> MessageId {
+    let source = msg.source().map(|peer_id| peer_id.to_bytes());
+    let seqno = msg.sequence_number().map(u64::to_be_bytes);
+
+    let mut hasher = Sha256::new();
+    for field in [
+        msg.topic_str().as_bytes(),
+        source.as_deref().unwrap_or_default(),
+        seqno.as_ref().map(|seqno| &seqno[..]).unwrap_or_default(),
+        msg.data(),
+    ] {
+        hasher.update((field.len() as u64).to_be_bytes());
+        hasher.update(field);
+    }
+
+    MessageId::new(hasher.finalize().to_vec())
+}
+
 #[cfg(test)]
 mod tests {
     use rand::Rng;
@@ -123,4 +161,117 @@ mod tests {
         //// Then
         assert_eq!(message_id, message_id2);
     }
+
+    #[test]
+    fn sha256_message_id_fn_should_return_same_32_byte_id_for_same_message() {
+        //// Given
+        let message = new_test_message(Some(PeerId::random()), Some(644222));
+
+        //// When
+        let message_id = sha256_message_id_fn(&message);
+        let message_id2 = sha256_message_id_fn(&message.clone());
+
+        //// Then
+        assert_eq!(message_id.as_ref().len(), 32);
+        assert_eq!(message_id, message_id2);
+    }
+
+    #[test]
+    fn sha256_message_id_fn_should_return_distinct_ids_for_distinct_messages() {
+        //// Given
+        let source = PeerId::random();
+        let message = new_test_message(Some(source), Some(644222));
+
+        let mut other_seqno = message.clone();
+        other_seqno.set_sequence_number(Some(644223));
+
+        let mut other_data = message.clone();
+        other_data.set_data(b"other-data".to_vec());
+
+        let mut other_source = message.clone();
+        other_source.set_source(Some(PeerId::random()));
+
+        let other_topic = new_test_message(Some(source), Some(644222));
+
+        //// When
+        let message_ids = [
+            &message,
+            &other_seqno,
+            &other_data,
+            &other_source,
+            &other_topic,
+        ]
+        .map(sha256_message_id_fn);
+
+        //// Then
+        assert!(message_ids.iter().all(|id| id.as_ref().len() == 32));
+        for (i, id) in message_ids.iter().enumerate() {
+            assert!(message_ids[i + 1..].iter().all(|other| other != id));
+        }
+    }
+
+    #[test]
+    fn sha256_message_id_fn_should_return_distinct_ids_for_distinct_anonymous_messages() {
+        //// Given
+        let topic = new_test_topic();
+        let message = Message::new(topic.clone(), b"test-data-1".to_vec());
+        let message2 = Message::new(topic, b"test-data-2".to_vec());
+
+        //// When
+        let message_id = sha256_message_id_fn(&message);
+        let message_id2 = sha256_message_id_fn(&message2);
+
+        //// Then
+        assert_ne!(message_id, message_id2);
+    }
+
+    #[test]
+    fn default_message_id_fn_should_return_distinct_ids_for_distinct_messages_without_seqno() {
+        //// Given
+        let source = PeerId::random();
+        let message = new_test_message(Some(source), None);
+
+        let mut other_data = message.clone();
+        other_data.set_data(b"other-data".to_vec());
+
+        let anonymous = new_test_message(None, None);
+
+        let mut other_anonymous = anonymous.clone();
+        other_anonymous.set_data(b"other-data".to_vec());
+
+        //// When
+        let message_ids =
+            [&message, &other_data, &anonymous, &other_anonymous].map(default_message_id_fn);
+
+        //// Then
+        for (i, id) in message_ids.iter().enumerate() {
+            assert!(message_ids[i + 1..].iter().all(|other| other != id));
+        }
+        assert_eq!(default_message_id_fn(&anonymous.clone()), message_ids[2]);
+    }
+
+    #[test]
+    fn default_message_id_fn_should_return_source_and_seqno_id_for_messages_with_seqno() {
+        //// Given
+        let source = PeerId::random();
+        let message = new_test_message(Some(source), Some(644222));
+        let anonymous = new_test_message(None, Some(644222));
+
+        //// When
+        let message_id = default_message_id_fn(&message);
+        let anonymous_id = default_message_id_fn(&anonymous);
+
+        //// Then
+        assert_eq!(
+            message_id,
+            MessageId::new(format!("{}644222", source.to_base58()))
+        );
+        assert_eq!(
+            anonymous_id,
+            MessageId::new(format!(
+                "{}644222",
+                PeerId::from_bytes(&[0, 1, 0]).unwrap().to_base58()
+            ))
+        );
+    }
 }
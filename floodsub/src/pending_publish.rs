@@ -0,0 +1,120 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::channel::oneshot;
+use futures::FutureExt;
+use futures_timer::Delay;
+use libp2p::identity::PeerId;
+
+use crate::behaviour::PublishError;
+use crate::frame::Frame;
+use crate::message_id::MessageId;
+
+/// A message published with [`crate::Behaviour::publish_async`], waiting for capacity in the
+/// send queues of some of the targeted peers.
+pub(crate) struct PendingPublish {
+    /// The ID of the published message.
+    pub(crate) message_id: MessageId,
+
+    /// The frame carrying the published message.
+    pub(crate) frame: Frame,
+
+    /// The targeted peers the message was not queued to yet.
+    pub(crate) peers: Vec<PeerId>,
+
+    /// The sender notifying the [`PublishFuture`] once the message is queued to all the peers.
+    pub(crate) sender: oneshot::Sender<MessageId>,
+}
+
+impl PendingPublish {
+    /// Create a pending publish, and the future resolving when the message is queued to all the
+    /// given peers, or when the timeout elapses.
+    pub(crate) fn new(
+        message_id: MessageId,
+        frame: Frame,
+        peers: Vec<PeerId>,
+        timeout: Duration,
+    ) -> (Self, PublishFuture) {
+        let (sender, receiver) = oneshot::channel();
+        let pending = Self {
+            message_id,
+            frame,
+            peers,
+            sender,
+        };
+        let future = PublishFuture {
+            state: State::Waiting {
+                receiver,
+                timeout: Delay::new(timeout),
+            },
+        };
+
+        (pending, future)
+    }
+
+    /// Whether the [`PublishFuture`] was dropped.
+    pub(crate) fn is_canceled(&self) -> bool {
+        self.sender.is_canceled()
+    }
+
+    /// Notify the [`PublishFuture`] the message was queued to all the targeted peers.
+    pub(crate) fn complete(self) {
+        // The future may have been dropped in the meantime.
+        let _ = self.sender.send(self.message_id);
+    }
+}
+
+/// The future returned by [`crate::Behaviour::publish_async`].
+///
+/// Resolves to the ID of the published message once it is queued to all the targeted peers, or
+/// to [`PublishError::Timeout`] if the send queues of some of the peers did not free up in time.
+/// The peers disconnecting in the meantime are no longer waited for.
+///
+/// The behaviour queues the message as capacity frees up, whether the future is polled or not.
+/// Dropping the future stops queueing the message to the remaining peers.
+#[must_use = "dropping the future stops queueing the message to the remaining peers"]
+pub struct PublishFuture {
+    state: State,
+}
+
+enum State {
+    /// The outcome is known at creation time (e.g., the message failed to publish).
+    Ready(Option<Result<MessageId, PublishError>>),
+
+    /// Waiting for the message to be queued to all the targeted peers.
+    Waiting {
+        receiver: oneshot::Receiver<MessageId>,
+        timeout: Delay,
+    },
+}
+
+impl PublishFuture {
+    /// Create a future resolving immediately to the given result.
+    pub(crate) fn ready(result: Result<MessageId, PublishError>) -> Self {
+        Self {
+            state: State::Ready(Some(result)),
+        }
+    }
+}
+
+impl Future for PublishFuture {
+    type Output = Result<MessageId, PublishError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match &mut self.state {
+            State::Ready(result) => {
+                Poll::Ready(result.take().expect("future polled after completion"))
+            }
+            State::Waiting { receiver, timeout } => {
+                if let Poll::Ready(result) = receiver.poll_unpin(cx) {
+                    // The sender is dropped without notifying if the behaviour is dropped.
+                    return Poll::Ready(result.map_err(|_| PublishError::Timeout));
+                }
+
+                timeout.poll_unpin(cx).map(|()| Err(PublishError::Timeout))
+            }
+        }
+    }
+}
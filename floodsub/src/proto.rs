@@ -7,8 +7,8 @@ pub use pb::waku::relay::v2::{
     Rpc as RpcProto, TopicDescriptor as TopicDescriptorProto,
 };
 pub use validation::{
-    validate_message_proto, validate_rpc_proto, validate_subopts_proto, MessageValidationError,
-    RpcValidationError, SubOptsValidationError,
+    validate_message_proto, validate_message_proto_fields, validate_rpc_proto,
+    validate_subopts_proto, MessageValidationError, RpcValidationError, SubOptsValidationError,
 };
 
 mod fragmentation;
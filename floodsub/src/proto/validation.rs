@@ -1,6 +1,6 @@
 use libp2p::PeerId;
 
-use crate::proto::{ControlMessageProto, MessageProto, RpcProto, SubOptsProto};
+use crate::proto::{MessageProto, RpcProto, SubOptsProto};
 
 #[derive(Debug, Clone, Copy, thiserror::Error)]
 pub enum MessageValidationError {
@@ -21,15 +21,22 @@ pub fn validate_message_proto(message: &MessageProto) -> Result<(), MessageValid
         return Err(MessageValidationError::EmptyTopic);
     }
 
-    // If present, from field must hold a valid PeerId
-    if let Some(peer_id) = message.from.as_ref() {
+    validate_message_proto_fields(message)
+}
+
+/// Validate the message fields other than the topic.
+pub fn validate_message_proto_fields(message: &MessageProto) -> Result<(), MessageValidationError> {
+    // If present, from field must hold a valid PeerId. An empty from field is interpreted as not
+    // present.
+    if let Some(peer_id) = message.from.as_ref().filter(|from| !from.is_empty()) {
         if PeerId::from_bytes(peer_id).is_err() {
             return Err(MessageValidationError::InvalidPeerId);
         }
     }
 
-    // If present, seqno field must be a 64-bit big-endian serialized unsigned integer
-    if let Some(seq_no) = message.seqno.as_ref() {
+    // If present, seqno field must be a 64-bit big-endian serialized unsigned integer. An empty
+    // seqno field is interpreted as not present.
+    if let Some(seq_no) = message.seqno.as_ref().filter(|seq_no| !seq_no.is_empty()) {
         if seq_no.len() != 8 {
             return Err(MessageValidationError::InvalidSequenceNumber);
         }
@@ -74,28 +81,6 @@ pub fn validate_subopts_proto(subopts: &SubOptsProto) -> Result<(), SubOptsValid
     Ok(())
 }
 
-#[derive(Debug, Clone, Copy, thiserror::Error)]
-pub enum ControlMessageValidationError {
-    /// Empty control message
-    #[error("empty control message")]
-    EmptyControl,
-}
-
-pub fn validate_control_proto(
-    control: &ControlMessageProto,
-) -> Result<(), ControlMessageValidationError> {
-    if control.ihave.is_empty()
-        && control.iwant.is_empty()
-        && control.graft.is_empty()
-        && control.prune.is_empty()
-    {
-        // Control message must not be empty.
-        return Err(ControlMessageValidationError::EmptyControl);
-    }
-
-    Ok(())
-}
-
 #[derive(Debug, Clone, Copy, thiserror::Error)]
 pub enum RpcValidationError {
     /// Empty rpc frame.
@@ -103,14 +88,48 @@ pub enum RpcValidationError {
     EmptyRpc,
 }
 
+/// Validate the RPC frame is not empty.
+///
+/// A control message counts as content even if none of its known fields is set: it may carry
+/// control fields unknown to floodsub (e.g., the gossipsub v1.2 `IDONTWANT`), skipped when decoding.
 pub fn validate_rpc_proto(rpc: &RpcProto) -> Result<(), RpcValidationError> {
-    if rpc.publish.is_empty()
-        && rpc.subscriptions.is_empty()
-        && !matches!(rpc.control.as_ref(), Some(control) if validate_control_proto(control).is_ok())
-    {
+    if rpc.publish.is_empty() && rpc.subscriptions.is_empty() && rpc.control.is_none() {
         // RPC frame must not be empty.
         return Err(RpcValidationError::EmptyRpc);
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::proto::ControlMessageProto;
+
+    use super::*;
+
+    #[test]
+    fn reject_empty_rpc_frame() {
+        //// When
+        let result = validate_rpc_proto(&RpcProto::default());
+
+        //// Then
+        assert!(matches!(result, Err(RpcValidationError::EmptyRpc)));
+    }
+
+    #[test]
+    fn accept_rpc_frame_with_control_fields_unknown_to_floodsub() {
+        //// Given
+        // The gossipsub v1.2 IDONTWANT control messages are skipped when decoding, leaving an
+        // empty control message.
+        let frame = RpcProto {
+            control: Some(ControlMessageProto::default()),
+            ..Default::default()
+        };
+
+        //// When
+        let result = validate_rpc_proto(&frame);
+
+        //// Then
+        assert!(result.is_ok());
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use instant::Instant;
+use libp2p::identity::PeerId;
+
+/// A token bucket.
+///
+/// The bucket holds up to `burst` tokens and it is refilled at a constant rate.
+#[derive(Debug)]
+struct TokenBucket {
+    /// The number of tokens currently available.
+    tokens: f64,
+
+    /// The last time the bucket was refilled.
+    last_refill: Instant,
+
+    /// The number of acquisitions rejected because the bucket was empty.
+    rejected: u64,
+}
+
+/// A per-peer token bucket rate limiter.
+///
+/// Each peer gets its own token bucket, created on first use.
+#[derive(Debug)]
+pub struct PeerRateLimiter {
+    /// The number of tokens added to each bucket per second.
+    rate: f64,
+
+    /// The maximum number of tokens a bucket can hold.
+    burst: f64,
+
+    /// The token buckets, keyed by peer.
+    buckets: HashMap<PeerId, TokenBucket>,
+}
+
+impl PeerRateLimiter {
+    /// Creates a new rate limiter allowing `max_per_sec` acquisitions per second per peer, with
+    /// bursts of up to `burst` acquisitions.
+    #[must_use]
+    pub fn new(max_per_sec: u32, burst: u32) -> Self {
+        Self {
+            rate: f64::from(max_per_sec),
+            burst: f64::from(burst),
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Try to acquire a token from the peer's bucket.
+    ///
+    /// Returns `true` if a token was available, `false` if the peer exceeded its budget.
+    pub fn try_acquire(&mut self, peer: &PeerId) -> bool {
+        let now = Instant::now();
+        let bucket = self.buckets.entry(*peer).or_insert_with(|| TokenBucket {
+            tokens: self.burst,
+            last_refill: now,
+            rejected: 0,
+        });
+
+        // Refill the bucket with the tokens accumulated since the last refill.
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.last_refill = now;
+
+        if bucket.tokens < 1.0 {
+            bucket.rejected = bucket.rejected.saturating_add(1);
+            return false;
+        }
+
+        bucket.tokens -= 1.0;
+        true
+    }
+
+    /// The number of acquisitions rejected for the given peer.
+    #[must_use]
+    pub fn rejected_count(&self, peer: &PeerId) -> u64 {
+        self.buckets.get(peer).map_or(0, |bucket| bucket.rejected)
+    }
+
+    /// Remove the peer's bucket.
+    pub fn remove_peer(&mut self, peer: &PeerId) {
+        self.buckets.remove(peer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burst_beyond_budget_is_rejected() {
+        //// Given
+        let peer = PeerId::random();
+        let mut limiter = PeerRateLimiter::new(1, 10);
+
+        //// When
+        let accepted = (0..100).filter(|_| limiter.try_acquire(&peer)).count();
+
+        //// Then
+        assert_eq!(accepted, 10);
+        assert_eq!(limiter.rejected_count(&peer), 90);
+    }
+
+    #[test]
+    fn peers_have_independent_budgets() {
+        //// Given
+        let peer_a = PeerId::random();
+        let peer_b = PeerId::random();
+        let mut limiter = PeerRateLimiter::new(1, 2);
+
+        //// When
+        let accepted_a = (0..5).filter(|_| limiter.try_acquire(&peer_a)).count();
+        let accepted_b = (0..5).filter(|_| limiter.try_acquire(&peer_b)).count();
+
+        //// Then
+        assert_eq!(accepted_a, 2);
+        assert_eq!(accepted_b, 2);
+    }
+
+    #[test]
+    fn removed_peer_budget_is_reset() {
+        //// Given
+        let peer = PeerId::random();
+        let mut limiter = PeerRateLimiter::new(1, 1);
+        limiter.try_acquire(&peer);
+        limiter.try_acquire(&peer);
+
+        //// When
+        limiter.remove_peer(&peer);
+
+        //// Then
+        assert_eq!(limiter.rejected_count(&peer), 0);
+        assert!(limiter.try_acquire(&peer));
+    }
+}
@@ -1,10 +1,25 @@
 use std::collections::{BTreeSet, HashMap};
 
 use libp2p::identity::PeerId;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use sha2::{Digest, Sha256};
 
+use crate::config::ForwardSubsetStrategy;
+use crate::message_id::MessageId;
 use crate::topic::TopicHash;
 
-#[derive(Default)]
+/// The suffix marking a topic as a prefix subscription.
+///
+/// A subscription to the topic `<prefix>*` matches all the topics starting with `<prefix>`.
+pub const PREFIX_WILDCARD: char = '*';
+
+/// Get the prefix of a prefix subscription topic, if the topic is a prefix subscription.
+fn subscription_prefix(topic: &TopicHash) -> Option<&str> {
+    topic.as_str().strip_suffix(PREFIX_WILDCARD)
+}
+
 pub struct Router {
     /// The topics this router is subscribed to.
     subscriptions: BTreeSet<TopicHash>,
@@ -25,6 +40,36 @@ pub struct Router {
     ///
     /// This is the reverse of `peers_to_topics`.
     topics_to_peers: HashMap<TopicHash, BTreeSet<PeerId>>,
+
+    /// The total number of tracked peer subscriptions.
+    peer_subscriptions_count: usize,
+
+    /// The random number generator of the random forward peers selection.
+    rng: StdRng,
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Self::with_rng(StdRng::from_entropy())
+    }
+}
+
+impl Router {
+    /// Create a router whose random forward peers selection is seeded with the given seed, so the
+    /// selection is reproducible.
+    pub fn with_rng_seed(seed: u64) -> Self {
+        Self::with_rng(StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(rng: StdRng) -> Self {
+        Self {
+            subscriptions: Default::default(),
+            peers_to_topics: Default::default(),
+            topics_to_peers: Default::default(),
+            peer_subscriptions_count: 0,
+            rng,
+        }
+    }
 }
 
 /// Subscription management.
@@ -34,6 +79,17 @@ impl Router {
         self.subscriptions.contains(topic)
     }
 
+    /// Whether the given topic matches any of the router subscriptions, either exactly or through
+    /// a prefix subscription.
+    pub fn matches_subscription(&self, topic: &TopicHash) -> bool {
+        self.is_subscribed(topic)
+            || self
+                .subscriptions
+                .iter()
+                .filter_map(subscription_prefix)
+                .any(|prefix| topic.as_str().starts_with(prefix))
+    }
+
     /// Subscribes the router to the given topic.
     ///
     /// Returns `false` if the router was already subscribed to the topic.
@@ -52,18 +108,30 @@ impl Router {
     pub fn subscriptions(&self) -> impl Iterator<Item = &TopicHash> {
         self.subscriptions.iter()
     }
+
+    /// Returns the number of topics the router is subscribed to.
+    pub fn subscriptions_count(&self) -> usize {
+        self.subscriptions.len()
+    }
 }
 
 /// PeerId to Subscription tracking.s
 impl Router {
     /// Adds a peer to the router and tracks it subscription to the given topic.
-    pub fn add_peer_subscription(&mut self, peer: PeerId, topic: TopicHash) {
+    ///
+    /// Returns `true` if the peer was not already subscribed to the topic.
+    pub fn add_peer_subscription(&mut self, peer: PeerId, topic: TopicHash) -> bool {
         self.peers_to_topics
             .entry(peer)
             .or_default()
             .insert(topic.clone());
 
-        self.topics_to_peers.entry(topic).or_default().insert(peer);
+        let added = self.topics_to_peers.entry(topic).or_default().insert(peer);
+        if added {
+            self.peer_subscriptions_count += 1;
+        }
+
+        added
     }
 
     /// Adds a peer to the router and subscribes it to the given topics.
@@ -87,7 +155,9 @@ impl Router {
         }
 
         if let Some(peers) = self.topics_to_peers.get_mut(topic) {
-            peers.remove(peer);
+            if peers.remove(peer) {
+                self.peer_subscriptions_count -= 1;
+            }
         }
 
         // If the peer is no longer subscribed to any topic, remove it from the router.
@@ -115,10 +185,27 @@ impl Router {
         self.peers_to_topics.get(peer)
     }
 
+    /// Whether the peer is subscribed to the topic, directly or through a matching prefix
+    /// subscription.
+    pub fn is_peer_subscribed(&self, peer: &PeerId, topic: &TopicHash) -> bool {
+        self.peer_subscriptions(peer).is_some_and(|subscriptions| {
+            subscriptions.iter().any(|subscription| {
+                subscription == topic
+                    || subscription_prefix(subscription)
+                        .is_some_and(|prefix| topic.as_str().starts_with(prefix))
+            })
+        })
+    }
+
     /// Get the peers subscribed to a topic.
     pub fn subscription_peers(&self, topic: &TopicHash) -> Option<&BTreeSet<PeerId>> {
         self.topics_to_peers.get(topic)
     }
+
+    /// Get the total number of tracked peer subscriptions, across all peers.
+    pub fn peer_subscriptions_count(&self) -> usize {
+        self.peer_subscriptions_count
+    }
 }
 
 /// Routing and propagation.
@@ -126,18 +213,83 @@ impl Router {
     /// Get the peers to propagate a message to for a given topic. In floodsub messages should
     /// be forwarded to all known peers subscribed to the topic.
     ///
+    /// The peers with a prefix subscription matching the topic are included.
+    ///
+    /// The router does not need to be subscribed to the topic (e.g., when publishing to a topic
+    /// the local node is not subscribed to).
+    ///
     /// **Note:** The returned set of peers may include the peer that propagated the message.
     pub fn propagation_routes(&self, topic: &TopicHash) -> impl IntoIterator<Item = PeerId> {
-        debug_assert!(self.is_subscribed(topic), "Not subscribed to topic");
+        let mut peers = self.subscription_peers(topic).cloned().unwrap_or_default();
+
+        for (subscription, subscribed_peers) in &self.topics_to_peers {
+            let Some(prefix) = subscription_prefix(subscription) else {
+                continue;
+            };
+
+            if topic.as_str().starts_with(prefix) {
+                peers.extend(subscribed_peers);
+            }
+        }
 
-        if let Some(peers) = self.subscription_peers(topic) {
-            return peers.clone().into_iter();
+        peers.into_iter()
+    }
+
+    /// Select a subset of at most `max_peers` peers out of the given propagation routes.
+    ///
+    /// With the [`ForwardSubsetStrategy::Random`] strategy, the peers are shuffled with the router
+    /// random number generator (see [`Router::with_rng_seed`]).
+    ///
+    /// With the [`ForwardSubsetStrategy::HashBased`] strategy, the peers are sorted and the subset
+    /// is the `max_peers` consecutive peers (wrapping around) starting at the index given by the
+    /// message ID hash modulo the number of peers. The selection is, therefore, reproducible for a
+    /// given message ID and set of peers.
+    pub fn select_forward_peers(
+        &mut self,
+        routes: impl IntoIterator<Item = PeerId>,
+        max_peers: usize,
+        strategy: ForwardSubsetStrategy,
+        message_id: &MessageId,
+    ) -> Vec<PeerId> {
+        let mut peers = routes.into_iter().collect::<Vec<_>>();
+        if peers.len() <= max_peers {
+            return peers;
         }
 
-        BTreeSet::<PeerId>::new().into_iter()
+        match strategy {
+            ForwardSubsetStrategy::Random => {
+                peers.shuffle(&mut self.rng);
+                peers.truncate(max_peers);
+                peers
+            }
+            ForwardSubsetStrategy::HashBased => {
+                peers.sort();
+
+                let digest = Sha256::digest(message_id.as_ref());
+                let hash =
+                    u64::from_be_bytes(digest[..8].try_into().expect("digest to be 32 bytes"));
+                let start = (hash % peers.len() as u64) as usize;
+
+                peers
+                    .iter()
+                    .cycle()
+                    .skip(start)
+                    .take(max_peers)
+                    .copied()
+                    .collect()
+            }
+        }
     }
 }
 
+/// Get the number of peers a message is sent to out of `peers` subscribed peers, given the flood
+/// factor: the fraction of the peers, rounded up, and at least `min_peers` (if there are enough
+/// peers).
+pub fn flood_fanout(peers: usize, flood_factor: f64, min_peers: usize) -> usize {
+    let fanout = (peers as f64 * flood_factor).ceil() as usize;
+    fanout.max(min_peers).min(peers)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -204,6 +356,21 @@ mod tests {
             assert!(router.is_subscribed(&topic));
         }
 
+        #[test]
+        fn prefix_subscription_matches_topics_with_prefix() {
+            //// Given
+            let mut router = test_router();
+
+            //// When
+            router.subscribe(TopicHash::from_raw("/app/room/*"));
+
+            //// Then
+            assert!(router.matches_subscription(&TopicHash::from_raw("/app/room/123")));
+            assert!(router.matches_subscription(&TopicHash::from_raw("/app/room/")));
+            assert!(!router.matches_subscription(&TopicHash::from_raw("/app/other/123")));
+            assert!(!router.is_subscribed(&TopicHash::from_raw("/app/room/123")));
+        }
+
         #[test]
         fn unsubscribe_when_not_subscribed() {
             //// Given
@@ -378,6 +545,25 @@ mod tests {
                 assert!(peers.contains(&peer_b));
             });
         }
+        #[test]
+        fn count_peer_subscriptions() {
+            //// Given
+            let peer_a = test_peer();
+            let peer_b = test_peer();
+            let topics = vec![test_topic(), test_topic()];
+
+            let mut router = test_router();
+            router.add_peer_subscriptions(peer_a, topics.clone());
+            router.add_peer_subscriptions(peer_b, topics.clone());
+            router.add_peer_subscription(peer_b, topics[0].clone());
+
+            //// When
+            router.remove_peer_subscription(&peer_b, &topics[1]);
+            router.remove_peer(&peer_a);
+
+            //// Then
+            assert_eq!(router.peer_subscriptions_count(), 1);
+        }
     }
 
     mod routing {
@@ -436,5 +622,181 @@ mod tests {
             let routes = routes.into_iter().collect::<Vec<_>>();
             assert!(routes.is_empty());
         }
+
+        #[test]
+        fn floodsub_propagation_routes_with_prefix_subscriptions() {
+            //// Given
+            let peer_a = test_peer();
+            let peer_b = test_peer();
+            let peer_c = test_peer();
+
+            let topic = TopicHash::from_raw("/app/room/123");
+
+            let mut router = test_router();
+            router.subscribe(TopicHash::from_raw("/app/room/*"));
+
+            router.add_peer_subscription(peer_a, topic.clone());
+            router.add_peer_subscription(peer_b, TopicHash::from_raw("/app/*"));
+            router.add_peer_subscription(peer_c, TopicHash::from_raw("/app/other/*"));
+
+            //// When
+            let routes = router.propagation_routes(&topic);
+
+            //// Then
+            let routes = routes.into_iter().collect::<Vec<_>>();
+            assert_eq!(routes.len(), 2);
+            assert!(routes.contains(&peer_a));
+            assert!(routes.contains(&peer_b));
+            assert!(!routes.contains(&peer_c));
+        }
+
+        #[test]
+        fn peer_is_subscribed_through_prefix_subscriptions() {
+            //// Given
+            let peer_a = test_peer();
+            let peer_b = test_peer();
+            let peer_c = test_peer();
+
+            let topic = TopicHash::from_raw("/app/room/123");
+
+            let mut router = test_router();
+            router.add_peer_subscription(peer_a, topic.clone());
+            router.add_peer_subscription(peer_b, TopicHash::from_raw("/app/*"));
+            router.add_peer_subscription(peer_c, TopicHash::from_raw("/app/other/*"));
+
+            //// When
+            let subscribed = [peer_a, peer_b, peer_c, test_peer()]
+                .map(|peer| router.is_peer_subscribed(&peer, &topic));
+
+            //// Then
+            assert_eq!(subscribed, [true, true, false, false]);
+        }
+
+        #[test]
+        fn flood_fanout_is_the_rounded_up_fraction_of_the_peers() {
+            //// When
+            let full = flood_fanout(100, 1.0, 6);
+            let fraction = flood_fanout(100, 0.25, 6);
+            let rounded_up = flood_fanout(10, 0.21, 1);
+
+            //// Then
+            assert_eq!(full, 100);
+            assert_eq!(fraction, 25);
+            assert_eq!(rounded_up, 3);
+        }
+
+        #[test]
+        fn flood_fanout_is_at_least_the_minimum_peers() {
+            //// When
+            let floored = flood_fanout(100, 0.01, 6);
+            let fewer_peers = flood_fanout(4, 0.1, 6);
+
+            //// Then
+            assert_eq!(floored, 6);
+            assert_eq!(fewer_peers, 4);
+        }
+
+        #[test]
+        fn select_all_forward_peers_below_the_limit() {
+            //// Given
+            let peers = vec![test_peer(), test_peer()];
+            let message_id = MessageId::new(b"test-message-id".to_vec());
+
+            //// When
+            let selected = Router::default().select_forward_peers(
+                peers.clone(),
+                3,
+                ForwardSubsetStrategy::Random,
+                &message_id,
+            );
+
+            //// Then
+            assert_eq!(selected, peers);
+        }
+
+        #[test]
+        fn select_random_forward_peers_subset() {
+            //// Given
+            let peers = (0..10).map(|_| test_peer()).collect::<Vec<_>>();
+            let message_id = MessageId::new(b"test-message-id".to_vec());
+
+            //// When
+            let selected = Router::default().select_forward_peers(
+                peers.clone(),
+                3,
+                ForwardSubsetStrategy::Random,
+                &message_id,
+            );
+
+            //// Then
+            assert_eq!(selected.len(), 3);
+            assert!(selected.iter().all(|peer| peers.contains(peer)));
+        }
+
+        #[test]
+        fn seeded_random_forward_peers_selection_is_reproducible() {
+            //// Given
+            let peers = (0..10).map(|_| test_peer()).collect::<Vec<_>>();
+            let message_id = MessageId::new(b"test-message-id".to_vec());
+
+            let mut router_a = Router::with_rng_seed(42);
+            let mut router_b = Router::with_rng_seed(42);
+
+            //// When
+            let selected_a = (0..5)
+                .map(|_| {
+                    router_a.select_forward_peers(
+                        peers.clone(),
+                        3,
+                        ForwardSubsetStrategy::Random,
+                        &message_id,
+                    )
+                })
+                .collect::<Vec<_>>();
+            let selected_b = (0..5)
+                .map(|_| {
+                    router_b.select_forward_peers(
+                        peers.clone(),
+                        3,
+                        ForwardSubsetStrategy::Random,
+                        &message_id,
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            //// Then
+            assert!(selected_a.iter().all(|selected| selected.len() == 3));
+            assert_eq!(selected_a, selected_b);
+        }
+
+        #[test]
+        fn hash_based_forward_peers_selection_is_reproducible() {
+            //// Given
+            let peers = (0..10).map(|_| test_peer()).collect::<Vec<_>>();
+            let message_id = MessageId::new(b"test-message-id".to_vec());
+
+            // Same peers, different order.
+            let mut shuffled_peers = peers.clone();
+            shuffled_peers.reverse();
+
+            //// When
+            let selected_a = Router::default().select_forward_peers(
+                peers.clone(),
+                3,
+                ForwardSubsetStrategy::HashBased,
+                &message_id,
+            );
+            let selected_b = Router::default().select_forward_peers(
+                shuffled_peers,
+                3,
+                ForwardSubsetStrategy::HashBased,
+                &message_id,
+            );
+
+            //// Then
+            assert_eq!(selected_a.len(), 3);
+            assert!(selected_a.iter().all(|peer| peers.contains(peer)));
+            assert_eq!(selected_a, selected_b);
+        }
     }
 }
@@ -0,0 +1,121 @@
+use crate::config::{Config, DedupStrategy, MessageExpiredObserver};
+use crate::message_cache::MessageCache;
+use crate::message_id::MessageId;
+use crate::topic::TopicHash;
+
+use self::bloom::RotatingBloomFilter;
+
+mod bloom;
+
+/// The key of the seen messages cache: the message ID, scoped to the message topic if the
+/// [`DedupScope::PerTopic`](crate::DedupScope::PerTopic) deduplication scope is configured.
+pub(crate) type SeenKey = (Option<TopicHash>, MessageId);
+
+/// Cache of the IDs of the messages seen recently, according to the configured
+/// [`DedupStrategy`].
+pub(crate) struct SeenCache {
+    /// The data structure remembering the seen IDs.
+    ids: SeenIds,
+
+    /// The observer of the IDs evicted from the cache, if any.
+    on_expired: Option<MessageExpiredObserver>,
+}
+
+enum SeenIds {
+    /// An exact cache of the seen IDs.
+    Lru(MessageCache<SeenKey, ()>),
+
+    /// A probabilistic set of the seen IDs, with false positives.
+    BloomFilter(RotatingBloomFilter),
+}
+
+impl SeenCache {
+    /// Create the seen messages cache configured by the given configuration.
+    pub(crate) fn new(config: &Config) -> Self {
+        let ids = match config.dedup_strategy() {
+            DedupStrategy::Lru => {
+                let cache = MessageCache::with_capacity_and_ttl(
+                    config.duplicate_cache_capacity(),
+                    config.duplicate_cache_time(),
+                );
+                if config.auto_cache_sizing() {
+                    SeenIds::Lru(cache.with_adaptive_capacity())
+                } else {
+                    SeenIds::Lru(cache)
+                }
+            }
+            DedupStrategy::BloomFilter { bits, hashes } => SeenIds::BloomFilter(
+                RotatingBloomFilter::new(bits, hashes, config.duplicate_cache_time()),
+            ),
+        };
+
+        Self {
+            ids,
+            on_expired: config.on_message_expired().cloned(),
+        }
+    }
+
+    /// Insert the ID in the cache.
+    ///
+    /// Returns `true` if the ID was not already in the cache.
+    pub(crate) fn put(&mut self, id: &SeenKey) -> bool {
+        match &mut self.ids {
+            SeenIds::Lru(cache) => {
+                let (inserted, evicted) = cache.put_and_evict(id, ());
+                if let Some(evicted) = evicted {
+                    self.notify_expired(&evicted);
+                }
+                inserted
+            }
+            SeenIds::BloomFilter(filter) => filter.insert(id),
+        }
+    }
+
+    /// Whether the cache contains the ID.
+    pub(crate) fn contains_key(&self, id: &SeenKey) -> bool {
+        match &self.ids {
+            SeenIds::Lru(cache) => cache.contains_key(id),
+            SeenIds::BloomFilter(filter) => filter.contains(id),
+        }
+    }
+
+    /// The number of IDs in the cache.
+    ///
+    /// For the Bloom filter, this is the number of IDs inserted in the filters currently
+    /// remembered, not accounting for false positives.
+    pub(crate) fn len(&self) -> usize {
+        match &self.ids {
+            SeenIds::Lru(cache) => cache.len(),
+            SeenIds::BloomFilter(filter) => filter.len(),
+        }
+    }
+
+    /// Remove all the IDs from the cache.
+    ///
+    /// The cleared IDs are not reported as expired.
+    pub(crate) fn clear(&mut self) {
+        match &mut self.ids {
+            SeenIds::Lru(cache) => cache.clear(),
+            SeenIds::BloomFilter(filter) => filter.clear(),
+        }
+    }
+
+    /// Remove the expired IDs from the cache.
+    pub(crate) fn clear_expired_entries(&mut self) {
+        match &mut self.ids {
+            SeenIds::Lru(cache) => {
+                for expired in cache.clear_expired_entries() {
+                    self.notify_expired(&expired);
+                }
+            }
+            SeenIds::BloomFilter(filter) => filter.rotate_if_expired(),
+        }
+    }
+
+    /// Notify the observer, if any, the ID was evicted from the cache.
+    fn notify_expired(&self, (_, message_id): &SeenKey) {
+        if let Some(observer) = &self.on_expired {
+            observer.notify(message_id);
+        }
+    }
+}
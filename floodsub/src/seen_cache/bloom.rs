@@ -0,0 +1,248 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::time::Duration;
+
+use instant::Instant;
+
+/// A Bloom filter: a fixed-size probabilistic set, with false positives but no false negatives.
+///
+/// The bit indexes of an item are derived from two hashes of the item (i.e., double hashing). The
+/// hashers are randomly keyed, so remote peers can't craft message IDs colliding in the filter.
+pub(crate) struct BloomFilter {
+    /// The filter bits.
+    bits: Vec<u64>,
+
+    /// The number of bits of the filter.
+    num_bits: usize,
+
+    /// The number of bits set per item.
+    hashes: u32,
+
+    /// The hashers deriving the bit indexes.
+    hashers: (RandomState, RandomState),
+
+    /// The number of items inserted in the filter.
+    items: usize,
+}
+
+impl BloomFilter {
+    pub(crate) fn new(bits: usize, hashes: u32) -> Self {
+        Self {
+            bits: vec![0; bits.div_ceil(64)],
+            num_bits: bits,
+            hashes,
+            hashers: (RandomState::new(), RandomState::new()),
+            items: 0,
+        }
+    }
+
+    /// Insert the item in the filter.
+    ///
+    /// Returns `true` if the item was not already in the filter, or `false` if it was or it is a
+    /// false positive.
+    pub(crate) fn insert<T: Hash>(&mut self, item: &T) -> bool {
+        let mut inserted = false;
+        for index in self.indexes(item).collect::<Vec<_>>() {
+            let (word, mask) = (index / 64, 1 << (index % 64));
+            inserted |= self.bits[word] & mask == 0;
+            self.bits[word] |= mask;
+        }
+
+        if inserted {
+            self.items += 1;
+        }
+
+        inserted
+    }
+
+    /// Whether the filter contains the item, or a false positive.
+    pub(crate) fn contains<T: Hash>(&self, item: &T) -> bool {
+        self.indexes(item)
+            .all(|index| self.bits[index / 64] & (1 << (index % 64)) != 0)
+    }
+
+    /// The number of items inserted in the filter, not accounting for false positives.
+    pub(crate) fn len(&self) -> usize {
+        self.items
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.bits.fill(0);
+        self.items = 0;
+    }
+
+    /// Get the bit indexes of the item.
+    fn indexes<T: Hash>(&self, item: &T) -> impl Iterator<Item = usize> {
+        let h1 = self.hashers.0.hash_one(item);
+        // An odd step visits distinct bits when the filter size is a power of two.
+        let h2 = self.hashers.1.hash_one(item) | 1;
+        let len = self.num_bits as u64;
+
+        (0..u64::from(self.hashes))
+            .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+}
+
+/// A pair of Bloom filters, remembering the items for one to two periods.
+///
+/// The items are inserted in the current filter, and looked up in both. Once the period elapses,
+/// the current filter becomes the previous one, and the items of the previous one are forgotten.
+pub(crate) struct RotatingBloomFilter {
+    /// The filter the items are inserted in.
+    current: BloomFilter,
+
+    /// The filter of the previous period.
+    previous: BloomFilter,
+
+    /// The period after which the filters are rotated.
+    period: Duration,
+
+    /// The instant the current filter was created at.
+    rotated_at: Instant,
+}
+
+impl RotatingBloomFilter {
+    pub(crate) fn new(bits: usize, hashes: u32, period: Duration) -> Self {
+        Self {
+            current: BloomFilter::new(bits, hashes),
+            previous: BloomFilter::new(bits, hashes),
+            period,
+            rotated_at: Instant::now(),
+        }
+    }
+
+    /// Insert the item in the current filter.
+    ///
+    /// Returns `true` if the item was not already in either filter.
+    pub(crate) fn insert<T: Hash>(&mut self, item: &T) -> bool {
+        self.insert_at(item, Instant::now())
+    }
+
+    /// Insert the item in the current filter, rotating the filters first if the period elapsed
+    /// at the given instant.
+    ///
+    /// Returns `true` if the item was not already in either filter.
+    fn insert_at<T: Hash>(&mut self, item: &T, now: Instant) -> bool {
+        self.rotate_if_expired_at(now);
+
+        if self.previous.contains(item) {
+            self.current.insert(item);
+            return false;
+        }
+
+        self.current.insert(item)
+    }
+
+    /// Whether either filter contains the item, or a false positive.
+    pub(crate) fn contains<T: Hash>(&self, item: &T) -> bool {
+        self.current.contains(item) || self.previous.contains(item)
+    }
+
+    /// The number of items inserted in the filters, not accounting for false positives.
+    pub(crate) fn len(&self) -> usize {
+        self.current.len() + self.previous.len()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.current.clear();
+        self.previous.clear();
+    }
+
+    /// Rotate the filters, if the period elapsed since the last rotation.
+    pub(crate) fn rotate_if_expired(&mut self) {
+        self.rotate_if_expired_at(Instant::now());
+    }
+
+    /// Rotate the filters, if the period elapsed at the given instant since the last rotation.
+    fn rotate_if_expired_at(&mut self, now: Instant) {
+        if now.saturating_duration_since(self.rotated_at) <= self.period {
+            return;
+        }
+
+        std::mem::swap(&mut self.current, &mut self.previous);
+        self.current.clear();
+        self.rotated_at = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::DedupStrategy;
+
+    use super::*;
+
+    #[test]
+    fn inserted_items_are_contained() {
+        //// Given
+        let mut filter = BloomFilter::new(1 << 16, 4);
+
+        //// When
+        for item in 0..1000u64 {
+            filter.insert(&item);
+        }
+
+        //// Then
+        assert!((0..1000u64).all(|item| filter.contains(&item)));
+        assert!(!filter.insert(&0u64));
+    }
+
+    #[test]
+    fn false_positive_rate_stays_under_the_expected_bound() {
+        //// Given
+        let (bits, hashes, items) = (1 << 17, 7, 10_000);
+        let mut filter = BloomFilter::new(bits, hashes);
+        let expected_rate = DedupStrategy::BloomFilter { bits, hashes }.false_positive_rate(items);
+
+        //// When
+        for item in 0..items {
+            filter.insert(&item);
+        }
+
+        //// Then
+        let queries = 100_000;
+        let false_positives = (items..items + queries)
+            .filter(|item| filter.contains(item))
+            .count();
+        let rate = false_positives as f64 / queries as f64;
+        assert!(
+            rate <= expected_rate * 1.5,
+            "false positive rate {rate} exceeds the expected {expected_rate}"
+        );
+    }
+
+    #[test]
+    fn items_are_forgotten_after_two_periods() {
+        //// Given
+        let period = Duration::from_secs(60);
+        let mut filter = RotatingBloomFilter::new(1 << 16, 4, period);
+        let start = filter.rotated_at;
+        filter.insert_at(&"test-item", start);
+
+        //// When
+        filter.rotate_if_expired_at(start + period + Duration::from_secs(1));
+        let contained_after_one_period = filter.contains(&"test-item");
+
+        filter.rotate_if_expired_at(start + 2 * period + Duration::from_secs(2));
+        let contained_after_two_periods = filter.contains(&"test-item");
+
+        //// Then
+        assert!(contained_after_one_period);
+        assert!(!contained_after_two_periods);
+    }
+
+    #[test]
+    fn items_are_remembered_within_the_period() {
+        //// Given
+        let period = Duration::from_secs(60);
+        let mut filter = RotatingBloomFilter::new(1 << 16, 4, period);
+        let start = filter.rotated_at;
+        filter.insert_at(&"test-item", start);
+
+        //// When
+        let inserted = filter.insert_at(&"test-item", start + period);
+
+        //// Then
+        assert!(!inserted);
+        assert!(filter.contains(&"test-item"));
+    }
+}
@@ -21,6 +21,13 @@ impl LinearSequenceNumber {
 
         Self(unix_timestamp as u64)
     }
+
+    /// Creates a new linear sequence number generator counting up from the given base. The
+    /// first sequence number is `base + 1`.
+    #[cfg(any(test, feature = "testing"))]
+    pub fn with_base(base: u64) -> Self {
+        Self(base)
+    }
 }
 
 impl MessageSeqNumberGenerator for LinearSequenceNumber {
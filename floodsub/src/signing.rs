@@ -0,0 +1,394 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use libp2p::identity::{PeerId, PublicKey};
+use prost::Message as _;
+
+use crate::frame::Message;
+
+/// The prefix prepended to the encoded message before signing it, as defined by the libp2p pubsub
+/// specification.
+const SIGNING_PREFIX: &[u8] = b"libp2p-pubsub:";
+
+/// The multihash code of the identity hash, used by the peer IDs embedding the public key.
+const IDENTITY_MULTIHASH_CODE: u64 = 0x00;
+
+/// A callback signing the given payload with the message author private key.
+///
+/// This allows delegating the signature generation to an external signer (e.g., a hardware
+/// security module) instead of holding the private key in memory.
+pub type SignerFn = dyn Fn(&[u8]) -> Result<Vec<u8>, SigningError> + Send + Sync + 'static;
+
+/// An error returned by a message signer.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("failed to sign the message: {reason}")]
+pub struct SigningError {
+    reason: String,
+}
+
+impl SigningError {
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+        }
+    }
+}
+
+/// The signer of the locally published messages.
+#[derive(Clone)]
+pub(crate) struct Signer {
+    /// The message author public key.
+    public_key: PublicKey,
+
+    /// The callback generating the signatures.
+    sign_fn: Arc<SignerFn>,
+}
+
+impl Debug for Signer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Signer")
+            .field("public_key", &self.public_key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Signer {
+    pub(crate) fn new(public_key: PublicKey, sign_fn: Box<SignerFn>) -> Self {
+        Self {
+            public_key,
+            sign_fn: Arc::from(sign_fn),
+        }
+    }
+
+    /// The peer ID of the messages author, derived from the signer public key.
+    pub(crate) fn author(&self) -> PeerId {
+        self.public_key.to_peer_id()
+    }
+
+    /// Whether the signer public key is embedded in the author peer ID, i.e., the peer ID is an
+    /// identity multihash of the key (e.g., Ed25519 or Secp256k1 keys, but not RSA keys).
+    pub(crate) fn is_key_inlined(&self) -> bool {
+        self.author().as_ref().code() == IDENTITY_MULTIHASH_CODE
+    }
+
+    /// Sign the message, setting its `signature` and `key` fields.
+    ///
+    /// If `omit_inlined_key` is set, the `key` field is left empty when the public key is embedded
+    /// in the author peer ID: the receivers derive it from the `from` field.
+    ///
+    /// The message source must be set to the signer [`author`](Self::author) beforehand.
+    pub(crate) fn sign(
+        &self,
+        message: &mut Message,
+        omit_inlined_key: bool,
+    ) -> Result<(), SigningError> {
+        let signature = (self.sign_fn)(&signature_payload(message))?;
+
+        message.set_signature(Some(signature));
+        if omit_inlined_key && self.is_key_inlined() {
+            message.set_key(None::<Vec<u8>>);
+        } else {
+            message.set_key(Some(self.public_key.encode_protobuf()));
+        }
+        Ok(())
+    }
+}
+
+/// Get the payload covered by the message signature: the signing prefix followed by the encoded
+/// message, without its `signature` and `key` fields.
+pub(crate) fn signature_payload(message: &Message) -> Vec<u8> {
+    let mut proto = message.as_proto().clone();
+    proto.signature = None;
+    proto.key = None;
+
+    let mut payload = Vec::with_capacity(SIGNING_PREFIX.len() + proto.encoded_len());
+    payload.extend_from_slice(SIGNING_PREFIX);
+    proto
+        .encode(&mut payload)
+        .expect("the payload buffer to have enough capacity");
+    payload
+}
+
+/// Check the message `key` field, if present, is the public key of the message author.
+///
+/// The key is decoded from its protobuf encoding, and the peer ID derived from it must match the
+/// `from` field, as gossipsub does in strict signing mode. Messages without `key` pass the check:
+/// the author public key may be inlined in the author peer ID (e.g., Ed25519 keys).
+pub(crate) fn is_author_key(message: &Message) -> bool {
+    let Some(key) = message.key() else {
+        return true;
+    };
+
+    match PublicKey::try_decode_protobuf(key) {
+        Ok(public_key) => message.source() == Some(public_key.to_peer_id()),
+        Err(_) => false,
+    }
+}
+
+/// Check the message signature, if present, is a valid signature of the message by its author.
+///
+/// The signature covers the whole message, including its topic: a signed message re-routed to
+/// another topic (i.e., its `topic` field rewritten) fails the check. The author public key is
+/// the `key` field, if present, otherwise the key inlined in the `from` peer ID. Unsigned messages
+/// pass the check.
+pub(crate) fn is_valid_signature(message: &Message) -> bool {
+    let Some(signature) = message.signature() else {
+        return true;
+    };
+
+    match author_public_key(message) {
+        Some(public_key) => public_key.verify(&signature_payload(message), signature),
+        None => false,
+    }
+}
+
+/// Get the public key of the message author, from the `key` field if present, otherwise from the
+/// `from` peer ID if the key is inlined in it.
+fn author_public_key(message: &Message) -> Option<PublicKey> {
+    if let Some(key) = message.key() {
+        return PublicKey::try_decode_protobuf(key).ok();
+    }
+
+    let author = message.source()?;
+    let multihash = author.as_ref();
+    if multihash.code() != IDENTITY_MULTIHASH_CODE {
+        return None;
+    }
+    PublicKey::try_decode_protobuf(multihash.digest()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use libp2p::identity::Keypair;
+
+    use crate::topic::TopicHash;
+
+    use super::*;
+
+    fn test_message(author: PeerId) -> Message {
+        let mut message =
+            Message::new(TopicHash::from_raw("/test/topic"), b"test-payload".to_vec());
+        message.set_source(Some(author));
+        message.set_sequence_number(Some(42));
+        message
+    }
+
+    /// A signer delegating the signature generation to the given keypair.
+    fn keypair_signer(keypair: Keypair) -> Signer {
+        let public_key = keypair.public();
+        Signer::new(
+            public_key,
+            Box::new(move |payload| {
+                keypair
+                    .sign(payload)
+                    .map_err(|err| SigningError::new(err.to_string()))
+            }),
+        )
+    }
+
+    #[test]
+    fn signed_message_signature_is_valid() {
+        //// Given
+        let keypair = Keypair::generate_secp256k1();
+        let public_key = keypair.public();
+        let signer = keypair_signer(keypair);
+
+        let mut message = test_message(signer.author());
+
+        //// When
+        let result = signer.sign(&mut message, false);
+
+        //// Then
+        assert!(result.is_ok());
+        assert_eq!(message.key(), Some(&public_key.encode_protobuf()[..]));
+
+        let signature = message.signature().expect("signed message");
+        assert!(public_key.verify(&signature_payload(&message), signature));
+    }
+
+    #[test]
+    fn tampered_message_signature_is_invalid() {
+        //// Given
+        let keypair = Keypair::generate_secp256k1();
+        let public_key = keypair.public();
+        let signer = keypair_signer(keypair);
+
+        let mut message = test_message(signer.author());
+        signer.sign(&mut message, false).expect("sign the message");
+
+        //// When
+        message.set_data(b"tampered-payload".to_vec());
+
+        //// Then
+        let signature = message.signature().expect("signed message");
+        assert!(!public_key.verify(&signature_payload(&message), signature));
+    }
+
+    #[test]
+    fn signed_message_key_is_the_author_key() {
+        //// Given
+        let signer = keypair_signer(Keypair::generate_secp256k1());
+
+        let mut message = test_message(signer.author());
+
+        //// When
+        signer.sign(&mut message, false).expect("sign the message");
+
+        //// Then
+        assert!(is_author_key(&message));
+    }
+
+    #[test]
+    fn key_of_another_peer_is_not_the_author_key() {
+        //// Given
+        let signer = keypair_signer(Keypair::generate_secp256k1());
+
+        let mut message = test_message(signer.author());
+        signer.sign(&mut message, false).expect("sign the message");
+
+        //// When
+        message.set_source(Some(PeerId::random()));
+
+        //// Then
+        assert!(!is_author_key(&message));
+    }
+
+    #[test]
+    fn undecodable_key_is_not_the_author_key() {
+        //// Given
+        let mut message = test_message(PeerId::random());
+
+        //// When
+        message.set_key(Some(b"invalid-key".to_vec()));
+
+        //// Then
+        assert!(!is_author_key(&message));
+    }
+
+    #[test]
+    fn inlined_ed25519_key_is_omitted() {
+        //// Given
+        let signer = keypair_signer(Keypair::generate_ed25519());
+
+        let mut message = test_message(signer.author());
+
+        //// When
+        signer.sign(&mut message, true).expect("sign the message");
+
+        //// Then
+        assert!(signer.is_key_inlined());
+        assert!(message.signature().is_some());
+        assert_eq!(message.key(), None);
+        assert!(is_author_key(&message));
+    }
+
+    #[test]
+    fn rsa_key_is_not_omitted() {
+        //// Given
+        // RSA keys can't be generated with libp2p, use a test fixture key.
+        let mut pkcs8 = include_bytes!("../tests/fixtures/rsa-2048.pk8").to_vec();
+        let keypair = Keypair::rsa_from_pkcs8(&mut pkcs8).expect("valid RSA key");
+        let public_key = keypair.public();
+        let signer = keypair_signer(keypair);
+
+        let mut message = test_message(signer.author());
+
+        //// When
+        signer.sign(&mut message, true).expect("sign the message");
+
+        //// Then
+        assert!(!signer.is_key_inlined());
+        assert_eq!(message.key(), Some(&public_key.encode_protobuf()[..]));
+        assert!(is_author_key(&message));
+    }
+
+    #[test]
+    fn inlined_key_is_kept_if_not_omitted() {
+        //// Given
+        let keypair = Keypair::generate_ed25519();
+        let public_key = keypair.public();
+        let signer = keypair_signer(keypair);
+
+        let mut message = test_message(signer.author());
+
+        //// When
+        signer.sign(&mut message, false).expect("sign the message");
+
+        //// Then
+        assert_eq!(message.key(), Some(&public_key.encode_protobuf()[..]));
+    }
+
+    #[test]
+    fn signer_error_is_returned() {
+        //// Given
+        let public_key = Keypair::generate_secp256k1().public();
+        let signer = Signer::new(
+            public_key,
+            Box::new(|_| Err(SigningError::new("signer unavailable"))),
+        );
+
+        let mut message = test_message(signer.author());
+
+        //// When
+        let result = signer.sign(&mut message, false);
+
+        //// Then
+        assert_eq!(result, Err(SigningError::new("signer unavailable")));
+        assert!(message.signature().is_none());
+    }
+
+    #[test]
+    fn signed_message_passes_verification() {
+        //// Given
+        let signer = keypair_signer(Keypair::generate_secp256k1());
+
+        let mut message = test_message(signer.author());
+
+        //// When
+        signer.sign(&mut message, false).expect("sign the message");
+
+        //// Then
+        assert!(is_valid_signature(&message));
+    }
+
+    #[test]
+    fn signed_message_with_inlined_key_passes_verification() {
+        //// Given
+        let signer = keypair_signer(Keypair::generate_ed25519());
+
+        let mut message = test_message(signer.author());
+
+        //// When
+        signer.sign(&mut message, true).expect("sign the message");
+
+        //// Then
+        assert_eq!(message.key(), None);
+        assert!(is_valid_signature(&message));
+    }
+
+    #[test]
+    fn signed_message_rerouted_to_another_topic_fails_verification() {
+        //// Given
+        let signer = keypair_signer(Keypair::generate_secp256k1());
+
+        let mut message = test_message(signer.author());
+        signer.sign(&mut message, false).expect("sign the message");
+
+        //// When
+        let mut proto = message.into_proto();
+        proto.topic = "/test/other-topic".to_string();
+        let rerouted = Message::from(proto);
+
+        //// Then
+        assert!(!is_valid_signature(&rerouted));
+    }
+
+    #[test]
+    fn unsigned_message_passes_verification() {
+        //// Given
+        let message = test_message(PeerId::random());
+
+        //// Then
+        assert!(is_valid_signature(&message));
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher as _};
+
+use libp2p::core::{ConnectedPoint, Endpoint};
+use libp2p::identity::PeerId;
+use libp2p::swarm::behaviour::ConnectionEstablished;
+use libp2p::swarm::{ConnectionId, FromSwarm, NetworkBehaviour, ToSwarm};
+use libp2p::Multiaddr;
+
+use crate::behaviour::{Behaviour, Event};
+use crate::frame::Message;
+use crate::handler::{Command as HandlerCommand, Event as HandlerEvent};
+use crate::proto::RpcProto;
+use crate::topic::TopicHash;
+
+/// Connect the two behaviours in memory, simulating a connection dialed by `a` to `b`.
+///
+/// No swarm, transport or async runtime is involved: the RPC frames the behaviours queue for
+/// each other, starting with their subscriptions, are exchanged by [`propagate`].
+pub fn connect_behaviours(a: &mut Behaviour, b: &mut Behaviour) {
+    let a_peer = *a.local_peer_id();
+    let b_peer = *b.local_peer_id();
+    let connection_id = connection_id(&a_peer, &b_peer);
+
+    let a_addr = memory_addr(&a_peer);
+    let b_addr = memory_addr(&b_peer);
+
+    a.handle_established_outbound_connection(connection_id, b_peer, &b_addr, Endpoint::Dialer)
+        .expect("connection to be accepted");
+    let other_established = a.connections().peer_connections_count(&b_peer);
+    a.on_swarm_event(FromSwarm::ConnectionEstablished(ConnectionEstablished {
+        peer_id: b_peer,
+        connection_id,
+        endpoint: &ConnectedPoint::Dialer {
+            address: b_addr.clone(),
+            role_override: Endpoint::Dialer,
+        },
+        failed_addresses: &[],
+        other_established,
+    }));
+
+    b.handle_established_inbound_connection(connection_id, a_peer, &b_addr, &a_addr)
+        .expect("connection to be accepted");
+    let other_established = b.connections().peer_connections_count(&a_peer);
+    b.on_swarm_event(FromSwarm::ConnectionEstablished(ConnectionEstablished {
+        peer_id: a_peer,
+        connection_id,
+        endpoint: &ConnectedPoint::Listener {
+            local_addr: b_addr,
+            send_back_addr: a_addr,
+        },
+        failed_addresses: &[],
+        other_established,
+    }));
+}
+
+/// Exchange the RPC frames queued between the two connected behaviours, until neither has frames
+/// left for the other (e.g., to propagate their subscriptions, or a published message).
+///
+/// Returns the events emitted by `a` and by `b` in the meantime, in order. The frames queued for
+/// other peers are left in place, so larger networks can be propagated pair by pair (e.g., along
+/// a chain of behaviours).
+pub fn propagate(a: &mut Behaviour, b: &mut Behaviour) -> (Vec<Event>, Vec<Event>) {
+    let a_peer = *a.local_peer_id();
+    let b_peer = *b.local_peer_id();
+    let connection_id = connection_id(&a_peer, &b_peer);
+
+    let mut a_events = Vec::new();
+    let mut b_events = Vec::new();
+    loop {
+        let a_frames = take_frames(a, &b_peer, &mut a_events);
+        let b_frames = take_frames(b, &a_peer, &mut b_events);
+        if a_frames.is_empty() && b_frames.is_empty() {
+            break;
+        }
+
+        deliver_frames(a, b, connection_id, a_frames);
+        deliver_frames(b, a, connection_id, b_frames);
+    }
+
+    (a_events, b_events)
+}
+
+/// Exchange the RPC frames queued between the publisher and the receiver, and return the
+/// messages delivered to the receiver.
+///
+/// The events emitted by the publisher are discarded. See [`propagate`].
+pub fn propagate_and_receive(publisher: &mut Behaviour, receiver: &mut Behaviour) -> Vec<Message> {
+    let (_, receiver_events) = propagate(publisher, receiver);
+    receiver_events
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::Message { message, .. } => Some(message),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Assert a message to the topic, with the given payload, was delivered.
+pub fn assert_delivered(messages: &[Message], topic: &TopicHash, data: &[u8]) {
+    assert!(
+        messages
+            .iter()
+            .any(|message| message.topic_hash() == topic && message.data() == data),
+        "no message to topic {topic} with the expected payload was delivered: {messages:?}"
+    );
+}
+
+/// Take the frames the behaviour queued for the peer, collecting the emitted events.
+fn take_frames(behaviour: &mut Behaviour, peer: &PeerId, events: &mut Vec<Event>) -> Vec<RpcProto> {
+    behaviour.flush_pending();
+
+    let mut frames = Vec::new();
+    let taken = behaviour.take_swarm_out_events(|event| match event {
+        ToSwarm::GenerateEvent(_) => true,
+        ToSwarm::NotifyHandler { peer_id, .. } => peer_id == peer,
+        _ => false,
+    });
+    for event in taken {
+        match event {
+            ToSwarm::GenerateEvent(event) => events.push(event),
+            ToSwarm::NotifyHandler {
+                event: HandlerCommand::SendFrame(frame),
+                ..
+            } => frames.push(frame),
+            _ => {}
+        }
+    }
+
+    frames
+}
+
+/// Deliver the frames sent by `src` to `dst`, reporting them as sent to `src`.
+fn deliver_frames(
+    src: &mut Behaviour,
+    dst: &mut Behaviour,
+    connection_id: ConnectionId,
+    frames: Vec<RpcProto>,
+) {
+    if frames.is_empty() {
+        return;
+    }
+
+    let src_peer = *src.local_peer_id();
+    let dst_peer = *dst.local_peer_id();
+
+    src.on_connection_handler_event(
+        dst_peer,
+        connection_id,
+        HandlerEvent::FramesDequeued(frames.len()),
+    );
+    for frame in frames {
+        dst.on_connection_handler_event(
+            src_peer,
+            connection_id,
+            HandlerEvent::FrameReceived(frame),
+        );
+    }
+}
+
+/// Get the ID of the simulated connection between the two peers, the same on both sides.
+fn connection_id(a: &PeerId, b: &PeerId) -> ConnectionId {
+    let mut hasher = DefaultHasher::new();
+    a.min(b).hash(&mut hasher);
+    a.max(b).hash(&mut hasher);
+    ConnectionId::new_unchecked(hasher.finish() as usize)
+}
+
+/// Get the simulated memory address of the peer.
+fn memory_addr(peer: &PeerId) -> Multiaddr {
+    let mut hasher = DefaultHasher::new();
+    peer.hash(&mut hasher);
+    format!("/memory/{}", hasher.finish())
+        .parse()
+        .expect("valid memory address")
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use crate::config::Config;
+    use crate::IdentTopic;
+
+    use super::*;
+
+    fn test_behaviour() -> Behaviour {
+        Behaviour::new(PeerId::random(), Config::default())
+    }
+
+    fn test_topic() -> IdentTopic {
+        IdentTopic::new(format!("/test/topic-{}", rand::random::<u64>()))
+    }
+
+    #[test]
+    fn propagate_subscriptions_between_connected_behaviours() {
+        //// Given
+        let topic = test_topic();
+
+        let mut a = test_behaviour();
+        let mut b = test_behaviour();
+        a.subscribe(&topic).expect("subscribe to topic");
+
+        //// When
+        connect_behaviours(&mut a, &mut b);
+        propagate(&mut a, &mut b);
+
+        //// Then
+        assert_matches!(b.peer_topics(a.local_peer_id()), Some(topics) => {
+            assert!(topics.contains(&topic.hash()));
+        });
+        assert_eq!(a.peer_count(), 1);
+        assert_eq!(b.peer_count(), 1);
+    }
+
+    #[test]
+    fn deliver_published_message_to_connected_behaviour() {
+        //// Given
+        let topic = test_topic();
+
+        let mut publisher = test_behaviour();
+        let mut receiver = test_behaviour();
+        receiver.subscribe(&topic).expect("subscribe to topic");
+
+        connect_behaviours(&mut publisher, &mut receiver);
+        propagate(&mut publisher, &mut receiver);
+
+        //// When
+        publisher
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+        let messages = propagate_and_receive(&mut publisher, &mut receiver);
+
+        //// Then
+        assert_eq!(messages.len(), 1);
+        assert_delivered(&messages, &topic.hash(), b"test-payload");
+    }
+
+    #[test]
+    fn deliver_published_message_along_a_chain() {
+        //// Given
+        let topic = test_topic();
+
+        let mut origin = test_behaviour();
+        let mut middle = test_behaviour();
+        let mut last = test_behaviour();
+        middle.subscribe(&topic).expect("subscribe to topic");
+        last.subscribe(&topic).expect("subscribe to topic");
+
+        connect_behaviours(&mut origin, &mut middle);
+        connect_behaviours(&mut middle, &mut last);
+        propagate(&mut origin, &mut middle);
+        propagate(&mut middle, &mut last);
+
+        //// When
+        origin
+            .publish(&topic, b"test-payload".to_vec())
+            .expect("publish the message");
+        let middle_messages = propagate_and_receive(&mut origin, &mut middle);
+        let last_messages = propagate_and_receive(&mut middle, &mut last);
+
+        //// Then
+        assert_delivered(&middle_messages, &topic.hash(), b"test-payload");
+        assert_delivered(&last_messages, &topic.hash(), b"test-payload");
+    }
+}
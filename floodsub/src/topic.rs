@@ -9,6 +9,26 @@ use sha2::{Digest, Sha256};
 
 use crate::proto::TopicDescriptorProto;
 
+/// The maximum length, in bytes, of the topics created with [`Topic::try_new`].
+pub const MAX_TOPIC_LENGTH: usize = 1024;
+
+/// Errors that can happen when creating a topic with [`Topic::try_new`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum TopicError {
+    /// The topic is empty.
+    #[error("empty topic")]
+    Empty,
+
+    /// The topic exceeds the maximum topic length.
+    #[error("topic length {0} exceeds the maximum {MAX_TOPIC_LENGTH}")]
+    TooLong(usize),
+
+    /// The topic contains non-printable (i.e., control) characters.
+    #[error("topic contains non-printable characters")]
+    NonPrintable,
+}
+
 /// A generic trait that can be extended for various hashing frame for a topic.
 pub trait Hasher {
     /// The function that takes a topic string and creates a topic hash.
@@ -54,14 +74,18 @@ pub struct TopicHash {
 }
 
 impl TopicHash {
+    /// Create a topic hash from its raw wire representation (i.e., the message and subscription
+    /// protobuf `topic` field), without knowing the hasher used to compute it.
     pub fn from_raw<T: Into<String>>(raw: T) -> Self {
         Self { hash: raw.into() }
     }
 
+    /// Consume the topic hash, returning its raw wire representation.
     pub fn into_string(self) -> String {
         self.hash
     }
 
+    /// The raw wire representation of the topic hash.
     pub fn as_str(&self) -> &str {
         &self.hash
     }
@@ -101,6 +125,9 @@ impl<H: Hasher> From<Topic<H>> for TopicHash {
 }
 
 impl<H: Hasher> Topic<H> {
+    /// Create a topic, without validating it.
+    ///
+    /// Prefer [`Topic::try_new`], which rejects empty, overlong and non-printable topics.
     pub fn new<T: Into<String>>(topic: T) -> Self {
         Topic {
             topic: topic.into(),
@@ -108,6 +135,24 @@ impl<H: Hasher> Topic<H> {
         }
     }
 
+    /// Create a topic, failing if it is empty, exceeds [`MAX_TOPIC_LENGTH`] bytes, or contains
+    /// non-printable (i.e., control) characters.
+    pub fn try_new(topic: &str) -> Result<Self, TopicError> {
+        if topic.is_empty() {
+            return Err(TopicError::Empty);
+        }
+
+        if topic.len() > MAX_TOPIC_LENGTH {
+            return Err(TopicError::TooLong(topic.len()));
+        }
+
+        if topic.chars().any(char::is_control) {
+            return Err(TopicError::NonPrintable);
+        }
+
+        Ok(Self::new(topic))
+    }
+
     pub fn hash(&self) -> TopicHash {
         H::hash(self.topic.clone())
     }
@@ -124,3 +169,80 @@ impl fmt::Display for TopicHash {
         write!(f, "{}", self.hash)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ident_topic_hash_round_trips_through_raw_string() {
+        //// Given
+        let topic = Topic::<IdentityHash>::new("/test/topic");
+
+        //// When
+        let raw = topic.hash().into_string();
+        let hash = TopicHash::from_raw(raw);
+
+        //// Then
+        assert_eq!(hash, topic.hash());
+    }
+
+    #[test]
+    fn sha256_topic_hash_round_trips_through_raw_string() {
+        //// Given
+        let topic = Topic::<Sha256Hash>::new("/test/topic");
+
+        //// When
+        let raw = topic.hash().into_string();
+        let hash = TopicHash::from_raw(raw);
+
+        //// Then
+        assert_eq!(hash, topic.hash());
+        assert_ne!(hash.as_str(), "/test/topic");
+    }
+
+    #[test]
+    fn try_new_accepts_valid_topic() {
+        //// When
+        let topic = Topic::<IdentityHash>::try_new("/test/topic");
+
+        //// Then
+        assert_eq!(
+            topic.map(|topic| topic.hash()),
+            Ok(TopicHash::from_raw("/test/topic"))
+        );
+    }
+
+    #[test]
+    fn try_new_rejects_empty_topic() {
+        //// When
+        let topic = Topic::<IdentityHash>::try_new("");
+
+        //// Then
+        assert_eq!(topic.err(), Some(TopicError::Empty));
+    }
+
+    #[test]
+    fn try_new_rejects_overlong_topic() {
+        //// Given
+        let topic = "a".repeat(MAX_TOPIC_LENGTH + 1);
+
+        //// When
+        let result = Topic::<IdentityHash>::try_new(&topic);
+
+        //// Then
+        assert_eq!(
+            result.err(),
+            Some(TopicError::TooLong(MAX_TOPIC_LENGTH + 1))
+        );
+    }
+
+    #[test]
+    fn try_new_rejects_non_printable_topic() {
+        //// When
+        let topic = Topic::<IdentityHash>::try_new("/test/topic\n");
+
+        //// Then
+        assert_eq!(topic.err(), Some(TopicError::NonPrintable));
+    }
+}
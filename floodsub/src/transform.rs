@@ -0,0 +1,100 @@
+use std::fmt::Debug;
+
+use bytes::Bytes;
+
+use crate::frame::Message;
+use crate::topic::TopicHash;
+
+#[cfg(feature = "gzip")]
+pub use self::gzip::GzipTransform;
+#[cfg(feature = "zstd")]
+pub use self::zstd::ZstdTransform;
+
+#[cfg(feature = "gzip")]
+mod gzip;
+#[cfg(feature = "zstd")]
+mod zstd;
+
+/// The default maximum size of the decompressed data of a received message.
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 4 * 1024 * 1024;
+
+/// Read the decompressed data from the decoder, failing if it exceeds the maximum size.
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+fn read_decompressed(
+    decoder: impl std::io::Read,
+    max_size: usize,
+) -> Result<Vec<u8>, std::io::Error> {
+    use std::io::Read;
+
+    let mut data = Vec::new();
+    decoder.take(max_size as u64 + 1).read_to_end(&mut data)?;
+
+    if data.len() > max_size {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "decompressed data exceeds the maximum size",
+        ));
+    }
+
+    Ok(data)
+}
+
+/// A transform applied to the messages data (e.g., compression or encryption).
+///
+/// The outbound transform is applied to the data of the locally published messages before they
+/// are signed. The inbound transform is applied to the received messages, after their signature
+/// is verified, before they are delivered to the application. Forwarded messages are not
+/// transformed.
+pub trait DataTransform: Debug + Send + Sync {
+    /// Transform a received message before delivering it to the application.
+    fn inbound_transform(&self, raw_message: Message) -> Result<Message, std::io::Error>;
+
+    /// Transform the data of a message to be published to the given topic.
+    fn outbound_transform(
+        &self,
+        topic: &TopicHash,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>, std::io::Error>;
+
+    /// Transform the data of a message to be published to the given topic, held in a shared
+    /// buffer.
+    ///
+    /// The default implementation copies the data and applies the
+    /// [`outbound_transform`](DataTransform::outbound_transform). Transforms leaving the data
+    /// unchanged should return the buffer as is, so it is not copied.
+    fn outbound_transform_bytes(
+        &self,
+        topic: &TopicHash,
+        data: Bytes,
+    ) -> Result<Bytes, std::io::Error> {
+        self.outbound_transform(topic, data.to_vec())
+            .map(Bytes::from)
+    }
+}
+
+/// The default transform, leaving the messages data unchanged.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct IdentityTransform;
+
+impl DataTransform for IdentityTransform {
+    fn inbound_transform(&self, raw_message: Message) -> Result<Message, std::io::Error> {
+        Ok(raw_message)
+    }
+
+    fn outbound_transform(
+        &self,
+        _topic: &TopicHash,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>, std::io::Error> {
+        Ok(data)
+    }
+
+    fn outbound_transform_bytes(
+        &self,
+        _topic: &TopicHash,
+        data: Bytes,
+    ) -> Result<Bytes, std::io::Error> {
+        Ok(data)
+    }
+}
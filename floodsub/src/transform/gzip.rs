@@ -0,0 +1,139 @@
+use std::io::Write;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::frame::Message;
+use crate::topic::TopicHash;
+use crate::transform::{read_decompressed, DataTransform, DEFAULT_MAX_DECOMPRESSED_SIZE};
+
+/// A transform compressing the messages data with gzip.
+///
+/// The maximum frame size applies to the compressed messages, while the application receives
+/// the decompressed data.
+#[derive(Debug, Copy, Clone)]
+pub struct GzipTransform {
+    /// The compression level.
+    level: Compression,
+
+    /// The maximum size of the decompressed data of a received message.
+    max_decompressed_size: usize,
+}
+
+impl Default for GzipTransform {
+    fn default() -> Self {
+        Self {
+            level: Compression::default(),
+            max_decompressed_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+        }
+    }
+}
+
+impl GzipTransform {
+    /// Create a new gzip transform with the given compression level (0-9).
+    #[must_use]
+    pub fn new(level: u32) -> Self {
+        Self {
+            level: Compression::new(level),
+            ..Default::default()
+        }
+    }
+
+    /// Set the maximum size of the decompressed data of a received message (default is 4 MiB).
+    /// Received messages exceeding it fail to transform.
+    #[must_use]
+    pub fn with_max_decompressed_size(mut self, max_decompressed_size: usize) -> Self {
+        self.max_decompressed_size = max_decompressed_size;
+        self
+    }
+}
+
+impl DataTransform for GzipTransform {
+    fn inbound_transform(&self, mut raw_message: Message) -> Result<Message, std::io::Error> {
+        let decoder = GzDecoder::new(raw_message.data());
+        let data = read_decompressed(decoder, self.max_decompressed_size)?;
+
+        raw_message.set_data(data);
+        Ok(raw_message)
+    }
+
+    fn outbound_transform(
+        &self,
+        _topic: &TopicHash,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>, std::io::Error> {
+        let mut encoder = GzEncoder::new(Vec::new(), self.level);
+        encoder.write_all(&data)?;
+        encoder.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_topic() -> TopicHash {
+        TopicHash::from_raw("/test/topic")
+    }
+
+    /// Compress the payload and decompress it back as a received message.
+    fn round_trip(transform: &GzipTransform, payload: &[u8]) -> Result<Message, std::io::Error> {
+        let compressed = transform.outbound_transform(&test_topic(), payload.to_vec())?;
+        transform.inbound_transform(Message::new(test_topic(), compressed))
+    }
+
+    #[test]
+    fn round_trip_payload() {
+        //// Given
+        let transform = GzipTransform::default();
+        let payload = b"test-payload".repeat(100);
+
+        //// When
+        let compressed = transform
+            .outbound_transform(&test_topic(), payload.clone())
+            .expect("compress the payload");
+        let message = round_trip(&transform, &payload).expect("round trip the payload");
+
+        //// Then
+        assert!(compressed.len() < payload.len());
+        assert_eq!(message.data(), payload);
+    }
+
+    #[test]
+    fn round_trip_empty_payload() {
+        //// Given
+        let transform = GzipTransform::new(9);
+
+        //// When
+        let message = round_trip(&transform, &[]).expect("round trip the payload");
+
+        //// Then
+        assert!(message.data().is_empty());
+    }
+
+    #[test]
+    fn fail_to_decompress_invalid_payload() {
+        //// Given
+        let transform = GzipTransform::default();
+        let message = Message::new(test_topic(), b"not gzip".to_vec());
+
+        //// When
+        let result = transform.inbound_transform(message);
+
+        //// Then
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn fail_to_decompress_payload_exceeding_max_size() {
+        //// Given
+        let transform = GzipTransform::default().with_max_decompressed_size(64);
+
+        //// When
+        let result = round_trip(&transform, &[0x00; 65]);
+
+        //// Then
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,134 @@
+use zstd::stream::read::Decoder;
+
+use crate::frame::Message;
+use crate::topic::TopicHash;
+use crate::transform::{read_decompressed, DataTransform, DEFAULT_MAX_DECOMPRESSED_SIZE};
+
+/// A transform compressing the messages data with zstd.
+///
+/// The maximum frame size applies to the compressed messages, while the application receives
+/// the decompressed data.
+#[derive(Debug, Copy, Clone)]
+pub struct ZstdTransform {
+    /// The compression level.
+    level: i32,
+
+    /// The maximum size of the decompressed data of a received message.
+    max_decompressed_size: usize,
+}
+
+impl Default for ZstdTransform {
+    fn default() -> Self {
+        Self {
+            level: zstd::DEFAULT_COMPRESSION_LEVEL,
+            max_decompressed_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+        }
+    }
+}
+
+impl ZstdTransform {
+    /// Create a new zstd transform with the given compression level (1-22, 0 selects the zstd
+    /// default level).
+    #[must_use]
+    pub fn new(level: i32) -> Self {
+        Self {
+            level,
+            ..Default::default()
+        }
+    }
+
+    /// Set the maximum size of the decompressed data of a received message (default is 4 MiB).
+    /// Received messages exceeding it fail to transform.
+    #[must_use]
+    pub fn with_max_decompressed_size(mut self, max_decompressed_size: usize) -> Self {
+        self.max_decompressed_size = max_decompressed_size;
+        self
+    }
+}
+
+impl DataTransform for ZstdTransform {
+    fn inbound_transform(&self, mut raw_message: Message) -> Result<Message, std::io::Error> {
+        let decoder = Decoder::new(raw_message.data())?;
+        let data = read_decompressed(decoder, self.max_decompressed_size)?;
+
+        raw_message.set_data(data);
+        Ok(raw_message)
+    }
+
+    fn outbound_transform(
+        &self,
+        _topic: &TopicHash,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>, std::io::Error> {
+        zstd::encode_all(data.as_slice(), self.level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_topic() -> TopicHash {
+        TopicHash::from_raw("/test/topic")
+    }
+
+    /// Compress the payload and decompress it back as a received message.
+    fn round_trip(transform: &ZstdTransform, payload: &[u8]) -> Result<Message, std::io::Error> {
+        let compressed = transform.outbound_transform(&test_topic(), payload.to_vec())?;
+        transform.inbound_transform(Message::new(test_topic(), compressed))
+    }
+
+    #[test]
+    fn round_trip_payload() {
+        //// Given
+        let transform = ZstdTransform::default();
+        let payload = b"test-payload".repeat(100);
+
+        //// When
+        let compressed = transform
+            .outbound_transform(&test_topic(), payload.clone())
+            .expect("compress the payload");
+        let message = round_trip(&transform, &payload).expect("round trip the payload");
+
+        //// Then
+        assert!(compressed.len() < payload.len());
+        assert_eq!(message.data(), payload);
+    }
+
+    #[test]
+    fn round_trip_empty_payload() {
+        //// Given
+        let transform = ZstdTransform::new(19);
+
+        //// When
+        let message = round_trip(&transform, &[]).expect("round trip the payload");
+
+        //// Then
+        assert!(message.data().is_empty());
+    }
+
+    #[test]
+    fn fail_to_decompress_invalid_payload() {
+        //// Given
+        let transform = ZstdTransform::default();
+        let message = Message::new(test_topic(), b"not zstd".to_vec());
+
+        //// When
+        let result = transform.inbound_transform(message);
+
+        //// Then
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn fail_to_decompress_payload_exceeding_max_size() {
+        //// Given
+        let transform = ZstdTransform::default().with_max_decompressed_size(64);
+
+        //// When
+        let result = round_trip(&transform, &[0x00; 65]);
+
+        //// Then
+        assert!(result.is_err());
+    }
+}
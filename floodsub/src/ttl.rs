@@ -0,0 +1,173 @@
+use std::time::Duration;
+
+use instant::SystemTime;
+
+/// The marker identifying the sequence numbers carrying an expiration time.
+const TTL_SEQNO_MARKER: u64 = 0x7717;
+
+/// The sequence number generator of the messages published with a TTL.
+///
+/// The pubsub message format has no field to carry a TTL, and adding one would break the
+/// compatibility with gossipsub peers. Instead, the expiration time of the messages published
+/// with a TTL is encoded in their 8-byte sequence number:
+///
+/// - Bits 63-48: A marker identifying the sequence number as carrying a TTL.
+/// - Bits 47-16: The expiration time, as unix timestamp in seconds.
+/// - Bits 15-0: A counter, keeping the sequence numbers unique.
+///
+/// Peers unaware of this encoding treat it as any other sequence number. As the marker may
+/// appear by chance in other sequence numbers (e.g., random ones), the encoding is only honoured
+/// on the topics opted in (see [`TopicParams::drop_expired_messages`]).
+///
+/// The counter does not wrap around: once exhausted for an expiration time, the expiration time
+/// is pushed back by one second, so the sequence numbers are never reused.
+///
+/// [`TopicParams::drop_expired_messages`]: crate::TopicParams::drop_expired_messages
+#[derive(Debug, Default)]
+pub(crate) struct TtlSequenceNumber {
+    /// The expiration time, in unix seconds, of the last sequence number.
+    expires_at_secs: u64,
+    counter: u16,
+}
+
+impl TtlSequenceNumber {
+    /// Get the next sequence number of a message expiring after the given TTL.
+    pub(crate) fn next(&mut self, ttl: Duration) -> u64 {
+        self.next_at(SystemTime::now() + ttl)
+    }
+
+    /// Get the next sequence number of a message expiring at the given time.
+    ///
+    /// The expiration times are monotonic: a message never expires before the previous one.
+    fn next_at(&mut self, expires_at: SystemTime) -> u64 {
+        let expires_at_secs = expiration_secs(expires_at);
+        if expires_at_secs > self.expires_at_secs {
+            self.expires_at_secs = expires_at_secs;
+            self.counter = 0;
+        } else if let Some(counter) = self.counter.checked_add(1) {
+            self.counter = counter;
+        } else {
+            log::debug!("TTL sequence numbers exhausted, pushing back the expiration time");
+            self.expires_at_secs += 1;
+            self.counter = 0;
+        }
+
+        encode_ttl_seqno(self.expires_at_secs, self.counter)
+    }
+}
+
+/// Encode the expiration time, rounded up to the next second, and the counter into a sequence
+/// number.
+#[cfg(test)]
+pub(crate) fn ttl_seqno(expires_at: SystemTime, counter: u16) -> u64 {
+    encode_ttl_seqno(expiration_secs(expires_at), counter)
+}
+
+fn encode_ttl_seqno(expires_at_secs: u64, counter: u16) -> u64 {
+    (TTL_SEQNO_MARKER << 48) | (expires_at_secs << 16) | u64::from(counter)
+}
+
+/// Get the expiration time as unix timestamp in seconds, rounded up to the next second.
+fn expiration_secs(expires_at: SystemTime) -> u64 {
+    let expires_at = unix_timestamp(expires_at);
+    let expires_at_secs = expires_at.as_secs() + u64::from(expires_at.subsec_nanos() > 0);
+    expires_at_secs.min(u64::from(u32::MAX))
+}
+
+/// Get the expiration time encoded in the sequence number, if any.
+pub(crate) fn seqno_expiration(seqno: u64) -> Option<SystemTime> {
+    if seqno >> 48 != TTL_SEQNO_MARKER {
+        return None;
+    }
+
+    let expires_at_secs = (seqno >> 16) & u64::from(u32::MAX);
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(expires_at_secs))
+}
+
+/// Check whether the sequence number carries an expiration time in the past.
+pub(crate) fn is_expired(seqno: u64) -> bool {
+    seqno_expiration(seqno).is_some_and(|expires_at| expires_at <= SystemTime::now())
+}
+
+fn unix_timestamp(time: SystemTime) -> Duration {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .expect("time to be linear")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn seqno_carries_the_expiration_time() {
+        //// Given
+        let mut generator = TtlSequenceNumber::default();
+        let now = unix_timestamp(SystemTime::now());
+
+        //// When
+        let seqno = generator.next(Duration::from_secs(60));
+
+        //// Then
+        let expires_at = seqno_expiration(seqno)
+            .map(unix_timestamp)
+            .expect("seqno with expiration time");
+        assert!(expires_at >= now + Duration::from_secs(60));
+        assert!(expires_at <= now + Duration::from_secs(62));
+        assert!(!is_expired(seqno));
+    }
+
+    #[test]
+    fn consecutive_seqnos_are_unique() {
+        //// Given
+        let mut generator = TtlSequenceNumber::default();
+
+        //// When
+        let first = generator.next(Duration::from_secs(60));
+        let second = generator.next(Duration::from_secs(60));
+
+        //// Then
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn seqnos_are_unique_once_the_counter_is_exhausted() {
+        //// Given
+        let mut generator = TtlSequenceNumber::default();
+        let expires_at = SystemTime::now() + Duration::from_secs(60);
+
+        //// When
+        let seqnos = (0..=u32::from(u16::MAX) + 1)
+            .map(|_| generator.next_at(expires_at))
+            .collect::<HashSet<_>>();
+
+        //// Then
+        assert_eq!(seqnos.len(), usize::from(u16::MAX) + 2);
+    }
+
+    #[test]
+    fn seqno_without_marker_has_no_expiration() {
+        //// Given
+        let seqno = 1_700_000_000_000_000_000;
+
+        //// When
+        let expires_at = seqno_expiration(seqno);
+
+        //// Then
+        assert!(expires_at.is_none());
+        assert!(!is_expired(seqno));
+    }
+
+    #[test]
+    fn seqno_with_past_expiration_is_expired() {
+        //// Given
+        let seqno = ttl_seqno(SystemTime::now() - Duration::from_secs(10), 1);
+
+        //// When
+        let expired = is_expired(seqno);
+
+        //// Then
+        assert!(expired);
+    }
+}
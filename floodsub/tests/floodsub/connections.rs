@@ -5,13 +5,13 @@ use libp2p::swarm::{Swarm, SwarmBuilder};
 use tokio::time::timeout;
 
 use common_test as testlib;
-use floodsub::{Behaviour, Config};
+use floodsub::{Behaviour, Config, ConfigBuilder, IdentTopic};
 use testlib::any_memory_addr;
 
 fn new_test_node(keypair: &Keypair, config: Config) -> Swarm<Behaviour> {
     let peer_id = PeerId::from(keypair.public());
     let transport = testlib::test_transport(keypair).expect("create the transport");
-    let behaviour = Behaviour::new(config);
+    let behaviour = Behaviour::new(peer_id, config);
     SwarmBuilder::with_tokio_executor(transport, behaviour, peer_id).build()
 }
 
@@ -64,3 +64,152 @@ async fn connection_is_established() {
         .active_peers()
         .contains(publisher.local_peer_id()));
 }
+
+/// Create two connected nodes with the given configuration, both subscribed to the given topic.
+async fn new_connected_subscribed_nodes(
+    config: Config,
+    topic: &IdentTopic,
+) -> (Swarm<Behaviour>, Swarm<Behaviour>) {
+    let publisher_key = testlib::secp256k1_keypair(testlib::keys::TEST_KEYPAIR_A);
+    let subscriber_key = testlib::secp256k1_keypair(testlib::keys::TEST_KEYPAIR_B);
+
+    let mut publisher = new_test_node(&publisher_key, config.clone());
+    testlib::swarm::should_listen_on_address(&mut publisher, any_memory_addr());
+
+    let mut subscriber = new_test_node(&subscriber_key, config);
+    testlib::swarm::should_listen_on_address(&mut subscriber, any_memory_addr());
+
+    let (publisher_addr, _subscriber_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut publisher, &mut subscriber),
+    )
+    .await
+    .expect("listening to start");
+
+    publisher
+        .behaviour_mut()
+        .subscribe(topic)
+        .expect("subscribe to topic");
+    subscriber
+        .behaviour_mut()
+        .subscribe(topic)
+        .expect("subscribe to topic");
+
+    testlib::swarm::should_dial_address(&mut subscriber, publisher_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut subscriber, &mut publisher),
+    )
+    .await
+    .expect("subscriber to connect to publisher");
+
+    (publisher, subscriber)
+}
+
+#[tokio::test]
+async fn subscribed_node_keeps_connection_alive_past_idle_timeout() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = IdentTopic::new("/pubsub/2/it-keep-alive-test");
+    let pubsub_config = ConfigBuilder::default()
+        .connection_idle_timeout(Duration::from_millis(500))
+        .build()
+        .expect("valid configuration");
+
+    let (mut publisher, mut subscriber) =
+        new_connected_subscribed_nodes(pubsub_config, &topic).await;
+
+    //// When
+    testlib::swarm::poll_mesh(Duration::from_secs(2), &mut publisher, &mut subscriber).await;
+
+    //// Then
+    assert_eq!(publisher.behaviour().connections().active_peers_count(), 1);
+    assert_eq!(subscriber.behaviour().connections().active_peers_count(), 1);
+}
+
+#[tokio::test]
+async fn configured_idle_timeout_closes_subscribed_node_connection() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = IdentTopic::new("/pubsub/2/it-keep-alive-test");
+    let pubsub_config = ConfigBuilder::default()
+        .idle_timeout(Duration::from_millis(500))
+        .build()
+        .expect("valid configuration");
+
+    let (mut publisher, mut subscriber) =
+        new_connected_subscribed_nodes(pubsub_config, &topic).await;
+
+    //// When
+    testlib::swarm::poll_mesh(Duration::from_secs(2), &mut publisher, &mut subscriber).await;
+
+    //// Then
+    assert_eq!(publisher.behaviour().connections().active_peers_count(), 0);
+    assert_eq!(subscriber.behaviour().connections().active_peers_count(), 0);
+}
+
+#[tokio::test]
+async fn disconnected_peer_state_is_removed() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = IdentTopic::new("/pubsub/2/it-disconnection-test");
+    let pubsub_config = Config::default();
+
+    let (mut publisher, mut subscriber) =
+        new_connected_subscribed_nodes(pubsub_config, &topic).await;
+
+    // Wait for the subscriptions to be exchanged
+    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut publisher, &mut subscriber).await;
+
+    let subscriber_id = *subscriber.local_peer_id();
+    let publisher_id = *publisher.local_peer_id();
+    assert!(publisher.behaviour().peer_topics(&subscriber_id).is_some());
+
+    //// When
+    subscriber
+        .disconnect_peer_id(publisher_id)
+        .expect("disconnect from the publisher");
+    testlib::swarm::poll_mesh(Duration::from_millis(100), &mut publisher, &mut subscriber).await;
+
+    //// Then
+    let behaviour = publisher.behaviour();
+    assert_eq!(behaviour.connections().active_peers_count(), 0);
+    assert!(behaviour.connections().active_peers().is_empty());
+    assert!(behaviour.peer_topics(&subscriber_id).is_none());
+    assert!(behaviour
+        .router()
+        .subscription_peers(&topic.hash())
+        .is_none());
+    assert!(!behaviour.would_deliver_to(&subscriber_id, &topic.hash(), &publisher_id));
+}
+
+#[tokio::test]
+async fn negotiated_protocol_is_reported() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = IdentTopic::new("/pubsub/2/it-negotiated-protocol-test");
+    let pubsub_config = Config::default();
+
+    let (mut publisher, mut subscriber) =
+        new_connected_subscribed_nodes(pubsub_config, &topic).await;
+
+    //// When
+    // Wait for the subscriptions to be exchanged, so the substreams are negotiated
+    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut publisher, &mut subscriber).await;
+
+    //// Then
+    let subscriber_id = *subscriber.local_peer_id();
+    let publisher_id = *publisher.local_peer_id();
+    assert_eq!(
+        publisher.behaviour().negotiated_protocol(&subscriber_id),
+        Some("/floodsub/1.0.0")
+    );
+    assert_eq!(
+        subscriber.behaviour().negotiated_protocol(&publisher_id),
+        Some("/floodsub/1.0.0")
+    );
+}
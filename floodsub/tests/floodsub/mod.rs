@@ -1,3 +1,4 @@
 mod connections;
 mod pubsub;
 mod subscriptions;
+mod substreams;
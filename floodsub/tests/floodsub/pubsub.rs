@@ -4,6 +4,7 @@ use std::time::Duration;
 use assert_matches::assert_matches;
 use bytes::Bytes;
 use futures::StreamExt;
+use libp2p::core::{ConnectedPoint, Endpoint};
 use libp2p::identity::{Keypair, PeerId};
 use libp2p::swarm::{SwarmBuilder, SwarmEvent};
 use libp2p::Swarm;
@@ -12,8 +13,11 @@ use tokio::time::timeout;
 
 use common_test as testlib;
 use common_test::any_memory_addr;
-use common_test::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
-use floodsub::{Behaviour, Config, Event, Hasher, IdentTopic, Topic};
+use common_test::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B, TEST_KEYPAIR_C};
+use floodsub::{
+    Behaviour, Config, ConfigBuilder, DropReason, Event, Hasher, IdentTopic, Message,
+    SubstreamMode, Topic,
+};
 
 /// Create a new test topic with a random name.
 fn new_test_topic() -> IdentTopic {
@@ -27,7 +31,7 @@ fn new_test_topic() -> IdentTopic {
 fn new_test_node(keypair: &Keypair, config: Config) -> Swarm<Behaviour> {
     let peer_id = PeerId::from(keypair.public());
     let transport = testlib::test_transport(keypair).expect("create the transport");
-    let behaviour = Behaviour::new(config);
+    let behaviour = Behaviour::new(peer_id, config);
     SwarmBuilder::with_tokio_executor(transport, behaviour, peer_id).build()
 }
 
@@ -74,6 +78,27 @@ async fn wait_for_message(swarm: &mut Swarm<Behaviour>) -> Vec<SwarmEvent<Event,
     events
 }
 
+/// Poll the three swarms for a given period of time and collect the last swarm's events.
+async fn poll_chain_and_collect_events(
+    duration: Duration,
+    swarm1: &mut Swarm<Behaviour>,
+    swarm2: &mut Swarm<Behaviour>,
+    swarm3: &mut Swarm<Behaviour>,
+) -> Vec<SwarmEvent<Event, Infallible>> {
+    let mut events = Vec::new();
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => break,
+            _ = testlib::swarm::poll(swarm1) => {},
+            _ = testlib::swarm::poll(swarm2) => {},
+            event = swarm3.select_next_some() => events.push(event),
+        }
+    }
+
+    events
+}
+
 #[tokio::test]
 async fn publish_to_topic() {
     testlib::init_logger();
@@ -142,3 +167,979 @@ async fn publish_to_topic() {
         assert_eq!(message.data()[..], message_payload[..]);
     });
 }
+
+/// The subscriptions made right before and while dialing a peer must reach it, so the messages it
+/// publishes are delivered.
+#[tokio::test]
+async fn subscribe_then_dial_and_receive_message_from_peer() {
+    testlib::init_logger();
+
+    //// Given
+    let pubsub_topic_a = new_test_topic();
+    let pubsub_topic_b = new_test_topic();
+
+    let publisher_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let subscriber_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let pubsub_config = Config::default();
+
+    //// Setup
+    let mut publisher = new_test_node(&publisher_key, pubsub_config.clone());
+    testlib::swarm::should_listen_on_address(&mut publisher, any_memory_addr());
+
+    let mut subscriber = new_test_node(&subscriber_key, pubsub_config.clone());
+    testlib::swarm::should_listen_on_address(&mut subscriber, any_memory_addr());
+
+    let (publisher_addr, _subscriber_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut publisher, &mut subscriber),
+    )
+    .await
+    .expect("listening to start");
+
+    //// When
+    // Subscribe and dial the publisher without polling the subscriber in between
+    should_subscribe_to_topic(&mut subscriber, &pubsub_topic_a);
+    testlib::swarm::should_dial_address(&mut subscriber, publisher_addr);
+    should_subscribe_to_topic(&mut subscriber, &pubsub_topic_b);
+
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut subscriber, &mut publisher),
+    )
+    .await
+    .expect("subscriber to connect to publisher");
+
+    // Wait for the subscriptions to reach the publisher
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut publisher, &mut subscriber).await;
+
+    should_publish_to_topic(&mut publisher, &pubsub_topic_a, b"test-payload-a".to_vec());
+    should_publish_to_topic(&mut publisher, &pubsub_topic_b, b"test-payload-b".to_vec());
+
+    let (_, sub_events) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(50),
+        &mut publisher,
+        &mut subscriber,
+    )
+    .await;
+
+    //// Then
+    let mut topics = sub_events
+        .into_iter()
+        .filter_map(|ev| match ev {
+            SwarmEvent::Behaviour(Event::Message { topic, .. }) => Some(topic),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    topics.sort();
+
+    let mut expected = vec![pubsub_topic_a.hash(), pubsub_topic_b.hash()];
+    expected.sort();
+    assert_eq!(topics, expected);
+}
+
+/// Connect a publisher and a subscriber, the publisher dialing the subscriber if `publisher_dials`
+/// is set, and publish a message to the subscriber.
+///
+/// Returns the subscriber's endpoint of the connection with the publisher, and the propagation
+/// endpoint reported with the received message.
+async fn receive_message_over_connection(
+    publisher_dials: bool,
+) -> (Option<ConnectedPoint>, Option<Endpoint>) {
+    let topic = new_test_topic();
+
+    let publisher_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let subscriber_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let mut publisher = new_test_node(&publisher_key, Config::default());
+    testlib::swarm::should_listen_on_address(&mut publisher, any_memory_addr());
+
+    let mut subscriber = new_test_node(&subscriber_key, Config::default());
+    testlib::swarm::should_listen_on_address(&mut subscriber, any_memory_addr());
+
+    let (publisher_addr, subscriber_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut publisher, &mut subscriber),
+    )
+    .await
+    .expect("listening to start");
+
+    should_subscribe_to_topic(&mut subscriber, &topic);
+    if publisher_dials {
+        testlib::swarm::should_dial_address(&mut publisher, subscriber_addr);
+        timeout(
+            Duration::from_secs(5),
+            testlib::swarm::wait_for_connection_establishment(&mut publisher, &mut subscriber),
+        )
+        .await
+        .expect("publisher to connect to subscriber");
+    } else {
+        testlib::swarm::should_dial_address(&mut subscriber, publisher_addr);
+        timeout(
+            Duration::from_secs(5),
+            testlib::swarm::wait_for_connection_establishment(&mut subscriber, &mut publisher),
+        )
+        .await
+        .expect("subscriber to connect to publisher");
+    }
+
+    // Wait for the subscriptions to reach the publisher
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut publisher, &mut subscriber).await;
+
+    should_publish_to_topic(&mut publisher, &topic, b"test-payload".to_vec());
+
+    let (_, sub_events) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(50),
+        &mut publisher,
+        &mut subscriber,
+    )
+    .await;
+
+    let propagation_endpoint = sub_events
+        .into_iter()
+        .find_map(|ev| match ev {
+            SwarmEvent::Behaviour(Event::Message {
+                propagation_endpoint,
+                ..
+            }) => Some(propagation_endpoint),
+            _ => None,
+        })
+        .expect("message to be received");
+
+    (
+        subscriber
+            .behaviour()
+            .peer_endpoint(publisher.local_peer_id()),
+        propagation_endpoint,
+    )
+}
+
+#[tokio::test]
+async fn message_from_dialed_peer_reports_dialer_endpoint() {
+    testlib::init_logger();
+
+    //// When
+    let (peer_endpoint, propagation_endpoint) = receive_message_over_connection(false).await;
+
+    //// Then
+    assert_matches!(peer_endpoint, Some(ConnectedPoint::Dialer { .. }));
+    assert_eq!(propagation_endpoint, Some(Endpoint::Dialer));
+}
+
+#[tokio::test]
+async fn message_from_dialing_peer_reports_listener_endpoint() {
+    testlib::init_logger();
+
+    //// When
+    let (peer_endpoint, propagation_endpoint) = receive_message_over_connection(true).await;
+
+    //// Then
+    assert_matches!(peer_endpoint, Some(ConnectedPoint::Listener { .. }));
+    assert_eq!(propagation_endpoint, Some(Endpoint::Listener));
+}
+
+/// A three-node chain where the origin and the last node are only connected to the middle node.
+///
+/// The message published by the origin reaches the last node through the middle node. The last
+/// node must see the middle node as the propagation source and the origin as the message author.
+#[tokio::test]
+async fn forwarded_message_keeps_source_and_propagation_source() {
+    testlib::init_logger();
+
+    //// Given
+    let pubsub_topic = new_test_topic();
+    let message_payload = Bytes::from_static(b"test-payload");
+
+    let origin_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let middle_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+    let last_key = testlib::secp256k1_keypair(TEST_KEYPAIR_C);
+
+    let pubsub_config = Config::default();
+
+    //// Setup
+    let mut origin = new_test_node(&origin_key, pubsub_config.clone());
+    testlib::swarm::should_listen_on_address(&mut origin, any_memory_addr());
+
+    let mut middle = new_test_node(&middle_key, pubsub_config.clone());
+    testlib::swarm::should_listen_on_address(&mut middle, any_memory_addr());
+
+    let mut last = new_test_node(&last_key, pubsub_config.clone());
+    testlib::swarm::should_listen_on_address(&mut last, any_memory_addr());
+
+    let (_origin_addr, middle_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut origin, &mut middle),
+    )
+    .await
+    .expect("listening to start");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_new_listen_addr(&mut last),
+    )
+    .await
+    .expect("listening to start");
+
+    // Subscribe to the topic
+    should_subscribe_to_topic(&mut origin, &pubsub_topic);
+    should_subscribe_to_topic(&mut middle, &pubsub_topic);
+    should_subscribe_to_topic(&mut last, &pubsub_topic);
+
+    // Dial the middle node from both the origin and the last nodes
+    testlib::swarm::should_dial_address(&mut origin, middle_addr.clone());
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut origin, &mut middle),
+    )
+    .await
+    .expect("origin to connect to the middle node");
+
+    testlib::swarm::should_dial_address(&mut last, middle_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut last, &mut middle),
+    )
+    .await
+    .expect("last node to connect to the middle node");
+
+    // Wait for pub-sub network to establish
+    poll_chain_and_collect_events(
+        Duration::from_millis(50),
+        &mut origin,
+        &mut middle,
+        &mut last,
+    )
+    .await;
+
+    //// When
+    // Publish a message authored by the origin node
+    let origin_id = *origin.local_peer_id();
+    let message = {
+        let mut msg = Message::new(pubsub_topic.hash(), message_payload.to_vec());
+        msg.set_source(Some(origin_id));
+        msg.set_sequence_number(Some(1));
+        msg
+    };
+    let result = origin.behaviour_mut().publish_raw(message);
+    assert_matches!(result, Ok(_), "publish to topic should succeed");
+
+    let last_events = poll_chain_and_collect_events(
+        Duration::from_millis(50),
+        &mut origin,
+        &mut middle,
+        &mut last,
+    )
+    .await;
+
+    //// Then
+    let messages = last_events
+        .into_iter()
+        .filter(|ev| matches!(ev, SwarmEvent::Behaviour(Event::Message { .. })))
+        .collect::<Vec<_>>();
+    assert_eq!(messages.len(), 1);
+
+    assert_matches!(&messages[0], SwarmEvent::Behaviour(Event::Message { propagation_source, message, .. }) => {
+        assert_eq!(propagation_source, middle.local_peer_id());
+        assert_eq!(message.source(), Some(origin_id));
+        assert_eq!(message.data()[..], message_payload[..]);
+    });
+}
+
+/// A two-node mesh where the publisher's message is sent back to it by the subscriber.
+///
+/// The publisher must recognize its own message in the seen messages cache and not deliver it
+/// again to the application.
+#[tokio::test]
+async fn published_message_sent_back_is_not_redelivered() {
+    testlib::init_logger();
+
+    //// Given
+    let pubsub_topic = new_test_topic();
+    let message_payload = Bytes::from_static(b"test-payload");
+
+    let publisher_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let subscriber_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let publisher_config = ConfigBuilder::default()
+        .allow_self_origin(true)
+        .emit_dropped_events(true)
+        .build()
+        .expect("valid configuration");
+
+    //// Setup
+    let mut publisher = new_test_node(&publisher_key, publisher_config);
+    testlib::swarm::should_listen_on_address(&mut publisher, any_memory_addr());
+
+    let mut subscriber = new_test_node(&subscriber_key, Config::default());
+    testlib::swarm::should_listen_on_address(&mut subscriber, any_memory_addr());
+
+    let (publisher_addr, _subscriber_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut publisher, &mut subscriber),
+    )
+    .await
+    .expect("listening to start");
+
+    // Subscribe to the topic
+    should_subscribe_to_topic(&mut publisher, &pubsub_topic);
+    should_subscribe_to_topic(&mut subscriber, &pubsub_topic);
+
+    // Dial the publisher node
+    testlib::swarm::should_dial_address(&mut subscriber, publisher_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut subscriber, &mut publisher),
+    )
+    .await
+    .expect("subscriber to connect to publisher");
+
+    // Wait for pub-sub network to establish
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut publisher, &mut subscriber).await;
+
+    should_publish_to_topic(&mut publisher, &pubsub_topic, message_payload.clone());
+
+    let (pub_events, sub_events) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(50),
+        &mut publisher,
+        &mut subscriber,
+    )
+    .await;
+
+    // The publisher delivered its own message locally.
+    assert_eq!(
+        pub_events
+            .iter()
+            .filter(|ev| matches!(ev, SwarmEvent::Behaviour(Event::Message { .. })))
+            .count(),
+        1
+    );
+
+    let received = sub_events
+        .into_iter()
+        .find_map(|ev| match ev {
+            SwarmEvent::Behaviour(Event::Message { message, .. }) => Some(message),
+            _ => None,
+        })
+        .expect("subscriber to receive the message");
+
+    //// When
+    // Send the message back to the publisher, as a forwarding loop would.
+    subscriber.behaviour_mut().clear_seen_cache();
+    let result = subscriber.behaviour_mut().publish_raw(received);
+    assert_matches!(result, Ok(_), "send the message back to the publisher");
+
+    let (pub_events, _sub_events) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(50),
+        &mut publisher,
+        &mut subscriber,
+    )
+    .await;
+
+    //// Then
+    assert!(!pub_events
+        .iter()
+        .any(|ev| matches!(ev, SwarmEvent::Behaviour(Event::Message { .. }))));
+    assert!(pub_events.iter().any(|ev| matches!(
+        ev,
+        SwarmEvent::Behaviour(Event::MessageDropped {
+            reason: DropReason::Duplicate,
+            ..
+        })
+    )));
+}
+
+/// A two-node mesh where the publisher sends a burst of messages exceeding the subscriber's
+/// per-peer message rate limit.
+///
+/// The messages beyond the burst allowance must be dropped and counted as rate limit hits.
+#[tokio::test]
+async fn messages_exceeding_the_peer_rate_limit_are_dropped() {
+    testlib::init_logger();
+
+    //// Given
+    let pubsub_topic = new_test_topic();
+
+    let publisher_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let subscriber_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let subscriber_config = ConfigBuilder::default()
+        .peer_message_rate(1, 3)
+        .emit_dropped_events(true)
+        .build()
+        .expect("valid configuration");
+
+    //// Setup
+    let mut publisher = new_test_node(&publisher_key, Config::default());
+    testlib::swarm::should_listen_on_address(&mut publisher, any_memory_addr());
+
+    let mut subscriber = new_test_node(&subscriber_key, subscriber_config);
+    testlib::swarm::should_listen_on_address(&mut subscriber, any_memory_addr());
+
+    let (publisher_addr, _subscriber_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut publisher, &mut subscriber),
+    )
+    .await
+    .expect("listening to start");
+
+    // Subscribe to the topic
+    should_subscribe_to_topic(&mut subscriber, &pubsub_topic);
+
+    // Dial the publisher node
+    testlib::swarm::should_dial_address(&mut subscriber, publisher_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut subscriber, &mut publisher),
+    )
+    .await
+    .expect("subscriber to connect to publisher");
+
+    // Wait for pub-sub network to establish
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut publisher, &mut subscriber).await;
+
+    //// When
+    for index in 0..10 {
+        should_publish_to_topic(
+            &mut publisher,
+            &pubsub_topic,
+            format!("test-payload-{index}"),
+        );
+    }
+
+    let (_pub_events, sub_events) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(100),
+        &mut publisher,
+        &mut subscriber,
+    )
+    .await;
+
+    //// Then
+    let delivered = sub_events
+        .iter()
+        .filter(|ev| matches!(ev, SwarmEvent::Behaviour(Event::Message { .. })))
+        .count();
+    let rate_limited = sub_events
+        .iter()
+        .filter(|ev| {
+            matches!(
+                ev,
+                SwarmEvent::Behaviour(Event::MessageDropped {
+                    reason: DropReason::RateLimited,
+                    ..
+                })
+            )
+        })
+        .count();
+    assert_eq!(delivered, 3);
+    assert_eq!(rate_limited, 7);
+
+    let publisher_id = *publisher.local_peer_id();
+    let stats = subscriber
+        .behaviour()
+        .peer_stats(&publisher_id)
+        .expect("publisher stats");
+    assert_eq!(stats.rate_limit_hits, 7);
+}
+
+/// Create a three-node chain where the origin and the last node are only connected to the middle
+/// node, configured with the given middle node config.
+async fn new_test_chain(
+    middle_config: Config,
+) -> (Swarm<Behaviour>, Swarm<Behaviour>, Swarm<Behaviour>) {
+    let origin_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let middle_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+    let last_key = testlib::secp256k1_keypair(TEST_KEYPAIR_C);
+
+    let mut origin = new_test_node(&origin_key, Config::default());
+    testlib::swarm::should_listen_on_address(&mut origin, any_memory_addr());
+
+    let mut middle = new_test_node(&middle_key, middle_config);
+    testlib::swarm::should_listen_on_address(&mut middle, any_memory_addr());
+
+    let mut last = new_test_node(&last_key, Config::default());
+    testlib::swarm::should_listen_on_address(&mut last, any_memory_addr());
+
+    let (_origin_addr, middle_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut origin, &mut middle),
+    )
+    .await
+    .expect("listening to start");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_new_listen_addr(&mut last),
+    )
+    .await
+    .expect("listening to start");
+
+    testlib::swarm::should_dial_address(&mut origin, middle_addr.clone());
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut origin, &mut middle),
+    )
+    .await
+    .expect("origin to connect to the middle node");
+
+    testlib::swarm::should_dial_address(&mut last, middle_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut last, &mut middle),
+    )
+    .await
+    .expect("last node to connect to the middle node");
+
+    (origin, middle, last)
+}
+
+/// A three-node chain where the middle node is not subscribed to the topic.
+///
+/// The message sent by the origin to the middle node is forwarded to the last node, subscribed
+/// to the topic.
+#[tokio::test]
+async fn unsubscribed_middle_node_forwards_message() {
+    testlib::init_logger();
+
+    //// Given
+    let pubsub_topic = new_test_topic();
+    let message_payload = Bytes::from_static(b"test-payload");
+
+    let (mut origin, mut middle, mut last) = new_test_chain(Config::default()).await;
+    should_subscribe_to_topic(&mut last, &pubsub_topic);
+
+    // Wait for pub-sub network to establish
+    poll_chain_and_collect_events(
+        Duration::from_millis(50),
+        &mut origin,
+        &mut middle,
+        &mut last,
+    )
+    .await;
+
+    //// When
+    let middle_id = *middle.local_peer_id();
+    let result = origin.behaviour_mut().publish_to_peers(
+        &pubsub_topic,
+        message_payload.clone(),
+        [middle_id],
+    );
+    assert_matches!(result, Ok(_), "publish to the middle node should succeed");
+
+    let last_events = poll_chain_and_collect_events(
+        Duration::from_millis(50),
+        &mut origin,
+        &mut middle,
+        &mut last,
+    )
+    .await;
+
+    //// Then
+    let messages = last_events
+        .into_iter()
+        .filter(|ev| matches!(ev, SwarmEvent::Behaviour(Event::Message { .. })))
+        .collect::<Vec<_>>();
+    assert_matches!(messages.as_slice(), [SwarmEvent::Behaviour(Event::Message { propagation_source, message, .. })] => {
+        assert_eq!(propagation_source, &middle_id);
+        assert_eq!(message.data()[..], message_payload[..]);
+    });
+}
+
+/// A three-node chain where the middle node is not subscribed to the topic and has the forwarding
+/// of unsubscribed topics disabled.
+///
+/// The message sent by the origin to the middle node is not forwarded to the last node.
+#[tokio::test]
+async fn unsubscribed_middle_node_with_forwarding_disabled_drops_message() {
+    testlib::init_logger();
+
+    //// Given
+    let pubsub_topic = new_test_topic();
+    let message_payload = Bytes::from_static(b"test-payload");
+
+    let middle_config = ConfigBuilder::default()
+        .forward_unsubscribed(false)
+        .build()
+        .expect("valid configuration");
+    let (mut origin, mut middle, mut last) = new_test_chain(middle_config).await;
+    should_subscribe_to_topic(&mut last, &pubsub_topic);
+
+    // Wait for pub-sub network to establish
+    poll_chain_and_collect_events(
+        Duration::from_millis(50),
+        &mut origin,
+        &mut middle,
+        &mut last,
+    )
+    .await;
+
+    //// When
+    let middle_id = *middle.local_peer_id();
+    let result =
+        origin
+            .behaviour_mut()
+            .publish_to_peers(&pubsub_topic, message_payload, [middle_id]);
+    assert_matches!(result, Ok(_), "publish to the middle node should succeed");
+
+    let last_events = poll_chain_and_collect_events(
+        Duration::from_millis(50),
+        &mut origin,
+        &mut middle,
+        &mut last,
+    )
+    .await;
+
+    //// Then
+    assert!(!last_events
+        .iter()
+        .any(|ev| matches!(ev, SwarmEvent::Behaviour(Event::Message { .. }))));
+}
+
+/// Connect two nodes with the given substream mode, and publish several messages, more than the
+/// maximum substream attempts of a connection, from one to the other.
+///
+/// Returns the payloads of the messages received by the subscriber, in order.
+async fn receive_messages_with_substream_modes(
+    publisher_mode: SubstreamMode,
+    subscriber_mode: SubstreamMode,
+) -> Vec<Bytes> {
+    let topic = new_test_topic();
+
+    let publisher_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let subscriber_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let publisher_config = ConfigBuilder::default()
+        .substream_mode(publisher_mode)
+        .build()
+        .expect("valid configuration");
+    let subscriber_config = ConfigBuilder::default()
+        .substream_mode(subscriber_mode)
+        .build()
+        .expect("valid configuration");
+
+    let mut publisher = new_test_node(&publisher_key, publisher_config);
+    testlib::swarm::should_listen_on_address(&mut publisher, any_memory_addr());
+
+    let mut subscriber = new_test_node(&subscriber_key, subscriber_config);
+    testlib::swarm::should_listen_on_address(&mut subscriber, any_memory_addr());
+
+    let (publisher_addr, _subscriber_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut publisher, &mut subscriber),
+    )
+    .await
+    .expect("listening to start");
+
+    should_subscribe_to_topic(&mut subscriber, &topic);
+    testlib::swarm::should_dial_address(&mut subscriber, publisher_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut subscriber, &mut publisher),
+    )
+    .await
+    .expect("subscriber to connect to publisher");
+
+    // Wait for the subscriptions to reach the publisher
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut publisher, &mut subscriber).await;
+
+    let mut received = Vec::new();
+    for index in 0..8 {
+        should_publish_to_topic(&mut publisher, &topic, format!("test-payload-{index}"));
+
+        let (_, sub_events) = testlib::swarm::poll_mesh_and_collect_events(
+            Duration::from_millis(50),
+            &mut publisher,
+            &mut subscriber,
+        )
+        .await;
+        received.extend(sub_events.into_iter().filter_map(|ev| match ev {
+            SwarmEvent::Behaviour(Event::Message { message, .. }) => {
+                Some(Bytes::copy_from_slice(message.data()))
+            }
+            _ => None,
+        }));
+    }
+
+    received
+}
+
+#[tokio::test]
+async fn messages_flow_over_a_persistent_substream() {
+    testlib::init_logger();
+
+    //// When
+    let received =
+        receive_messages_with_substream_modes(SubstreamMode::Persistent, SubstreamMode::Persistent)
+            .await;
+
+    //// Then
+    let expected = (0..8)
+        .map(|index| Bytes::from(format!("test-payload-{index}")))
+        .collect::<Vec<_>>();
+    assert_eq!(received, expected);
+}
+
+#[tokio::test]
+async fn messages_flow_over_a_substream_per_message() {
+    testlib::init_logger();
+
+    //// When
+    let received =
+        receive_messages_with_substream_modes(SubstreamMode::PerMessage, SubstreamMode::PerMessage)
+            .await;
+
+    //// Then
+    let expected = (0..8)
+        .map(|index| Bytes::from(format!("test-payload-{index}")))
+        .collect::<Vec<_>>();
+    assert_eq!(received, expected);
+}
+
+#[tokio::test]
+async fn messages_flow_from_a_substream_per_message_peer_to_a_persistent_peer() {
+    testlib::init_logger();
+
+    //// When
+    let received =
+        receive_messages_with_substream_modes(SubstreamMode::PerMessage, SubstreamMode::Persistent)
+            .await;
+
+    //// Then
+    let expected = (0..8)
+        .map(|index| Bytes::from(format!("test-payload-{index}")))
+        .collect::<Vec<_>>();
+    assert_eq!(received, expected);
+}
+
+#[tokio::test]
+async fn messages_flow_from_a_persistent_peer_to_a_substream_per_message_peer() {
+    testlib::init_logger();
+
+    //// When
+    let received =
+        receive_messages_with_substream_modes(SubstreamMode::Persistent, SubstreamMode::PerMessage)
+            .await;
+
+    //// Then
+    let expected = (0..8)
+        .map(|index| Bytes::from(format!("test-payload-{index}")))
+        .collect::<Vec<_>>();
+    assert_eq!(received, expected);
+}
+
+#[cfg(feature = "debug")]
+#[tokio::test]
+async fn raw_rpc_observer_receives_inbound_publish_bytes() {
+    use std::sync::{Arc, Mutex};
+
+    testlib::init_logger();
+
+    //// Given
+    let pubsub_topic = new_test_topic();
+    let message_payload = Bytes::from_static(b"test-payload");
+
+    let publisher_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let subscriber_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let raw_rpcs = Arc::new(Mutex::new(Vec::<(PeerId, Vec<u8>)>::new()));
+    let subscriber_config = ConfigBuilder::default()
+        .on_raw_rpc({
+            let raw_rpcs = raw_rpcs.clone();
+            Box::new(move |peer, bytes| raw_rpcs.lock().unwrap().push((*peer, bytes.to_vec())))
+        })
+        .build()
+        .expect("valid configuration");
+
+    //// Setup
+    let mut publisher = new_test_node(&publisher_key, Config::default());
+    testlib::swarm::should_listen_on_address(&mut publisher, any_memory_addr());
+
+    let mut subscriber = new_test_node(&subscriber_key, subscriber_config);
+    testlib::swarm::should_listen_on_address(&mut subscriber, any_memory_addr());
+
+    let (publisher_addr, _subscriber_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut publisher, &mut subscriber),
+    )
+    .await
+    .expect("listening to start");
+
+    // Subscribe to the topic
+    should_subscribe_to_topic(&mut publisher, &pubsub_topic);
+    should_subscribe_to_topic(&mut subscriber, &pubsub_topic);
+
+    // Dial the publisher node
+    testlib::swarm::should_dial_address(&mut subscriber, publisher_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut subscriber, &mut publisher),
+    )
+    .await
+    .expect("subscriber to connect to publisher");
+
+    // Wait for pub-sub network to establish
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut publisher, &mut subscriber).await;
+
+    //// When
+    should_publish_to_topic(&mut publisher, &pubsub_topic, message_payload.clone());
+
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut publisher, &mut subscriber).await;
+
+    //// Then
+    let raw_rpcs = raw_rpcs.lock().unwrap();
+    assert!(raw_rpcs
+        .iter()
+        .all(|(peer, _)| peer == publisher.local_peer_id()));
+    assert!(raw_rpcs.iter().any(|(_, bytes)| bytes
+        .windows(message_payload.len())
+        .any(|window| window == message_payload)));
+}
+
+/// Connect a publisher dialing a subscriber, each with the given config, and publish a message to
+/// the subscriber.
+///
+/// Returns the protocol negotiated by the publisher and by the subscriber, and the payloads of
+/// the messages received by the subscriber.
+async fn receive_message_with_configs(
+    publisher_config: Config,
+    subscriber_config: Config,
+) -> (Option<String>, Option<String>, Vec<Bytes>) {
+    let topic = new_test_topic();
+
+    let publisher_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let subscriber_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let mut publisher = new_test_node(&publisher_key, publisher_config);
+    testlib::swarm::should_listen_on_address(&mut publisher, any_memory_addr());
+
+    let mut subscriber = new_test_node(&subscriber_key, subscriber_config);
+    testlib::swarm::should_listen_on_address(&mut subscriber, any_memory_addr());
+
+    let (_publisher_addr, subscriber_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut publisher, &mut subscriber),
+    )
+    .await
+    .expect("listening to start");
+
+    should_subscribe_to_topic(&mut subscriber, &topic);
+    testlib::swarm::should_dial_address(&mut publisher, subscriber_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut publisher, &mut subscriber),
+    )
+    .await
+    .expect("publisher to connect to subscriber");
+
+    // Wait for the subscriptions to reach the publisher
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut publisher, &mut subscriber).await;
+
+    should_publish_to_topic(&mut publisher, &topic, b"test-payload".repeat(64));
+
+    let (_, sub_events) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(50),
+        &mut publisher,
+        &mut subscriber,
+    )
+    .await;
+
+    let payloads = sub_events
+        .into_iter()
+        .filter_map(|ev| match ev {
+            SwarmEvent::Behaviour(Event::Message { message, .. }) => {
+                Some(Bytes::copy_from_slice(message.data()))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let publisher_protocol = publisher
+        .behaviour()
+        .negotiated_protocol(subscriber.local_peer_id())
+        .map(ToString::to_string);
+    let subscriber_protocol = subscriber
+        .behaviour()
+        .negotiated_protocol(publisher.local_peer_id())
+        .map(ToString::to_string);
+
+    (publisher_protocol, subscriber_protocol, payloads)
+}
+
+/// Create a config compressing the whole RPC frames, if the peer supports it.
+#[cfg(feature = "zstd")]
+fn compressed_rpc_config() -> Config {
+    ConfigBuilder::default()
+        .compress_rpc_frames(true)
+        .build()
+        .expect("valid configuration")
+}
+
+#[cfg(feature = "zstd")]
+#[tokio::test]
+async fn compression_capable_nodes_exchange_compressed_frames() {
+    testlib::init_logger();
+
+    //// When
+    let (publisher_protocol, subscriber_protocol, payloads) =
+        receive_message_with_configs(compressed_rpc_config(), compressed_rpc_config()).await;
+
+    //// Then
+    assert_eq!(publisher_protocol.as_deref(), Some("/floodsub/1.0.0/zstd"));
+    assert_eq!(subscriber_protocol.as_deref(), Some("/floodsub/1.0.0/zstd"));
+    assert_eq!(payloads, vec![Bytes::from(b"test-payload".repeat(64))]);
+}
+
+#[cfg(feature = "zstd")]
+#[tokio::test]
+async fn compression_capable_node_falls_back_to_uncompressed_frames() {
+    testlib::init_logger();
+
+    //// When
+    let (publisher_protocol, subscriber_protocol, payloads) =
+        receive_message_with_configs(compressed_rpc_config(), Config::default()).await;
+
+    //// Then
+    assert_eq!(publisher_protocol.as_deref(), Some("/floodsub/1.0.0"));
+    assert_eq!(subscriber_protocol.as_deref(), Some("/floodsub/1.0.0"));
+    assert_eq!(payloads, vec![Bytes::from(b"test-payload".repeat(64))]);
+}
+
+/// Create a config advertising the pubsub protocol with the given protocol ID prefix.
+fn prefixed_protocol_config(prefix: &str) -> Config {
+    ConfigBuilder::default()
+        .protocol_id_prefix(prefix)
+        .allow_publish_with_no_peers(true)
+        .build()
+        .expect("valid configuration")
+}
+
+#[tokio::test]
+async fn nodes_with_matching_protocol_id_prefixes_exchange_messages() {
+    testlib::init_logger();
+
+    //// When
+    let (publisher_protocol, subscriber_protocol, payloads) = receive_message_with_configs(
+        prefixed_protocol_config("acme/pubsub"),
+        prefixed_protocol_config("acme/pubsub"),
+    )
+    .await;
+
+    //// Then
+    assert_eq!(publisher_protocol.as_deref(), Some("/acme/pubsub/1.0.0"));
+    assert_eq!(subscriber_protocol.as_deref(), Some("/acme/pubsub/1.0.0"));
+    assert_eq!(payloads, vec![Bytes::from(b"test-payload".repeat(64))]);
+}
+
+#[tokio::test]
+async fn nodes_with_mismatched_protocol_id_prefixes_do_not_negotiate_pubsub_substreams() {
+    testlib::init_logger();
+
+    //// When
+    let (publisher_protocol, subscriber_protocol, payloads) = receive_message_with_configs(
+        prefixed_protocol_config("acme/pubsub"),
+        prefixed_protocol_config("floodsub"),
+    )
+    .await;
+
+    //// Then
+    assert_eq!(publisher_protocol, None);
+    assert_eq!(subscriber_protocol, None);
+    assert!(payloads.is_empty());
+}
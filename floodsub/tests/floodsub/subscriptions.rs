@@ -22,7 +22,7 @@ fn new_test_topic() -> IdentTopic {
 fn new_test_node(keypair: &Keypair, config: Config) -> Swarm<Behaviour> {
     let peer_id = PeerId::from(keypair.public());
     let transport = testlib::test_transport(keypair).expect("create the transport");
-    let behaviour = Behaviour::new(config);
+    let behaviour = Behaviour::new(peer_id, config);
     SwarmBuilder::with_tokio_executor(transport, behaviour, peer_id).build()
 }
 
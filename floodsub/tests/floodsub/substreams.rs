@@ -0,0 +1,257 @@
+use std::collections::VecDeque;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use libp2p::core::upgrade::{DeniedUpgrade, ReadyUpgrade};
+use libp2p::core::Endpoint;
+use libp2p::identity::{Keypair, PeerId};
+use libp2p::swarm::handler::{ConnectionEvent, FullyNegotiatedOutbound};
+use libp2p::swarm::{
+    ConnectionDenied, ConnectionHandler, ConnectionHandlerEvent, ConnectionId, FromSwarm,
+    KeepAlive, NetworkBehaviour, PollParameters, Stream, SubstreamProtocol, Swarm, SwarmBuilder,
+    SwarmEvent, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+};
+use libp2p::{Multiaddr, StreamProtocol};
+use tokio::time::timeout;
+
+use common_test as testlib;
+use floodsub::{Behaviour, Config, ConfigBuilder};
+use testlib::any_memory_addr;
+
+const FLOODSUB_PROTOCOL: StreamProtocol = StreamProtocol::new("/floodsub/1.0.0");
+
+/// The outcome of a floodsub substream opening attempt.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum OpenerEvent {
+    Accepted,
+    Refused,
+}
+
+/// A connection handler opening the given number of floodsub substreams, one after the other, and
+/// keeping the accepted ones open.
+struct OpenerHandler {
+    remaining: usize,
+    opening: bool,
+    substreams: Vec<Stream>,
+    events: VecDeque<OpenerEvent>,
+}
+
+impl ConnectionHandler for OpenerHandler {
+    type FromBehaviour = void::Void;
+    type ToBehaviour = OpenerEvent;
+    type Error = void::Void;
+    type InboundProtocol = DeniedUpgrade;
+    type OutboundProtocol = ReadyUpgrade<StreamProtocol>;
+    type InboundOpenInfo = ();
+    type OutboundOpenInfo = ();
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol, Self::InboundOpenInfo> {
+        SubstreamProtocol::new(DeniedUpgrade, ())
+    }
+
+    fn connection_keep_alive(&self) -> KeepAlive {
+        KeepAlive::Yes
+    }
+
+    fn poll(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<
+        ConnectionHandlerEvent<
+            Self::OutboundProtocol,
+            Self::OutboundOpenInfo,
+            Self::ToBehaviour,
+            Self::Error,
+        >,
+    > {
+        if let Some(event) = self.events.pop_front() {
+            return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(event));
+        }
+
+        if self.remaining > 0 && !self.opening {
+            self.remaining -= 1;
+            self.opening = true;
+            return Poll::Ready(ConnectionHandlerEvent::OutboundSubstreamRequest {
+                protocol: SubstreamProtocol::new(ReadyUpgrade::new(FLOODSUB_PROTOCOL), ()),
+            });
+        }
+
+        Poll::Pending
+    }
+
+    fn on_behaviour_event(&mut self, event: Self::FromBehaviour) {
+        void::unreachable(event)
+    }
+
+    fn on_connection_event(
+        &mut self,
+        event: ConnectionEvent<
+            Self::InboundProtocol,
+            Self::OutboundProtocol,
+            Self::InboundOpenInfo,
+            Self::OutboundOpenInfo,
+        >,
+    ) {
+        match event {
+            ConnectionEvent::FullyNegotiatedOutbound(FullyNegotiatedOutbound {
+                protocol, ..
+            }) => {
+                self.opening = false;
+                self.substreams.push(protocol);
+                self.events.push_back(OpenerEvent::Accepted);
+            }
+            ConnectionEvent::DialUpgradeError(_) => {
+                self.opening = false;
+                self.events.push_back(OpenerEvent::Refused);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A network behaviour opening the given number of floodsub substreams on every connection.
+struct StreamOpener {
+    substreams: usize,
+    events: VecDeque<OpenerEvent>,
+}
+
+impl StreamOpener {
+    fn new(substreams: usize) -> Self {
+        Self {
+            substreams,
+            events: VecDeque::new(),
+        }
+    }
+
+    fn new_handler(&self) -> OpenerHandler {
+        OpenerHandler {
+            remaining: self.substreams,
+            opening: false,
+            substreams: Vec::new(),
+            events: VecDeque::new(),
+        }
+    }
+}
+
+impl NetworkBehaviour for StreamOpener {
+    type ConnectionHandler = OpenerHandler;
+    type ToSwarm = OpenerEvent;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _connection_id: ConnectionId,
+        _peer: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(self.new_handler())
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _connection_id: ConnectionId,
+        _peer: PeerId,
+        _addr: &Multiaddr,
+        _role_override: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(self.new_handler())
+    }
+
+    fn on_swarm_event(&mut self, _event: FromSwarm<Self::ConnectionHandler>) {}
+
+    fn on_connection_handler_event(
+        &mut self,
+        _peer_id: PeerId,
+        _connection_id: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        self.events.push_back(event);
+    }
+
+    fn poll(
+        &mut self,
+        _cx: &mut Context<'_>,
+        _params: &mut impl PollParameters,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        match self.events.pop_front() {
+            Some(event) => Poll::Ready(ToSwarm::GenerateEvent(event)),
+            None => Poll::Pending,
+        }
+    }
+}
+
+fn new_test_node(keypair: &Keypair, config: Config) -> Swarm<Behaviour> {
+    let peer_id = PeerId::from(keypair.public());
+    let transport = testlib::test_transport(keypair).expect("create the transport");
+    let behaviour = Behaviour::new(peer_id, config);
+    SwarmBuilder::with_tokio_executor(transport, behaviour, peer_id).build()
+}
+
+fn new_stream_opener_node(keypair: &Keypair, substreams: usize) -> Swarm<StreamOpener> {
+    let peer_id = PeerId::from(keypair.public());
+    let transport = testlib::test_transport(keypair).expect("create the transport");
+    let behaviour = StreamOpener::new(substreams);
+    SwarmBuilder::with_tokio_executor(transport, behaviour, peer_id).build()
+}
+
+#[tokio::test]
+async fn inbound_substreams_beyond_the_limit_are_refused() {
+    testlib::init_logger();
+
+    //// Given
+    let node_key = testlib::secp256k1_keypair(testlib::keys::TEST_KEYPAIR_A);
+    let opener_key = testlib::secp256k1_keypair(testlib::keys::TEST_KEYPAIR_B);
+
+    let pubsub_config = ConfigBuilder::default()
+        .max_inbound_substreams(2)
+        .build()
+        .expect("valid configuration");
+
+    let mut node = new_test_node(&node_key, pubsub_config);
+    testlib::swarm::should_listen_on_address(&mut node, any_memory_addr());
+
+    let mut opener = new_stream_opener_node(&opener_key, 6);
+    testlib::swarm::should_listen_on_address(&mut opener, any_memory_addr());
+
+    let (node_addr, _opener_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node, &mut opener),
+    )
+    .await
+    .expect("listening to start");
+
+    //// When
+    testlib::swarm::should_dial_address(&mut opener, node_addr);
+
+    let (_node_events, opener_events) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_secs(2),
+        &mut node,
+        &mut opener,
+    )
+    .await;
+
+    //// Then
+    let outcomes = opener_events
+        .into_iter()
+        .filter_map(|event| match event {
+            SwarmEvent::Behaviour(outcome) => Some(outcome),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        outcomes,
+        vec![
+            // The open substreams
+            OpenerEvent::Accepted,
+            OpenerEvent::Accepted,
+            // The substreams superseding the open ones, which are drained
+            OpenerEvent::Accepted,
+            OpenerEvent::Accepted,
+            // The substreams opened while as many substreams are draining
+            OpenerEvent::Refused,
+            OpenerEvent::Refused,
+        ]
+    );
+
+    assert_eq!(node.behaviour().connections().active_peers_count(), 1);
+}
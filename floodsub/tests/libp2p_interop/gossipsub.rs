@@ -12,16 +12,19 @@ use libp2p::gossipsub::{
     MessageAuthenticity as Libp2pGossipsubMessageAuthenticity,
     ValidationMode as Libp2pGossipsubValidationMode,
 };
+use libp2p::identify::{Behaviour as Libp2pIdentifyBehaviour, Config as Libp2pIdentifyConfig};
 use libp2p::identity::{Keypair, PeerId};
-use libp2p::swarm::{Swarm, SwarmBuilder, SwarmEvent};
+use libp2p::swarm::{NetworkBehaviour, Swarm, SwarmBuilder, SwarmEvent};
 use rand::Rng;
 use tokio::time::timeout;
 use void::Void;
 
 use common_test as testlib;
 use common_test::any_memory_addr;
-use common_test::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
-use floodsub::{Behaviour, Config, Event, IdentTopic};
+use common_test::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B, TEST_KEYPAIR_C};
+use floodsub::{
+    AnonymousSeqno, Behaviour, Config, ConfigBuilder, Event, IdentTopic, PeerKind, SigningError,
+};
 
 fn new_test_topic() -> IdentTopic {
     IdentTopic::new(format!(
@@ -37,7 +40,7 @@ fn new_libp2p_topic(raw: &str) -> Libp2pGossipsubIdentTopic {
 fn new_test_node(keypair: &Keypair, config: Config) -> Swarm<Behaviour> {
     let peer_id = PeerId::from(keypair.public());
     let transport = testlib::test_transport(keypair).expect("create the transport");
-    let behaviour = Behaviour::new(config);
+    let behaviour = Behaviour::new(peer_id, config);
     SwarmBuilder::with_tokio_executor(transport, behaviour, peer_id).build()
 }
 
@@ -53,6 +56,52 @@ fn new_libp2p_gossipsub_node(
     SwarmBuilder::with_tokio_executor(transport, behaviour, peer_id).build()
 }
 
+/// A Floodsub node running the identify protocol, advertising the supported protocols.
+#[derive(NetworkBehaviour)]
+struct IdentifiedNode {
+    floodsub: Behaviour,
+    identify: Libp2pIdentifyBehaviour,
+}
+
+/// A Libp2p Gossipsub node running the identify protocol, advertising the supported protocols.
+#[derive(NetworkBehaviour)]
+struct IdentifiedLibp2pGossipsubNode {
+    gossipsub: Libp2pGossipsubBehaviour,
+    identify: Libp2pIdentifyBehaviour,
+}
+
+fn new_identify_behaviour(keypair: &Keypair) -> Libp2pIdentifyBehaviour {
+    Libp2pIdentifyBehaviour::new(Libp2pIdentifyConfig::new(
+        "/it-pubsub-test/1.0.0".to_string(),
+        keypair.public(),
+    ))
+}
+
+fn new_identified_test_node(keypair: &Keypair, config: Config) -> Swarm<IdentifiedNode> {
+    let peer_id = PeerId::from(keypair.public());
+    let transport = testlib::test_transport(keypair).expect("create the transport");
+    let behaviour = IdentifiedNode {
+        floodsub: Behaviour::new(peer_id, config),
+        identify: new_identify_behaviour(keypair),
+    };
+    SwarmBuilder::with_tokio_executor(transport, behaviour, peer_id).build()
+}
+
+fn new_identified_libp2p_gossipsub_node(
+    keypair: &Keypair,
+    privacy: Libp2pGossipsubMessageAuthenticity,
+    config: Libp2pGossipsubConfig,
+) -> Swarm<IdentifiedLibp2pGossipsubNode> {
+    let peer_id = PeerId::from(keypair.public());
+    let transport = testlib::test_transport(keypair).expect("create the transport");
+    let behaviour = IdentifiedLibp2pGossipsubNode {
+        gossipsub: Libp2pGossipsubBehaviour::new(privacy, config)
+            .expect("valid gossipsub configuration"),
+        identify: new_identify_behaviour(keypair),
+    };
+    SwarmBuilder::with_tokio_executor(transport, behaviour, peer_id).build()
+}
+
 async fn wait_for_message_event(
     swarm: &mut Swarm<Behaviour>,
 ) -> Vec<SwarmEvent<Event, Infallible>> {
@@ -117,11 +166,27 @@ async fn wait_mesh_libp2p_gossipsub_message_propagation(
     }
 }
 
+/// Poll the three swarms of a `gossipsub - floodsub - gossipsub` mesh for a given period of time.
+async fn poll_forwarding_mesh(
+    duration: Duration,
+    swarm1: &mut Swarm<Libp2pGossipsubBehaviour>,
+    swarm2: &mut Swarm<Behaviour>,
+    swarm3: &mut Swarm<Libp2pGossipsubBehaviour>,
+) {
+    tokio::select! {
+        _ = tokio::time::sleep(duration) => {},
+        _ = testlib::swarm::poll(swarm1) => {},
+        _ = testlib::swarm::poll(swarm2) => {},
+        _ = testlib::swarm::poll(swarm3) => {},
+    }
+}
+
 /// Interoperability test where a Floodsub node acts publisher and a Libp2p Gosssipsub Node (with
 /// Floodsub support enabled) acts as subscriber.
 ///
 /// The publisher sends a message to the pubsub topic, the subscriber asserts the propagation and
-/// reception of the message.
+/// reception of the message. The publisher is anonymous and, with the default anonymous sequence
+/// number policy, the message carries a random sequence number.
 #[tokio::test]
 async fn floodsub_node_publish_and_gossipsub_node_subscribes() {
     testlib::init_logger();
@@ -208,6 +273,101 @@ async fn floodsub_node_publish_and_gossipsub_node_subscribes() {
     });
 }
 
+/// Interoperability test where a Floodsub node acts publisher and a Libp2p Gosssipsub Node (with
+/// Floodsub support enabled) acts as subscriber.
+///
+/// The publisher sends an anonymous message without sequence number to the pubsub topic, the
+/// subscriber, in permissive validation mode, asserts the propagation and reception of the
+/// message.
+#[tokio::test]
+async fn floodsub_node_publish_without_seqno_and_gossipsub_node_subscribes() {
+    testlib::init_logger();
+
+    //// Given
+    let pubsub_topic = new_test_topic();
+    let libp2p_pubsub_topic = new_libp2p_topic(pubsub_topic.hash().as_str());
+
+    let message_payload = Bytes::from_static(b"test-payload");
+
+    let publisher_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let subscriber_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let publisher_config = ConfigBuilder::default()
+        .anonymous_seqno(AnonymousSeqno::None)
+        .build()
+        .expect("valid configuration");
+    let subscriber_config = Libp2pGossipsubConfigBuilder::default()
+        .validation_mode(Libp2pGossipsubValidationMode::Permissive)
+        .support_floodsub()
+        .build()
+        .expect("valid gossipsub configuration");
+
+    let mut publisher = new_test_node(&publisher_key, publisher_config.clone());
+    testlib::swarm::should_listen_on_address(&mut publisher, any_memory_addr());
+
+    let mut libp2p_subscriber = new_libp2p_gossipsub_node(
+        &subscriber_key,
+        Libp2pGossipsubMessageAuthenticity::Anonymous,
+        subscriber_config.clone(),
+    );
+    testlib::swarm::should_listen_on_address(&mut libp2p_subscriber, any_memory_addr());
+
+    let (_publisher_addr, subscriber_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut publisher, &mut libp2p_subscriber),
+    )
+    .await
+    .expect("listening to start");
+
+    // Subscribe to the topic
+    publisher
+        .behaviour_mut()
+        .subscribe(&pubsub_topic)
+        .expect("subscribe to topic");
+    libp2p_subscriber
+        .behaviour_mut()
+        .subscribe(&libp2p_pubsub_topic)
+        .expect("subscribe to topic");
+
+    // Dial the publisher node
+    testlib::swarm::should_dial_address(&mut publisher, subscriber_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut publisher, &mut libp2p_subscriber),
+    )
+    .await
+    .expect("publisher to dial the subscriber");
+
+    testlib::swarm::poll_mesh(
+        Duration::from_millis(50),
+        &mut publisher,
+        &mut libp2p_subscriber,
+    )
+    .await;
+
+    //// When
+    publisher
+        .behaviour_mut()
+        .publish(&pubsub_topic, message_payload.clone())
+        .expect("publish the message");
+
+    let sub_events = wait_mesh_message_propagation(
+        Duration::from_millis(50),
+        &mut publisher,
+        &mut libp2p_subscriber,
+    )
+    .await;
+
+    //// Then
+    let last_event = sub_events.last().expect("at least one event");
+    assert_matches!(last_event, SwarmEvent::Behaviour(Libp2pGossipsubEvent::Message { message, .. }) => {
+        assert!(message.sequence_number.is_none());
+        assert!(message.source.is_none());
+        assert_eq!(message.topic.as_str(), pubsub_topic.hash().as_str());
+        assert_eq!(message.data[..], message_payload[..]);
+    });
+}
+
 /// Interoperability test where a Libp2p Gossipsub node (with Floodsub support enabled) acts
 /// publisher and a Floodsub node acts as subscriber.
 ///
@@ -298,3 +458,285 @@ async fn gossipsub_node_publish_and_floodsub_node_subscribes() {
         assert_eq!(message.data()[..], message_payload[..]);
     });
 }
+
+/// Interoperability test where a Libp2p Gossipsub node publishes a signed message, a Floodsub node
+/// forwards it, and a second Libp2p Gossipsub node, in strict validation mode, subscribes.
+///
+/// The forwarder must preserve the message authorship fields verbatim so the subscriber accepts the
+/// message signature.
+#[tokio::test]
+async fn floodsub_node_forwards_signed_message_preserving_signature() {
+    testlib::init_logger();
+
+    //// Given
+    let pubsub_topic = new_test_topic();
+    let libp2p_pubsub_topic = new_libp2p_topic(pubsub_topic.hash().as_str());
+
+    let message_payload = Bytes::from_static(b"test-payload");
+
+    let publisher_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let forwarder_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+    let subscriber_key = testlib::secp256k1_keypair(TEST_KEYPAIR_C);
+
+    let libp2p_config = Libp2pGossipsubConfigBuilder::default()
+        .validation_mode(Libp2pGossipsubValidationMode::Strict)
+        .support_floodsub()
+        .build()
+        .expect("valid gossipsub configuration");
+    let forwarder_config = Config::default();
+
+    let mut libp2p_publisher = new_libp2p_gossipsub_node(
+        &publisher_key,
+        Libp2pGossipsubMessageAuthenticity::Signed(publisher_key.clone()),
+        libp2p_config.clone(),
+    );
+    testlib::swarm::should_listen_on_address(&mut libp2p_publisher, any_memory_addr());
+
+    let mut forwarder = new_test_node(&forwarder_key, forwarder_config.clone());
+    testlib::swarm::should_listen_on_address(&mut forwarder, any_memory_addr());
+
+    let mut libp2p_subscriber = new_libp2p_gossipsub_node(
+        &subscriber_key,
+        Libp2pGossipsubMessageAuthenticity::Signed(subscriber_key.clone()),
+        libp2p_config.clone(),
+    );
+    testlib::swarm::should_listen_on_address(&mut libp2p_subscriber, any_memory_addr());
+
+    let (_publisher_addr, forwarder_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut libp2p_publisher, &mut forwarder),
+    )
+    .await
+    .expect("listening to start");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_new_listen_addr(&mut libp2p_subscriber),
+    )
+    .await
+    .expect("listening to start");
+
+    // Subscribe to the topic
+    libp2p_publisher
+        .behaviour_mut()
+        .subscribe(&libp2p_pubsub_topic)
+        .expect("subscribe to topic");
+    forwarder
+        .behaviour_mut()
+        .subscribe(&pubsub_topic)
+        .expect("subscribe to topic");
+    libp2p_subscriber
+        .behaviour_mut()
+        .subscribe(&libp2p_pubsub_topic)
+        .expect("subscribe to topic");
+
+    // Dial the forwarder node from both the publisher and the subscriber nodes
+    testlib::swarm::should_dial_address(&mut libp2p_publisher, forwarder_addr.clone());
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut libp2p_publisher, &mut forwarder),
+    )
+    .await
+    .expect("publisher to dial the forwarder");
+
+    testlib::swarm::should_dial_address(&mut libp2p_subscriber, forwarder_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut libp2p_subscriber, &mut forwarder),
+    )
+    .await
+    .expect("subscriber to dial the forwarder");
+
+    poll_forwarding_mesh(
+        Duration::from_millis(50),
+        &mut libp2p_publisher,
+        &mut forwarder,
+        &mut libp2p_subscriber,
+    )
+    .await;
+
+    //// When
+    libp2p_publisher
+        .behaviour_mut()
+        .publish(libp2p_pubsub_topic.hash(), message_payload.clone())
+        .expect("publish the message");
+
+    let sub_events = tokio::select! {
+        _ = tokio::time::sleep(Duration::from_millis(250)) => panic!("timeout reached"),
+        _ = testlib::swarm::poll(&mut libp2p_publisher) => unreachable!("polling never ends"),
+        _ = testlib::swarm::poll(&mut forwarder) => unreachable!("polling never ends"),
+        res = wait_for_libp2p_gossipsub_message_event(&mut libp2p_subscriber) => res,
+    };
+
+    //// Then
+    let last_event = sub_events.last().expect("at least one event");
+    assert_matches!(last_event, SwarmEvent::Behaviour(Libp2pGossipsubEvent::Message { propagation_source, message, .. }) => {
+        assert_eq!(propagation_source, forwarder.local_peer_id());
+        assert_eq!(message.source.as_ref(), Some(libp2p_publisher.local_peer_id()));
+        assert!(message.sequence_number.is_some());
+        assert_eq!(message.topic.as_str(), pubsub_topic.hash().as_str());
+        assert_eq!(message.data[..], message_payload[..]);
+    });
+}
+
+/// Interoperability test where a Floodsub node connects to a Libp2p Gossipsub node (with Floodsub
+/// support enabled), both running the identify protocol.
+///
+/// The Floodsub node detects the gossipsub protocol among the protocols advertised by the peer.
+#[tokio::test]
+async fn floodsub_node_detects_gossipsub_peer() {
+    testlib::init_logger();
+
+    //// Given
+    let node_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let libp2p_node_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let libp2p_node_config = Libp2pGossipsubConfigBuilder::default()
+        .validation_mode(Libp2pGossipsubValidationMode::Permissive)
+        .support_floodsub()
+        .build()
+        .expect("valid gossipsub configuration");
+
+    let mut node = new_identified_test_node(&node_key, Config::default());
+    testlib::swarm::should_listen_on_address(&mut node, any_memory_addr());
+
+    let mut libp2p_node = new_identified_libp2p_gossipsub_node(
+        &libp2p_node_key,
+        Libp2pGossipsubMessageAuthenticity::Anonymous,
+        libp2p_node_config,
+    );
+    testlib::swarm::should_listen_on_address(&mut libp2p_node, any_memory_addr());
+
+    let (_node_addr, libp2p_node_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node, &mut libp2p_node),
+    )
+    .await
+    .expect("listening to start");
+
+    //// When
+    testlib::swarm::should_dial_address(&mut node, libp2p_node_addr);
+
+    let detected_peer = tokio::select! {
+        _ = tokio::time::sleep(Duration::from_secs(5)) => panic!("timeout reached"),
+        _ = testlib::swarm::poll(&mut libp2p_node) => unreachable!("polling never ends"),
+        peer_id = async {
+            loop {
+                if let SwarmEvent::Behaviour(IdentifiedNodeEvent::Floodsub(
+                    Event::GossipsubPeerDetected { peer_id },
+                )) = node.select_next_some().await
+                {
+                    break peer_id;
+                }
+            }
+        } => peer_id,
+    };
+
+    //// Then
+    assert_eq!(&detected_peer, libp2p_node.local_peer_id());
+    assert_eq!(
+        node.behaviour().floodsub.peer_kind(&detected_peer),
+        Some(PeerKind::Gossipsub)
+    );
+}
+
+/// Interoperability test where a Floodsub node, signing the messages with an external signer
+/// callback, acts as publisher and a Libp2p Gossipsub node, in strict validation mode, subscribes.
+///
+/// The subscriber must accept the message signature produced by the signer.
+#[tokio::test]
+async fn floodsub_node_publish_signed_message_and_strict_gossipsub_node_subscribes() {
+    testlib::init_logger();
+
+    //// Given
+    let pubsub_topic = new_test_topic();
+    let libp2p_pubsub_topic = new_libp2p_topic(pubsub_topic.hash().as_str());
+
+    let message_payload = Bytes::from_static(b"test-payload");
+
+    let publisher_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let subscriber_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    // A mock external signer, holding the key pair outside the behaviour.
+    let signer_key = publisher_key.clone();
+    let publisher_config = ConfigBuilder::default()
+        .signer(
+            publisher_key.public(),
+            Box::new(move |payload| {
+                signer_key
+                    .sign(payload)
+                    .map_err(|err| SigningError::new(err.to_string()))
+            }),
+        )
+        .build()
+        .expect("valid configuration");
+    let subscriber_config = Libp2pGossipsubConfigBuilder::default()
+        .validation_mode(Libp2pGossipsubValidationMode::Strict)
+        .support_floodsub()
+        .build()
+        .expect("valid gossipsub configuration");
+
+    let mut publisher = new_test_node(&publisher_key, publisher_config);
+    testlib::swarm::should_listen_on_address(&mut publisher, any_memory_addr());
+
+    let mut libp2p_subscriber = new_libp2p_gossipsub_node(
+        &subscriber_key,
+        Libp2pGossipsubMessageAuthenticity::Signed(subscriber_key.clone()),
+        subscriber_config,
+    );
+    testlib::swarm::should_listen_on_address(&mut libp2p_subscriber, any_memory_addr());
+
+    let (_publisher_addr, subscriber_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut publisher, &mut libp2p_subscriber),
+    )
+    .await
+    .expect("listening to start");
+
+    // Subscribe to the topic
+    publisher
+        .behaviour_mut()
+        .subscribe(&pubsub_topic)
+        .expect("subscribe to topic");
+    libp2p_subscriber
+        .behaviour_mut()
+        .subscribe(&libp2p_pubsub_topic)
+        .expect("subscribe to topic");
+
+    // Dial the subscriber node
+    testlib::swarm::should_dial_address(&mut publisher, subscriber_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut publisher, &mut libp2p_subscriber),
+    )
+    .await
+    .expect("publisher to dial the subscriber");
+
+    testlib::swarm::poll_mesh(
+        Duration::from_millis(50),
+        &mut publisher,
+        &mut libp2p_subscriber,
+    )
+    .await;
+
+    //// When
+    publisher
+        .behaviour_mut()
+        .publish(&pubsub_topic, message_payload.clone())
+        .expect("publish the message");
+
+    let sub_events = wait_mesh_message_propagation(
+        Duration::from_millis(50),
+        &mut publisher,
+        &mut libp2p_subscriber,
+    )
+    .await;
+
+    //// Then
+    let last_event = sub_events.last().expect("at least one event");
+    assert_matches!(last_event, SwarmEvent::Behaviour(Libp2pGossipsubEvent::Message { message, .. }) => {
+        assert_eq!(message.source.as_ref(), Some(publisher.local_peer_id()));
+        assert!(message.sequence_number.is_some());
+        assert_eq!(message.topic.as_str(), pubsub_topic.hash().as_str());
+        assert_eq!(message.data[..], message_payload[..]);
+    });
+}